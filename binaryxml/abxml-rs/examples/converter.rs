@@ -22,7 +22,7 @@ fn main() {
     if let Err(ref e) = run() {
         println!("error: {}", e);
 
-        for e in e.iter_causes() {
+        for e in e.chain().skip(1) {
             println!("caused by: {}", e);
         }
 
@@ -85,7 +85,7 @@ fn run() -> Result<(), Error> {
     Ok(())
 }
 
-fn parse_xml<'a>(content: &[u8], resources: &'a Resources<'a>) -> Result<String, Error> {
+fn parse_xml(content: &[u8], resources: &Resources) -> Result<String, Error> {
     let cursor = Cursor::new(content);
     let mut visitor = XmlVisitor::new(resources);
 
@@ -4,7 +4,7 @@ extern crate env_logger;
 extern crate log;
 extern crate zip;
 
-use std::{env, path::Path};
+use std::{env, fs::File, path::Path};
 
 use anyhow::{Error, Context};
 
@@ -16,7 +16,7 @@ fn main() {
     if let Err(ref e) = run() {
         println!("error: {}", e);
 
-        for e in e.iter_causes() {
+        for e in e.chain().skip(1) {
             println!("caused by: {}", e);
         }
 
@@ -41,9 +41,16 @@ fn run() -> Result<(), Error> {
         }
     };
 
-    let mut apk = Apk::from_path(&apk_path).context("error loading APK")?;
-    apk.export(Path::new(&output), true)
+    let mut apk = Apk::<File>::from_path(&apk_path).context("error loading APK")?;
+    let problems = apk
+        .export(Path::new(&output), true)
         .context("APK could not be exported")?;
 
+    for (path, diagnostics) in problems {
+        for diagnostic in diagnostics {
+            println!("{}: {}", path, diagnostic);
+        }
+    }
+
     Ok(())
 }
@@ -0,0 +1,17 @@
+#![no_main]
+extern crate abxml;
+#[macro_use]
+extern crate libfuzzer_sys;
+
+use std::fs::File;
+
+use abxml::{apk::Apk, visitor::DecodeMode};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(mut apk) = Apk::<File>::from_bytes(data) {
+        apk.set_decode_mode(DecodeMode::Lenient);
+        let _ = apk.export_files();
+        let _ = apk.list_resources();
+        let _ = apk.resource_usage();
+    }
+});
@@ -0,0 +1,11 @@
+#![no_main]
+extern crate abxml;
+#[macro_use]
+extern crate libfuzzer_sys;
+
+use abxml::visitor::{Executor, ModelVisitor};
+
+fuzz_target!(|data: &[u8]| {
+    let mut visitor = ModelVisitor::default();
+    let _ = Executor::arsc(data, &mut visitor);
+});
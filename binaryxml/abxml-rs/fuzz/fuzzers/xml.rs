@@ -0,0 +1,18 @@
+#![no_main]
+extern crate abxml;
+#[macro_use]
+extern crate libfuzzer_sys;
+
+use std::io::Cursor;
+
+use abxml::visitor::{DecodeMode, Executor, Resources, XmlVisitor};
+
+fuzz_target!(|data: &[u8]| {
+    let resources = Resources::default();
+
+    for mode in &[DecodeMode::Strict, DecodeMode::Lenient] {
+        let mut visitor = XmlVisitor::with_mode(&resources, *mode);
+        let _ = Executor::xml(Cursor::new(data), &mut visitor);
+        let _ = visitor.into_decoded();
+    }
+});
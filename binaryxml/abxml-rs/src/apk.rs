@@ -1,24 +1,60 @@
 //! High level abstraction to easy the extraction to file system of APKs
 
 use std::{
+    cell::RefCell,
+    collections::HashMap,
     fs::{self, File},
     io::{Cursor, Read, Seek, Write},
     path::Path,
+    sync::Arc,
 };
 
 use anyhow::anyhow;
 use anyhow::{Context, Error};
-use zip::read::ZipArchive;
+use log::warn;
+use zip::{read::ZipArchive, CompressionMethod};
 
 use crate::{
-    decoder::BufferedDecoder,
+    decoder::Decoder,
+    interpret::{PrivacyReport, TableResolver},
     model::Library as LibraryTrait,
+    native::{self, NativeInventory, NativeLibrary},
+    usage::{dex, ResourceUsage},
+    values::{self, ResourcePath, ValuesFile},
+    visitor::{DecodeMode, Diagnostic, Executor, ReferenceVisitor, Resources, XmlVisitor},
 };
 
+/// Layout of the files written by `Apk::export`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ExportMode {
+    /// The files of the APK, on the same paths they have on the ZIP
+    #[default]
+    Raw,
+    /// Same layout as apktool: `resources.arsc` is replaced by the `res/values*/` files generated
+    /// from it, and the resource files are moved back to `res/<type>[-<qualifiers>]/<name>.<ext>`.
+    Apktool,
+}
+
 #[derive(Debug)]
 pub struct Apk<Reader: Read + Seek = File> {
     handler: ZipArchive<Reader>,
-    decoder: BufferedDecoder,
+    /// Resources of the APK, decoded once and shared by all its files
+    resources: Arc<Resources>,
+    mode: DecodeMode,
+    export_mode: ExportMode,
+    rename_paths: bool,
+}
+
+/// A file of the APK, with its binary XML transcoded to text when possible
+#[derive(Debug)]
+pub struct ExportedFile {
+    pub name: String,
+    /// Path of the file on the APK, when it was renamed after the resource it holds
+    pub original_name: Option<String>,
+    pub contents: Vec<u8>,
+    /// Problems found while transcoding the file. If it could not be transcoded, `contents` holds
+    /// the original bytes.
+    pub diagnostics: Vec<Diagnostic>,
 }
 
 #[derive(Debug, serde::Serialize)]
@@ -42,7 +78,10 @@ impl<Reader: Read + Seek> Apk<Reader> {
 
         Ok(Apk {
             handler: zip_handler,
-            decoder: buffer.into(),
+            resources: Arc::new(Self::decode_resources(&buffer)?),
+            mode: DecodeMode::default(),
+            export_mode: ExportMode::default(),
+            rename_paths: false,
         })
     }
 
@@ -55,18 +94,62 @@ impl<Reader: Read + Seek> Apk<Reader> {
 
         Ok(Apk {
             handler: zip_handler,
-            decoder: buffer.into(),
+            resources: Arc::new(Self::decode_resources(&buffer)?),
+            mode: DecodeMode::default(),
+            export_mode: ExportMode::default(),
+            rename_paths: false,
         })
     }
 
+    fn decode_resources(buffer: &[u8]) -> Result<Resources, Error> {
+        Ok(Decoder::from_buffer(buffer)
+            .context("could not decode resources.arsc")?
+            .into_resources())
+    }
+
+    /// Resources of the APK, along the ones of the framework. They can be shared with other
+    /// threads to decode the files of the APK in parallel.
+    pub fn get_resources(&self) -> &Arc<Resources> {
+        &self.resources
+    }
+
+    /// Sets how malformed binary XML files are handled when exporting
+    pub fn set_decode_mode(&mut self, mode: DecodeMode) {
+        self.mode = mode;
+    }
+
+    /// Sets the layout of the files written by `export`
+    pub fn set_export_mode(&mut self, mode: ExportMode) {
+        self.export_mode = mode;
+    }
+
+    /// Sets whether the resource files are renamed after the resource they hold on the exported
+    /// files, so the shortened paths of shrunk APKs (`res/Ab.xml`) become readable again
+    /// (`res/layout/activity_main.xml`). It is always done on the apktool export mode.
+    pub fn set_rename_paths(&mut self, rename: bool) {
+        self.rename_paths = rename;
+    }
+
+    /// Maps the path of each resource file of the APK to the resource it holds
+    pub fn resource_paths(&self) -> Result<HashMap<String, ResourcePath>, Error> {
+        Ok(Self::app_resource_paths(&self.resources))
+    }
+
     pub fn export_string(&mut self) -> Result<Vec<(String, Vec<u8>)>, Error> {
-        use crate::visitor::XmlVisitor;
+        Ok(self
+            .export_files()?
+            .into_iter()
+            .map(|file| (file.name, file.contents))
+            .collect())
+    }
 
-        let decoder = self
-            .decoder
-            .get_decoder()
-            .context("could not get the decoder")?;
+    /// Returns all the files of the APK, transcoding the binary XML files found on it and keeping
+    /// track of the problems found on each one of them.
+    pub fn export_files(&mut self) -> Result<Vec<ExportedFile>, Error> {
+        let resources = &*self.resources;
 
+        let paths = Self::app_resource_paths(resources);
+        let rename = self.rename_paths || self.export_mode == ExportMode::Apktool;
         let mut result = Vec::new();
 
         // Iterate over all the files on the ZIP and extract them
@@ -85,32 +168,34 @@ impl<Reader: Read + Seek> Apk<Reader> {
                 (is_xml, contents)
             };
 
-            let contents = if (file_name.starts_with("res/") && file_name.ends_with(".xml"))
-                || file_name == "AndroidManifest.xml"
-            {
-                decoder
-                    .xml_visitor(&contents)
-                    .and_then(XmlVisitor::into_string)
-                    .map(String::into_bytes)
-                    .unwrap_or(contents)
-            } else {
-                contents
+            let (contents, diagnostics) =
+                Self::transcode(resources, self.mode, &paths, &file_name, contents);
+
+            let (name, original_name) = match paths.get(&file_name).filter(|_| rename) {
+                Some(path) => (path.path(), Some(file_name)),
+                None => (file_name, None),
             };
 
-            result.push((file_name, contents));
+            result.push(ExportedFile {
+                name,
+                original_name,
+                contents,
+                diagnostics,
+            });
         }
         Ok(result)
     }
 
     /// It exports to target output_path the contents of the APK, transcoding the binary XML files
-    /// found on it.
-    pub fn export<P: AsRef<Path>>(&mut self, output_path: P, force: bool) -> Result<(), Error> {
-        use crate::visitor::XmlVisitor;
-
-        let decoder = self
-            .decoder
-            .get_decoder()
-            .context("could not get the decoder")?;
+    /// found on it. See `ExportMode` for the layout of the output. It returns the problems found
+    /// while transcoding, keyed by the path each file was written to; files without problems are
+    /// left out.
+    pub fn export<P: AsRef<Path>>(
+        &mut self,
+        output_path: P,
+        force: bool,
+    ) -> Result<Vec<(String, Vec<Diagnostic>)>, Error> {
+        let resources = &*self.resources;
 
         if fs::create_dir_all(&output_path).is_err() && force {
             fs::remove_dir_all(&output_path).context(anyhow!(
@@ -123,6 +208,14 @@ impl<Reader: Read + Seek> Apk<Reader> {
             ))?;
         }
 
+        let paths = Self::app_resource_paths(resources);
+        let rename = self.rename_paths || self.export_mode == ExportMode::Apktool;
+        let values_files = match self.export_mode {
+            ExportMode::Raw => Vec::new(),
+            ExportMode::Apktool => Self::app_values_files(resources)?,
+        };
+        let mut problems = Vec::new();
+
         // Iterate over all the files on the ZIP and extract them
         for i in 0..self.handler.len() {
             let (file_name, contents) = {
@@ -139,22 +232,90 @@ impl<Reader: Read + Seek> Apk<Reader> {
                 (is_xml, contents)
             };
 
-            let contents = if (file_name.starts_with("res/") && file_name.ends_with(".xml"))
-                || file_name == "AndroidManifest.xml"
-            {
-                decoder
-                    .xml_visitor(&contents)
-                    .and_then(XmlVisitor::into_string)
-                    .map(String::into_bytes)
-                    .unwrap_or(contents)
-            } else {
-                contents
+            if self.export_mode == ExportMode::Apktool && file_name == "resources.arsc" {
+                continue;
+            }
+
+            let (contents, diagnostics) =
+                Self::transcode(resources, self.mode, &paths, &file_name, contents);
+            let target = match paths.get(&file_name).filter(|_| rename) {
+                Some(path) => path.path(),
+                None => file_name,
             };
 
-            Self::write_file(&output_path, &file_name, &contents)
+            Self::write_file(&output_path, &target, &contents)
                 .context("could not write output file")?;
+
+            if !diagnostics.is_empty() {
+                problems.push((target, diagnostics));
+            }
+        }
+
+        for file in values_files {
+            Self::write_file(&output_path, &file.path, file.contents.as_bytes())
+                .context("could not write values file")?;
+        }
+
+        Ok(problems)
+    }
+
+    /// Resource files of the application packages, keyed by their path on the APK
+    fn app_resource_paths(resources: &Resources) -> HashMap<String, ResourcePath> {
+        resources
+            .packages
+            .keys()
+            .filter(|id| **id != 1)
+            .flat_map(|package_id| values::resource_paths(resources, *package_id))
+            .collect()
+    }
+
+    /// `res/values*/` files of the application packages
+    fn app_values_files(resources: &Resources) -> Result<Vec<ValuesFile>, Error> {
+        let mut files = Vec::new();
+
+        for package_id in resources.packages.keys().filter(|id| **id != 1) {
+            files.extend(
+                values::values_files(resources, *package_id).context(format!(
+                    "could not generate the values of package {}",
+                    package_id
+                ))?,
+            );
+        }
+
+        Ok(files)
+    }
+
+    /// Transcodes the file to text XML if it is a binary XML: the manifest, the XML files of
+    /// `res/` and the XML files of any resource, whatever their path is on shrunk APKs. The
+    /// original contents are kept if the file can not be decoded.
+    fn transcode(
+        resources: &Resources,
+        mode: DecodeMode,
+        paths: &HashMap<String, ResourcePath>,
+        file_name: &str,
+        contents: Vec<u8>,
+    ) -> (Vec<u8>, Vec<Diagnostic>) {
+        let is_xml = file_name == "AndroidManifest.xml"
+            || (file_name.starts_with("res/") && file_name.ends_with(".xml"))
+            || paths
+                .get(file_name)
+                .is_some_and(|path| path.extension == ".xml");
+        if !is_xml {
+            return (contents, Vec::new());
+        }
+
+        let decoded = XmlVisitor::decode(resources, &contents, mode);
+        for diagnostic in &decoded.diagnostics {
+            warn!("{}: {}", file_name, diagnostic);
+        }
+
+        match decoded.xml {
+            Some(xml) => (xml.into_bytes(), decoded.diagnostics),
+            None => {
+                warn!("{}: could not be decoded, exporting it as is", file_name);
+                (contents, decoded.diagnostics)
+            }
         }
-        Ok(())
     }
 
     fn write_file<B: AsRef<Path>, R: AsRef<Path>>(
@@ -181,12 +342,7 @@ impl<Reader: Read + Seek> Apk<Reader> {
     }
 
     pub fn list_resources(&mut self) -> Result<Vec<ArscResource>, Error> {
-        let decoder = self
-            .decoder
-            .get_decoder()
-            .context("could not get the decoder")?;
-
-        let resources = decoder.get_resources();
+        let resources = &*self.resources;
         let mut result = Vec::new();
 
         // Iterate through all packages
@@ -227,4 +383,228 @@ impl<Reader: Read + Seek> Apk<Reader> {
 
         Ok(result)
     }
+
+    /// Lists the native libraries of the APK (`lib/<abi>/*.so`), with the metadata found on their
+    /// ELF headers. Libraries that can not be parsed are listed with an error.
+    pub fn native_libraries(&mut self) -> Result<NativeInventory, Error> {
+        let mut inventory = NativeInventory::default();
+
+        for i in 0..self.handler.len() {
+            let mut current_file = self
+                .handler
+                .by_index(i)
+                .context("could not read ZIP entry")?;
+            let path = current_file.name().to_string();
+            let abi = match native::library_abi(&path) {
+                Some(abi) => abi.to_string(),
+                None => continue,
+            };
+
+            let stored = current_file.compression() == CompressionMethod::Stored;
+            let zip_aligned_16k = if stored {
+                Some(current_file.data_start() % native::PAGE_SIZE_16K == 0)
+            } else {
+                None
+            };
+
+            let mut contents = Vec::new();
+            current_file
+                .read_to_end(&mut contents)
+                .context(format!("could not read: {}", path))?;
+            let (elf, error) = match native::parse_elf(&contents) {
+                Ok(elf) => (Some(elf), None),
+                Err(e) => (None, Some(e.to_string())),
+            };
+
+            inventory.add(NativeLibrary {
+                path,
+                abi,
+                size: contents.len() as u64,
+                stored,
+                zip_aligned_16k,
+                elf,
+                error,
+            });
+        }
+
+        Ok(inventory)
+    }
+
+    /// Builds the reference graph between the resources of the APK and the manifest, the binary
+    /// XML files and the `const` literals on the dex files. Files which can not be scanned are
+    /// recorded on the diagnostics of the graph instead of failing the whole report.
+    pub fn resource_usage(&mut self) -> Result<ResourceUsage, Error> {
+        let resources = &*self.resources;
+
+        let mut usage = ResourceUsage::default();
+        let package_ids: Vec<u8> = resources
+            .packages
+            .keys()
+            .filter(|id| **id != 1)
+            .copied()
+            .collect();
+
+        for package_id in &package_ids {
+            usage.add_package(resources, *package_id);
+        }
+
+        for i in 0..self.handler.len() {
+            let (file_name, contents) = {
+                let mut current_file = self
+                    .handler
+                    .by_index(i)
+                    .context("could not read ZIP entry")?;
+                let mut contents = Vec::new();
+                current_file
+                    .read_to_end(&mut contents)
+                    .context(format!("could not read: {}", current_file.name()))?;
+
+                (current_file.name().to_string(), contents)
+            };
+
+            let is_resource_xml = file_name.ends_with(".xml")
+                && (file_name.starts_with("res/") || usage.get_file_owner(&file_name).is_some());
+
+            if is_resource_xml || file_name == "AndroidManifest.xml" {
+                let mut visitor = ReferenceVisitor::default();
+                match Executor::xml(Cursor::new(&contents), &mut visitor) {
+                    Ok(()) => usage.add_xml_references(&file_name, visitor.into_references()),
+                    Err(e) => usage.add_diagnostic(&file_name, format!("{:#}", e)),
+                }
+            } else if file_name.starts_with("classes") && file_name.ends_with(".dex") {
+                match dex::const_literals(&contents, &package_ids) {
+                    Ok(literals) => usage.add_dex_references(&file_name, literals),
+                    Err(e) => usage.add_diagnostic(&file_name, format!("{:#}", e)),
+                }
+            }
+        }
+
+        Ok(usage)
+    }
+
+    /// Reports the network security config and the backup rules the manifest points to, with
+    /// the defaults of the platform applied to them
+    pub fn privacy_report(&mut self) -> Result<PrivacyReport, Error> {
+        let resources = &*self.resources;
+
+        let contents = Self::read_file(&mut self.handler, "AndroidManifest.xml")?;
+        let manifest = XmlVisitor::decode(resources, &contents, self.mode)
+            .root
+            .ok_or_else(|| anyhow!("could not decode AndroidManifest.xml"))?;
+
+        // The resolver only gets shared access, but reading from the ZIP needs a mutable one
+        let handler = RefCell::new(&mut self.handler);
+        let resolver = TableResolver::new(resources, |path: &str| {
+            Self::read_file(&mut handler.borrow_mut(), path).ok()
+        });
+
+        PrivacyReport::from_manifest(&manifest, &resolver)
+    }
+
+    fn read_file(handler: &mut ZipArchive<Reader>, path: &str) -> Result<Vec<u8>, Error> {
+        let mut file = handler
+            .by_name(path)
+            .context(format!("could not find: {}", path))?;
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)
+            .context(format!("could not read: {}", path))?;
+
+        Ok(contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env,
+        fs::{self, File},
+        io::{Cursor, Write},
+        path::PathBuf,
+    };
+
+    use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+    use super::{Apk, ExportMode};
+    use crate::{
+        model::{
+            builder::Xml,
+            owned::{StringTableBuf, XmlTagEndBuf, XmlTagStartBuf},
+        },
+        test::example_arsc,
+    };
+
+    /// APK with the example resources and `layout/main` on its shortened path, `r/a.xml`
+    fn example_apk() -> Vec<u8> {
+        let mut xml = Xml::default();
+        let mut st = StringTableBuf::default();
+        st.add_string("LinearLayout".to_string());
+        xml.push_owned(Box::new(st));
+        xml.push_owned(Box::new(XmlTagStartBuf::new(2, 0, 0xFFFF_FFFF, 0, 0, 0)));
+        xml.push_owned(Box::new(XmlTagEndBuf::new(0)));
+
+        let files = [
+            ("resources.arsc", example_arsc()),
+            ("r/a.xml", xml.into_vec().unwrap()),
+        ];
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        for (name, contents) in &files {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(contents).unwrap();
+        }
+
+        zip.finish().unwrap().into_inner()
+    }
+
+    fn output_dir(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("abxml-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+
+        path
+    }
+
+    #[test]
+    fn it_exports_shortened_paths_as_apktool() {
+        let content = example_apk();
+        let mut apk = Apk::<File>::from_bytes(&content).unwrap();
+        apk.set_export_mode(ExportMode::Apktool);
+
+        let output = output_dir("apktool");
+        let problems = apk.export(&output, true).unwrap();
+        assert!(problems.is_empty());
+
+        let layout = fs::read_to_string(output.join("res/layout/main.xml")).unwrap();
+        assert!(layout.contains("<LinearLayout"));
+        assert!(!output.join("r/a.xml").exists());
+        assert!(!output.join("resources.arsc").exists());
+        assert!(output.join("res/values/strings.xml").exists());
+
+        fs::remove_dir_all(&output).unwrap();
+    }
+
+    #[test]
+    fn it_renames_shortened_paths_on_export() {
+        let content = example_apk();
+        let mut apk = Apk::<File>::from_bytes(&content).unwrap();
+
+        let paths = apk.resource_paths().unwrap();
+        assert_eq!("res/layout/main.xml", paths["r/a.xml"].path());
+
+        apk.set_rename_paths(true);
+        let files = apk.export_files().unwrap();
+        let layout = files
+            .iter()
+            .find(|file| file.name == "res/layout/main.xml")
+            .unwrap();
+        assert_eq!(Some("r/a.xml"), layout.original_name.as_deref());
+        assert!(layout.diagnostics.is_empty());
+        assert!(String::from_utf8_lossy(&layout.contents).contains("<LinearLayout"));
+
+        // The resource table is kept as is
+        let arsc = files
+            .iter()
+            .find(|file| file.name == "resources.arsc")
+            .unwrap();
+        assert_eq!(None, arsc.original_name);
+    }
 }
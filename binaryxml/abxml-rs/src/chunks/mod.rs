@@ -2,7 +2,7 @@
 use std::io::Cursor;
 
 use byteorder::{LittleEndian, ReadBytesExt};
-use anyhow::Error;
+use anyhow::{ensure, Context, Error};
 use log::error;
 
 mod chunk_header;
@@ -11,15 +11,17 @@ mod resource;
 pub mod string_table;
 pub mod table_type;
 mod table_type_spec;
+mod tree;
 mod xml;
 
 pub use self::{
     chunk_header::ChunkHeader,
     package::PackageWrapper,
     resource::ResourceWrapper,
-    string_table::{StringTableCache, StringTableWrapper},
+    string_table::{OwnedStringTable, StringTableCache, StringTableWrapper},
     table_type::{ConfigurationWrapper, TableTypeWrapper},
     table_type_spec::TypeSpecWrapper,
+    tree::{chunk_tree, ChunkField, ChunkNode},
     xml::{
         XmlNamespaceEndWrapper, XmlNamespaceStartWrapper, XmlTagEndWrapper, XmlTagStartWrapper,
         XmlTextWrapper,
@@ -56,6 +58,8 @@ pub enum Chunk<'a> {
 pub struct ChunkLoaderStream<'a> {
     cursor: Cursor<&'a [u8]>,
     previous: Option<u64>,
+    current_header: Option<ChunkHeader>,
+    failed: bool,
 }
 
 impl<'a> ChunkLoaderStream<'a> {
@@ -63,15 +67,35 @@ impl<'a> ChunkLoaderStream<'a> {
         Self {
             cursor,
             previous: None,
+            current_header: None,
+            failed: false,
         }
     }
 
+    /// Returns the header of the last chunk returned by the iterator
+    pub fn get_current_header(&self) -> Option<ChunkHeader> {
+        self.current_header
+    }
+
     fn read_one(&mut self) -> Result<Chunk<'a>, Error> {
         let initial_position = self.cursor.position();
         let token = self.cursor.read_u16::<LittleEndian>()?;
         let header_size = self.cursor.read_u16::<LittleEndian>()?;
         let chunk_size = self.cursor.read_u32::<LittleEndian>()?;
         let chunk_header = ChunkHeader::new(initial_position, header_size, chunk_size, token);
+        self.current_header = Some(chunk_header);
+
+        ensure!(
+            header_size >= 8 && u32::from(header_size) <= chunk_size,
+            "invalid header size {} on chunk of {} bytes",
+            header_size,
+            chunk_size
+        );
+        ensure!(
+            chunk_header.get_chunk_end() <= self.cursor.get_ref().len() as u64,
+            "chunk of {} bytes overflows the buffer",
+            chunk_size
+        );
 
         let chunk = self.get_chunk(&chunk_header);
 
@@ -117,6 +141,11 @@ impl<'a> Iterator for ChunkLoaderStream<'a> {
     type Item = Result<Chunk<'a>, Error>;
 
     fn next(&mut self) -> Option<Result<Chunk<'a>, Error>> {
+        // Once a chunk can not be read, there is no reliable way to find where the next one starts
+        if self.failed {
+            return None;
+        }
+
         if let Some(prev) = self.previous {
             if prev == self.cursor.position() {
                 return None;
@@ -127,8 +156,12 @@ impl<'a> Iterator for ChunkLoaderStream<'a> {
             return None;
         }
 
-        self.previous = Some(self.cursor.position());
-        let chunk = self.read_one();
+        let position = self.cursor.position();
+        self.previous = Some(position);
+        let chunk = self
+            .read_one()
+            .with_context(|| format!("could not read chunk at offset {}", position));
+        self.failed = chunk.is_err();
 
         Some(chunk)
     }
@@ -180,4 +213,24 @@ mod tests {
 
         assert!(stream.next().is_none());
     }
+
+    #[test]
+    fn it_fails_if_the_header_is_bigger_than_the_chunk() {
+        let data = vec![1, 0, 32, 0, 16, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        let cursor: Cursor<&[u8]> = Cursor::new(&data);
+        let mut stream = ChunkLoaderStream::new(cursor);
+
+        assert!(stream.next().unwrap().is_err());
+        assert!(stream.next().is_none());
+    }
+
+    #[test]
+    fn it_fails_if_the_chunk_overflows_the_buffer() {
+        let data = vec![1, 0, 8, 0, 0xFF, 0xFF, 0xFF, 0x7F, 0, 0, 0, 0];
+        let cursor: Cursor<&[u8]> = Cursor::new(&data);
+        let mut stream = ChunkLoaderStream::new(cursor);
+
+        assert!(stream.next().unwrap().is_err());
+        assert!(stream.next().is_none());
+    }
 }
@@ -1,11 +1,7 @@
 use std::{
-    cell::RefCell,
-    collections::{
-        hash_map::Entry::{Occupied, Vacant},
-        HashMap,
-    },
+    collections::HashMap,
     io::Cursor,
-    rc::Rc,
+    sync::{Arc, PoisonError, RwLock},
 };
 
 use byteorder::{LittleEndian, ReadBytesExt};
@@ -34,6 +30,13 @@ impl<'a> StringTableWrapper<'a> {
         cursor.read_u32::<LittleEndian>().unwrap_or(0)
     }
 
+    /// Copies the chunk, so the strings can be read after the document is dropped
+    pub fn to_owned_table(&self) -> OwnedStringTable {
+        OwnedStringTable {
+            raw_data: self.raw_data.into(),
+        }
+    }
+
     pub fn to_buffer(&self) -> Result<StringTableBuf, Error> {
         let mut owned = StringTableBuf::default();
 
@@ -51,24 +54,25 @@ impl<'a> StringTableWrapper<'a> {
 
     fn get_string_position(&self, idx: u32) -> Result<u64, Error> {
         let mut cursor = Cursor::new(self.raw_data);
+        cursor.set_position(2);
+        let header_size = cursor.read_u16::<LittleEndian>()?;
+        ensure!(header_size >= 28, "invalid string table header size");
+
         cursor.set_position(20);
         let str_offset = cursor.read_u32::<LittleEndian>()?;
 
-        cursor.set_position(28);
+        // The offsets table starts right after the header, with one entry per string, so any
+        // string can be found without walking the ones before it
+        let entry_position = u64::from(header_size) + u64::from(idx) * 4;
+        ensure!(
+            entry_position + 4 <= self.raw_data.len() as u64,
+            "string offset out of the string table"
+        );
 
-        let mut position = str_offset;
-        let mut max_offset = 0;
-
-        for _ in 0..=idx {
-            let current_offset = cursor.read_u32::<LittleEndian>()?;
-            position = str_offset.wrapping_add(current_offset);
-
-            if current_offset > max_offset {
-                max_offset = current_offset
-            }
-        }
+        cursor.set_position(entry_position);
+        let current_offset = cursor.read_u32::<LittleEndian>()?;
 
-        Ok(u64::from(position))
+        Ok(u64::from(str_offset) + u64::from(current_offset))
     }
 
     fn parse_string(&self, offset: u32) -> Result<String, Error> {
@@ -76,40 +80,16 @@ impl<'a> StringTableWrapper<'a> {
         cursor.set_position(u64::from(offset));
 
         if self.is_utf8() {
-            let mut ini_offset = offset;
-            let v = u32::from(cursor.read_u8()?);
-            if v == 0x80 {
-                ini_offset += 2;
-                cursor.read_u8()?;
-            } else {
-                ini_offset += 1;
-            }
-
-            let v = u32::from(cursor.read_u8()?);
-            if v == 0x80 {
-                ini_offset += 2;
-                cursor.read_u8()?;
-            } else {
-                ini_offset += 1;
-            }
-
-            let mut length = 0;
+            // Length in UTF-16 code units followed by the length in bytes. Only the latter is
+            // needed to decode the string.
+            let _characters = read_utf8_length(&mut cursor)?;
+            let bytes = read_utf8_length(&mut cursor)?;
 
-            loop {
-                let v = cursor.read_u8()?;
-
-                if v == 0 {
-                    break;
-                } else {
-                    length += 1;
-                }
-            }
-
-            let a = ini_offset;
-            let b = ini_offset + length;
+            let a = cursor.position();
+            let b = a + u64::from(bytes);
 
             ensure!(
-                a <= self.raw_data.len() as u32 && b <= self.raw_data.len() as u32 && a <= b,
+                b <= self.raw_data.len() as u64,
                 "sub-slice out of raw_data range"
             );
 
@@ -126,16 +106,13 @@ impl<'a> StringTableWrapper<'a> {
                 Err(format_err!("error decoding UTF8 string"))
             }
         } else {
-            let size1 = u32::from(cursor.read_u8()?);
-            let size2 = u32::from(cursor.read_u8()?);
-
-            let val = ((size2 & 0xFF) << 8) | size1 & 0xFF;
+            let units = read_utf16_length(&mut cursor)?;
 
-            let a = offset + 2;
-            let b = a + val * 2;
+            let a = cursor.position();
+            let b = a + u64::from(units) * 2;
 
             ensure!(
-                a <= self.raw_data.len() as u32 && b <= self.raw_data.len() as u32 && a <= b,
+                b <= self.raw_data.len() as u64,
                 "sub-slice out of raw_data range"
             );
 
@@ -173,28 +150,89 @@ impl<'a> StringTable for StringTableWrapper<'a> {
         cursor.read_u32::<LittleEndian>().unwrap_or(0)
     }
 
-    fn get_string(&self, idx: u32) -> Result<Rc<String>, Error> {
-        ensure!(idx <= self.get_strings_len(), "index out of bounds");
+    fn get_string(&self, idx: u32) -> Result<Arc<String>, Error> {
+        ensure!(idx < self.get_strings_len(), "index out of bounds");
+
+        let position = self.get_string_position(idx)?;
+        ensure!(
+            position < self.raw_data.len() as u64,
+            "string {} out of the string table",
+            idx
+        );
+        let string = self.parse_string(position as u32)?;
+
+        Ok(Arc::new(string))
+    }
+}
+
+/// Reads a length on a UTF-8 string pool: one byte, or two if the high bit of the first one is
+/// set.
+fn read_utf8_length(cursor: &mut Cursor<&[u8]>) -> Result<u32, Error> {
+    let first = u32::from(cursor.read_u8()?);
+
+    if first & 0x80 == 0 {
+        Ok(first)
+    } else {
+        let second = u32::from(cursor.read_u8()?);
+
+        Ok(((first & 0x7F) << 8) | second)
+    }
+}
+
+/// Reads a length on a UTF-16 string pool: one word, or two if the high bit of the first one is
+/// set.
+fn read_utf16_length(cursor: &mut Cursor<&[u8]>) -> Result<u32, Error> {
+    let first = u32::from(cursor.read_u16::<LittleEndian>()?);
 
-        let string = self
-            .get_string_position(idx)
-            .and_then(|position| self.parse_string(position as u32))?;
+    if first & 0x8000 == 0 {
+        Ok(first)
+    } else {
+        let second = u32::from(cursor.read_u16::<LittleEndian>()?);
 
-        Ok(Rc::new(string))
+        Ok(((first & 0x7FFF) << 16) | second)
     }
 }
 
+/// A string table chunk copied out of its document. Strings are decoded on demand, the same way
+/// `StringTableWrapper` does.
+#[derive(Debug, Clone)]
+pub struct OwnedStringTable {
+    raw_data: Box<[u8]>,
+}
+
+impl OwnedStringTable {
+    fn as_wrapper(&self) -> StringTableWrapper<'_> {
+        StringTableWrapper::new(&self.raw_data)
+    }
+}
+
+impl StringTable for OwnedStringTable {
+    fn get_strings_len(&self) -> u32 {
+        self.as_wrapper().get_strings_len()
+    }
+
+    fn get_styles_len(&self) -> u32 {
+        self.as_wrapper().get_styles_len()
+    }
+
+    fn get_string(&self, idx: u32) -> Result<Arc<String>, Error> {
+        self.as_wrapper().get_string(idx)
+    }
+}
+
+/// Keeps the strings already decoded by the inner table. It can be shared between threads: the
+/// strings are decoded without holding the lock, so lookups only wait on each other to insert.
 #[derive(Debug)]
 pub struct StringTableCache<S: StringTable> {
     inner: S,
-    cache: RefCell<HashMap<u32, Rc<String>>>,
+    cache: RwLock<HashMap<u32, Arc<String>>>,
 }
 
 impl<S: StringTable> StringTableCache<S> {
     pub fn new(inner: S) -> Self {
         Self {
             inner,
-            cache: RefCell::new(HashMap::new()),
+            cache: RwLock::new(HashMap::new()),
         }
     }
 }
@@ -208,18 +246,75 @@ impl<S: StringTable> StringTable for StringTableCache<S> {
         self.inner.get_styles_len()
     }
 
-    fn get_string(&self, idx: u32) -> Result<Rc<String>, Error> {
-        let mut cache = self.cache.borrow_mut();
-        let entry = cache.entry(idx);
+    fn get_string(&self, idx: u32) -> Result<Arc<String>, Error> {
+        // The cache is still consistent if another thread panicked while holding it
+        let cached = self
+            .cache
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&idx)
+            .cloned();
+        if let Some(string) = cached {
+            return Ok(string);
+        }
 
-        match entry {
-            Vacant(entry) => {
-                let string_ref = self.inner.get_string(idx)?;
-                entry.insert(string_ref.clone());
+        // Two threads may decode the same string: the first one inserted is kept
+        let string = self.inner.get_string(idx)?;
+        let mut cache = self.cache.write().unwrap_or_else(PoisonError::into_inner);
 
-                Ok(string_ref)
-            }
-            Occupied(entry) => Ok(entry.get().clone()),
+        Ok(cache.entry(idx).or_insert(string).clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    use super::StringTableWrapper;
+    use crate::model::StringTable;
+
+    /// UTF-8 string pool with the given strings data, each one at the given offset
+    fn utf8_pool(offsets: &[u32], data: &[u8]) -> Vec<u8> {
+        let strings_start = 28 + offsets.len() as u32 * 4;
+
+        let mut out = Vec::new();
+        out.write_u16::<LittleEndian>(0x0001).unwrap();
+        out.write_u16::<LittleEndian>(28).unwrap();
+        out.write_u32::<LittleEndian>(strings_start + data.len() as u32)
+            .unwrap();
+        out.write_u32::<LittleEndian>(offsets.len() as u32).unwrap();
+        out.write_u32::<LittleEndian>(0).unwrap();
+        out.write_u32::<LittleEndian>(0x100).unwrap();
+        out.write_u32::<LittleEndian>(strings_start).unwrap();
+        out.write_u32::<LittleEndian>(0).unwrap();
+        for offset in offsets {
+            out.write_u32::<LittleEndian>(*offset).unwrap();
         }
+        out.extend_from_slice(data);
+
+        out
+    }
+
+    #[test]
+    fn it_reads_two_bytes_utf8_lengths() {
+        // 0x81 0x2C: 300 characters and bytes, whose length has the high bit set but is not 0x80
+        let mut data = vec![0x81, 0x2C, 0x81, 0x2C];
+        data.extend_from_slice(&[b'a'; 300]);
+        data.push(0);
+        let raw = utf8_pool(&[0], &data);
+
+        let string = StringTableWrapper::new(&raw).get_string(0).unwrap();
+        assert_eq!("a".repeat(300), *string);
+    }
+
+    #[test]
+    fn it_can_not_read_strings_out_of_the_pool() {
+        let raw = utf8_pool(&[0, 0xFFFF_FF00], &[1, 1, b'a', 0]);
+        let table = StringTableWrapper::new(&raw);
+
+        assert_eq!("a", *table.get_string(0).unwrap());
+        // The index that matches the amount of strings used to be accepted
+        assert!(table.get_string(2).is_err());
+        assert!(table.get_string(1).is_err());
     }
 }
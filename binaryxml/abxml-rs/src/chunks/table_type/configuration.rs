@@ -117,14 +117,20 @@ impl<'a> Configuration for ConfigurationWrapper<'a> {
 
     fn get_screen_layout(&self) -> Result<u8, Error> {
         let size = self.get_size()?;
-        ensure!(size >= 28, "not enough bytes to retrieve the field");
+        ensure!(
+            size >= 28 && self.slice.len() > 28,
+            "not enough bytes to retrieve the field"
+        );
 
         Ok(self.slice[28])
     }
 
     fn get_ui_mode(&self) -> Result<u8, Error> {
         let size = self.get_size()?;
-        ensure!(size >= 29, "not enough bytes to retrieve the field");
+        ensure!(
+            size >= 29 && self.slice.len() > 29,
+            "not enough bytes to retrieve the field"
+        );
 
         Ok(self.slice[29])
     }
@@ -272,4 +278,22 @@ mod tests {
         assert_eq!(0, wrapper.get_width().unwrap());
         assert_eq!(0, wrapper.get_height().unwrap());
     }
+
+    #[test]
+    fn it_formats_the_qualifiers_of_a_configuration() {
+        let wrapper = ConfigurationWrapper::new(EXAMPLE_CONFIGURATION);
+
+        assert_eq!("mcc310-mnc800-bs-rBA", wrapper.get_qualifiers());
+
+        let mut raw = [0u8; 36];
+        raw[0] = 36;
+        raw[8..12].copy_from_slice(b"enUS");
+        raw[12] = 2;
+        raw[14..16].copy_from_slice(&320u16.to_le_bytes());
+        raw[24] = 21;
+        raw[29] = 0x20;
+        let wrapper = ConfigurationWrapper::new(&raw);
+
+        assert_eq!("en-rUS-land-night-xhdpi-v21", wrapper.get_qualifiers());
+    }
 }
@@ -1,11 +1,11 @@
 use std::io::Cursor;
 
 use byteorder::{LittleEndian, ReadBytesExt};
-use anyhow::{ensure, format_err, Error};
+use anyhow::{ensure, format_err, Context, Error};
 use log::debug;
 
 use crate::model::{
-    owned::{ComplexEntry, Entry, EntryHeader, SimpleEntry, TableTypeBuf},
+    owned::{ComplexEntry, Entry, EntryHeader, SimpleEntry, TableTypeBuf, TypeFlags},
     TableType,
 };
 
@@ -29,18 +29,25 @@ impl<'a> TableTypeWrapper<'a> {
 
     pub fn to_buffer(&self) -> Result<TableTypeBuf, Error> {
         let id = self.get_id()?;
-        let amount = self.get_amount()?;
         let config = self.get_configuration()?.to_buffer()?;
         let mut owned = TableTypeBuf::new(id & 0xF, config);
 
-        for i in 0..amount {
-            let entry = self.get_entry(i)?;
+        // Sparse and 16 bits offset chunks are written back as regular ones
+        for entry in self.get_entries()? {
             owned.add_entry(entry);
         }
 
         Ok(owned)
     }
 
+    /// Flags of the chunk, which tell how its entries are laid out
+    pub fn get_flags(&self) -> Result<TypeFlags, Error> {
+        let mut cursor = Cursor::new(self.raw_data);
+        cursor.set_position(9);
+
+        Ok(TypeFlags::new(cursor.read_u8()?))
+    }
+
     pub fn get_entries(&self) -> Result<Vec<Entry>, Error> {
         let mut cursor = Cursor::new(self.raw_data);
         cursor.set_position(self.data_offset);
@@ -48,33 +55,89 @@ impl<'a> TableTypeWrapper<'a> {
         self.decode_entries(&mut cursor)
     }
 
+    /// Decodes the entries of the chunk, indexed by their ID. The offsets to the entries are
+    /// either 32 bits (`0xFFFFFFFF` for missing entries), 16 bits counted in units of 4 bytes
+    /// (`0xFFFF` for missing entries) or, on sparse chunks, pairs of 16 bits index and offset for
+    /// the present entries only.
     fn decode_entries(&self, cursor: &mut Cursor<&[u8]>) -> Result<Vec<Entry>, Error> {
-        let mut offsets = Vec::new();
-        let mut entries = Vec::new();
-
-        for _ in 0..self.get_amount()? {
-            offsets.push(cursor.read_u32::<LittleEndian>()?);
-        }
+        let flags = self.get_flags()?;
+        let amount = self.get_amount()?;
+        let entries_start = self.get_entries_start()?;
 
-        for i in 0..self.get_amount()? {
-            let id = i & 0xFFFF;
+        // Remaining bytes bound the amount of offsets, whatever the header says
+        let remaining = (cursor.get_ref().len() as u64).saturating_sub(cursor.position());
+        let offset_size = if flags.is_offset16() && !flags.is_sparse() {
+            2
+        } else {
+            4
+        };
+        ensure!(
+            u64::from(amount) * offset_size <= remaining,
+            "table type with {} entries overflows the chunk",
+            amount
+        );
 
-            if offsets[i as usize] == 0xFFFF_FFFF {
-                entries.push(Entry::Empty(id, id));
+        let mut offsets: Vec<Option<u64>> = Vec::with_capacity(amount as usize);
+        for i in 0..amount {
+            if flags.is_sparse() {
+                let index = usize::from(cursor.read_u16::<LittleEndian>()?);
+                let offset = u64::from(cursor.read_u16::<LittleEndian>()?) * 4;
+                ensure!(
+                    index >= offsets.len(),
+                    "sparse entry {} is not sorted by index",
+                    i
+                );
+                offsets.resize(index, None);
+                offsets.push(Some(offset));
+            } else if flags.is_offset16() {
+                let offset = cursor.read_u16::<LittleEndian>()?;
+                offsets.push(if offset == 0xFFFF {
+                    None
+                } else {
+                    Some(u64::from(offset) * 4)
+                });
             } else {
-                let maybe_entry = Self::decode_entry(cursor, id)?;
-
-                if let Some(e) = maybe_entry {
-                    entries.push(e);
+                let offset = cursor.read_u32::<LittleEndian>()?;
+                offsets.push(if offset == 0xFFFF_FFFF {
+                    None
                 } else {
-                    debug!("Entry with a negative count");
-                }
+                    Some(u64::from(offset))
+                });
             }
         }
 
+        let mut entries = Vec::with_capacity(offsets.len());
+        for (i, offset) in offsets.into_iter().enumerate() {
+            let id = i as u32 & 0xFFFF;
+
+            let entry = match offset {
+                Some(offset) => {
+                    cursor.set_position(u64::from(entries_start) + offset);
+                    Self::decode_entry(cursor, id)?
+                }
+                None => Some(Entry::Empty(id, id)),
+            };
+
+            // Keep the position of the entry, so the following ones keep their ID
+            entries.push(entry.unwrap_or_else(|| {
+                debug!("Entry with a negative count");
+                Entry::Empty(id, id)
+            }));
+        }
+
         Ok(entries)
     }
 
+    /// Offset of the entries data, from the start of the chunk
+    fn get_entries_start(&self) -> Result<u32, Error> {
+        let mut cursor = Cursor::new(self.raw_data);
+        cursor.set_position(16);
+
+        cursor
+            .read_u32::<LittleEndian>()
+            .context("could not read the start of the entries")
+    }
+
     fn decode_entry(cursor: &mut Cursor<&[u8]>, id: u32) -> Result<Option<Entry>, Error> {
         let header_size = cursor.read_u16::<LittleEndian>()?;
         let flags = cursor.read_u16::<LittleEndian>()?;
@@ -112,12 +175,20 @@ impl<'a> TableTypeWrapper<'a> {
     ) -> Result<Option<Entry>, Error> {
         let parent_entry = cursor.read_u32::<LittleEndian>()?;
         let value_count = cursor.read_u32::<LittleEndian>()?;
-        let mut entries = Vec::with_capacity(value_count as usize);
 
         if value_count == 0xFFFF_FFFF {
             return Ok(None);
         }
 
+        // Each value takes 12 bytes: do not trust the count beyond what the chunk can hold
+        let remaining = (cursor.get_ref().len() as u64).saturating_sub(cursor.position());
+        ensure!(
+            u64::from(value_count) * 12 <= remaining,
+            "complex entry with {} values overflows the chunk",
+            value_count
+        );
+        let mut entries = Vec::with_capacity(value_count as usize);
+
         for _ in 0..value_count {
             let val_id = cursor.read_u32::<LittleEndian>()?;
             cursor.read_u16::<LittleEndian>()?;
@@ -182,3 +253,118 @@ impl<'a> TableType for TableTypeWrapper<'a> {
             .ok_or_else(|| format_err!("entry not found"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    use super::TableTypeWrapper;
+    use crate::model::owned::{Entry, OwnedBuf};
+
+    const HEADER_SIZE: u16 = 20 + 36;
+
+    /// Table type chunk with an empty configuration, the given offsets and two simple entries
+    fn chunk(flags: u8, amount: u32, offsets: &[u8]) -> Vec<u8> {
+        let entries_start = u32::from(HEADER_SIZE) + offsets.len() as u32;
+
+        let mut out = Vec::new();
+        out.write_u16::<LittleEndian>(0x0201).unwrap();
+        out.write_u16::<LittleEndian>(HEADER_SIZE).unwrap();
+        out.write_u32::<LittleEndian>(entries_start + 32).unwrap();
+        out.write_u8(1).unwrap();
+        out.write_u8(flags).unwrap();
+        out.write_u16::<LittleEndian>(0).unwrap();
+        out.write_u32::<LittleEndian>(amount).unwrap();
+        out.write_u32::<LittleEndian>(entries_start).unwrap();
+        out.write_u32::<LittleEndian>(36).unwrap();
+        out.extend_from_slice(&[0; 32]);
+        out.extend_from_slice(offsets);
+
+        for (key, data) in &[(10, 0xAAAA), (11, 0xBBBB)] {
+            out.write_u16::<LittleEndian>(8).unwrap();
+            out.write_u16::<LittleEndian>(0).unwrap();
+            out.write_u32::<LittleEndian>(*key).unwrap();
+            out.write_u16::<LittleEndian>(8).unwrap();
+            out.write_u8(0).unwrap();
+            out.write_u8(0x10).unwrap();
+            out.write_u32::<LittleEndian>(*data).unwrap();
+        }
+
+        out
+    }
+
+    fn summary(entries: &[Entry]) -> Vec<(u32, u32, Option<u32>)> {
+        entries
+            .iter()
+            .map(|entry| (entry.get_id(), entry.get_key(), entry.get_value()))
+            .collect()
+    }
+
+    #[test]
+    fn it_decodes_entries_by_their_offset() {
+        // The second entry is stored before the first one
+        let raw = chunk(0, 3, &[16, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF, 0, 0, 0, 0]);
+        let entries = TableTypeWrapper::new(&raw, u64::from(HEADER_SIZE))
+            .get_entries()
+            .unwrap();
+
+        assert_eq!(
+            vec![(0, 11, Some(0xBBBB)), (1, 1, None), (2, 10, Some(0xAAAA))],
+            summary(&entries)
+        );
+    }
+
+    #[test]
+    fn it_decodes_offset16_entries() {
+        let raw = chunk(0x02, 3, &[0, 0, 0xFF, 0xFF, 4, 0]);
+        let entries = TableTypeWrapper::new(&raw, u64::from(HEADER_SIZE))
+            .get_entries()
+            .unwrap();
+
+        assert_eq!(
+            vec![(0, 10, Some(0xAAAA)), (1, 1, None), (2, 11, Some(0xBBBB))],
+            summary(&entries)
+        );
+    }
+
+    #[test]
+    fn it_decodes_sparse_entries() {
+        let raw = chunk(0x01, 2, &[1, 0, 0, 0, 4, 0, 4, 0]);
+        let wrapper = TableTypeWrapper::new(&raw, u64::from(HEADER_SIZE));
+        let entries = wrapper.get_entries().unwrap();
+
+        assert_eq!(
+            vec![
+                (0, 0, None),
+                (1, 10, Some(0xAAAA)),
+                (2, 2, None),
+                (3, 3, None),
+                (4, 11, Some(0xBBBB))
+            ],
+            summary(&entries)
+        );
+
+        // Written back with regular offsets
+        let raw = wrapper.to_buffer().unwrap().to_vec().unwrap();
+        let entries = TableTypeWrapper::new(&raw, u64::from(HEADER_SIZE))
+            .get_entries()
+            .unwrap();
+        assert_eq!(5, entries.len());
+        assert_eq!(Some(0xBBBB), entries[4].get_value());
+    }
+
+    #[test]
+    fn it_can_not_decode_complex_entries_with_more_values_than_the_chunk() {
+        let mut raw = chunk(0, 1, &[0, 0, 0, 0]);
+        let entry = usize::from(HEADER_SIZE) + 4;
+        // Complex flag and value count of the first entry
+        raw[entry + 2] = 1;
+        (&mut raw[entry + 12..entry + 16])
+            .write_u32::<LittleEndian>(0xFFFF_FFF0)
+            .unwrap();
+
+        assert!(TableTypeWrapper::new(&raw, u64::from(HEADER_SIZE))
+            .get_entries()
+            .is_err());
+    }
+}
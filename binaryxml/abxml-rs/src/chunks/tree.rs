@@ -0,0 +1,371 @@
+//! Raw view of the chunk structure of a binary document, meant to inspect files that can not be
+//! decoded.
+
+use std::{fmt::Write, io::Cursor};
+
+use byteorder::{LittleEndian, ReadBytesExt};
+use serde::Serialize;
+
+use super::{
+    ChunkHeader, PackageWrapper, TOKEN_PACKAGE, TOKEN_RESOURCE, TOKEN_STRING_TABLE,
+    TOKEN_TABLE_SPEC, TOKEN_TABLE_TYPE, TOKEN_XML_END_NAMESPACE, TOKEN_XML_START_NAMESPACE,
+    TOKEN_XML_TAG_END, TOKEN_XML_TAG_START, TOKEN_XML_TEXT,
+};
+
+const TOKEN_TABLE: u16 = 0x0002;
+const TOKEN_XML: u16 = 0x0003;
+const TOKEN_TABLE_LIBRARY: u16 = 0x0203;
+const TOKEN_TABLE_OVERLAYABLE: u16 = 0x0204;
+const TOKEN_TABLE_OVERLAYABLE_POLICY: u16 = 0x0205;
+const TOKEN_TABLE_STAGED_ALIAS: u16 = 0x0206;
+
+/// Maximum amount of bytes of an unknown chunk included on its dump
+const MAX_HEX_BYTES: usize = 4096;
+/// Maximum nesting of container chunks. Valid files nest up to two levels (table and package),
+/// so anything deeper is garbage that would otherwise overflow the stack.
+const MAX_DEPTH: usize = 8;
+
+/// A decoded field of a chunk header
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChunkField {
+    pub name: &'static str,
+    pub value: String,
+}
+
+/// A chunk and all the chunks it contains
+#[derive(Debug, Clone, Serialize)]
+pub struct ChunkNode {
+    pub chunk_type: u16,
+    pub type_name: &'static str,
+    pub offset: u64,
+    pub header_size: u16,
+    pub size: u32,
+    pub fields: Vec<ChunkField>,
+    pub children: Vec<ChunkNode>,
+    /// Contents of unknown chunks, as hexadecimal (capped to the first 4096 bytes)
+    pub data: Option<String>,
+    /// Problem found reading this chunk or its children
+    pub error: Option<String>,
+}
+
+/// Returns the chunk tree of the given `resources.arsc` or binary XML file. It never fails: the
+/// chunks that can not be read are returned with an error, and the walk stops on them.
+pub fn chunk_tree(buffer: &[u8]) -> Vec<ChunkNode> {
+    read_chunks(buffer, 0, buffer.len() as u64, 0)
+}
+
+fn read_chunks(buffer: &[u8], start: u64, end: u64, depth: usize) -> Vec<ChunkNode> {
+    let mut nodes = Vec::new();
+    let mut position = start;
+
+    while position + 8 <= end {
+        let mut cursor = Cursor::new(buffer);
+        cursor.set_position(position);
+
+        // The bounds were checked above
+        let token = cursor.read_u16::<LittleEndian>().unwrap_or(0);
+        let header_size = cursor.read_u16::<LittleEndian>().unwrap_or(0);
+        let chunk_size = cursor.read_u32::<LittleEndian>().unwrap_or(0);
+        let header = ChunkHeader::new(position, header_size, chunk_size, token);
+
+        let mut node = ChunkNode {
+            chunk_type: token,
+            type_name: type_name(token),
+            offset: position,
+            header_size,
+            size: chunk_size,
+            fields: Vec::new(),
+            children: Vec::new(),
+            data: None,
+            error: None,
+        };
+
+        if header_size < 8 || u32::from(header_size) > chunk_size || header.get_chunk_end() > end {
+            node.error = Some(format!(
+                "invalid chunk: header of {} bytes, chunk of {} bytes, {} bytes available",
+                header_size,
+                chunk_size,
+                end - position
+            ));
+            nodes.push(node);
+            break;
+        }
+
+        let raw = &buffer[position as usize..header.get_chunk_end() as usize];
+        node.fields = decode_fields(token, raw, header_size);
+
+        match token {
+            TOKEN_TABLE | TOKEN_XML | TOKEN_PACKAGE if depth >= MAX_DEPTH => {
+                node.error = Some(format!(
+                    "chunks nested more than {} levels, children not read",
+                    MAX_DEPTH
+                ));
+            }
+            TOKEN_TABLE | TOKEN_XML | TOKEN_PACKAGE => {
+                node.children = read_chunks(
+                    buffer,
+                    header.get_data_offset(),
+                    header.get_chunk_end(),
+                    depth + 1,
+                );
+            }
+            TOKEN_STRING_TABLE
+            | TOKEN_RESOURCE
+            | TOKEN_TABLE_TYPE
+            | TOKEN_TABLE_SPEC
+            | TOKEN_TABLE_LIBRARY
+            | TOKEN_TABLE_OVERLAYABLE
+            | TOKEN_TABLE_OVERLAYABLE_POLICY
+            | TOKEN_TABLE_STAGED_ALIAS
+            | TOKEN_XML_START_NAMESPACE
+            | TOKEN_XML_END_NAMESPACE
+            | TOKEN_XML_TAG_START
+            | TOKEN_XML_TAG_END
+            | TOKEN_XML_TEXT => (),
+            _ => node.data = Some(to_hex(raw)),
+        }
+
+        nodes.push(node);
+        position = header.get_chunk_end();
+    }
+
+    nodes
+}
+
+fn type_name(token: u16) -> &'static str {
+    match token {
+        TOKEN_STRING_TABLE => "string_pool",
+        TOKEN_TABLE => "table",
+        TOKEN_XML => "xml",
+        TOKEN_RESOURCE => "xml_resource_map",
+        TOKEN_PACKAGE => "table_package",
+        TOKEN_TABLE_TYPE => "table_type",
+        TOKEN_TABLE_SPEC => "table_type_spec",
+        TOKEN_TABLE_LIBRARY => "table_library",
+        TOKEN_TABLE_OVERLAYABLE => "table_overlayable",
+        TOKEN_TABLE_OVERLAYABLE_POLICY => "table_overlayable_policy",
+        TOKEN_TABLE_STAGED_ALIAS => "table_staged_alias",
+        TOKEN_XML_START_NAMESPACE => "xml_start_namespace",
+        TOKEN_XML_END_NAMESPACE => "xml_end_namespace",
+        TOKEN_XML_TAG_START => "xml_start_element",
+        TOKEN_XML_TAG_END => "xml_end_element",
+        TOKEN_XML_TEXT => "xml_cdata",
+        _ => "unknown",
+    }
+}
+
+/// Layout of the header fields after the common 8 bytes: name, offset and width in bytes
+fn field_layout(token: u16) -> &'static [(&'static str, u64, u8)] {
+    match token {
+        TOKEN_STRING_TABLE => &[
+            ("string_count", 8, 4),
+            ("style_count", 12, 4),
+            ("flags", 16, 4),
+            ("strings_start", 20, 4),
+            ("styles_start", 24, 4),
+        ],
+        TOKEN_TABLE => &[("package_count", 8, 4)],
+        TOKEN_PACKAGE => &[
+            ("id", 8, 4),
+            ("type_strings", 268, 4),
+            ("last_public_type", 272, 4),
+            ("key_strings", 276, 4),
+            ("last_public_key", 280, 4),
+            ("type_id_offset", 284, 4),
+        ],
+        TOKEN_TABLE_TYPE => &[
+            ("id", 8, 1),
+            ("flags", 9, 1),
+            ("reserved", 10, 2),
+            ("entry_count", 12, 4),
+            ("entries_start", 16, 4),
+            ("config_size", 20, 4),
+        ],
+        TOKEN_TABLE_SPEC => &[
+            ("id", 8, 1),
+            ("res0", 9, 1),
+            ("types_count", 10, 2),
+            ("entry_count", 12, 4),
+        ],
+        TOKEN_TABLE_LIBRARY | TOKEN_TABLE_STAGED_ALIAS => &[("count", 8, 4)],
+        TOKEN_TABLE_OVERLAYABLE_POLICY => &[("policy_flags", 8, 4), ("entry_count", 12, 4)],
+        TOKEN_XML_START_NAMESPACE | TOKEN_XML_END_NAMESPACE => &[
+            ("line", 8, 4),
+            ("comment", 12, 4),
+            ("prefix", 16, 4),
+            ("uri", 20, 4),
+        ],
+        TOKEN_XML_TAG_START => &[
+            ("line", 8, 4),
+            ("comment", 12, 4),
+            ("namespace", 16, 4),
+            ("name", 20, 4),
+            ("attribute_start", 24, 2),
+            ("attribute_size", 26, 2),
+            ("attribute_count", 28, 2),
+            ("id_index", 30, 2),
+            ("class_index", 32, 2),
+            ("style_index", 34, 2),
+        ],
+        TOKEN_XML_TAG_END => &[
+            ("line", 8, 4),
+            ("comment", 12, 4),
+            ("namespace", 16, 4),
+            ("name", 20, 4),
+        ],
+        TOKEN_XML_TEXT => &[("line", 8, 4), ("comment", 12, 4), ("data", 16, 4)],
+        _ => &[],
+    }
+}
+
+fn decode_fields(token: u16, raw: &[u8], header_size: u16) -> Vec<ChunkField> {
+    let mut fields = Vec::new();
+    let mut cursor = Cursor::new(raw);
+
+    for (name, offset, width) in field_layout(token) {
+        // Fields of the start/end element and namespace chunks live on the body
+        let limit = match token {
+            TOKEN_XML_START_NAMESPACE
+            | TOKEN_XML_END_NAMESPACE
+            | TOKEN_XML_TAG_START
+            | TOKEN_XML_TAG_END
+            | TOKEN_XML_TEXT => raw.len() as u64,
+            _ => u64::from(header_size),
+        };
+        if offset + u64::from(*width) > limit {
+            continue;
+        }
+
+        cursor.set_position(*offset);
+        let value = match width {
+            1 => cursor.read_u8().map(u32::from),
+            2 => cursor.read_u16::<LittleEndian>().map(u32::from),
+            _ => cursor.read_u32::<LittleEndian>(),
+        };
+
+        if let Ok(value) = value {
+            let value = match *name {
+                "flags" | "policy_flags" => format!("0x{:x}", value),
+                _ => value.to_string(),
+            };
+            fields.push(ChunkField { name, value });
+        }
+    }
+
+    match token {
+        TOKEN_PACKAGE if header_size >= 268 => {
+            if let Ok(name) = PackageWrapper::new(raw).get_name() {
+                fields.insert(
+                    1,
+                    ChunkField {
+                        name: "name",
+                        value: name,
+                    },
+                );
+            }
+        }
+        TOKEN_RESOURCE => fields.push(ChunkField {
+            name: "resource_count",
+            value: ((raw.len() - usize::from(header_size)) / 4).to_string(),
+        }),
+        _ => (),
+    }
+
+    fields
+}
+
+fn to_hex(raw: &[u8]) -> String {
+    let mut out = String::with_capacity(raw.len().min(MAX_HEX_BYTES) * 2);
+
+    for byte in raw.iter().take(MAX_HEX_BYTES) {
+        let _ = write!(out, "{:02x}", byte);
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::chunk_tree;
+    use crate::model::{
+        builder::Xml,
+        owned::{StringTableBuf, XmlTagEndBuf, XmlTagStartBuf},
+    };
+
+    #[test]
+    fn it_dumps_the_chunk_tree_of_a_binary_xml() {
+        let mut xml = Xml::default();
+        let mut st = StringTableBuf::default();
+        st.add_string("manifest".to_string());
+        xml.push_owned(Box::new(st));
+        xml.push_owned(Box::new(XmlTagStartBuf::new(
+            7,
+            0,
+            0xFFFF_FFFF,
+            0,
+            0x0014_0014,
+            0,
+        )));
+        xml.push_owned(Box::new(XmlTagEndBuf::new(0)));
+
+        let mut content = xml.into_vec().unwrap();
+        // Unknown chunk of 12 bytes at the end of the document
+        content.extend(&[0x34, 0x12, 8, 0, 12, 0, 0, 0, 0xde, 0xad, 0xbe, 0xef]);
+        let size = content.len() as u32;
+        content[4..8].copy_from_slice(&size.to_le_bytes());
+
+        let tree = chunk_tree(&content);
+        assert_eq!(1, tree.len());
+        assert_eq!("xml", tree[0].type_name);
+
+        let children = &tree[0].children;
+        let types: Vec<&str> = children.iter().map(|c| c.type_name).collect();
+        assert_eq!(
+            vec![
+                "string_pool",
+                "xml_start_element",
+                "xml_end_element",
+                "unknown"
+            ],
+            types
+        );
+        assert_eq!(8, children[0].offset);
+        assert!(children[1]
+            .fields
+            .iter()
+            .any(|f| f.name == "line" && f.value == "7"));
+        assert_eq!(
+            Some("341208000c000000deadbeef".to_string()),
+            children[3].data
+        );
+    }
+
+    #[test]
+    fn it_reports_invalid_chunks() {
+        let content = [3, 0, 8, 0, 24, 0, 0, 0, 1, 0, 28, 0, 0xff, 0, 0, 0];
+
+        let tree = chunk_tree(&content);
+        assert!(tree[0].error.is_some());
+    }
+
+    #[test]
+    fn it_stops_on_deeply_nested_chunks() {
+        let levels = 200_000;
+        let mut content = Vec::with_capacity(levels * 8);
+        for level in 0..levels {
+            let size = ((levels - level) * 8) as u32;
+            content.extend(&[3, 0, 8, 0]);
+            content.extend(&size.to_le_bytes());
+        }
+
+        let mut tree = chunk_tree(&content);
+        let mut depth = 0;
+        while tree[0].error.is_none() {
+            tree = tree.remove(0).children;
+            depth += 1;
+        }
+
+        assert_eq!(8, depth);
+        assert!(tree[0].children.is_empty());
+    }
+}
@@ -1,4 +1,4 @@
-use std::{io::Cursor, rc::Rc};
+use std::{io::Cursor, sync::Arc};
 
 use anyhow::{ensure, Context, Error};
 use byteorder::{LittleEndian, ReadBytesExt};
@@ -44,14 +44,14 @@ impl<'a> XmlNamespaceStartWrapper<'a> {
 }
 
 impl<'a> NamespaceStart for XmlNamespaceStartWrapper<'a> {
-    fn get_prefix<S: StringTable>(&self, string_table: &S) -> Result<Rc<String>, Error> {
+    fn get_prefix<S: StringTable>(&self, string_table: &S) -> Result<Arc<String>, Error> {
         let index = self.get_prefix_index()?;
         let string = string_table.get_string(index)?;
 
         Ok(string)
     }
 
-    fn get_namespace<S: StringTable>(&self, string_table: &S) -> Result<Rc<String>, Error> {
+    fn get_namespace<S: StringTable>(&self, string_table: &S) -> Result<Arc<String>, Error> {
         let index = self.get_namespace_index()?;
         let string = string_table.get_string(index)?;
 
@@ -110,14 +110,14 @@ impl<'a> NamespaceEnd for XmlNamespaceEndWrapper<'a> {
         Ok(cursor.read_u32::<LittleEndian>()?)
     }
 
-    fn get_prefix<S: StringTable>(&self, string_table: &S) -> Result<Rc<String>, Error> {
+    fn get_prefix<S: StringTable>(&self, string_table: &S) -> Result<Arc<String>, Error> {
         let index = self.get_prefix_index()?;
         let string = string_table.get_string(index)?;
 
         Ok(string)
     }
 
-    fn get_namespace<S: StringTable>(&self, string_table: &S) -> Result<Rc<String>, Error> {
+    fn get_namespace<S: StringTable>(&self, string_table: &S) -> Result<Arc<String>, Error> {
         let index = self.get_namespace_index()?;
         let string = string_table.get_string(index)?;
 
@@ -223,6 +223,45 @@ impl<'a> XmlTagStartWrapper<'a> {
         Self { raw_data }
     }
 
+    /// Offset of the first attribute, relative to the start of the attribute extension (byte 16)
+    pub fn get_attribute_start(&self) -> Result<u16, Error> {
+        let mut cursor = Cursor::new(self.raw_data);
+        cursor.set_position(24);
+
+        cursor
+            .read_u16::<LittleEndian>()
+            .context("could not get attribute start")
+    }
+
+    /// Size of each of the attributes
+    pub fn get_attribute_size(&self) -> Result<u16, Error> {
+        let mut cursor = Cursor::new(self.raw_data);
+        cursor.set_position(26);
+
+        cursor
+            .read_u16::<LittleEndian>()
+            .context("could not get attribute size")
+    }
+
+    /// Returns the attribute on the `index` position using the layout declared on the chunk
+    /// (`attributeStart` and `attributeSize`), which is what the Android runtime uses. Returns an
+    /// error if the declared layout does not fit on the chunk.
+    pub fn get_declared_attribute(&self, index: u32) -> Result<AttributeWrapper<'a>, Error> {
+        let start = 16 + u64::from(self.get_attribute_start()?);
+        let size = u64::from(self.get_attribute_size()?);
+        let initial_position = start + u64::from(index) * size;
+        let final_position = initial_position + 5 * 4;
+
+        ensure!(
+            self.raw_data.len() as u64 >= final_position,
+            "requested attribute out of bounds"
+        );
+
+        let slice = &self.raw_data[initial_position as usize..final_position as usize];
+
+        Ok(AttributeWrapper::new(slice))
+    }
+
     /// It converts the wrapper into a `XmlTagStartBuf` which can be later manipulated
     pub fn to_buffer(&self) -> Result<XmlTagStartBuf, Error> {
         let mut tag_start = XmlTagStartBuf::new(
@@ -5,7 +5,7 @@ use std::io::{Cursor, Read};
 use anyhow::{Context, Error};
 
 use crate::{
-    visitor::{Executor, ModelVisitor, Resources, XmlVisitor},
+    visitor::{DecodeMode, DecodedXml, Executor, ModelVisitor, Resources, XmlVisitor},
     STR_ARSC,
 };
 
@@ -65,6 +65,16 @@ impl<'a> Decoder<'a> {
         Ok(decoder)
     }
 
+    /// Loads only the Android framework resources, for documents whose resource table is not
+    /// available. References to application resources can not be resolved.
+    pub fn from_framework() -> Result<ModelVisitor<'static>, Error> {
+        let mut visitor = ModelVisitor::default();
+
+        Executor::arsc(STR_ARSC, &mut visitor).context("could not read Android lib resources")?;
+
+        Ok(visitor)
+    }
+
     pub fn from_arsc(buffer: &'a [u8]) -> Result<ModelVisitor<'a>, Error> {
         let mut visitor = ModelVisitor::default();
 
@@ -74,10 +84,15 @@ impl<'a> Decoder<'a> {
         Ok(visitor)
     }
 
-    pub fn get_resources(&self) -> &'a Resources {
+    pub fn get_resources(&self) -> &Resources {
         self.visitor.get_resources()
     }
 
+    /// Returns the decoded resources, which do not borrow the buffers of the decoder
+    pub fn into_resources(self) -> Resources {
+        self.visitor.into_resources()
+    }
+
     pub fn xml_visitor<T: AsRef<[u8]>>(&self, content: &'a T) -> Result<XmlVisitor, Error> {
         let cursor = Cursor::new(content.as_ref());
         let mut visitor = XmlVisitor::new(self.get_resources());
@@ -86,13 +101,26 @@ impl<'a> Decoder<'a> {
 
         Ok(visitor)
     }
+
+    /// Decodes a binary XML, collecting all the problems found instead of failing on the first
+    /// one. See `XmlVisitor::decode`.
+    pub fn decode_xml<T: AsRef<[u8]>>(&self, content: &'a T, mode: DecodeMode) -> DecodedXml {
+        XmlVisitor::decode(self.get_resources(), content.as_ref(), mode)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::io::Cursor;
+    use std::{io::Cursor, sync::Arc, thread};
 
     use super::BufferedDecoder;
+    use crate::{
+        model::{
+            builder::Xml,
+            owned::{StringTableBuf, XmlTagEndBuf, XmlTagStartBuf},
+        },
+        visitor::{DecodeMode, Resources, XmlVisitor},
+    };
 
     #[test]
     fn it_can_not_decode_an_empty_binary_xml() {
@@ -103,11 +131,31 @@ mod tests {
         let decoder = owned.get_decoder().unwrap();
 
         // Empty binary XML file
-        let another = vec![3, 0, 0, 0, 0, 0, 0, 0];
+        let another = vec![3, 0, 8, 0, 8, 0, 0, 0];
         let xml_result = decoder.xml_visitor(&another).unwrap().into_string();
         assert!(xml_result.is_err());
     }
 
+    #[test]
+    fn it_rejects_a_binary_xml_with_a_zero_header_size() {
+        let buffer = vec![2, 0, 12, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+        let owned = BufferedDecoder::from(buffer);
+        let decoder = owned.get_decoder().unwrap();
+
+        // It used to be read again and again from the same position
+        let another = vec![3, 0, 0, 0, 0, 0, 0, 0];
+        assert!(decoder.xml_visitor(&another).is_err());
+    }
+
+    #[test]
+    fn it_rejects_a_resource_table_with_a_zero_header_size() {
+        let buffer = vec![2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+        let owned = BufferedDecoder::from(buffer);
+        assert!(owned.get_decoder().is_err());
+    }
+
     #[test]
     fn it_can_create_a_buffer_decoder_from_read() {
         let buffer = vec![2, 0, 12, 0, 0, 0, 0, 0, 0, 0, 0, 0];
@@ -115,4 +163,41 @@ mod tests {
         let owned = BufferedDecoder::from_read(Cursor::new(buffer)).unwrap();
         let _ = owned.get_decoder().unwrap();
     }
+
+    #[test]
+    fn it_shares_the_resources_between_threads() {
+        fn assert_send_sync<T: Send + Sync + 'static>() {}
+        assert_send_sync::<Resources>();
+
+        let resources = {
+            let owned = BufferedDecoder::from(vec![2, 0, 12, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            Arc::new(owned.get_decoder().unwrap().into_resources())
+        };
+
+        let mut xml = Xml::default();
+        let mut st = StringTableBuf::default();
+        st.add_string("manifest".to_string());
+        xml.push_owned(Box::new(st));
+        let manifest = XmlTagStartBuf::new(1, 0, 0xFFFF_FFFF, 0, 0x0014_0014, 0);
+        xml.push_owned(Box::new(manifest));
+        xml.push_owned(Box::new(XmlTagEndBuf::new(0)));
+        let content = Arc::new(xml.into_vec().unwrap());
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let resources = Arc::clone(&resources);
+                let content = Arc::clone(&content);
+
+                thread::spawn(move || {
+                    XmlVisitor::decode(&resources, &content, DecodeMode::Strict)
+                        .xml
+                        .unwrap()
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            assert!(handle.join().unwrap().contains("<manifest"));
+        }
+    }
 }
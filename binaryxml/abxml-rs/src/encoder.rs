@@ -8,7 +8,10 @@ use xml::{
     writer::{EmitterConfig, EventWriter, XmlEvent},
 };
 
-use crate::model::{Element as AbxmlElement, Namespaces};
+use crate::{
+    model::{Element as AbxmlElement, Namespaces, Node},
+    visitor::PullEvent,
+};
 
 #[derive(Debug, Copy, Clone)]
 pub struct Xml;
@@ -20,12 +23,7 @@ impl Xml {
             .perform_indent(true)
             .create_writer(target);
 
-        let version = XmlVersion::Version10;
-        writer.write(XmlEvent::StartDocument {
-            version,
-            encoding: None,
-            standalone: Some(false),
-        })?;
+        Self::start_document(&mut writer)?;
         Self::encode_element(&mut writer, namespaces, element)
             .context("error decoding an element")?;
 
@@ -33,6 +31,64 @@ impl Xml {
         String::from_utf8(inner).context("could not export XML")
     }
 
+    /// Encodes the events of a `XmlPullParser`, without building the element tree
+    pub fn encode_events<I>(events: I) -> Result<String, Error>
+    where
+        I: IntoIterator<Item = Result<PullEvent, Error>>,
+    {
+        let target: Vec<u8> = Vec::new();
+        let mut writer = EmitterConfig::new()
+            .perform_indent(true)
+            .create_writer(target);
+        // Same as the elements of the tree, every tag declares the namespaces in scope
+        let mut namespaces: Vec<(String, String)> = Vec::new();
+
+        for event in events {
+            match event? {
+                PullEvent::StartDocument => Self::start_document(&mut writer)?,
+                PullEvent::StartNamespace { prefix, uri } => namespaces.push((prefix, uri)),
+                PullEvent::EndNamespace { .. } => {
+                    let _ = namespaces.pop();
+                }
+                PullEvent::StartTag {
+                    name, attributes, ..
+                } => {
+                    let names: Vec<String> = attributes
+                        .iter()
+                        .map(|attribute| attribute.get_qualified_name())
+                        .collect();
+                    let mut xml_element = XmlEvent::start_element(name.as_str());
+
+                    for (name, attribute) in names.iter().zip(&attributes) {
+                        xml_element = xml_element.attr(name.as_str(), &attribute.value);
+                    }
+
+                    for (prefix, uri) in &namespaces {
+                        xml_element = xml_element.ns(prefix.as_str(), uri.as_str());
+                    }
+
+                    writer.write(xml_element)?;
+                }
+                PullEvent::Text(text) => writer.write(XmlEvent::characters(&text))?,
+                PullEvent::EndTag { .. } => writer.write(XmlEvent::end_element())?,
+                PullEvent::EndDocument => (),
+            }
+        }
+
+        let inner = writer.into_inner();
+        String::from_utf8(inner).context("could not export XML")
+    }
+
+    fn start_document<W: Write>(writer: &mut EventWriter<W>) -> Result<(), Error> {
+        writer.write(XmlEvent::StartDocument {
+            version: XmlVersion::Version10,
+            encoding: None,
+            standalone: Some(false),
+        })?;
+
+        Ok(())
+    }
+
     fn encode_element<W: Write>(
         writer: &mut EventWriter<W>,
         namespaces: &Namespaces,
@@ -56,8 +112,13 @@ impl Xml {
 
         writer.write(xml_element)?;
 
-        for child in element.get_children() {
-            Self::encode_element(writer, namespaces, child)?;
+        for node in element.get_nodes() {
+            match node {
+                Node::Text(text) => writer.write(XmlEvent::characters(text))?,
+                Node::Element(index) => {
+                    Self::encode_element(writer, namespaces, &element.get_children()[*index])?
+                }
+            }
         }
 
         writer.write(XmlEvent::end_element())?;
@@ -65,3 +126,40 @@ impl Xml {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use super::Xml;
+    use crate::model::{Element, ElementContainer, Namespaces, Tag};
+
+    fn element(name: &str) -> Element {
+        Element::new(
+            Tag::new(Arc::new(name.to_string()), Vec::new()),
+            HashMap::new(),
+        )
+    }
+
+    #[test]
+    fn it_keeps_the_order_of_mixed_content() {
+        let mut container = ElementContainer::default();
+        container.start_element(element("string"));
+        container.add_text("Hello ");
+        container.start_element(element("b"));
+        container.add_text("x");
+        container.end_element();
+        container.add_text(" again");
+        container.end_element();
+
+        let root = container.into_root().unwrap();
+        assert_eq!("Hello  again", root.get_text());
+
+        let xml = Xml::encode(&Namespaces::new(), &root).unwrap();
+        assert!(
+            xml.ends_with("<string>Hello <b>x</b> again</string>"),
+            "{}",
+            xml
+        );
+    }
+}
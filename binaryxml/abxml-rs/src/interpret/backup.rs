@@ -0,0 +1,134 @@
+//! Backup rules: the `android:fullBackupContent` file of Auto Backup (`<full-backup-content>`) and
+//! the `android:dataExtractionRules` file which replaces it from Android 12
+//! (`<data-extraction-rules>`). Both list which files are included on or excluded from the backups.
+
+use anyhow::{ensure, Error};
+use serde::Serialize;
+
+use super::{attribute, boolean, children, is_tag};
+use crate::model::Element;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BackupRules {
+    /// Only on cloud backups: skip the backup if the device can not encrypt it on the client
+    pub disable_if_no_encryption_capabilities: Option<bool>,
+    /// Files which are backed up. If empty, all the files are, except the excluded ones.
+    pub include: Vec<BackupPath>,
+    pub exclude: Vec<BackupPath>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupPath {
+    /// `root`, `file`, `database`, `sharedpref`, `external`, or their device protected variants
+    /// (`device_file`...)
+    pub domain: String,
+    /// Path relative to the domain. `.` or a missing path mean the whole domain.
+    pub path: String,
+    /// Conditions the transport must meet to back the path up, like `clientSideEncryption`
+    pub require_flags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DataExtractionRules {
+    pub cloud_backup: Option<BackupRules>,
+    pub device_transfer: Option<BackupRules>,
+}
+
+impl BackupRules {
+    /// Reads a `<full-backup-content>` document
+    pub fn from_element(root: &Element) -> Result<Self, Error> {
+        ensure!(
+            is_tag(root, "full-backup-content"),
+            "expected a full backup content, found {}",
+            root.get_tag().get_name()
+        );
+
+        Ok(rules(root))
+    }
+}
+
+impl DataExtractionRules {
+    pub fn from_element(root: &Element) -> Result<Self, Error> {
+        ensure!(
+            is_tag(root, "data-extraction-rules"),
+            "expected data extraction rules, found {}",
+            root.get_tag().get_name()
+        );
+
+        Ok(Self {
+            cloud_backup: children(root, "cloud-backup").next().map(rules),
+            device_transfer: children(root, "device-transfer").next().map(rules),
+        })
+    }
+}
+
+fn rules(element: &Element) -> BackupRules {
+    BackupRules {
+        disable_if_no_encryption_capabilities: attribute(
+            element,
+            "disableIfNoEncryptionCapabilities",
+        )
+        .and_then(boolean),
+        include: children(element, "include").map(path).collect(),
+        exclude: children(element, "exclude").map(path).collect(),
+    }
+}
+
+fn path(element: &Element) -> BackupPath {
+    BackupPath {
+        domain: attribute(element, "domain").unwrap_or_default().to_string(),
+        path: attribute(element, "path").unwrap_or(".").to_string(),
+        require_flags: attribute(element, "requireFlags")
+            .map(|flags| flags.split('|').map(ToString::to_string).collect())
+            .unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BackupRules, DataExtractionRules};
+    use crate::interpret::tests::element;
+
+    #[test]
+    fn it_summarizes_backup_rules() {
+        let mut full = element("full-backup-content", &[]);
+        full.append(element(
+            "include",
+            &[
+                ("domain", "sharedpref"),
+                ("path", "settings.xml"),
+                (
+                    "requireFlags",
+                    "clientSideEncryption|deviceToDeviceTransfer",
+                ),
+            ],
+        ));
+        full.append(element("exclude", &[("domain", "database")]));
+
+        let rules = BackupRules::from_element(&full).unwrap();
+        assert_eq!("settings.xml", rules.include[0].path);
+        assert_eq!(
+            vec!["clientSideEncryption", "deviceToDeviceTransfer"],
+            rules.include[0].require_flags
+        );
+        assert_eq!("database", rules.exclude[0].domain);
+        assert_eq!(".", rules.exclude[0].path);
+
+        let mut extraction = element("data-extraction-rules", &[]);
+        let mut cloud = element(
+            "cloud-backup",
+            &[("disableIfNoEncryptionCapabilities", "true")],
+        );
+        cloud.append(element(
+            "exclude",
+            &[("domain", "root"), ("path", "tokens")],
+        ));
+        extraction.append(cloud);
+
+        let rules = DataExtractionRules::from_element(&extraction).unwrap();
+        let cloud = rules.cloud_backup.unwrap();
+        assert_eq!(Some(true), cloud.disable_if_no_encryption_capabilities);
+        assert_eq!("tokens", cloud.exclude[0].path);
+        assert!(rules.device_transfer.is_none());
+    }
+}
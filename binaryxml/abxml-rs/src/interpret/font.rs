@@ -0,0 +1,124 @@
+//! Font families (`res/font/`): the fonts bundled for each style and weight, or the downloadable
+//! font requested to a font provider.
+
+use anyhow::{ensure, Error};
+use serde::Serialize;
+
+use super::{attribute, children, is_tag};
+use crate::model::Element;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FontFamily {
+    /// Provider the font is downloaded from, if it is a downloadable font
+    pub provider: Option<FontProvider>,
+    pub fonts: Vec<Font>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FontProvider {
+    pub authority: Option<String>,
+    pub package: Option<String>,
+    pub query: Option<String>,
+    /// Reference to the array with the certificates of the provider
+    pub certificates: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Font {
+    /// Reference to the font file, like `@font/roboto_bold`
+    pub font: Option<String>,
+    /// `normal` or `italic`
+    pub style: String,
+    pub weight: Option<u32>,
+    pub ttc_index: Option<u32>,
+    pub variation_settings: Option<String>,
+}
+
+impl FontFamily {
+    pub fn from_element(root: &Element) -> Result<Self, Error> {
+        ensure!(
+            is_tag(root, "font-family"),
+            "expected a font family, found {}",
+            root.get_tag().get_name()
+        );
+
+        let value =
+            |element: &Element, name: &str| attribute(element, name).map(ToString::to_string);
+
+        let provider = FontProvider {
+            authority: value(root, "fontProviderAuthority"),
+            package: value(root, "fontProviderPackage"),
+            query: value(root, "fontProviderQuery"),
+            certificates: value(root, "fontProviderCerts"),
+        };
+        let is_downloadable = provider.authority.is_some() || provider.query.is_some();
+
+        let fonts = children(root, "font")
+            .map(|font| Font {
+                font: value(font, "font"),
+                style: match attribute(font, "fontStyle") {
+                    Some("italic") | Some("1") => "italic".to_string(),
+                    _ => "normal".to_string(),
+                },
+                weight: attribute(font, "fontWeight").and_then(|weight| weight.parse().ok()),
+                ttc_index: attribute(font, "ttcIndex").and_then(|index| index.parse().ok()),
+                variation_settings: value(font, "fontVariationSettings"),
+            })
+            .collect();
+
+        Ok(Self {
+            provider: if is_downloadable {
+                Some(provider)
+            } else {
+                None
+            },
+            fonts,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FontFamily;
+    use crate::interpret::tests::element;
+
+    #[test]
+    fn it_summarizes_a_font_family() {
+        let mut root = element("font-family", &[]);
+        root.append(element(
+            "font",
+            &[
+                ("android:font", "@font/lobster_regular"),
+                ("android:fontStyle", "normal"),
+                ("android:fontWeight", "400"),
+            ],
+        ));
+        root.append(element(
+            "font",
+            &[
+                ("app:font", "@font/lobster_italic"),
+                ("app:fontStyle", "1"),
+                ("app:fontWeight", "700"),
+            ],
+        ));
+
+        let family = FontFamily::from_element(&root).unwrap();
+        assert!(family.provider.is_none());
+        assert_eq!(
+            Some("@font/lobster_italic"),
+            family.fonts[1].font.as_deref()
+        );
+        assert_eq!("italic", family.fonts[1].style);
+        assert_eq!(Some(700), family.fonts[1].weight);
+
+        let downloadable = element(
+            "font-family",
+            &[
+                ("app:fontProviderAuthority", "com.google.android.gms.fonts"),
+                ("app:fontProviderQuery", "Lobster"),
+            ],
+        );
+        let family = FontFamily::from_element(&downloadable).unwrap();
+        assert_eq!(Some("Lobster"), family.provider.unwrap().query.as_deref());
+    }
+}
@@ -0,0 +1,289 @@
+//! Typed interpreters for the common families of `res/` XMLs. They work on the element tree
+//! produced by `XmlVisitor`, after its names and references have been resolved, and turn it into
+//! something more useful than the plain XML: vector drawables become SVG, navigation graphs become
+//! a DOT graph and font families, network security configs, backup rules and shortcuts become
+//! structured summaries. [`PrivacyReport`] puts together the network and backup settings an app
+//! declares on its manifest.
+//!
+//! aapt2 moves inline resources (`<aapt:attr>`) to their own files and leaves a reference to them
+//! on the document, so some interpreters need to follow references to colors and files. They do it
+//! through a [`Resolver`], usually a [`TableResolver`] built from the table of the APK.
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug, Formatter},
+};
+
+use anyhow::Error;
+use serde::Serialize;
+
+use crate::{
+    model::{
+        owned::Entry,
+        value::{
+            TOKEN_TYPE_ARGB4, TOKEN_TYPE_ARGB8, TOKEN_TYPE_REFERENCE_ID, TOKEN_TYPE_RGB4,
+            TOKEN_TYPE_RGB8, TOKEN_TYPE_STRING,
+        },
+        Element, Identifier, Library as _,
+    },
+    search::ResourceIndex,
+    visitor::{DecodeMode, Resources, XmlVisitor},
+};
+
+pub mod backup;
+pub mod font;
+pub mod navigation;
+pub mod network_security;
+pub mod privacy;
+pub mod shortcuts;
+pub mod vector;
+
+pub use self::{
+    backup::{BackupRules, DataExtractionRules},
+    font::FontFamily,
+    navigation::NavigationGraph,
+    network_security::NetworkSecurityConfig,
+    privacy::PrivacyReport,
+    shortcuts::Shortcuts,
+    vector::VectorDrawable,
+};
+
+/// Follows the references found on a document
+pub trait Resolver {
+    /// Returns the color a reference like `@color/accent` points to, as `#aarrggbb`
+    fn color(&self, reference: &str) -> Option<String>;
+    /// Returns the decoded XML a reference like `@drawable/$ic_logo__0` points to
+    fn xml(&self, reference: &str) -> Option<Element>;
+}
+
+/// Resolver for documents which are interpreted on their own: no reference is followed
+#[derive(Debug, Copy, Clone, Default)]
+pub struct NoResolver;
+
+impl Resolver for NoResolver {
+    fn color(&self, _reference: &str) -> Option<String> {
+        None
+    }
+
+    fn xml(&self, _reference: &str) -> Option<Element> {
+        None
+    }
+}
+
+/// Resolves references on a resource table. Files are read with the given function, which
+/// receives their path on the APK (`res/drawable/$ic_logo__0.xml`).
+pub struct TableResolver<'r, F> {
+    resources: &'r Resources,
+    index: ResourceIndex,
+    files: F,
+}
+
+impl<'r, F> Debug for TableResolver<'r, F> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("TableResolver")
+            .field("index", &self.index)
+            .finish()
+    }
+}
+
+impl<'r, F: Fn(&str) -> Option<Vec<u8>>> TableResolver<'r, F> {
+    pub fn new(resources: &'r Resources, files: F) -> Self {
+        Self {
+            resources,
+            index: ResourceIndex::new(resources),
+            files,
+        }
+    }
+
+    /// Returns the type and data of the default value of the resource, following references to
+    /// other resources
+    fn value(&self, reference: &str) -> Option<(u8, u32, u32)> {
+        let mut id = self.index.resolve(reference)?;
+
+        // Bounded, as a malicious table may hold reference loops
+        for _ in 0..8 {
+            let package = self.resources.packages.get(&id.get_package())?;
+            let entry = match package.get_entry(id).ok()? {
+                Entry::Simple(simple) => simple,
+                _ => return None,
+            };
+
+            if entry.get_type() != TOKEN_TYPE_REFERENCE_ID {
+                return Some((entry.get_type(), entry.get_value(), id));
+            }
+            id = entry.get_value();
+        }
+
+        None
+    }
+}
+
+impl<'r, F: Fn(&str) -> Option<Vec<u8>>> Resolver for TableResolver<'r, F> {
+    fn color(&self, reference: &str) -> Option<String> {
+        match self.value(reference)? {
+            (TOKEN_TYPE_ARGB8, data, _)
+            | (TOKEN_TYPE_RGB8, data, _)
+            | (TOKEN_TYPE_ARGB4, data, _)
+            | (TOKEN_TYPE_RGB4, data, _) => Some(format!("#{:08x}", data)),
+            _ => None,
+        }
+    }
+
+    fn xml(&self, reference: &str) -> Option<Element> {
+        let (value_type, data, id) = self.value(reference)?;
+        if value_type != TOKEN_TYPE_STRING {
+            return None;
+        }
+
+        let package = self.resources.packages.get(&id.get_package())?;
+        let path = package.get_string(data).ok()?;
+        let content = (self.files)(&path)?;
+
+        XmlVisitor::decode(self.resources, &content, DecodeMode::Lenient).root
+    }
+}
+
+/// A document interpreted after its root tag
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum Interpretation {
+    Vector(VectorDrawable),
+    Navigation(NavigationGraph),
+    FontFamily(FontFamily),
+    NetworkSecurityConfig(NetworkSecurityConfig),
+    FullBackupContent(BackupRules),
+    DataExtractionRules(DataExtractionRules),
+    Shortcuts(Shortcuts),
+}
+
+/// Interprets the document if its root is one of the known families. Returns `None` otherwise.
+pub fn interpret(root: &Element, resolver: &dyn Resolver) -> Option<Result<Interpretation, Error>> {
+    let interpretation = match root.get_tag().get_name().as_str() {
+        "vector" | "animated-vector" => {
+            VectorDrawable::from_element(root, resolver).map(Interpretation::Vector)
+        }
+        "navigation" => NavigationGraph::from_element(root).map(Interpretation::Navigation),
+        "font-family" => FontFamily::from_element(root).map(Interpretation::FontFamily),
+        "network-security-config" => {
+            NetworkSecurityConfig::from_element(root).map(Interpretation::NetworkSecurityConfig)
+        }
+        "full-backup-content" => {
+            BackupRules::from_element(root).map(Interpretation::FullBackupContent)
+        }
+        "data-extraction-rules" => {
+            DataExtractionRules::from_element(root).map(Interpretation::DataExtractionRules)
+        }
+        "shortcuts" => Shortcuts::from_element(root).map(Interpretation::Shortcuts),
+        _ => return None,
+    };
+
+    Some(interpretation)
+}
+
+/// Returns the value of the attribute with the given name, whatever its prefix is: the same
+/// attribute may be on the `android` namespace, on the namespace of the app (`app:`) or on none.
+/// The `android` one wins if there are several.
+pub(crate) fn attribute<'e>(element: &'e Element, name: &str) -> Option<&'e str> {
+    let attributes: &HashMap<String, String> = element.get_attributes();
+
+    if let Some(value) = attributes
+        .get(name)
+        .or_else(|| attributes.get(&format!("android:{}", name)))
+    {
+        return Some(value);
+    }
+
+    attributes
+        .iter()
+        .filter(|(key, _)| key.rsplit_once(':').map(|(_, local)| local) == Some(name))
+        .min_by(|a, b| a.0.cmp(b.0))
+        .map(|(_, value)| value.as_str())
+}
+
+pub(crate) fn is_tag(element: &Element, name: &str) -> bool {
+    element.get_tag().get_name().as_str() == name
+}
+
+pub(crate) fn children<'e>(
+    element: &'e Element,
+    name: &'e str,
+) -> impl Iterator<Item = &'e Element> {
+    element
+        .get_children()
+        .iter()
+        .filter(move |child| is_tag(child, name))
+}
+
+/// Parses the numeric part of a value like `24.0dip` or `0.5`
+pub(crate) fn number(value: &str) -> Option<f32> {
+    let end = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+'))
+        .unwrap_or(value.len());
+
+    value[..end].parse().ok()
+}
+
+/// Parses a boolean, as written by the decoder or by hand
+pub(crate) fn boolean(value: &str) -> Option<bool> {
+    match value {
+        "true" | "1" => Some(true),
+        "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+pub(crate) fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use super::{attribute, interpret, number, Interpretation, NoResolver};
+    use crate::model::{Element, Tag};
+
+    pub(crate) fn element(name: &str, attributes: &[(&str, &str)]) -> Element {
+        let attributes: HashMap<String, String> = attributes
+            .iter()
+            .map(|(key, value)| ((*key).to_string(), (*value).to_string()))
+            .collect();
+
+        Element::new(Tag::new(Arc::new(name.to_string()), Vec::new()), attributes)
+    }
+
+    #[test]
+    fn it_reads_attributes_whatever_their_prefix() {
+        let element = element(
+            "fragment",
+            &[
+                ("android:id", "@id/home"),
+                ("app:startDestination", "@id/start"),
+                ("cleartextTrafficPermitted", "true"),
+            ],
+        );
+
+        assert_eq!(Some("@id/home"), attribute(&element, "id"));
+        assert_eq!(Some("@id/start"), attribute(&element, "startDestination"));
+        assert_eq!(
+            Some("true"),
+            attribute(&element, "cleartextTrafficPermitted")
+        );
+        assert_eq!(None, attribute(&element, "label"));
+        assert_eq!(Some(24.0), number("24.0dip"));
+        assert_eq!(Some(-0.5), number("-0.5"));
+    }
+
+    #[test]
+    fn it_interprets_documents_after_their_root() {
+        let root = element("network-security-config", &[]);
+        assert!(matches!(
+            interpret(&root, &NoResolver),
+            Some(Ok(Interpretation::NetworkSecurityConfig(_)))
+        ));
+        assert!(interpret(&element("LinearLayout", &[]), &NoResolver).is_none());
+    }
+}
@@ -0,0 +1,332 @@
+//! Navigation graphs (`res/navigation/`) as the destinations they declare and the actions between
+//! them, which can be exported to DOT to be drawn with graphviz.
+
+use anyhow::{ensure, Error};
+use serde::Serialize;
+
+use super::{attribute, boolean, children, is_tag};
+use crate::model::Element;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NavigationGraph {
+    pub id: Option<String>,
+    pub label: Option<String>,
+    pub start_destination: Option<String>,
+    pub destinations: Vec<Destination>,
+    /// Actions declared on the graph itself, available from all its destinations
+    pub global_actions: Vec<Action>,
+    /// Nested graphs
+    pub graphs: Vec<NavigationGraph>,
+    /// Graphs included from other files, like `@navigation/settings`
+    pub includes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Destination {
+    pub id: Option<String>,
+    /// Tag of the destination: `fragment`, `activity`, `dialog` or a custom navigator
+    pub kind: String,
+    /// Class of the destination
+    pub name: Option<String>,
+    pub label: Option<String>,
+    pub actions: Vec<Action>,
+    pub arguments: Vec<Argument>,
+    pub deep_links: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Action {
+    pub id: Option<String>,
+    pub destination: Option<String>,
+    pub pop_up_to: Option<String>,
+    pub pop_up_to_inclusive: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Argument {
+    pub name: String,
+    pub arg_type: Option<String>,
+    pub default_value: Option<String>,
+    pub nullable: bool,
+}
+
+impl NavigationGraph {
+    pub fn from_element(root: &Element) -> Result<Self, Error> {
+        ensure!(
+            is_tag(root, "navigation"),
+            "expected a navigation graph, found {}",
+            root.get_tag().get_name()
+        );
+
+        let mut graph = Self {
+            id: value(root, "id"),
+            label: value(root, "label"),
+            start_destination: value(root, "startDestination"),
+            destinations: Vec::new(),
+            global_actions: children(root, "action").map(action).collect(),
+            graphs: Vec::new(),
+            includes: Vec::new(),
+        };
+
+        for child in root.get_children() {
+            match child.get_tag().get_name().as_str() {
+                "navigation" => graph.graphs.push(Self::from_element(child)?),
+                "include" => graph.includes.extend(value(child, "graph")),
+                "action" | "argument" | "deepLink" => (),
+                kind => graph.destinations.push(Destination {
+                    id: value(child, "id"),
+                    kind: kind.to_string(),
+                    name: value(child, "name"),
+                    label: value(child, "label"),
+                    actions: children(child, "action").map(action).collect(),
+                    arguments: children(child, "argument")
+                        .filter_map(|argument| {
+                            Some(Argument {
+                                name: value(argument, "name")?,
+                                arg_type: value(argument, "argType"),
+                                default_value: value(argument, "defaultValue"),
+                                nullable: attribute(argument, "nullable")
+                                    .and_then(boolean)
+                                    .unwrap_or(false),
+                            })
+                        })
+                        .collect(),
+                    deep_links: children(child, "deepLink")
+                        .filter_map(|link| value(link, "uri"))
+                        .collect(),
+                }),
+            }
+        }
+
+        Ok(graph)
+    }
+
+    /// Exports the graph to DOT. Each graph is drawn as a folder with a dashed edge to its start
+    /// destination, nested graphs as clusters, and actions as edges labelled with their ID.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph navigation {\n    node [shape=box];\n");
+        let mut clusters = 0;
+        self.write_dot(&mut out, 1, &mut clusters);
+        out.push_str("}\n");
+
+        out
+    }
+
+    fn write_dot(&self, out: &mut String, depth: usize, clusters: &mut usize) {
+        let indent = "    ".repeat(depth);
+        let graph_node = self.id.as_deref().map_or("navigation", short_id);
+
+        out.push_str(&format!(
+            "{}{} [shape=folder, label={}];\n",
+            indent,
+            quote(graph_node),
+            quote(self.label.as_deref().unwrap_or(graph_node))
+        ));
+        if let Some(start) = &self.start_destination {
+            out.push_str(&format!(
+                "{}{} -> {} [style=dashed];\n",
+                indent,
+                quote(graph_node),
+                quote(short_id(start))
+            ));
+        }
+
+        for destination in &self.destinations {
+            let node = destination.id.as_deref().map_or("?", short_id);
+            let mut label = destination
+                .label
+                .clone()
+                .unwrap_or_else(|| node.to_string());
+            if let Some(name) = &destination.name {
+                label.push('\n');
+                label.push_str(name.rsplit('.').next().unwrap_or(name));
+            }
+            let shape = match destination.kind.as_str() {
+                "activity" => "box3d",
+                "dialog" => "note",
+                _ => "box",
+            };
+
+            out.push_str(&format!(
+                "{}{} [shape={}, label={}];\n",
+                indent,
+                quote(node),
+                shape,
+                quote(&label)
+            ));
+            for action in &destination.actions {
+                write_action(out, &indent, node, action);
+            }
+        }
+
+        for action in &self.global_actions {
+            write_action(out, &indent, graph_node, action);
+        }
+
+        for include in &self.includes {
+            out.push_str(&format!(
+                "{}{} [shape=folder, style=dashed];\n",
+                indent,
+                quote(include)
+            ));
+        }
+
+        for graph in &self.graphs {
+            *clusters += 1;
+            out.push_str(&format!("{}subgraph cluster_{} {{\n", indent, clusters));
+            graph.write_dot(out, depth + 1, clusters);
+            out.push_str(&format!("{}}}\n", indent));
+        }
+    }
+}
+
+fn write_action(out: &mut String, indent: &str, from: &str, action: &Action) {
+    let destination = match (&action.destination, &action.pop_up_to) {
+        (Some(destination), _) => destination,
+        // Actions that only pop the back stack go back to the `popUpTo` destination
+        (None, Some(pop_up_to)) => pop_up_to,
+        (None, None) => return,
+    };
+
+    let mut label = action
+        .id
+        .as_deref()
+        .map(short_id)
+        .unwrap_or_default()
+        .to_string();
+    if let Some(pop_up_to) = &action.pop_up_to {
+        label.push_str(&format!(
+            "\npopUpTo {}{}",
+            short_id(pop_up_to),
+            if action.pop_up_to_inclusive {
+                " (inclusive)"
+            } else {
+                ""
+            }
+        ));
+    }
+
+    out.push_str(&format!(
+        "{}{} -> {} [label={}];\n",
+        indent,
+        quote(from),
+        quote(short_id(destination)),
+        quote(&label)
+    ));
+}
+
+fn action(element: &Element) -> Action {
+    Action {
+        id: value(element, "id"),
+        destination: value(element, "destination"),
+        pop_up_to: value(element, "popUpTo"),
+        pop_up_to_inclusive: attribute(element, "popUpToInclusive")
+            .and_then(boolean)
+            .unwrap_or(false),
+    }
+}
+
+fn value(element: &Element, name: &str) -> Option<String> {
+    attribute(element, name).map(ToString::to_string)
+}
+
+/// Name of an ID reference without its type: `@id/home` becomes `home`
+fn short_id(id: &str) -> &str {
+    id.rsplit('/').next().unwrap_or(id)
+}
+
+fn quote(text: &str) -> String {
+    format!(
+        "\"{}\"",
+        text.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NavigationGraph;
+    use crate::interpret::tests::element;
+
+    #[test]
+    fn it_exports_a_navigation_graph_to_dot() {
+        let mut root = element(
+            "navigation",
+            &[
+                ("android:id", "@id/nav_main"),
+                ("app:startDestination", "@id/home"),
+            ],
+        );
+
+        let mut home = element(
+            "fragment",
+            &[
+                ("android:id", "@id/home"),
+                ("android:name", "com.example.HomeFragment"),
+                ("android:label", "Home"),
+            ],
+        );
+        home.append(element(
+            "action",
+            &[
+                ("android:id", "@id/to_details"),
+                ("app:destination", "@id/details"),
+            ],
+        ));
+        let mut details = element("activity", &[("android:id", "@id/details")]);
+        details.append(element(
+            "argument",
+            &[("android:name", "itemId"), ("app:argType", "long")],
+        ));
+        details.append(element(
+            "deepLink",
+            &[("app:uri", "example://item/{itemId}")],
+        ));
+
+        let mut settings = element(
+            "navigation",
+            &[
+                ("android:id", "@id/nav_settings"),
+                ("app:startDestination", "@id/settings"),
+            ],
+        );
+        settings.append(element("fragment", &[("android:id", "@id/settings")]));
+
+        root.append(home);
+        root.append(details);
+        root.append(settings);
+        root.append(element(
+            "action",
+            &[
+                ("android:id", "@id/logout"),
+                ("app:popUpTo", "@id/nav_main"),
+                ("app:popUpToInclusive", "true"),
+            ],
+        ));
+        root.append(element(
+            "include",
+            &[("app:graph", "@navigation/onboarding")],
+        ));
+
+        let graph = NavigationGraph::from_element(&root).unwrap();
+        assert_eq!(2, graph.destinations.len());
+        assert_eq!("itemId", graph.destinations[1].arguments[0].name);
+        assert_eq!(
+            vec!["example://item/{itemId}"],
+            graph.destinations[1].deep_links
+        );
+
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("digraph navigation {\n"));
+        assert!(dot.contains("    \"nav_main\" -> \"home\" [style=dashed];\n"));
+        assert!(dot.contains("    \"home\" [shape=box, label=\"Home\\nHomeFragment\"];\n"));
+        assert!(dot.contains("    \"home\" -> \"details\" [label=\"to_details\"];\n"));
+        assert!(dot.contains("    \"details\" [shape=box3d, label=\"details\"];\n"));
+        assert!(dot.contains(
+            "    \"nav_main\" -> \"nav_main\" [label=\"logout\\npopUpTo nav_main (inclusive)\"];\n"
+        ));
+        assert!(dot.contains("    subgraph cluster_1 {\n        \"nav_settings\" [shape=folder"));
+        assert!(dot.contains("    \"@navigation/onboarding\" [shape=folder, style=dashed];\n"));
+    }
+}
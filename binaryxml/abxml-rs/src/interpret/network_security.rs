@@ -0,0 +1,179 @@
+//! Network security configs (`res/xml/network_security_config.xml`): which domains allow
+//! cleartext traffic, which certificate authorities are trusted and which keys are pinned.
+
+use anyhow::{ensure, Error};
+use serde::Serialize;
+
+use super::{attribute, boolean, children, is_tag};
+use crate::model::Element;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct NetworkSecurityConfig {
+    /// Configuration of the domains not covered by any domain config
+    pub base_config: Option<BaseConfig>,
+    pub domain_configs: Vec<DomainConfig>,
+    /// Trust anchors added when the app is debuggable
+    pub debug_overrides: Vec<TrustAnchor>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BaseConfig {
+    pub cleartext_traffic_permitted: Option<bool>,
+    pub trust_anchors: Vec<TrustAnchor>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DomainConfig {
+    pub domains: Vec<Domain>,
+    /// Unset values are inherited from the enclosing domain config, or the base config
+    pub cleartext_traffic_permitted: Option<bool>,
+    pub trust_anchors: Vec<TrustAnchor>,
+    pub pin_set: Option<PinSet>,
+    /// Nested domain configs
+    pub domain_configs: Vec<DomainConfig>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Domain {
+    pub name: String,
+    pub include_subdomains: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TrustAnchor {
+    /// `system`, `user` or a reference to a raw resource with the certificates
+    pub source: String,
+    pub override_pins: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PinSet {
+    /// Date, as `yyyy-MM-dd`, after which the pins are no longer enforced
+    pub expiration: Option<String>,
+    pub pins: Vec<Pin>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Pin {
+    /// Digest algorithm, always `SHA-256` for now
+    pub digest: String,
+    /// Base64 digest of the public key
+    pub value: String,
+}
+
+impl NetworkSecurityConfig {
+    pub fn from_element(root: &Element) -> Result<Self, Error> {
+        ensure!(
+            is_tag(root, "network-security-config"),
+            "expected a network security config, found {}",
+            root.get_tag().get_name()
+        );
+
+        Ok(Self {
+            base_config: children(root, "base-config").next().map(|base| BaseConfig {
+                cleartext_traffic_permitted: cleartext(base),
+                trust_anchors: trust_anchors(base),
+            }),
+            domain_configs: children(root, "domain-config").map(domain_config).collect(),
+            debug_overrides: children(root, "debug-overrides")
+                .flat_map(trust_anchors)
+                .collect(),
+        })
+    }
+}
+
+fn domain_config(element: &Element) -> DomainConfig {
+    DomainConfig {
+        domains: children(element, "domain")
+            .map(|domain| Domain {
+                name: domain.get_text().trim().to_string(),
+                include_subdomains: attribute(domain, "includeSubdomains")
+                    .and_then(boolean)
+                    .unwrap_or(false),
+            })
+            .collect(),
+        cleartext_traffic_permitted: cleartext(element),
+        trust_anchors: trust_anchors(element),
+        pin_set: children(element, "pin-set").next().map(|pin_set| PinSet {
+            expiration: attribute(pin_set, "expiration").map(ToString::to_string),
+            pins: children(pin_set, "pin")
+                .map(|pin| Pin {
+                    digest: attribute(pin, "digest").unwrap_or("SHA-256").to_string(),
+                    value: pin.get_text().trim().to_string(),
+                })
+                .collect(),
+        }),
+        domain_configs: children(element, "domain-config")
+            .map(domain_config)
+            .collect(),
+    }
+}
+
+fn cleartext(element: &Element) -> Option<bool> {
+    attribute(element, "cleartextTrafficPermitted").and_then(boolean)
+}
+
+fn trust_anchors(element: &Element) -> Vec<TrustAnchor> {
+    children(element, "trust-anchors")
+        .flat_map(|anchors| children(anchors, "certificates"))
+        .map(|certificates| TrustAnchor {
+            source: attribute(certificates, "src")
+                .unwrap_or_default()
+                .to_string(),
+            override_pins: attribute(certificates, "overridePins")
+                .and_then(boolean)
+                .unwrap_or(false),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NetworkSecurityConfig;
+    use crate::interpret::tests::element;
+
+    #[test]
+    fn it_summarizes_a_network_security_config() {
+        let mut root = element("network-security-config", &[]);
+
+        let mut base = element("base-config", &[("cleartextTrafficPermitted", "false")]);
+        let mut anchors = element("trust-anchors", &[]);
+        anchors.append(element("certificates", &[("src", "system")]));
+        base.append(anchors);
+
+        let mut config = element("domain-config", &[("cleartextTrafficPermitted", "true")]);
+        let mut domain = element("domain", &[("includeSubdomains", "true")]);
+        domain.append_text(" example.com ");
+        let mut pin_set = element("pin-set", &[("expiration", "2030-01-01")]);
+        let mut pin = element("pin", &[("digest", "SHA-256")]);
+        pin.append_text("7HIpactkIAq2Y49orFOOQKurWxmmSFZhBCoQYcRhJ3Y=");
+        pin_set.append(pin);
+        config.append(domain);
+        config.append(pin_set);
+
+        let mut debug = element("debug-overrides", &[]);
+        let mut anchors = element("trust-anchors", &[]);
+        anchors.append(element("certificates", &[("src", "user")]));
+        debug.append(anchors);
+
+        root.append(base);
+        root.append(config);
+        root.append(debug);
+
+        let config = NetworkSecurityConfig::from_element(&root).unwrap();
+        let base = config.base_config.unwrap();
+        assert_eq!(Some(false), base.cleartext_traffic_permitted);
+        assert_eq!("system", base.trust_anchors[0].source);
+
+        let domain_config = &config.domain_configs[0];
+        assert_eq!("example.com", domain_config.domains[0].name);
+        assert!(domain_config.domains[0].include_subdomains);
+        let pin_set = domain_config.pin_set.as_ref().unwrap();
+        assert_eq!(Some("2030-01-01"), pin_set.expiration.as_deref());
+        assert_eq!(
+            "7HIpactkIAq2Y49orFOOQKurWxmmSFZhBCoQYcRhJ3Y=",
+            pin_set.pins[0].value
+        );
+        assert_eq!("user", config.debug_overrides[0].source);
+    }
+}
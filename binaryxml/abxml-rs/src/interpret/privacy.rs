@@ -0,0 +1,488 @@
+//! Report of the network security and backup settings of an app, for privacy reviews. It starts
+//! from the manifest and follows its `android:networkSecurityConfig`, `android:fullBackupContent`
+//! and `android:dataExtractionRules` references to the documents they point to.
+//!
+//! The values the platform falls back to are applied: cleartext traffic and user certificate
+//! authorities are trusted by default before API 28 and API 24 respectively, and domain configs
+//! inherit what they do not set from the enclosing config.
+
+use anyhow::{ensure, Error};
+use serde::Serialize;
+
+use super::{
+    attribute, boolean, children, is_tag,
+    network_security::{DomainConfig, Pin, PinSet, TrustAnchor},
+    number, BackupRules, DataExtractionRules, NetworkSecurityConfig, Resolver,
+};
+use crate::model::Element;
+
+/// Name of the scope of the base config on the report
+pub const BASE_CONFIG: &str = "base-config";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PrivacyReport {
+    /// `android:targetSdkVersion`, or `android:minSdkVersion` when it is not set, as the platform
+    /// does
+    pub target_sdk: Option<u32>,
+    pub network: NetworkReport,
+    pub backup: BackupReport,
+    /// References which could not be followed or documents which could not be read
+    pub problems: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkReport {
+    /// `android:usesCleartextTraffic`, ignored by the platform when there is a config
+    pub uses_cleartext_traffic: Option<bool>,
+    /// Value of `android:networkSecurityConfig`, like `@xml/network_security_config`
+    pub config_reference: Option<String>,
+    pub config: Option<NetworkSecurityConfig>,
+    /// Whether cleartext traffic is permitted to the domains not covered by any domain config
+    pub cleartext_by_default: bool,
+    /// Domains of the domain configs which permit cleartext traffic
+    pub cleartext_domains: Vec<String>,
+    /// Certificate authorities trusted on each scope: the base config and each domain config
+    pub trust_anchors: Vec<TrustScope>,
+    pub pin_sets: Vec<PinSetScope>,
+    /// Trust anchors added when the app is debuggable
+    pub debug_overrides: Vec<TrustAnchor>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TrustScope {
+    /// `base-config`, or the domains of the domain config (`*.example.com` if subdomains are
+    /// included)
+    pub scope: String,
+    pub anchors: Vec<TrustAnchor>,
+}
+
+impl TrustScope {
+    /// Whether the certificate authorities added by the user are trusted
+    pub fn trusts_user_certificates(&self) -> bool {
+        self.anchors.iter().any(|anchor| anchor.source == "user")
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PinSetScope {
+    pub scope: String,
+    /// Date, as `yyyy-MM-dd`, after which the pins are no longer enforced
+    pub expiration: Option<String>,
+    pub pins: Vec<Pin>,
+}
+
+impl PinSetScope {
+    /// Whether the pins are no longer enforced on the given date, as `yyyy-MM-dd`
+    pub fn is_expired(&self, today: &str) -> bool {
+        self.expiration
+            .as_deref()
+            .is_some_and(|expiration| expiration < today)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupReport {
+    /// `android:allowBackup`, which defaults to true
+    pub allow_backup: bool,
+    /// Value of `android:fullBackupContent`: a reference to the rules, or `false` to back
+    /// nothing up with Auto Backup
+    pub full_backup_content_reference: Option<String>,
+    pub full_backup_content: Option<BackupRules>,
+    /// Value of `android:dataExtractionRules`, which replaces the full backup content from API 31
+    pub data_extraction_rules_reference: Option<String>,
+    pub data_extraction_rules: Option<DataExtractionRules>,
+}
+
+impl PrivacyReport {
+    /// Builds the report of a decoded `AndroidManifest.xml`, reading the documents it references
+    /// with the resolver
+    pub fn from_manifest(manifest: &Element, resolver: &dyn Resolver) -> Result<Self, Error> {
+        ensure!(
+            is_tag(manifest, "manifest"),
+            "expected a manifest, found {}",
+            manifest.get_tag().get_name()
+        );
+
+        let mut problems = Vec::new();
+        let sdk = |name: &str| {
+            children(manifest, "uses-sdk")
+                .find_map(|uses_sdk| attribute(uses_sdk, name))
+                .and_then(number)
+                .map(|sdk| sdk as u32)
+        };
+        let target_sdk = sdk("targetSdkVersion").or_else(|| sdk("minSdkVersion"));
+        let application = children(manifest, "application").next();
+        let value = |name: &str| {
+            application
+                .and_then(|application| attribute(application, name))
+                .map(ToString::to_string)
+        };
+
+        let config_reference = value("networkSecurityConfig");
+        let config = config_reference.as_deref().and_then(|reference| {
+            read(
+                resolver,
+                reference,
+                &mut problems,
+                NetworkSecurityConfig::from_element,
+            )
+        });
+        let network = NetworkReport::new(
+            value("usesCleartextTraffic").as_deref().and_then(boolean),
+            config_reference,
+            config,
+            target_sdk,
+        );
+
+        let full_backup_content_reference = value("fullBackupContent");
+        let full_backup_content = full_backup_content_reference
+            .as_deref()
+            .filter(|reference| boolean(reference).is_none())
+            .and_then(|reference| {
+                read(
+                    resolver,
+                    reference,
+                    &mut problems,
+                    BackupRules::from_element,
+                )
+            });
+        let data_extraction_rules_reference = value("dataExtractionRules");
+        let data_extraction_rules =
+            data_extraction_rules_reference
+                .as_deref()
+                .and_then(|reference| {
+                    read(
+                        resolver,
+                        reference,
+                        &mut problems,
+                        DataExtractionRules::from_element,
+                    )
+                });
+
+        Ok(Self {
+            target_sdk,
+            network,
+            backup: BackupReport {
+                allow_backup: value("allowBackup")
+                    .as_deref()
+                    .and_then(boolean)
+                    .unwrap_or(true),
+                full_backup_content_reference,
+                full_backup_content,
+                data_extraction_rules_reference,
+                data_extraction_rules,
+            },
+            problems,
+        })
+    }
+}
+
+impl NetworkReport {
+    fn new(
+        uses_cleartext_traffic: Option<bool>,
+        config_reference: Option<String>,
+        config: Option<NetworkSecurityConfig>,
+        target_sdk: Option<u32>,
+    ) -> Self {
+        // Without target nor minimum SDK, the app targets API 1
+        let target_sdk = target_sdk.unwrap_or(1);
+        let base = config
+            .as_ref()
+            .and_then(|config| config.base_config.as_ref());
+
+        let cleartext_by_default = match (&config, base) {
+            (Some(_), base) => base
+                .and_then(|base| base.cleartext_traffic_permitted)
+                .unwrap_or(target_sdk < 28),
+            (None, _) => uses_cleartext_traffic.unwrap_or(target_sdk < 28),
+        };
+        let base_anchors = match base {
+            Some(base) if !base.trust_anchors.is_empty() => base.trust_anchors.clone(),
+            _ => {
+                let mut anchors = vec![anchor("system")];
+                if target_sdk < 24 {
+                    anchors.push(anchor("user"));
+                }
+                anchors
+            }
+        };
+
+        let mut report = Self {
+            uses_cleartext_traffic,
+            config_reference,
+            config: None,
+            cleartext_by_default,
+            cleartext_domains: Vec::new(),
+            trust_anchors: vec![TrustScope {
+                scope: BASE_CONFIG.to_string(),
+                anchors: base_anchors.clone(),
+            }],
+            pin_sets: Vec::new(),
+            debug_overrides: Vec::new(),
+        };
+
+        if let Some(config) = &config {
+            for domain_config in &config.domain_configs {
+                report.add_domain_config(domain_config, cleartext_by_default, &base_anchors, None);
+            }
+            report.debug_overrides = config.debug_overrides.clone();
+        }
+        report.config = config;
+
+        report
+    }
+
+    /// Adds the effective settings of the domain config and the ones nested on it, given the ones
+    /// of the enclosing config
+    fn add_domain_config(
+        &mut self,
+        domain_config: &DomainConfig,
+        cleartext: bool,
+        anchors: &[TrustAnchor],
+        pin_set: Option<&PinSet>,
+    ) {
+        let scope = domain_config
+            .domains
+            .iter()
+            .map(|domain| {
+                if domain.include_subdomains {
+                    format!("*.{}", domain.name)
+                } else {
+                    domain.name.clone()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let cleartext = domain_config
+            .cleartext_traffic_permitted
+            .unwrap_or(cleartext);
+        if cleartext {
+            self.cleartext_domains.extend(
+                domain_config
+                    .domains
+                    .iter()
+                    .map(|domain| domain.name.clone()),
+            );
+        }
+
+        let anchors = if domain_config.trust_anchors.is_empty() {
+            anchors.to_vec()
+        } else {
+            domain_config.trust_anchors.clone()
+        };
+        self.trust_anchors.push(TrustScope {
+            scope: scope.clone(),
+            anchors: anchors.clone(),
+        });
+
+        let pin_set = domain_config.pin_set.as_ref().or(pin_set);
+        if let Some(pin_set) = pin_set {
+            self.pin_sets.push(PinSetScope {
+                scope,
+                expiration: pin_set.expiration.clone(),
+                pins: pin_set.pins.clone(),
+            });
+        }
+
+        for nested in &domain_config.domain_configs {
+            self.add_domain_config(nested, cleartext, &anchors, pin_set);
+        }
+    }
+}
+
+fn anchor(source: &str) -> TrustAnchor {
+    TrustAnchor {
+        source: source.to_string(),
+        override_pins: false,
+    }
+}
+
+/// Reads the document the reference points to, recording why if it can not be done
+fn read<T>(
+    resolver: &dyn Resolver,
+    reference: &str,
+    problems: &mut Vec<String>,
+    parse: fn(&Element) -> Result<T, Error>,
+) -> Option<T> {
+    let root = match resolver.xml(reference) {
+        Some(root) => root,
+        None => {
+            problems.push(format!("could not read {}", reference));
+            return None;
+        }
+    };
+
+    match parse(&root) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            problems.push(format!("{}: {}", reference, e));
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PrivacyReport, BASE_CONFIG};
+    use crate::{
+        interpret::{tests::element, Resolver},
+        model::Element,
+    };
+
+    struct Files;
+
+    impl Resolver for Files {
+        fn color(&self, _reference: &str) -> Option<String> {
+            None
+        }
+
+        fn xml(&self, reference: &str) -> Option<Element> {
+            match reference {
+                "@xml/network_security_config" => {
+                    let mut root = element("network-security-config", &[]);
+                    let mut config =
+                        element("domain-config", &[("cleartextTrafficPermitted", "true")]);
+                    let mut domain = element("domain", &[("includeSubdomains", "true")]);
+                    domain.append_text("example.com");
+                    let mut anchors = element("trust-anchors", &[]);
+                    anchors.append(element("certificates", &[("src", "user")]));
+                    let mut nested = element("domain-config", &[]);
+                    let mut nested_domain = element("domain", &[]);
+                    nested_domain.append_text("api.example.com");
+                    let mut pin_set = element("pin-set", &[("expiration", "2024-01-01")]);
+                    pin_set.append(element("pin", &[("digest", "SHA-256")]));
+                    nested.append(nested_domain);
+                    nested.append(pin_set);
+                    config.append(domain);
+                    config.append(anchors);
+                    config.append(nested);
+                    root.append(config);
+
+                    Some(root)
+                }
+                "@xml/pinned" => {
+                    let mut root = element("network-security-config", &[]);
+                    let mut config = element("domain-config", &[]);
+                    let mut domain = element("domain", &[]);
+                    domain.append_text("example.com");
+                    let mut pin_set = element("pin-set", &[]);
+                    pin_set.append(element("pin", &[("digest", "SHA-256")]));
+                    let mut nested = element("domain-config", &[]);
+                    let mut nested_domain = element("domain", &[]);
+                    nested_domain.append_text("api.example.com");
+                    nested.append(nested_domain);
+                    config.append(domain);
+                    config.append(pin_set);
+                    config.append(nested);
+                    root.append(config);
+
+                    Some(root)
+                }
+                "@xml/data_extraction_rules" => {
+                    let mut root = element("data-extraction-rules", &[]);
+                    let mut transfer = element("device-transfer", &[]);
+                    transfer.append(element("include", &[("domain", "file")]));
+                    root.append(transfer);
+
+                    Some(root)
+                }
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn it_reports_the_network_and_backup_settings_of_a_manifest() {
+        let mut manifest = element("manifest", &[]);
+        manifest.append(element("uses-sdk", &[("android:targetSdkVersion", "34")]));
+        manifest.append(element(
+            "application",
+            &[
+                (
+                    "android:networkSecurityConfig",
+                    "@xml/network_security_config",
+                ),
+                ("android:allowBackup", "true"),
+                ("android:fullBackupContent", "@xml/missing"),
+                ("android:dataExtractionRules", "@xml/data_extraction_rules"),
+            ],
+        ));
+
+        let report = PrivacyReport::from_manifest(&manifest, &Files).unwrap();
+        assert_eq!(Some(34), report.target_sdk);
+
+        let network = &report.network;
+        assert!(!network.cleartext_by_default);
+        // Nested configs inherit the cleartext and trust anchor settings
+        assert_eq!(
+            vec!["example.com", "api.example.com"],
+            network.cleartext_domains
+        );
+        assert_eq!(BASE_CONFIG, network.trust_anchors[0].scope);
+        assert!(!network.trust_anchors[0].trusts_user_certificates());
+        assert_eq!("*.example.com", network.trust_anchors[1].scope);
+        assert!(network.trust_anchors[2].trusts_user_certificates());
+        assert_eq!("api.example.com", network.pin_sets[0].scope);
+        assert!(network.pin_sets[0].is_expired("2026-10-18"));
+        assert!(!network.pin_sets[0].is_expired("2023-12-31"));
+
+        let backup = &report.backup;
+        assert!(backup.allow_backup);
+        assert!(backup.full_backup_content.is_none());
+        let transfer = backup
+            .data_extraction_rules
+            .as_ref()
+            .unwrap()
+            .device_transfer
+            .as_ref()
+            .unwrap();
+        assert_eq!("file", transfer.include[0].domain);
+        assert_eq!(vec!["could not read @xml/missing"], report.problems);
+    }
+
+    #[test]
+    fn it_inherits_pin_sets_and_targets_the_min_sdk_by_default() {
+        let mut manifest = element("manifest", &[]);
+        manifest.append(element("uses-sdk", &[("android:minSdkVersion", "30")]));
+        manifest.append(element(
+            "application",
+            &[("android:networkSecurityConfig", "@xml/pinned")],
+        ));
+
+        let report = PrivacyReport::from_manifest(&manifest, &Files).unwrap();
+        assert_eq!(Some(30), report.target_sdk);
+
+        let network = &report.network;
+        assert!(!network.cleartext_by_default);
+        assert!(!network.trust_anchors[0].trusts_user_certificates());
+        let scopes: Vec<&str> = network
+            .pin_sets
+            .iter()
+            .map(|pin_set| pin_set.scope.as_str())
+            .collect();
+        assert_eq!(vec!["example.com", "api.example.com"], scopes);
+    }
+
+    #[test]
+    fn it_falls_back_to_the_manifest_without_network_security_config() {
+        let mut manifest = element("manifest", &[]);
+        manifest.append(element(
+            "application",
+            &[
+                ("android:usesCleartextTraffic", "true"),
+                ("android:fullBackupContent", "false"),
+            ],
+        ));
+
+        let report = PrivacyReport::from_manifest(&manifest, &Files).unwrap();
+        assert!(report.network.cleartext_by_default);
+        // Before API 24 the certificates added by the user are trusted
+        assert!(report.network.trust_anchors[0].trusts_user_certificates());
+        assert_eq!(
+            Some("false"),
+            report.backup.full_backup_content_reference.as_deref()
+        );
+        assert!(report.problems.is_empty());
+    }
+}
@@ -0,0 +1,158 @@
+//! Static shortcuts (`res/xml/shortcuts.xml`): the entries shown on the launcher menu of the app
+//! and the App Actions capabilities it declares.
+
+use anyhow::{ensure, Error};
+use serde::Serialize;
+
+use super::{attribute, boolean, children, is_tag};
+use crate::model::Element;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Shortcuts {
+    pub shortcuts: Vec<Shortcut>,
+    pub capabilities: Vec<Capability>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Shortcut {
+    pub id: Option<String>,
+    pub enabled: bool,
+    pub icon: Option<String>,
+    pub short_label: Option<String>,
+    pub long_label: Option<String>,
+    pub disabled_message: Option<String>,
+    /// Intents launched by the shortcut. The last one is the one shown, the others make the back
+    /// stack.
+    pub intents: Vec<ShortcutIntent>,
+    pub categories: Vec<String>,
+    /// Capabilities the shortcut is bound to
+    pub capability_bindings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ShortcutIntent {
+    pub action: Option<String>,
+    pub target_package: Option<String>,
+    pub target_class: Option<String>,
+    pub data: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Capability {
+    /// Built-in intent, like `actions.intent.OPEN_APP_FEATURE`
+    pub name: Option<String>,
+    pub intents: Vec<ShortcutIntent>,
+}
+
+impl Shortcuts {
+    pub fn from_element(root: &Element) -> Result<Self, Error> {
+        ensure!(
+            is_tag(root, "shortcuts"),
+            "expected shortcuts, found {}",
+            root.get_tag().get_name()
+        );
+
+        Ok(Self {
+            shortcuts: children(root, "shortcut")
+                .map(|shortcut| Shortcut {
+                    id: value(shortcut, "shortcutId"),
+                    enabled: attribute(shortcut, "enabled")
+                        .and_then(boolean)
+                        .unwrap_or(true),
+                    icon: value(shortcut, "icon"),
+                    short_label: value(shortcut, "shortcutShortLabel"),
+                    long_label: value(shortcut, "shortcutLongLabel"),
+                    disabled_message: value(shortcut, "shortcutDisabledMessage"),
+                    intents: intents(shortcut),
+                    categories: children(shortcut, "categories")
+                        .filter_map(|category| value(category, "name"))
+                        .collect(),
+                    capability_bindings: children(shortcut, "capability-binding")
+                        .filter_map(|binding| value(binding, "key"))
+                        .collect(),
+                })
+                .collect(),
+            capabilities: children(root, "capability")
+                .map(|capability| Capability {
+                    name: value(capability, "name"),
+                    intents: intents(capability),
+                })
+                .collect(),
+        })
+    }
+}
+
+fn intents(element: &Element) -> Vec<ShortcutIntent> {
+    children(element, "intent")
+        .map(|intent| ShortcutIntent {
+            action: value(intent, "action"),
+            target_package: value(intent, "targetPackage"),
+            target_class: value(intent, "targetClass"),
+            data: value(intent, "data"),
+        })
+        .collect()
+}
+
+fn value(element: &Element, name: &str) -> Option<String> {
+    attribute(element, name).map(ToString::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Shortcuts;
+    use crate::interpret::tests::element;
+
+    #[test]
+    fn it_summarizes_shortcuts() {
+        let mut root = element("shortcuts", &[]);
+        let mut shortcut = element(
+            "shortcut",
+            &[
+                ("android:shortcutId", "compose"),
+                ("android:enabled", "true"),
+                ("android:shortcutShortLabel", "@string/compose_short"),
+            ],
+        );
+        shortcut.append(element(
+            "intent",
+            &[
+                ("android:action", "android.intent.action.VIEW"),
+                ("android:targetPackage", "com.example"),
+                ("android:targetClass", "com.example.ComposeActivity"),
+            ],
+        ));
+        shortcut.append(element(
+            "categories",
+            &[("android:name", "android.shortcut.conversation")],
+        ));
+        root.append(shortcut);
+        root.append(element(
+            "shortcut",
+            &[("android:shortcutId", "old"), ("android:enabled", "false")],
+        ));
+        root.append(element(
+            "capability",
+            &[("android:name", "actions.intent.CREATE_MESSAGE")],
+        ));
+
+        let shortcuts = Shortcuts::from_element(&root).unwrap();
+        assert_eq!(2, shortcuts.shortcuts.len());
+        let compose = &shortcuts.shortcuts[0];
+        assert_eq!(Some("compose"), compose.id.as_deref());
+        assert!(compose.enabled);
+        assert_eq!(
+            Some("@string/compose_short"),
+            compose.short_label.as_deref()
+        );
+        assert_eq!(
+            Some("com.example.ComposeActivity"),
+            compose.intents[0].target_class.as_deref()
+        );
+        assert_eq!(vec!["android.shortcut.conversation"], compose.categories);
+        assert!(!shortcuts.shortcuts[1].enabled);
+        assert_eq!(
+            Some("actions.intent.CREATE_MESSAGE"),
+            shortcuts.capabilities[0].name.as_deref()
+        );
+    }
+}
@@ -0,0 +1,595 @@
+//! Vector drawables (`<vector>`, and the drawable of an `<animated-vector>`) rendered to SVG.
+//!
+//! The path data of a vector drawable already uses the SVG syntax, so the conversion maps groups
+//! to `<g>` with the equivalent transform, clip paths to `<clipPath>` applied to the siblings that
+//! follow them, and colors and gradients to their SVG counterparts. Tints and theme attributes
+//! (`?attr/colorControlNormal`) depend on the context the drawable is shown on, so they are not
+//! applied: paints that can not be resolved are rendered with `currentColor`.
+
+use anyhow::{ensure, format_err, Error};
+use serde::Serialize;
+
+use super::{attribute, children, escape_xml, is_tag, number, Resolver};
+use crate::model::Element;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VectorDrawable {
+    /// Intrinsic size, in dp
+    pub width: f32,
+    pub height: f32,
+    /// Size of the canvas the path data is drawn on
+    pub viewport_width: f32,
+    pub viewport_height: f32,
+    pub alpha: f32,
+    pub children: Vec<VectorNode>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum VectorNode {
+    Group(VectorGroup),
+    Path(VectorPath),
+    /// Clips the siblings that follow it
+    ClipPath(String),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VectorGroup {
+    pub name: Option<String>,
+    /// Rotation in degrees around the pivot
+    pub rotation: f32,
+    pub pivot_x: f32,
+    pub pivot_y: f32,
+    pub scale_x: f32,
+    pub scale_y: f32,
+    pub translate_x: f32,
+    pub translate_y: f32,
+    pub children: Vec<VectorNode>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VectorPath {
+    pub name: Option<String>,
+    pub path_data: String,
+    pub fill: Option<Paint>,
+    pub stroke: Option<Paint>,
+    pub stroke_width: f32,
+    pub fill_alpha: f32,
+    pub stroke_alpha: f32,
+    pub even_odd: bool,
+    pub line_cap: Option<String>,
+    pub line_join: Option<String>,
+    pub miter_limit: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum Paint {
+    /// Color as `#aarrggbb`, or the reference that could not be resolved
+    Color(String),
+    Gradient(Gradient),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GradientKind {
+    Linear,
+    Radial,
+    Sweep,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Gradient {
+    pub kind: GradientKind,
+    pub start_x: f32,
+    pub start_y: f32,
+    pub end_x: f32,
+    pub end_y: f32,
+    pub center_x: f32,
+    pub center_y: f32,
+    pub radius: f32,
+    /// Offset and color of each stop
+    pub stops: Vec<(f32, String)>,
+}
+
+impl VectorDrawable {
+    /// Reads a `<vector>` or an `<animated-vector>`, whose drawable may be inline or a reference
+    pub fn from_element(root: &Element, resolver: &dyn Resolver) -> Result<Self, Error> {
+        if is_tag(root, "animated-vector") {
+            let inline = children(root, "attr")
+                .filter(|attr| {
+                    attribute(attr, "name").is_some_and(|name| name.ends_with("drawable"))
+                })
+                .flat_map(|attr| children(attr, "vector"))
+                .next();
+            if let Some(vector) = inline {
+                return Self::from_element(vector, resolver);
+            }
+
+            let reference = attribute(root, "drawable")
+                .ok_or_else(|| format_err!("animated vector without drawable"))?;
+            let vector = resolver
+                .xml(reference)
+                .ok_or_else(|| format_err!("could not resolve the drawable {}", reference))?;
+            ensure!(is_tag(&vector, "vector"), "{} is not a vector", reference);
+
+            return Self::from_element(&vector, resolver);
+        }
+
+        ensure!(
+            is_tag(root, "vector"),
+            "expected a vector, found {}",
+            root.get_tag().get_name()
+        );
+
+        let width = attribute(root, "width").and_then(number).unwrap_or(0.0);
+        let height = attribute(root, "height").and_then(number).unwrap_or(0.0);
+
+        Ok(Self {
+            width,
+            height,
+            viewport_width: attribute(root, "viewportWidth")
+                .and_then(number)
+                .unwrap_or(width),
+            viewport_height: attribute(root, "viewportHeight")
+                .and_then(number)
+                .unwrap_or(height),
+            alpha: attribute(root, "alpha").and_then(number).unwrap_or(1.0),
+            children: nodes(root, resolver),
+        })
+    }
+
+    pub fn to_svg(&self) -> String {
+        let mut writer = SvgWriter::default();
+        writer.out.push_str(&format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
+            self.width, self.height, self.viewport_width, self.viewport_height
+        ));
+
+        if self.alpha < 1.0 {
+            writer
+                .out
+                .push_str(&format!("<g opacity=\"{}\">\n", self.alpha));
+            writer.write_nodes(&self.children);
+            writer.out.push_str("</g>\n");
+        } else {
+            writer.write_nodes(&self.children);
+        }
+
+        writer.out.push_str("</svg>\n");
+        writer.out
+    }
+}
+
+fn nodes(element: &Element, resolver: &dyn Resolver) -> Vec<VectorNode> {
+    element
+        .get_children()
+        .iter()
+        .filter_map(|child| match child.get_tag().get_name().as_str() {
+            "group" => Some(VectorNode::Group(VectorGroup {
+                name: attribute(child, "name").map(ToString::to_string),
+                rotation: attribute(child, "rotation").and_then(number).unwrap_or(0.0),
+                pivot_x: attribute(child, "pivotX").and_then(number).unwrap_or(0.0),
+                pivot_y: attribute(child, "pivotY").and_then(number).unwrap_or(0.0),
+                scale_x: attribute(child, "scaleX").and_then(number).unwrap_or(1.0),
+                scale_y: attribute(child, "scaleY").and_then(number).unwrap_or(1.0),
+                translate_x: attribute(child, "translateX")
+                    .and_then(number)
+                    .unwrap_or(0.0),
+                translate_y: attribute(child, "translateY")
+                    .and_then(number)
+                    .unwrap_or(0.0),
+                children: nodes(child, resolver),
+            })),
+            "path" => Some(VectorNode::Path(VectorPath {
+                name: attribute(child, "name").map(ToString::to_string),
+                path_data: attribute(child, "pathData").unwrap_or_default().to_string(),
+                fill: paint(child, "fillColor", resolver),
+                stroke: paint(child, "strokeColor", resolver),
+                stroke_width: attribute(child, "strokeWidth")
+                    .and_then(number)
+                    .unwrap_or(0.0),
+                fill_alpha: attribute(child, "fillAlpha")
+                    .and_then(number)
+                    .unwrap_or(1.0),
+                stroke_alpha: attribute(child, "strokeAlpha")
+                    .and_then(number)
+                    .unwrap_or(1.0),
+                even_odd: matches!(attribute(child, "fillType"), Some("evenOdd") | Some("1")),
+                line_cap: attribute(child, "strokeLineCap")
+                    .and_then(|cap| enum_name(cap, &["butt", "round", "square"])),
+                line_join: attribute(child, "strokeLineJoin")
+                    .and_then(|join| enum_name(join, &["miter", "round", "bevel"])),
+                miter_limit: attribute(child, "strokeMiterLimit").and_then(number),
+            })),
+            "clip-path" => Some(VectorNode::ClipPath(
+                attribute(child, "pathData").unwrap_or_default().to_string(),
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Name of an enum value, which the decoder writes as a name or as its index
+fn enum_name(value: &str, names: &[&str]) -> Option<String> {
+    match value.parse::<usize>() {
+        Ok(index) => names.get(index).map(ToString::to_string),
+        Err(_) => Some(value.to_string()),
+    }
+}
+
+/// Reads a paint, given inline (`<aapt:attr name="android:fillColor">`) or as an attribute whose
+/// value may be a color, a reference to a color or a reference to a gradient
+fn paint(element: &Element, name: &str, resolver: &dyn Resolver) -> Option<Paint> {
+    let inline = children(element, "attr")
+        .filter(|attr| {
+            attribute(attr, "name")
+                .is_some_and(|attr_name| attr_name.rsplit(':').next() == Some(name))
+        })
+        .flat_map(|attr| children(attr, "gradient"))
+        .next();
+    if let Some(gradient) = inline {
+        return Some(Paint::Gradient(Gradient::from_element(gradient, resolver)));
+    }
+
+    let value = attribute(element, name)?;
+    if value == "@null" {
+        return None;
+    }
+    if !value.starts_with('@') && !value.starts_with('?') {
+        return Some(Paint::Color(value.to_string()));
+    }
+
+    if let Some(color) = resolver.color(value) {
+        return Some(Paint::Color(color));
+    }
+
+    match resolver.xml(value) {
+        Some(gradient) if is_tag(&gradient, "gradient") => {
+            Some(Paint::Gradient(Gradient::from_element(&gradient, resolver)))
+        }
+        _ => Some(Paint::Color(value.to_string())),
+    }
+}
+
+impl Gradient {
+    fn from_element(element: &Element, resolver: &dyn Resolver) -> Self {
+        let color = |name: &str| {
+            attribute(element, name)
+                .map(|value| resolver.color(value).unwrap_or_else(|| value.to_string()))
+        };
+
+        let mut stops: Vec<(f32, String)> = children(element, "item")
+            .filter_map(|item| {
+                let offset = attribute(item, "offset").and_then(number)?;
+                let value = attribute(item, "color")?;
+                let color = resolver.color(value).unwrap_or_else(|| value.to_string());
+
+                Some((offset, color))
+            })
+            .collect();
+        if stops.is_empty() {
+            stops = [
+                (0.0, color("startColor")),
+                (0.5, color("centerColor")),
+                (1.0, color("endColor")),
+            ]
+            .iter()
+            .filter_map(|(offset, color)| color.clone().map(|color| (*offset, color)))
+            .collect();
+        }
+
+        let kind = match attribute(element, "type") {
+            Some("radial") | Some("1") => GradientKind::Radial,
+            Some("sweep") | Some("2") => GradientKind::Sweep,
+            _ => GradientKind::Linear,
+        };
+        let value = |name: &str| attribute(element, name).and_then(number).unwrap_or(0.0);
+
+        Self {
+            kind,
+            start_x: value("startX"),
+            start_y: value("startY"),
+            end_x: value("endX"),
+            end_y: value("endY"),
+            center_x: value("centerX"),
+            center_y: value("centerY"),
+            radius: value("gradientRadius"),
+            stops,
+        }
+    }
+}
+
+/// Splits a color like `#aarrggbb`, `#rrggbb`, `#argb` or `#rgb` on its SVG color and opacity
+fn svg_color(value: &str) -> Option<(String, f32)> {
+    let hex = value.strip_prefix('#')?;
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let expand = |short: &str| short.chars().flat_map(|c| [c, c]).collect::<String>();
+    let (alpha, rgb) = match hex.len() {
+        8 => (hex[..2].to_string(), hex[2..].to_string()),
+        6 => ("ff".to_string(), hex.to_string()),
+        4 => (expand(&hex[..1]), expand(&hex[1..])),
+        3 => ("ff".to_string(), expand(hex)),
+        _ => return None,
+    };
+    let alpha = u8::from_str_radix(&alpha, 16).ok()?;
+
+    Some((format!("#{}", rgb), f32::from(alpha) / 255.0))
+}
+
+#[derive(Debug, Default)]
+struct SvgWriter {
+    out: String,
+    next_id: usize,
+}
+
+impl SvgWriter {
+    fn id(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{}{}", prefix, self.next_id)
+    }
+
+    fn write_nodes(&mut self, nodes: &[VectorNode]) {
+        let mut clips = 0;
+
+        for node in nodes {
+            match node {
+                VectorNode::Group(group) => self.write_group(group),
+                VectorNode::Path(path) => self.write_path(path),
+                VectorNode::ClipPath(path_data) => {
+                    let id = self.id("clip");
+                    self.out.push_str(&format!(
+                        "<clipPath id=\"{}\"><path d=\"{}\"/></clipPath>\n<g clip-path=\"url(#{})\">\n",
+                        id,
+                        escape_xml(path_data),
+                        id
+                    ));
+                    clips += 1;
+                }
+            }
+        }
+
+        for _ in 0..clips {
+            self.out.push_str("</g>\n");
+        }
+    }
+
+    fn write_group(&mut self, group: &VectorGroup) {
+        let identity = group.rotation == 0.0
+            && group.scale_x == 1.0
+            && group.scale_y == 1.0
+            && group.translate_x == 0.0
+            && group.translate_y == 0.0;
+
+        if identity {
+            self.out.push_str("<g>\n");
+        } else {
+            // Same order Android applies them: scale and rotate around the pivot, then translate
+            self.out.push_str(&format!(
+                "<g transform=\"translate({} {}) rotate({}) scale({} {}) translate({} {})\">\n",
+                group.translate_x + group.pivot_x,
+                group.translate_y + group.pivot_y,
+                group.rotation,
+                group.scale_x,
+                group.scale_y,
+                -group.pivot_x,
+                -group.pivot_y
+            ));
+        }
+
+        self.write_nodes(&group.children);
+        self.out.push_str("</g>\n");
+    }
+
+    fn write_path(&mut self, path: &VectorPath) {
+        let mut attributes = vec![format!("d=\"{}\"", escape_xml(&path.path_data))];
+
+        attributes.extend(self.write_paint("fill", path.fill.as_ref(), path.fill_alpha));
+        if path.even_odd {
+            attributes.push("fill-rule=\"evenodd\"".to_string());
+        }
+
+        if path.stroke.is_some() && path.stroke_width > 0.0 {
+            attributes.extend(self.write_paint("stroke", path.stroke.as_ref(), path.stroke_alpha));
+            attributes.push(format!("stroke-width=\"{}\"", path.stroke_width));
+            if let Some(cap) = &path.line_cap {
+                attributes.push(format!("stroke-linecap=\"{}\"", escape_xml(cap)));
+            }
+            if let Some(join) = &path.line_join {
+                attributes.push(format!("stroke-linejoin=\"{}\"", escape_xml(join)));
+            }
+            if let Some(limit) = path.miter_limit {
+                attributes.push(format!("stroke-miterlimit=\"{}\"", limit));
+            }
+        }
+
+        self.out
+            .push_str(&format!("<path {}/>\n", attributes.join(" ")));
+    }
+
+    /// Returns the attributes that apply the paint, writing the gradient it needs, if any
+    fn write_paint(&mut self, property: &str, paint: Option<&Paint>, alpha: f32) -> Vec<String> {
+        let mut attributes = Vec::new();
+        let mut opacity = alpha;
+
+        match paint {
+            None => attributes.push(format!("{}=\"none\"", property)),
+            Some(Paint::Gradient(gradient)) if gradient.kind != GradientKind::Sweep => {
+                let id = self.write_gradient(gradient);
+                attributes.push(format!("{}=\"url(#{})\"", property, id));
+            }
+            Some(Paint::Gradient(gradient)) => {
+                // SVG has no sweep gradients: use the first color
+                let color = gradient.stops.first().map(|(_, color)| color.as_str());
+                match color.and_then(svg_color) {
+                    Some((color, color_alpha)) => {
+                        attributes.push(format!("{}=\"{}\"", property, color));
+                        opacity *= color_alpha;
+                    }
+                    None => attributes.push(format!("{}=\"currentColor\"", property)),
+                }
+            }
+            Some(Paint::Color(color)) => match svg_color(color) {
+                Some((color, color_alpha)) => {
+                    attributes.push(format!("{}=\"{}\"", property, color));
+                    opacity *= color_alpha;
+                }
+                None => attributes.push(format!("{}=\"currentColor\"", property)),
+            },
+        }
+
+        if paint.is_some() && opacity < 1.0 {
+            attributes.push(format!("{}-opacity=\"{}\"", property, opacity));
+        }
+
+        attributes
+    }
+
+    fn write_gradient(&mut self, gradient: &Gradient) -> String {
+        let id = self.id("gradient");
+
+        let (tag, geometry) = match gradient.kind {
+            GradientKind::Radial => (
+                "radialGradient",
+                format!(
+                    "cx=\"{}\" cy=\"{}\" r=\"{}\"",
+                    gradient.center_x, gradient.center_y, gradient.radius
+                ),
+            ),
+            _ => (
+                "linearGradient",
+                format!(
+                    "x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"",
+                    gradient.start_x, gradient.start_y, gradient.end_x, gradient.end_y
+                ),
+            ),
+        };
+
+        self.out.push_str(&format!(
+            "<{} id=\"{}\" gradientUnits=\"userSpaceOnUse\" {}>\n",
+            tag, id, geometry
+        ));
+        for (offset, color) in &gradient.stops {
+            let (color, alpha) =
+                svg_color(color).unwrap_or_else(|| ("currentColor".to_string(), 1.0));
+            self.out.push_str(&format!(
+                "<stop offset=\"{}\" stop-color=\"{}\" stop-opacity=\"{}\"/>\n",
+                offset, color, alpha
+            ));
+        }
+        self.out.push_str(&format!("</{}>\n", tag));
+
+        id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{svg_color, VectorDrawable};
+    use crate::{
+        interpret::{tests::element, NoResolver, Resolver},
+        model::Element,
+    };
+
+    fn vector() -> Element {
+        let mut root = element(
+            "vector",
+            &[
+                ("android:width", "24.0dip"),
+                ("android:height", "24.0dip"),
+                ("android:viewportWidth", "48.0"),
+                ("android:viewportHeight", "48.0"),
+            ],
+        );
+        let mut group = element(
+            "group",
+            &[("android:rotation", "90.0"), ("android:pivotX", "24.0")],
+        );
+        group.append(element("clip-path", &[("android:pathData", "M0,0h24v24z")]));
+        group.append(element(
+            "path",
+            &[
+                ("android:pathData", "M12,2L2,22h20z"),
+                ("android:fillColor", "#80ff0000"),
+                ("android:fillType", "evenOdd"),
+            ],
+        ));
+
+        let gradient = element(
+            "gradient",
+            &[
+                ("android:type", "linear"),
+                ("android:endX", "48.0"),
+                ("android:startColor", "#ff000000"),
+                ("android:endColor", "@color/accent"),
+            ],
+        );
+        let mut attr = element("attr", &[("name", "android:fillColor")]);
+        attr.append(gradient);
+        let mut path = element("path", &[("android:pathData", "M0,0h48v48h-48z")]);
+        path.append(attr);
+
+        root.append(group);
+        root.append(path);
+        root
+    }
+
+    #[test]
+    fn it_renders_a_vector_to_svg() {
+        let svg = VectorDrawable::from_element(&vector(), &NoResolver)
+            .unwrap()
+            .to_svg();
+
+        assert!(svg.starts_with(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"24\" height=\"24\" viewBox=\"0 0 48 48\">"
+        ));
+        assert!(svg
+            .contains("<g transform=\"translate(24 0) rotate(90) scale(1 1) translate(-24 -0)\">"));
+        assert!(svg.contains("<clipPath id=\"clip1\"><path d=\"M0,0h24v24z\"/></clipPath>"));
+        assert!(svg.contains(
+            "<path d=\"M12,2L2,22h20z\" fill=\"#ff0000\" fill-opacity=\"0.5019608\" fill-rule=\"evenodd\"/>"
+        ));
+        assert!(svg.contains("<linearGradient id=\"gradient2\" gradientUnits=\"userSpaceOnUse\" x1=\"0\" y1=\"0\" x2=\"48\" y2=\"0\">"));
+        assert!(svg.contains("<stop offset=\"1\" stop-color=\"currentColor\" stop-opacity=\"1\"/>"));
+        assert!(svg.contains("<path d=\"M0,0h48v48h-48z\" fill=\"url(#gradient2)\"/>"));
+
+        assert_eq!(Some(("#112233".to_string(), 1.0)), svg_color("#123"));
+        assert_eq!(None, svg_color("@color/accent"));
+    }
+
+    struct FakeResolver;
+
+    impl Resolver for FakeResolver {
+        fn color(&self, reference: &str) -> Option<String> {
+            match reference {
+                "@color/accent" => Some("#ff00ff00".to_string()),
+                _ => None,
+            }
+        }
+
+        fn xml(&self, reference: &str) -> Option<Element> {
+            match reference {
+                "@drawable/$avd__0" => Some(vector()),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn it_follows_the_drawable_of_animated_vectors() {
+        let root = element(
+            "animated-vector",
+            &[("android:drawable", "@drawable/$avd__0")],
+        );
+
+        let svg = VectorDrawable::from_element(&root, &FakeResolver)
+            .unwrap()
+            .to_svg();
+        assert!(svg.contains("<stop offset=\"1\" stop-color=\"#00ff00\" stop-opacity=\"1\"/>"));
+
+        assert!(VectorDrawable::from_element(&root, &NoResolver).is_err());
+    }
+}
@@ -38,11 +38,18 @@ pub mod apk;
 pub mod chunks;
 pub mod decoder;
 pub mod encoder;
+pub mod interpret;
 pub mod model;
+pub mod native;
 #[cfg(test)]
 pub mod raw_chunks;
+pub mod search;
+pub mod snapshot;
+pub mod symbols;
 #[cfg(test)]
 pub mod test;
+pub mod usage;
+pub mod values;
 pub mod visitor;
 
 /// Contents of android's resources.arsc
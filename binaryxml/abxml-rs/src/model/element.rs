@@ -1,37 +1,48 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fmt::{self, Display, Formatter},
     iter,
-    rc::Rc,
+    sync::Arc,
 };
 
 use log::error;
+use serde::{ser::SerializeStruct, Serialize, Serializer};
 
 #[derive(Default, Debug, PartialEq, Eq, Hash)]
 pub struct Tag {
-    name: Rc<String>,
-    prefixes: Vec<Rc<String>>,
+    name: Arc<String>,
+    prefixes: Vec<Arc<String>>,
 }
 
 impl Tag {
-    pub fn new(name: Rc<String>, prefixes: Vec<Rc<String>>) -> Self {
+    pub fn new(name: Arc<String>, prefixes: Vec<Arc<String>>) -> Self {
         Self { name, prefixes }
     }
 
-    pub fn get_name(&self) -> Rc<String> {
+    pub fn get_name(&self) -> Arc<String> {
         self.name.clone()
     }
 
-    pub fn get_prefixes(&self) -> &Vec<Rc<String>> {
+    pub fn get_prefixes(&self) -> &Vec<Arc<String>> {
         &self.prefixes
     }
 }
 
+/// Content of an element, on document order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+    Text(String),
+    /// Index of the child element on `Element::get_children`
+    Element(usize),
+}
+
 #[derive(Default, Debug)]
 pub struct Element {
     tag: Tag,
     attrs: HashMap<String, String>,
     children: Vec<Element>,
+    /// Text and children, on the order they appear: `<b>Hello <i>x</i> again</b>`
+    nodes: Vec<Node>,
     level: u32,
 }
 
@@ -41,11 +52,13 @@ impl Element {
             tag,
             attrs,
             children: Vec::new(),
+            nodes: Vec::new(),
             level: 0,
         }
     }
 
     pub fn append(&mut self, element: Self) {
+        self.nodes.push(Node::Element(self.children.len()));
         self.children.push(element)
     }
 
@@ -57,6 +70,29 @@ impl Element {
         &self.attrs
     }
 
+    pub fn append_text(&mut self, text: &str) {
+        match self.nodes.last_mut() {
+            Some(Node::Text(last)) => last.push_str(text),
+            _ => self.nodes.push(Node::Text(text.to_string())),
+        }
+    }
+
+    /// Text held by the element, like the domain of a `<domain>` tag. Text around the children
+    /// is joined.
+    pub fn get_text(&self) -> String {
+        self.nodes
+            .iter()
+            .filter_map(|node| match node {
+                Node::Text(text) => Some(text.as_str()),
+                Node::Element(_) => None,
+            })
+            .collect()
+    }
+
+    pub fn get_nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
     pub fn get_tag(&self) -> &Tag {
         &self.tag
     }
@@ -66,6 +102,19 @@ impl Element {
     }
 }
 
+/// Serializes the element as `{ name, attributes, children }`, with the attributes sorted by name
+impl Serialize for Element {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let attributes: BTreeMap<&String, &String> = self.attrs.iter().collect();
+
+        let mut state = serializer.serialize_struct("Element", 3)?;
+        state.serialize_field("name", self.tag.get_name().as_str())?;
+        state.serialize_field("attributes", &attributes)?;
+        state.serialize_field("children", &self.children)?;
+        state.end()
+    }
+}
+
 impl Display for Element {
     fn fmt(&self, formatter: &mut Formatter) -> Result<(), fmt::Error> {
         let tabs = iter::repeat("\t")
@@ -112,7 +161,23 @@ impl ElementContainer {
             });
     }
 
+    /// Appends text to the element that is currently open. Text outside the root is ignored.
+    pub fn add_text(&mut self, text: &str) {
+        if let Some(element) = self.stack.last_mut() {
+            element.append_text(text);
+        }
+    }
+
     pub fn get_root(&self) -> &Option<Element> {
         &self.root
     }
+
+    pub fn into_root(self) -> Option<Element> {
+        self.root
+    }
+
+    /// Amount of elements which have been started but not ended yet
+    pub fn get_depth(&self) -> usize {
+        self.stack.len()
+    }
 }
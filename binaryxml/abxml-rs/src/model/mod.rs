@@ -2,7 +2,7 @@
 
 use std::{
     collections::{BTreeMap, HashMap},
-    rc::Rc,
+    sync::Arc,
 };
 
 use anyhow::Error;
@@ -15,7 +15,7 @@ pub mod value;
 
 use self::owned::Entry;
 pub use self::{
-    element::{Element, ElementContainer, Tag},
+    element::{Element, ElementContainer, Node, Tag},
     value::Value,
 };
 use crate::visitor::Origin;
@@ -56,7 +56,7 @@ impl Identifier for u32 {
 pub trait StringTable {
     fn get_strings_len(&self) -> u32;
     fn get_styles_len(&self) -> u32;
-    fn get_string(&self, idx: u32) -> Result<Rc<String>, Error>;
+    fn get_string(&self, idx: u32) -> Result<Arc<String>, Error>;
 }
 
 // TODO: Decide if the trait should return Results or not
@@ -75,8 +75,8 @@ pub trait Library {
     ) -> Result<String, Error>;
     // fn get_entries(&self) -> &Entries;
     fn get_entry(&self, id: u32) -> Result<&Entry, Error>;
-    fn get_entries_string(&self, str_id: u32) -> Result<Rc<String>, Error>;
-    fn get_spec_string(&self, str_id: u32) -> Result<Rc<String>, Error>;
+    fn get_entries_string(&self, str_id: u32) -> Result<Arc<String>, Error>;
+    fn get_spec_string(&self, str_id: u32) -> Result<Arc<String>, Error>;
 
     fn resid_to_string(&self, resid: u32, namespace: Option<String>) -> String {
         self.get_entry(resid)
@@ -166,14 +166,14 @@ pub trait TagEnd {
 
 pub trait NamespaceStart {
     fn get_line(&self) -> Result<u32, Error>;
-    fn get_prefix<S: StringTable>(&self, string_table: &S) -> Result<Rc<String>, Error>;
-    fn get_namespace<S: StringTable>(&self, string_table: &S) -> Result<Rc<String>, Error>;
+    fn get_prefix<S: StringTable>(&self, string_table: &S) -> Result<Arc<String>, Error>;
+    fn get_namespace<S: StringTable>(&self, string_table: &S) -> Result<Arc<String>, Error>;
 }
 
 pub trait NamespaceEnd {
     fn get_line(&self) -> Result<u32, Error>;
-    fn get_prefix<S: StringTable>(&self, string_table: &S) -> Result<Rc<String>, Error>;
-    fn get_namespace<S: StringTable>(&self, string_table: &S) -> Result<Rc<String>, Error>;
+    fn get_prefix<S: StringTable>(&self, string_table: &S) -> Result<Arc<String>, Error>;
+    fn get_namespace<S: StringTable>(&self, string_table: &S) -> Result<Arc<String>, Error>;
 }
 
 pub trait TypeSpec {
@@ -215,6 +215,170 @@ pub trait Configuration {
     fn get_locale_script(&self) -> Result<Option<String>, Error>;
     fn get_locale_variant(&self) -> Result<Option<String>, Error>;
     fn get_secondary_layout(&self) -> Result<Option<u8>, Error>;
+
+    /// Returns the qualifiers of the configuration as they appear on the resource folder names
+    /// (for example, `es-rES-land-v21`). The default configuration has no qualifiers and returns
+    /// an empty string.
+    fn get_qualifiers(&self) -> String {
+        let mut qualifiers = Vec::new();
+
+        match self.get_mcc() {
+            Ok(mcc) if mcc != 0 => qualifiers.push(format!("mcc{}", mcc)),
+            _ => (),
+        }
+        match self.get_mnc() {
+            Ok(0xFFFF) => qualifiers.push("mnc00".to_string()),
+            Ok(mnc) if mnc != 0 => qualifiers.push(format!("mnc{}", mnc)),
+            _ => (),
+        }
+
+        let known = |s: &String| !s.is_empty() && s != "any";
+        let language = self.get_language().ok().filter(known);
+        let region = self.get_region().ok().filter(known);
+        let script = self.get_locale_script().ok().flatten();
+        let variant = self.get_locale_variant().ok().flatten();
+        if let Some(language) = language {
+            if script.is_some() || variant.is_some() {
+                let mut locale = format!("b+{}", language);
+                for subtag in script.iter().chain(region.iter()).chain(variant.iter()) {
+                    locale.push('+');
+                    locale.push_str(subtag);
+                }
+                qualifiers.push(locale);
+            } else {
+                qualifiers.push(language);
+                if let Some(region) = region {
+                    qualifiers.push(format!("r{}", region));
+                }
+            }
+        }
+
+        let screen_layout = self.get_screen_layout().unwrap_or(0);
+        match screen_layout & 0xC0 {
+            0x40 => qualifiers.push("ldltr".to_string()),
+            0x80 => qualifiers.push("ldrtl".to_string()),
+            _ => (),
+        }
+        for (dp, prefix) in &[
+            (self.get_smallest_screen(), "sw"),
+            (self.get_screen_width(), "w"),
+            (self.get_screen_height(), "h"),
+        ] {
+            match dp {
+                Ok(dp) if *dp != 0 => qualifiers.push(format!("{}{}dp", prefix, dp)),
+                _ => (),
+            }
+        }
+        let size = match screen_layout & 0x0F {
+            1 => "small",
+            2 => "normal",
+            3 => "large",
+            4 => "xlarge",
+            _ => "",
+        };
+        let long = match screen_layout & 0x30 {
+            0x10 => "notlong",
+            0x20 => "long",
+            _ => "",
+        };
+        let round = match self.get_secondary_layout().ok().flatten().unwrap_or(0) & 0x03 {
+            1 => "notround",
+            2 => "round",
+            _ => "",
+        };
+        let orientation = match self.get_orientation().unwrap_or(0) {
+            1 => "port",
+            2 => "land",
+            3 => "square",
+            _ => "",
+        };
+        let ui_mode = self.get_ui_mode().unwrap_or(0);
+        let ui_mode_type = match ui_mode & 0x0F {
+            2 => "desk",
+            3 => "car",
+            4 => "television",
+            5 => "appliance",
+            6 => "watch",
+            7 => "vrheadset",
+            _ => "",
+        };
+        let night = match ui_mode & 0x30 {
+            0x10 => "notnight",
+            0x20 => "night",
+            _ => "",
+        };
+        qualifiers.extend(
+            [size, long, round, orientation, ui_mode_type, night]
+                .iter()
+                .filter(|q| !q.is_empty())
+                .map(|q| q.to_string()),
+        );
+
+        match self.get_density().unwrap_or(0) {
+            0 => (),
+            120 => qualifiers.push("ldpi".to_string()),
+            160 => qualifiers.push("mdpi".to_string()),
+            213 => qualifiers.push("tvdpi".to_string()),
+            240 => qualifiers.push("hdpi".to_string()),
+            320 => qualifiers.push("xhdpi".to_string()),
+            480 => qualifiers.push("xxhdpi".to_string()),
+            640 => qualifiers.push("xxxhdpi".to_string()),
+            0xFFFE => qualifiers.push("anydpi".to_string()),
+            0xFFFF => qualifiers.push("nodpi".to_string()),
+            density => qualifiers.push(format!("{}dpi", density)),
+        }
+
+        let input_flags = self.get_input_flags().unwrap_or(0);
+        let touchscreen = match self.get_touchscreen().unwrap_or(0) {
+            1 => "notouch",
+            2 => "stylus",
+            3 => "finger",
+            _ => "",
+        };
+        let keys_hidden = match input_flags & 0x03 {
+            1 => "keysexposed",
+            2 => "keyshidden",
+            3 => "keyssoft",
+            _ => "",
+        };
+        let keyboard = match self.get_keyboard().unwrap_or(0) {
+            1 => "nokeys",
+            2 => "qwerty",
+            3 => "12key",
+            _ => "",
+        };
+        let nav_hidden = match input_flags & 0x0C {
+            0x04 => "navexposed",
+            0x08 => "navhidden",
+            _ => "",
+        };
+        let navigation = match self.get_navigation().unwrap_or(0) {
+            1 => "nonav",
+            2 => "dpad",
+            3 => "trackball",
+            4 => "wheel",
+            _ => "",
+        };
+        qualifiers.extend(
+            [touchscreen, keys_hidden, keyboard, nav_hidden, navigation]
+                .iter()
+                .filter(|q| !q.is_empty())
+                .map(|q| q.to_string()),
+        );
+
+        match (self.get_width(), self.get_height()) {
+            (Ok(width), Ok(height)) if width != 0 && height != 0 => {
+                qualifiers.push(format!("{}x{}", width, height))
+            }
+            _ => (),
+        }
+        match self.get_sdk_version() {
+            Ok(sdk) if sdk != 0 => qualifiers.push(format!("v{}", sdk)),
+            _ => (),
+        }
+
+        qualifiers.join("-")
+    }
 }
 
 #[cfg(test)]
@@ -7,8 +7,14 @@ pub use self::{
     package::PackageBuf,
     resources::ResourcesBuf,
     string_table::{Encoding, StringTableBuf},
-    table_type::{ComplexEntry, ConfigurationBuf, Entry, EntryHeader, SimpleEntry, TableTypeBuf},
-    table_type_spec::TableTypeSpecBuf,
+    table_type::{
+        format_names, AttrDefinition, Bag, BagKey, ComplexEntry, ConfigurationBuf, Entry,
+        EntryHeader, Quantity, SimpleEntry, TableTypeBuf,
+    },
+    table_type_spec::{
+        SpecFlags, TableTypeSpecBuf, TypeFlags, SPEC_PUBLIC, SPEC_STAGED_API, TYPE_FLAG_OFFSET16,
+        TYPE_FLAG_SPARSE,
+    },
     xml::{AttributeBuf, XmlNamespaceEndBuf, XmlNamespaceStartBuf, XmlTagEndBuf, XmlTagStartBuf},
 };
 
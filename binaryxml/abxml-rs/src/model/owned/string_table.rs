@@ -1,4 +1,4 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use byteorder::{LittleEndian, WriteBytesExt};
 use encoding::{
@@ -20,8 +20,8 @@ pub enum Encoding {
 
 #[derive(Debug)]
 pub struct StringTableBuf {
-    strings: Vec<Rc<String>>,
-    styles: Vec<Rc<String>>,
+    strings: Vec<Arc<String>>,
+    styles: Vec<Arc<String>>,
     encoding: Encoding,
 }
 
@@ -45,7 +45,7 @@ impl StringTableBuf {
     }
 
     pub fn add_string(&mut self, new_string: String) {
-        self.strings.push(Rc::new(new_string));
+        self.strings.push(Arc::new(new_string));
     }
 }
 
@@ -97,21 +97,23 @@ impl OwnedBuf for StringTableBuf {
         for string in &self.strings {
             string_offsets.push(current_offset);
             let mut encoded_string = Vec::new();
-            let (size, error) = encoder.raw_feed(string, &mut encoded_string);
+            let (_, error) = encoder.raw_feed(string, &mut encoded_string);
 
             ensure!(error.is_none(), "error encoding string");
 
-            // Write size
-            let low = (size & 0xFF) as u8;
-            let high = ((size & 0xFF00) >> 8) as u8;
-            string_buffer.push(low);
-            string_buffer.push(high);
-            string_buffer.extend(&encoded_string);
-            string_buffer.push(0x00);
-            string_buffer.push(0x00);
+            if self.encoding == Encoding::Utf8 {
+                // Length in UTF-16 code units, then in bytes, and a NUL terminator
+                write_utf8_length(&mut string_buffer, string.encode_utf16().count())?;
+                write_utf8_length(&mut string_buffer, encoded_string.len())?;
+                string_buffer.extend(&encoded_string);
+                string_buffer.push(0x00);
+            } else {
+                write_utf16_length(&mut string_buffer, encoded_string.len() / 2)?;
+                string_buffer.extend(&encoded_string);
+                string_buffer.write_u16::<LittleEndian>(0)?;
+            }
 
             current_offset = string_buffer.len() as u32;
-            // println!("Current offset: {}", current_offset);
         }
 
         // Encode styles and save offsets
@@ -135,6 +137,34 @@ impl OwnedBuf for StringTableBuf {
     }
 }
 
+fn write_utf8_length(out: &mut Vec<u8>, length: usize) -> Result<(), Error> {
+    ensure!(
+        length <= 0x7FFF,
+        "string too long for an UTF-8 string table"
+    );
+
+    if length > 0x7F {
+        out.push(0x80 | (length >> 8) as u8);
+    }
+    out.push((length & 0xFF) as u8);
+
+    Ok(())
+}
+
+fn write_utf16_length(out: &mut Vec<u8>, length: usize) -> Result<(), Error> {
+    ensure!(
+        length <= 0x7FFF_FFFF,
+        "string too long for an UTF-16 string table"
+    );
+
+    if length > 0x7FFF {
+        out.write_u16::<LittleEndian>(0x8000 | (length >> 16) as u16)?;
+    }
+    out.write_u16::<LittleEndian>((length & 0xFFFF) as u16)?;
+
+    Ok(())
+}
+
 impl StringTable for StringTableBuf {
     fn get_strings_len(&self) -> u32 {
         self.strings.len() as u32
@@ -144,7 +174,7 @@ impl StringTable for StringTableBuf {
         self.styles.len() as u32
     }
 
-    fn get_string(&self, idx: u32) -> Result<Rc<String>, Error> {
+    fn get_string(&self, idx: u32) -> Result<Arc<String>, Error> {
         if let Some(s) = self.strings.get(idx as usize) {
             Ok(s.clone())
         } else {
@@ -156,7 +186,7 @@ impl StringTable for StringTableBuf {
 #[cfg(test)]
 #[allow(clippy::non_ascii_literal)]
 mod tests {
-    use super::{OwnedBuf, StringTable, StringTableBuf};
+    use super::{Encoding, OwnedBuf, StringTable, StringTableBuf};
     use crate::{chunks::StringTableWrapper, raw_chunks, test::compare_chunks};
 
     #[test]
@@ -194,6 +224,31 @@ mod tests {
 
     #[test]
     fn identity_utf8() {
-        // TODO: Test with UTF8 encoding
+        let mut string_table = StringTableBuf::default();
+        string_table.add_string("some string".to_string());
+        string_table.add_string("忠犬ハチ公".to_string());
+        string_table.add_string("a".repeat(200));
+
+        let raw = string_table.to_vec().unwrap();
+        let wrapper = StringTableWrapper::new(&raw);
+
+        assert_eq!("some string", *wrapper.get_string(0).unwrap());
+        assert_eq!("忠犬ハチ公", *wrapper.get_string(1).unwrap());
+        assert_eq!("a".repeat(200), *wrapper.get_string(2).unwrap());
+        assert!(wrapper.get_string(3).is_err());
+    }
+
+    #[test]
+    fn it_decodes_long_utf16_strings() {
+        let mut string_table = StringTableBuf::default();
+        string_table.set_encoding(Encoding::Utf16);
+        string_table.add_string("b".repeat(0x8001));
+        string_table.add_string("ハチ".to_string());
+
+        let raw = string_table.to_vec().unwrap();
+        let wrapper = StringTableWrapper::new(&raw);
+
+        assert_eq!("b".repeat(0x8001), *wrapper.get_string(0).unwrap());
+        assert_eq!("ハチ", *wrapper.get_string(1).unwrap());
     }
 }
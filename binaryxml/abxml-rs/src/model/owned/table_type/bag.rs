@@ -0,0 +1,313 @@
+use std::{cmp::Reverse, fmt};
+
+use super::entry::{ComplexEntry, SimpleEntry};
+
+const KEY_TYPE: u32 = 0x0100_0000;
+const KEY_MIN: u32 = 0x0100_0001;
+const KEY_MAX: u32 = 0x0100_0002;
+const KEY_L10N: u32 = 0x0100_0003;
+const KEY_QUANTITY_START: u32 = 0x0100_0004;
+const KEY_INDEX_MASK: u32 = 0xFFFF_0000;
+const KEY_INDEX: u32 = 0x0200_0000;
+
+/// Format of the values an attribute accepts, as found on its `^type` item
+const FORMATS: [(u32, &str); 10] = [
+    (0x0000_0001, "reference"),
+    (0x0000_0002, "string"),
+    (0x0000_0004, "integer"),
+    (0x0000_0008, "boolean"),
+    (0x0000_0010, "color"),
+    (0x0000_0020, "float"),
+    (0x0000_0040, "dimension"),
+    (0x0000_0080, "fraction"),
+    (FORMAT_ENUM, "enum"),
+    (FORMAT_FLAGS, "flags"),
+];
+const FORMAT_ENUM: u32 = 0x0001_0000;
+const FORMAT_FLAGS: u32 = 0x0002_0000;
+
+/// Quantity of an item of a `plurals` resource
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Quantity {
+    Other,
+    Zero,
+    One,
+    Two,
+    Few,
+    Many,
+}
+
+impl Quantity {
+    const ALL: [Self; 6] = [
+        Self::Other,
+        Self::Zero,
+        Self::One,
+        Self::Two,
+        Self::Few,
+        Self::Many,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Other => "other",
+            Self::Zero => "zero",
+            Self::One => "one",
+            Self::Two => "two",
+            Self::Few => "few",
+            Self::Many => "many",
+        }
+    }
+}
+
+/// Meaning of the key of an item of a complex entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BagKey {
+    /// `^type`: format mask of an `attr`
+    Type,
+    /// `^min`: minimum value of an integer `attr`
+    Min,
+    /// `^max`: maximum value of an integer `attr`
+    Max,
+    /// `^l10n`: localization hint of an `attr`
+    L10n,
+    /// Item of a `plurals`
+    Quantity(Quantity),
+    /// `^index_N`: position of the item on an `array`
+    Index(u32),
+    /// Any other resource: the attribute set by a `style` or a symbol of an enum or flags `attr`
+    Resource(u32),
+}
+
+impl BagKey {
+    pub fn new(key: u32) -> Self {
+        match key {
+            KEY_TYPE => Self::Type,
+            KEY_MIN => Self::Min,
+            KEY_MAX => Self::Max,
+            KEY_L10N => Self::L10n,
+            _ if key >= KEY_QUANTITY_START
+                && key < KEY_QUANTITY_START + Quantity::ALL.len() as u32 =>
+            {
+                Self::Quantity(Quantity::ALL[(key - KEY_QUANTITY_START) as usize])
+            }
+            _ if key & KEY_INDEX_MASK == KEY_INDEX => Self::Index(key & !KEY_INDEX_MASK),
+            _ => Self::Resource(key),
+        }
+    }
+}
+
+impl fmt::Display for BagKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Type => write!(f, "^type"),
+            Self::Min => write!(f, "^min"),
+            Self::Max => write!(f, "^max"),
+            Self::L10n => write!(f, "^l10n"),
+            Self::Quantity(quantity) => write!(f, "quantity={}", quantity.as_str()),
+            Self::Index(index) => write!(f, "^index_{}", index),
+            Self::Resource(id) => write!(f, "0x{:08x}", id),
+        }
+    }
+}
+
+/// Definition of an attribute (`<attr>` resource)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AttrDefinition {
+    /// Mask of the formats the attribute accepts
+    pub format: u32,
+    pub min: Option<i32>,
+    pub max: Option<i32>,
+    pub l10n: Option<u32>,
+    /// Symbols of an enum or flags attribute: the ID of their `@id` resource and their value
+    pub symbols: Vec<(u32, u32)>,
+}
+
+impl AttrDefinition {
+    pub fn new(entry: &ComplexEntry) -> Self {
+        let mut definition = Self::default();
+
+        for item in entry.get_entries() {
+            match BagKey::new(item.get_id()) {
+                BagKey::Type => definition.format = item.get_value(),
+                BagKey::Min => definition.min = Some(item.get_value() as i32),
+                BagKey::Max => definition.max = Some(item.get_value() as i32),
+                BagKey::L10n => definition.l10n = Some(item.get_value()),
+                _ => definition.symbols.push((item.get_id(), item.get_value())),
+            }
+        }
+
+        definition
+    }
+
+    /// Names of the accepted formats, like `reference` or `dimension`
+    pub fn get_format_names(&self) -> Vec<&'static str> {
+        format_names(self.format)
+    }
+
+    /// True if the value is exactly one of the symbols, rather than a combination of them
+    pub fn is_enum(&self) -> bool {
+        self.format & FORMAT_ENUM != 0 && self.format & FORMAT_FLAGS == 0
+    }
+
+    /// Returns the IDs of the symbols that represent the given value. Enums match a single symbol,
+    /// while flags are decomposed on the fewest symbols that cover them, in the same order as
+    /// apktool prints them.
+    pub fn resolve_symbols(&self, value: u32) -> Vec<u32> {
+        if self.is_enum() || value == 0 {
+            return self
+                .symbols
+                .iter()
+                .find(|(_, symbol_value)| *symbol_value == value)
+                .map(|(id, _)| *id)
+                .into_iter()
+                .collect();
+        }
+
+        let mut sorted: Vec<&(u32, u32)> = self.symbols.iter().filter(|(_, v)| *v != 0).collect();
+        sorted.sort_by_key(|(_, mask)| Reverse(mask.count_ones()));
+
+        let mut masks: Vec<u32> = Vec::new();
+        let mut ids = Vec::new();
+        for (id, mask) in sorted {
+            if mask & value == *mask && !masks.iter().any(|m| mask & m == *mask) {
+                masks.push(*mask);
+                ids.push(*id);
+            }
+        }
+
+        ids
+    }
+}
+
+/// Typed view of a complex entry, depending on the type of the resource that holds it
+#[derive(Debug, Clone)]
+pub enum Bag {
+    /// `plurals`: value for each quantity
+    Plurals(Vec<(Quantity, SimpleEntry)>),
+    /// `array`, `string-array` and `integer-array`: values on order
+    Array(Vec<SimpleEntry>),
+    /// `attr`: definition of the attribute
+    Attr(AttrDefinition),
+    /// `style`: value of each attribute, keyed by the ID of the attribute
+    Style(Vec<(u32, SimpleEntry)>),
+    /// Any other complex entry
+    Other(Vec<(BagKey, SimpleEntry)>),
+}
+
+impl Bag {
+    /// Interprets the complex entry as a resource of the given type (`plurals`, `style`...)
+    pub fn new(type_name: &str, entry: &ComplexEntry) -> Self {
+        let items = entry.get_entries();
+
+        match type_name {
+            "plurals" => Self::Plurals(
+                items
+                    .iter()
+                    .filter_map(|item| match BagKey::new(item.get_id()) {
+                        BagKey::Quantity(quantity) => Some((quantity, *item)),
+                        _ => None,
+                    })
+                    .collect(),
+            ),
+            "array" | "string-array" | "integer-array" => {
+                let mut indexed: Vec<(u32, SimpleEntry)> = items
+                    .iter()
+                    .map(|item| match BagKey::new(item.get_id()) {
+                        BagKey::Index(index) => (index, *item),
+                        _ => (u32::MAX, *item),
+                    })
+                    .collect();
+                indexed.sort_by_key(|(index, _)| *index);
+
+                Self::Array(indexed.into_iter().map(|(_, item)| item).collect())
+            }
+            "attr" | "^attr-private" => Self::Attr(AttrDefinition::new(entry)),
+            "style" => Self::Style(items.iter().map(|item| (item.get_id(), *item)).collect()),
+            _ => Self::Other(
+                items
+                    .iter()
+                    .map(|item| (BagKey::new(item.get_id()), *item))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Names of the formats on the given `^type` mask
+pub fn format_names(format: u32) -> Vec<&'static str> {
+    FORMATS
+        .iter()
+        .filter(|(mask, _)| format & mask != 0)
+        .map(|(_, name)| *name)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AttrDefinition, Bag, BagKey, Quantity};
+    use crate::model::owned::{ComplexEntry, SimpleEntry};
+
+    fn bag(items: &[(u32, u32)]) -> ComplexEntry {
+        let items = items
+            .iter()
+            .map(|(key, value)| SimpleEntry::new(*key, 0, 0x10, *value))
+            .collect();
+
+        ComplexEntry::new(1, 1, 0, items)
+    }
+
+    #[test]
+    fn it_interprets_bag_keys() {
+        assert_eq!(BagKey::Type, BagKey::new(0x0100_0000));
+        assert_eq!(BagKey::Quantity(Quantity::One), BagKey::new(0x0100_0006));
+        assert_eq!(BagKey::Index(12), BagKey::new(0x0200_000c));
+        assert_eq!(BagKey::Resource(0x7f01_0000), BagKey::new(0x7f01_0000));
+        assert_eq!("quantity=many", BagKey::new(0x0100_0009).to_string());
+        assert_eq!("^index_3", BagKey::new(0x0200_0003).to_string());
+    }
+
+    #[test]
+    fn it_sorts_array_items_by_index() {
+        let entry = bag(&[(0x0200_0001, 20), (0x0200_0000, 10)]);
+
+        match Bag::new("array", &entry) {
+            Bag::Array(items) => {
+                let values: Vec<u32> = items.iter().map(SimpleEntry::get_value).collect();
+                assert_eq!(vec![10, 20], values);
+            }
+            other => panic!("unexpected bag: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_decodes_attribute_definitions() {
+        let entry = bag(&[
+            (0x0100_0000, 0x0002_0004),
+            (0x0100_0001, 0),
+            (0x7f05_0001, 1),
+            (0x7f05_0002, 2),
+            (0x7f05_0003, 3),
+        ]);
+        let definition = AttrDefinition::new(&entry);
+
+        assert_eq!(vec!["integer", "flags"], definition.get_format_names());
+        assert_eq!(Some(0), definition.min);
+        assert!(!definition.is_enum());
+        assert_eq!(vec![0x7f05_0003], definition.resolve_symbols(3));
+        assert_eq!(vec![0x7f05_0002], definition.resolve_symbols(2));
+    }
+
+    #[test]
+    fn it_resolves_enum_symbols_exactly() {
+        let entry = bag(&[
+            (0x0100_0000, 0x0001_0000),
+            (0x7f05_0001, 1),
+            (0x7f05_0002, 3),
+        ]);
+        let definition = AttrDefinition::new(&entry);
+
+        assert!(definition.is_enum());
+        assert_eq!(vec![0x7f05_0002], definition.resolve_symbols(3));
+        assert!(definition.resolve_symbols(2).is_empty());
+    }
+}
@@ -3,6 +3,7 @@ use std::collections::HashMap;
 use anyhow::{format_err, Error};
 use byteorder::{LittleEndian, WriteBytesExt};
 
+use super::bag::{format_names, BagKey};
 use crate::{
     model::{
         value::{TOKEN_TYPE_ATTRIBUTE_REFERENCE_ID, TOKEN_TYPE_REFERENCE_ID, TOKEN_TYPE_STRING},
@@ -13,6 +14,34 @@ use crate::{
 
 const MASK_COMPLEX: u16 = 0x0001;
 
+/// Name of the resource with the given ID, or its hexadecimal ID if the package it belongs to is
+/// not loaded (a shared library, or the framework)
+fn resource_name(packages: &HashMap<u8, Library>, id: u32) -> String {
+    let package_id = id.get_package();
+
+    match packages.get(&package_id) {
+        Some(package) => package.resid_to_string(
+            id,
+            if package_id == 1 {
+                Some("android".into())
+            } else {
+                None
+            },
+        ),
+        None => format!("0x{:08x}", id),
+    }
+}
+
+fn format_type(format: u32) -> String {
+    let names = format_names(format);
+
+    if names.is_empty() {
+        "unknown".into()
+    } else {
+        names.join("|")
+    }
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Copy, Clone)]
 pub struct EntryHeader {
@@ -51,24 +80,13 @@ impl SimpleEntry {
     pub fn to_string(&self, packages: &HashMap<u8, Library>, main_package_id: u8) -> String {
         match self.value_type {
             TOKEN_TYPE_REFERENCE_ID | TOKEN_TYPE_ATTRIBUTE_REFERENCE_ID => {
-                let package_id = self.value_data.get_package();
-                let package = packages.get(&package_id).unwrap();
-                package.resid_to_string(
-                    self.value_data,
-                    if package_id == 1 {
-                        Some("android".into())
-                    } else {
-                        None
-                    },
-                )
-            }
-            TOKEN_TYPE_STRING => {
-                let package = packages.get(&main_package_id).unwrap();
-                package
-                    .get_string(self.value_data)
-                    .map(|e| e.to_string())
-                    .unwrap_or_else(|_| format!("Unknown string({})", self.value_data))
+                resource_name(packages, self.value_data)
             }
+            TOKEN_TYPE_STRING => packages
+                .get(&main_package_id)
+                .and_then(|package| package.get_string(self.value_data).ok())
+                .map(|string| string.to_string())
+                .unwrap_or_else(|| format!("Unknown string({})", self.value_data)),
             _ => Value::create(self.value_type, self.value_data)
                 .map(|x| x.to_string())
                 .unwrap_or_else(|_| format!("Unknown({})", self.value_data)),
@@ -134,47 +152,6 @@ pub struct ComplexEntry {
     entries: Vec<SimpleEntry>,
 }
 
-pub fn get_type_string(type_value: u32) -> String {
-    let mut types = Vec::new();
-
-    if type_value & 0x00000001 != 0 {
-        types.push("reference");
-    }
-    if type_value & 0x00000002 != 0 {
-        types.push("string");
-    }
-    if type_value & 0x00000004 != 0 {
-        types.push("integer");
-    }
-    if type_value & 0x00000008 != 0 {
-        types.push("boolean");
-    }
-    if type_value & 0x00000010 != 0 {
-        types.push("color");
-    }
-    if type_value & 0x00000020 != 0 {
-        types.push("float");
-    }
-    if type_value & 0x00000040 != 0 {
-        types.push("dimension");
-    }
-    if type_value & 0x00000080 != 0 {
-        types.push("fraction");
-    }
-    if type_value & 0x00010000 != 0 {
-        types.push("enum");
-    }
-    if type_value & 0x00020000 != 0 {
-        types.push("flags");
-    }
-
-    if types.is_empty() {
-        "unknown".into()
-    } else {
-        types.join("|")
-    }
-}
-
 impl ComplexEntry {
     pub fn to_hash_map(
         &self,
@@ -183,41 +160,19 @@ impl ComplexEntry {
     ) -> HashMap<String, String> {
         self.entries
             .iter()
-            .map(|e| {
-                if e.get_id() == 0x1000000 {
-                    ("type".into(), get_type_string(e.value_data))
-                } else {
-                    (
-                        {
-                            let package_id = e.get_id().get_package();
-                            let package = packages.get(&package_id).unwrap();
-                            package.resid_to_string(
-                                e.get_id(),
-                                if package_id == 1 {
-                                    Some("android".into())
-                                } else {
-                                    None
-                                },
-                            )
-                        },
-                        e.to_string(packages, main_package_id),
-                    )
-                }
+            .map(|e| match BagKey::new(e.get_id()) {
+                BagKey::Type => ("type".into(), format_type(e.value_data)),
+                BagKey::Resource(id) => (
+                    resource_name(packages, id),
+                    e.to_string(packages, main_package_id),
+                ),
+                key => (key.to_string(), e.to_string(packages, main_package_id)),
             })
             .collect()
     }
 
     pub fn to_string(&self, packages: &HashMap<u8, Library>) -> String {
-        let package_id = self.parent_entry_id.get_package();
-        let package = packages.get(&package_id).unwrap();
-        let refname = package.resid_to_string(
-            self.parent_entry_id,
-            if package_id == 1 {
-                Some("android".into())
-            } else {
-                None
-            },
-        );
+        let refname = resource_name(packages, self.parent_entry_id);
         format!("parent: {refname}")
     }
 }
@@ -240,6 +195,10 @@ impl ComplexEntry {
         self.key_index
     }
 
+    pub fn get_parent_entry_id(&self) -> u32 {
+        self.parent_entry_id
+    }
+
     pub fn get_referent_id(&self, value: u32) -> Option<u32> {
         for e in &self.entries {
             if e.get_value() == value {
@@ -363,3 +322,27 @@ impl Entry {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{ComplexEntry, SimpleEntry};
+    use crate::model::value::TOKEN_TYPE_REFERENCE_ID;
+
+    #[test]
+    fn it_formats_bags_of_packages_that_are_not_loaded() {
+        let items = vec![SimpleEntry::new(
+            0x0201_0000,
+            0,
+            TOKEN_TYPE_REFERENCE_ID,
+            0x0302_0000,
+        )];
+        let complex = ComplexEntry::new(0, 0, 0x0101_0000, items);
+        let packages = HashMap::new();
+
+        let map = complex.to_hash_map(&packages, 0x7f);
+        assert_eq!(Some(&"0x03020000".to_string()), map.get("0x02010000"));
+        assert_eq!("parent: 0x01010000", complex.to_string(&packages));
+    }
+}
@@ -3,10 +3,12 @@ use anyhow::{format_err, Error};
 
 use crate::model::{owned::OwnedBuf, TableType};
 
+mod bag;
 mod configuration;
 mod entry;
 
 pub use self::{
+    bag::{format_names, AttrDefinition, Bag, BagKey, Quantity},
     configuration::ConfigurationBuf,
     entry::{ComplexEntry, Entry, EntryHeader, SimpleEntry},
 };
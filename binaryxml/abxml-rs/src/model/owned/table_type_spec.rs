@@ -3,6 +3,106 @@ use anyhow::{format_err, Error};
 
 use crate::model::{owned::OwnedBuf, TypeSpec};
 
+/// The entry is part of the public API of its package (declared on `public.xml`)
+pub const SPEC_PUBLIC: u32 = 0x4000_0000;
+/// The entry was exposed on a staged API, and its ID may change once the API is finalized
+pub const SPEC_STAGED_API: u32 = 0x2000_0000;
+
+/// Configuration changes an entry varies on, as found on the `CONFIG_*` bits of `ResTable_config`
+const CONFIG_CHANGES: [(u32, &str); 18] = [
+    (0x0000_0001, "mcc"),
+    (0x0000_0002, "mnc"),
+    (0x0000_0004, "locale"),
+    (0x0000_0008, "touchscreen"),
+    (0x0000_0010, "keyboard"),
+    (0x0000_0020, "keyboard_hidden"),
+    (0x0000_0040, "navigation"),
+    (0x0000_0080, "orientation"),
+    (0x0000_0100, "density"),
+    (0x0000_0200, "screen_size"),
+    (0x0000_0400, "version"),
+    (0x0000_0800, "screen_layout"),
+    (0x0000_1000, "ui_mode"),
+    (0x0000_2000, "smallest_screen_size"),
+    (0x0000_4000, "layout_direction"),
+    (0x0000_8000, "screen_round"),
+    (0x0001_0000, "color_mode"),
+    (0x0002_0000, "grammatical_gender"),
+];
+
+/// The table type only stores the entries it defines, indexed by their ID
+pub const TYPE_FLAG_SPARSE: u8 = 0x01;
+/// The offsets of the entries of the table type take 16 bits
+pub const TYPE_FLAG_OFFSET16: u8 = 0x02;
+
+/// Decoded flags of an entry of a type spec
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SpecFlags {
+    pub public: bool,
+    pub staged_api: bool,
+    /// Mask of the configuration changes the value of the entry depends on
+    pub config_changes: u32,
+}
+
+impl SpecFlags {
+    pub fn new(flags: u32) -> Self {
+        Self {
+            public: flags & SPEC_PUBLIC != 0,
+            staged_api: flags & SPEC_STAGED_API != 0,
+            config_changes: flags & !(SPEC_PUBLIC | SPEC_STAGED_API),
+        }
+    }
+
+    /// Names of the configuration changes, like `locale` or `orientation`. An activity that does
+    /// not handle them is recreated when they happen.
+    pub fn get_config_change_names(&self) -> Vec<&'static str> {
+        CONFIG_CHANGES
+            .iter()
+            .filter(|(mask, _)| self.config_changes & mask != 0)
+            .map(|(_, name)| *name)
+            .collect()
+    }
+}
+
+/// Flags of the table type chunks of a type, merged across all its configurations
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TypeFlags(u8);
+
+impl TypeFlags {
+    pub fn new(flags: u8) -> Self {
+        Self(flags)
+    }
+
+    pub fn get_raw(self) -> u8 {
+        self.0
+    }
+
+    pub fn is_sparse(self) -> bool {
+        self.0 & TYPE_FLAG_SPARSE != 0
+    }
+
+    pub fn is_offset16(self) -> bool {
+        self.0 & TYPE_FLAG_OFFSET16 != 0
+    }
+
+    pub fn merge(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    /// Names of the flags that are set: `sparse` and `offset16`
+    pub fn get_names(self) -> Vec<&'static str> {
+        let mut names = Vec::new();
+        if self.is_sparse() {
+            names.push("sparse");
+        }
+        if self.is_offset16() {
+            names.push("offset16");
+        }
+
+        names
+    }
+}
+
 #[derive(Debug)]
 pub struct TableTypeSpecBuf {
     id: u16,
@@ -65,7 +165,7 @@ impl TypeSpec for TableTypeSpecBuf {
 
 #[cfg(test)]
 mod tests {
-    use super::{TableTypeSpecBuf, TypeSpec};
+    use super::{SpecFlags, TableTypeSpecBuf, TypeFlags, TypeSpec};
     use crate::{
         chunks::TypeSpecWrapper, model::owned::OwnedBuf, raw_chunks, test::compare_chunks,
     };
@@ -86,4 +186,27 @@ mod tests {
 
         compare_chunks(&new_raw, &raw_chunks::EXAMPLE_TYPE_SPEC);
     }
+
+    #[test]
+    fn it_decodes_the_flags_of_an_entry() {
+        let flags = SpecFlags::new(0x4000_0184);
+
+        assert!(flags.public);
+        assert!(!flags.staged_api);
+        assert_eq!(0x184, flags.config_changes);
+        assert_eq!(
+            vec!["locale", "orientation", "density"],
+            flags.get_config_change_names()
+        );
+        assert!(SpecFlags::new(0x2000_0000).staged_api);
+    }
+
+    #[test]
+    fn it_merges_type_flags() {
+        let flags = TypeFlags::new(0x01).merge(TypeFlags::new(0x02));
+
+        assert!(flags.is_sparse());
+        assert_eq!(vec!["sparse", "offset16"], flags.get_names());
+        assert!(TypeFlags::default().get_names().is_empty());
+    }
 }
@@ -1,4 +1,4 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use byteorder::{LittleEndian, WriteBytesExt};
 use anyhow::Error;
@@ -30,13 +30,13 @@ impl NamespaceStart for XmlNamespaceStartBuf {
         Ok(self.line)
     }
 
-    fn get_prefix<S: StringTable>(&self, string_table: &S) -> Result<Rc<String>, Error> {
+    fn get_prefix<S: StringTable>(&self, string_table: &S) -> Result<Arc<String>, Error> {
         let string = string_table.get_string(self.prefix_index)?;
 
         Ok(string)
     }
 
-    fn get_namespace<S: StringTable>(&self, string_table: &S) -> Result<Rc<String>, Error> {
+    fn get_namespace<S: StringTable>(&self, string_table: &S) -> Result<Arc<String>, Error> {
         let string = string_table.get_string(self.namespace_index)?;
 
         Ok(string)
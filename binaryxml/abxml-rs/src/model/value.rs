@@ -18,6 +18,11 @@ pub(crate) const TOKEN_TYPE_RGB8: u8 = 0x1D;
 pub(crate) const TOKEN_TYPE_ARGB4: u8 = 0x1E;
 pub(crate) const TOKEN_TYPE_RGB4: u8 = 0x1F;
 
+/// Units of the dimension values, indexed by the lower 4 bits of the payload
+pub const DIMENSION_UNITS: [&str; 6] = ["px", "dip", "sp", "pt", "in", "mm"];
+/// Units of the fraction values, indexed by the lower 4 bits of the payload
+pub const FRACTION_UNITS: [&str; 2] = ["%", "%p"];
+
 #[derive(Debug)]
 /// Represents a value on the binary documents. It is formed by a type and a 32 bits payload. The
 /// payloads are interpreted depending on the type.
@@ -85,11 +90,10 @@ impl Value {
             }
             TOKEN_TYPE_STRING => Self::StringReference(data),
             TOKEN_TYPE_DIMENSION => {
-                let units: [&str; 6] = ["px", "dip", "sp", "pt", "in", "mm"];
                 let value = Self::complex(data);
                 let unit_idx = data & 0xF;
 
-                if let Some(unit) = units.get(unit_idx as usize) {
+                if let Some(unit) = DIMENSION_UNITS.get(unit_idx as usize) {
                     let formatted = format!("{:.*}{}", 1, value, unit);
                     Self::Dimension(formatted)
                 } else {
@@ -100,11 +104,10 @@ impl Value {
                 }
             }
             TOKEN_TYPE_FRACTION => {
-                let units: [&str; 2] = ["%", "%p"];
                 let unit_idx = (data & 0xF) as usize;
                 let final_value = Self::complex(data) * 100.0;
 
-                if let Some(unit) = units.get(unit_idx) {
+                if let Some(unit) = FRACTION_UNITS.get(unit_idx) {
                     let integer = final_value.round();
                     let diff = final_value - integer;
                     let formatted_fraction = if diff > 0.0000001 {
@@ -150,9 +153,10 @@ impl Value {
         Ok(value)
     }
 
+    /// Returns the numeric value of a dimension or fraction payload, without its unit
     // TODO: maybe remove the unsafe code.
     #[allow(unsafe_code)]
-    fn complex(data: u32) -> f32 {
+    pub fn complex(data: u32) -> f32 {
         // TODO: Clean this mess
         let mantissa = 0xffffff << 8;
         let u_value = data & mantissa;
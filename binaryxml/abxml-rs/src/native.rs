@@ -0,0 +1,523 @@
+//! Inventory of the native libraries (`lib/<abi>/*.so`) of an APK. The ELF files are parsed with
+//! a minimal reader that only looks at the headers, the dynamic section and the notes, so it does
+//! not need any native dependency and also works on wasm.
+
+use std::convert::TryFrom;
+
+use anyhow::{ensure, format_err, Error};
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use serde::Serialize;
+
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+const ELF_CLASS_64: u8 = 2;
+const ELF_DATA_BIG_ENDIAN: u8 = 2;
+
+const PT_LOAD: u32 = 1;
+const PT_DYNAMIC: u32 = 2;
+const PT_NOTE: u32 = 4;
+
+const DT_NULL: u64 = 0;
+const DT_NEEDED: u64 = 1;
+const DT_STRTAB: u64 = 5;
+const DT_STRSZ: u64 = 10;
+const DT_SONAME: u64 = 14;
+
+const SHT_SYMTAB: u32 = 2;
+const NT_GNU_BUILD_ID: u32 = 3;
+
+/// Alignment of the LOAD segments needed to run on devices with 16 KB pages (Android 15+)
+pub const PAGE_SIZE_16K: u64 = 0x4000;
+
+/// A native library found on the APK
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NativeLibrary {
+    /// Path of the library on the APK, like `lib/arm64-v8a/libfoo.so`
+    pub path: String,
+    /// ABI folder the library is on, like `arm64-v8a`
+    pub abi: String,
+    /// Uncompressed size of the library
+    pub size: u64,
+    /// True if the library is stored without compression, which allows loading it directly from
+    /// the APK
+    pub stored: bool,
+    /// For stored libraries, whether their contents start at a 16 KB boundary of the APK
+    pub zip_aligned_16k: Option<bool>,
+    pub elf: Option<ElfInfo>,
+    /// Problem found parsing the library as an ELF file
+    pub error: Option<String>,
+}
+
+/// Metadata read from the headers of an ELF file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ElfInfo {
+    pub is_64_bit: bool,
+    pub big_endian: bool,
+    /// `e_machine` field of the header
+    pub machine: u16,
+    /// Name of the architecture, like `aarch64`
+    pub machine_name: &'static str,
+    pub soname: Option<String>,
+    /// Libraries this one depends on
+    pub needed: Vec<String>,
+    /// GNU build ID, as hexadecimal
+    pub build_id: Option<String>,
+    /// True if the library does not have a symbol table (`.symtab`)
+    pub stripped: bool,
+    /// Alignment of each LOAD segment
+    pub load_alignments: Vec<u64>,
+    /// Problems found reading the tables of the file. What comes after the problem on each table
+    /// is missing from the rest of the fields.
+    pub diagnostics: Vec<String>,
+}
+
+impl ElfInfo {
+    /// True if all the LOAD segments are aligned to, at least, 16 KB
+    pub fn is_16k_aligned(&self) -> bool {
+        !self.load_alignments.is_empty()
+            && self
+                .load_alignments
+                .iter()
+                .all(|align| *align >= PAGE_SIZE_16K)
+    }
+}
+
+/// All the native libraries of an APK
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct NativeInventory {
+    /// ABIs the APK ships libraries for, sorted by name
+    pub abis: Vec<String>,
+    pub libraries: Vec<NativeLibrary>,
+}
+
+impl NativeInventory {
+    pub fn add(&mut self, library: NativeLibrary) {
+        if !self.abis.contains(&library.abi) {
+            self.abis.push(library.abi.clone());
+            self.abis.sort();
+        }
+
+        self.libraries.push(library);
+    }
+
+    /// Libraries that will not load on devices with 16 KB pages, either because of the alignment
+    /// of their segments or because of their alignment inside the APK
+    pub fn not_16k_aligned(&self) -> impl Iterator<Item = &NativeLibrary> {
+        self.libraries.iter().filter(|library| {
+            let elf_aligned = library.elf.as_ref().is_none_or(ElfInfo::is_16k_aligned);
+
+            !elf_aligned || library.zip_aligned_16k == Some(false)
+        })
+    }
+}
+
+/// Returns the ABI of the library on the given path of the APK (`lib/<abi>/<name>.so`), or `None`
+/// if the path is not the one of a native library
+pub fn library_abi(path: &str) -> Option<&str> {
+    let mut components = path.split('/');
+
+    match (components.next(), components.next(), components.next()) {
+        (Some("lib"), Some(abi), Some(name))
+            if name.ends_with(".so") && components.next().is_none() =>
+        {
+            Some(abi)
+        }
+        _ => None,
+    }
+}
+
+/// Parses the headers of the given ELF file. Only an invalid ELF header is an error: truncated or
+/// corrupt tables are recorded on the diagnostics, and the entries read until then are kept.
+pub fn parse_elf(data: &[u8]) -> Result<ElfInfo, Error> {
+    ensure!(
+        data.len() >= 0x34 && data.starts_with(ELF_MAGIC),
+        "buffer is not an ELF file"
+    );
+
+    let elf = ElfReader {
+        data,
+        is_64_bit: data[4] == ELF_CLASS_64,
+        big_endian: data[5] == ELF_DATA_BIG_ENDIAN,
+    };
+    ensure!(!elf.is_64_bit || data.len() >= 0x40, "truncated ELF header");
+
+    let machine = elf.u16(0x12)?;
+    let (program_offset, section_offset) = if elf.is_64_bit {
+        (elf.u64(0x20)?, elf.u64(0x28)?)
+    } else {
+        (u64::from(elf.u32(0x1c)?), u64::from(elf.u32(0x20)?))
+    };
+    let header_base = if elf.is_64_bit { 0x36 } else { 0x2a };
+    let program_size = u64::from(elf.u16(header_base)?);
+    let program_count = u64::from(elf.u16(header_base + 2)?);
+    let section_size = u64::from(elf.u16(header_base + 4)?);
+    let section_count = u64::from(elf.u16(header_base + 6)?);
+
+    let mut diagnostics = Vec::new();
+    let mut segments = Vec::new();
+    for i in 0..program_count {
+        match elf.segment(program_offset.saturating_add(i * program_size)) {
+            Ok(segment) => segments.push(segment),
+            Err(e) => {
+                diagnostics.push(format!("program header {}: {}", i, e));
+                break;
+            }
+        }
+    }
+
+    let mut info = ElfInfo {
+        is_64_bit: elf.is_64_bit,
+        big_endian: elf.big_endian,
+        machine,
+        machine_name: machine_name(machine),
+        soname: None,
+        needed: Vec::new(),
+        build_id: None,
+        stripped: true,
+        load_alignments: segments
+            .iter()
+            .filter(|segment| segment.kind == PT_LOAD)
+            .map(|segment| segment.align)
+            .collect(),
+        diagnostics,
+    };
+
+    if let Some(dynamic) = segments.iter().find(|segment| segment.kind == PT_DYNAMIC) {
+        if let Err(e) = elf.read_dynamic(dynamic, &segments, &mut info) {
+            info.diagnostics.push(format!("dynamic segment: {}", e));
+        }
+    }
+
+    for note in segments.iter().filter(|segment| segment.kind == PT_NOTE) {
+        match elf.build_id(note) {
+            Ok(Some(build_id)) => info.build_id = Some(build_id),
+            Ok(None) => (),
+            Err(e) => info.diagnostics.push(format!("note segment: {}", e)),
+        }
+    }
+
+    for i in 0..section_count {
+        match elf.u32(section_offset.saturating_add(i * section_size + 4)) {
+            Ok(SHT_SYMTAB) => info.stripped = false,
+            Ok(_) => (),
+            Err(e) => {
+                info.diagnostics
+                    .push(format!("section header {}: {}", i, e));
+                break;
+            }
+        }
+    }
+
+    Ok(info)
+}
+
+fn machine_name(machine: u16) -> &'static str {
+    match machine {
+        3 => "x86",
+        8 => "mips",
+        40 => "arm",
+        62 => "x86_64",
+        183 => "aarch64",
+        243 => "riscv",
+        _ => "unknown",
+    }
+}
+
+#[derive(Debug)]
+struct Segment {
+    kind: u32,
+    offset: u64,
+    address: u64,
+    file_size: u64,
+    align: u64,
+}
+
+struct ElfReader<'a> {
+    data: &'a [u8],
+    is_64_bit: bool,
+    big_endian: bool,
+}
+
+impl<'a> ElfReader<'a> {
+    fn bytes(&self, offset: u64, len: u64) -> Result<&'a [u8], Error> {
+        let out_of_bounds = || format_err!("offset out of bounds: {}", offset);
+        let start = usize::try_from(offset).map_err(|_| out_of_bounds())?;
+        let end = offset
+            .checked_add(len)
+            .and_then(|end| usize::try_from(end).ok())
+            .ok_or_else(out_of_bounds)?;
+
+        self.data.get(start..end).ok_or_else(out_of_bounds)
+    }
+
+    fn u16(&self, offset: u64) -> Result<u16, Error> {
+        let bytes = self.bytes(offset, 2)?;
+
+        Ok(if self.big_endian {
+            BigEndian::read_u16(bytes)
+        } else {
+            LittleEndian::read_u16(bytes)
+        })
+    }
+
+    fn u32(&self, offset: u64) -> Result<u32, Error> {
+        let bytes = self.bytes(offset, 4)?;
+
+        Ok(if self.big_endian {
+            BigEndian::read_u32(bytes)
+        } else {
+            LittleEndian::read_u32(bytes)
+        })
+    }
+
+    fn u64(&self, offset: u64) -> Result<u64, Error> {
+        let bytes = self.bytes(offset, 8)?;
+
+        Ok(if self.big_endian {
+            BigEndian::read_u64(bytes)
+        } else {
+            LittleEndian::read_u64(bytes)
+        })
+    }
+
+    /// Reads an address or size: 4 bytes on 32 bits files and 8 bytes on 64 bits ones
+    fn word(&self, offset: u64) -> Result<u64, Error> {
+        if self.is_64_bit {
+            self.u64(offset)
+        } else {
+            self.u32(offset).map(u64::from)
+        }
+    }
+
+    fn segment(&self, offset: u64) -> Result<Segment, Error> {
+        if self.is_64_bit {
+            Ok(Segment {
+                kind: self.u32(offset)?,
+                offset: self.u64(offset + 8)?,
+                address: self.u64(offset + 16)?,
+                file_size: self.u64(offset + 32)?,
+                align: self.u64(offset + 48)?,
+            })
+        } else {
+            Ok(Segment {
+                kind: self.u32(offset)?,
+                offset: u64::from(self.u32(offset + 4)?),
+                address: u64::from(self.u32(offset + 8)?),
+                file_size: u64::from(self.u32(offset + 16)?),
+                align: u64::from(self.u32(offset + 28)?),
+            })
+        }
+    }
+
+    /// Reads the SONAME and NEEDED entries of the dynamic segment
+    fn read_dynamic(
+        &self,
+        dynamic: &Segment,
+        segments: &[Segment],
+        info: &mut ElfInfo,
+    ) -> Result<(), Error> {
+        let entry_size = if self.is_64_bit { 16 } else { 8 };
+        let word_size = entry_size / 2;
+
+        let mut string_table = None;
+        let mut string_size = 0;
+        let mut soname = None;
+        let mut needed = Vec::new();
+
+        let mut offset = dynamic.offset;
+        let end = dynamic.offset.saturating_add(dynamic.file_size);
+        while offset.saturating_add(entry_size) <= end {
+            let tag = self.word(offset)?;
+            let value = self.word(offset + word_size)?;
+
+            match tag {
+                DT_NULL => break,
+                DT_NEEDED => needed.push(value),
+                DT_SONAME => soname = Some(value),
+                DT_STRTAB => string_table = Some(value),
+                DT_STRSZ => string_size = value,
+                _ => (),
+            }
+
+            offset += entry_size;
+        }
+
+        // The string table is given by its virtual address
+        let table_offset = match string_table.and_then(|address| file_offset(segments, address)) {
+            Some(offset) => offset,
+            None => return Ok(()),
+        };
+        let table = self.bytes(table_offset, string_size)?;
+
+        info.soname = soname.and_then(|index| c_string(table, index));
+        info.needed = needed
+            .into_iter()
+            .filter_map(|index| c_string(table, index))
+            .collect();
+
+        Ok(())
+    }
+
+    /// Returns the GNU build ID found on the note segment, if any
+    fn build_id(&self, note: &Segment) -> Result<Option<String>, Error> {
+        let end = note.offset.saturating_add(note.file_size);
+        let mut offset = note.offset;
+
+        while offset.saturating_add(12) <= end {
+            let name_size = u64::from(self.u32(offset)?);
+            let desc_size = u64::from(self.u32(offset + 4)?);
+            let note_type = self.u32(offset + 8)?;
+            let name = self.bytes(offset + 12, name_size)?;
+            let desc_offset = offset + 12 + align4(name_size);
+
+            if note_type == NT_GNU_BUILD_ID && name == b"GNU\0" {
+                let desc = self.bytes(desc_offset, desc_size)?;
+
+                return Ok(Some(
+                    desc.iter().map(|byte| format!("{:02x}", byte)).collect(),
+                ));
+            }
+
+            offset = desc_offset + align4(desc_size);
+        }
+
+        Ok(None)
+    }
+}
+
+/// Translates a virtual address to an offset of the file, using the LOAD segment that maps it
+fn file_offset(segments: &[Segment], address: u64) -> Option<u64> {
+    segments
+        .iter()
+        .find(|segment| {
+            segment.kind == PT_LOAD
+                && address >= segment.address
+                && address < segment.address.saturating_add(segment.file_size)
+        })
+        .and_then(|segment| (address - segment.address).checked_add(segment.offset))
+}
+
+fn c_string(table: &[u8], index: u64) -> Option<String> {
+    let bytes = table.get(usize::try_from(index).ok()?..)?;
+    let end = bytes.iter().position(|byte| *byte == 0)?;
+
+    Some(String::from_utf8_lossy(&bytes[..end]).into_owned())
+}
+
+fn align4(value: u64) -> u64 {
+    (value + 3) & !3
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{library_abi, parse_elf};
+
+    /// Builds a little endian ELF64 with a LOAD, a DYNAMIC and a NOTE segment
+    fn elf64(align: u64) -> Vec<u8> {
+        let mut data = vec![0; 0x300];
+        data[..4].copy_from_slice(b"\x7fELF");
+        data[4] = 2;
+        data[5] = 1;
+        data[0x12..0x14].copy_from_slice(&183u16.to_le_bytes());
+        data[0x20..0x28].copy_from_slice(&64u64.to_le_bytes());
+        data[0x36..0x38].copy_from_slice(&56u16.to_le_bytes());
+        data[0x38..0x3a].copy_from_slice(&3u16.to_le_bytes());
+
+        // (type, offset, address, size, align)
+        let segments = [
+            (1u32, 0u64, 0u64, 0x300u64, align),
+            (2, 0xe8, 0xe8, 80, 8),
+            (4, 0x240, 0x240, 20, 4),
+        ];
+        for (i, (kind, offset, address, size, align)) in segments.iter().enumerate() {
+            let base = 64 + i * 56;
+            data[base..base + 4].copy_from_slice(&kind.to_le_bytes());
+            data[base + 8..base + 16].copy_from_slice(&offset.to_le_bytes());
+            data[base + 16..base + 24].copy_from_slice(&address.to_le_bytes());
+            data[base + 32..base + 40].copy_from_slice(&size.to_le_bytes());
+            data[base + 48..base + 56].copy_from_slice(&align.to_le_bytes());
+        }
+
+        let strings = b"\0libc.so\0libfoo.so\0";
+        let dynamic = [
+            (1u64, 1u64),
+            (14, 9),
+            (5, 0x200),
+            (10, strings.len() as u64),
+            (0, 0),
+        ];
+        for (i, (tag, value)) in dynamic.iter().enumerate() {
+            let base = 0xe8 + i * 16;
+            data[base..base + 8].copy_from_slice(&tag.to_le_bytes());
+            data[base + 8..base + 16].copy_from_slice(&value.to_le_bytes());
+        }
+        data[0x200..0x200 + strings.len()].copy_from_slice(strings);
+
+        let note = [4u32, 4, 3];
+        for (i, value) in note.iter().enumerate() {
+            data[0x240 + i * 4..0x244 + i * 4].copy_from_slice(&value.to_le_bytes());
+        }
+        data[0x24c..0x254].copy_from_slice(b"GNU\0\xde\xad\xbe\xef");
+
+        data
+    }
+
+    #[test]
+    fn it_parses_the_headers_of_an_elf_file() {
+        let info = parse_elf(&elf64(0x4000)).unwrap();
+
+        assert!(info.is_64_bit);
+        assert_eq!("aarch64", info.machine_name);
+        assert_eq!(Some("libfoo.so".to_string()), info.soname);
+        assert_eq!(vec!["libc.so".to_string()], info.needed);
+        assert_eq!(Some("deadbeef".to_string()), info.build_id);
+        assert!(info.stripped);
+        assert!(info.is_16k_aligned());
+
+        assert!(!parse_elf(&elf64(0x1000)).unwrap().is_16k_aligned());
+    }
+
+    #[test]
+    fn it_rejects_files_that_are_not_elf() {
+        assert!(parse_elf(b"dex\n035\0").is_err());
+        assert!(parse_elf(&elf64(0x4000)[..0x30]).is_err());
+    }
+
+    #[test]
+    fn it_keeps_what_it_can_read_of_truncated_files() {
+        let mut data = elf64(0x4000);
+        // Section table with 2 entries of 64 bytes, of which only the first one is on the file
+        data[0x28..0x30].copy_from_slice(&0x2c0u64.to_le_bytes());
+        data[0x3a..0x3c].copy_from_slice(&64u16.to_le_bytes());
+        data[0x3c..0x3e].copy_from_slice(&2u16.to_le_bytes());
+        data[0x2c4..0x2c8].copy_from_slice(&2u32.to_le_bytes());
+
+        let info = parse_elf(&data).unwrap();
+        assert!(!info.stripped);
+        assert_eq!(Some("libfoo.so".to_string()), info.soname);
+        assert_eq!(1, info.diagnostics.len());
+        assert!(info.diagnostics[0].starts_with("section header 1:"));
+
+        let info = parse_elf(&data[..0x100]).unwrap();
+        assert!(info.is_16k_aligned());
+        assert_eq!(None, info.soname);
+        assert!(!info.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn it_ignores_load_segments_beyond_the_address_space() {
+        let mut data = elf64(0x4000);
+        // Offset of the LOAD segment that maps the string table
+        data[0x48..0x50].copy_from_slice(&u64::MAX.to_le_bytes());
+
+        let info = parse_elf(&data).unwrap();
+        assert_eq!(None, info.soname);
+        assert!(info.needed.is_empty());
+    }
+
+    #[test]
+    fn it_detects_the_abi_of_a_library() {
+        assert_eq!(Some("arm64-v8a"), library_abi("lib/arm64-v8a/libfoo.so"));
+        assert_eq!(None, library_abi("lib/arm64-v8a/readme.txt"));
+        assert_eq!(None, library_abi("assets/lib/x86/libfoo.so"));
+    }
+}
@@ -0,0 +1,295 @@
+//! Index of the resources of a table by name, to resolve references like `@string/app_name` to
+//! their ID and to search over the names and string values of the resources.
+//!
+//! Names and values are formatted once, when the index is built, so lookups are a hash access
+//! and searches do not touch the resource table again.
+
+use std::collections::HashMap;
+
+use anyhow::Error;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::{
+    model::{owned::Entry, value::TOKEN_TYPE_STRING, Identifier, Library as _, Resources as _},
+    visitor::{model::Library, Resources},
+};
+
+/// Resolves resource names to IDs and searches over names and string values
+#[derive(Debug, Default)]
+pub struct ResourceIndex {
+    /// ID of each resource, by package and `type/name`
+    ids: HashMap<u8, HashMap<String, u32>>,
+    /// ID of each package, by name
+    packages: HashMap<String, u8>,
+    main_package: Option<u8>,
+    /// Indexed resources, sorted by ID
+    entries: Vec<IndexedEntry>,
+}
+
+#[derive(Debug)]
+struct IndexedEntry {
+    id: u32,
+    name: String,
+    /// String values of the resource, along the qualifiers of their configuration
+    values: Vec<(String, String)>,
+}
+
+/// How the text of a search matches names and values
+#[derive(Debug)]
+pub enum Matcher {
+    /// Case insensitive substring
+    Substring(String),
+    Regex(Regex),
+}
+
+impl Matcher {
+    pub fn substring(text: &str) -> Self {
+        Self::Substring(text.to_lowercase())
+    }
+
+    pub fn regex(pattern: &str) -> Result<Self, Error> {
+        Ok(Self::Regex(Regex::new(pattern)?))
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        match self {
+            Self::Substring(needle) => text.to_lowercase().contains(needle.as_str()),
+            Self::Regex(regex) => regex.is_match(text),
+        }
+    }
+}
+
+/// A resource whose name or value matched a search
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SearchHit {
+    pub id: u32,
+    /// Name of the resource, like `@string/app_name` or `@android:string/ok`
+    pub name: String,
+    /// Qualifiers of the configuration of the matching value (empty for the default one), or
+    /// `None` if the name matched
+    pub config: Option<String>,
+    /// Text that matched: the name or the value
+    pub text: String,
+}
+
+impl ResourceIndex {
+    pub fn new(resources: &Resources) -> Self {
+        let mut index = Self::default();
+
+        let mut package_ids: Vec<&u8> = resources.packages.keys().collect();
+        package_ids.sort();
+
+        for package_id in package_ids {
+            let package = &resources.packages[package_id];
+            if resources.is_main_package(*package_id) {
+                index.main_package = Some(*package_id);
+            }
+            if let Some(name) = package.get_name() {
+                let _ = index.packages.entry(name).or_insert(*package_id);
+            }
+
+            index.add_package(resources, *package_id, package);
+        }
+
+        index
+    }
+
+    fn add_package(&mut self, resources: &Resources, package_id: u8, package: &Library) {
+        let namespace = if resources.is_main_package(package_id) {
+            None
+        } else {
+            package.get_name()
+        };
+
+        let mut type_names = HashMap::new();
+        let mut entries: Vec<(&u32, &Entry)> = package.iter_entries().collect();
+        entries.sort_by_key(|(id, _)| **id);
+
+        for (id, entry) in entries {
+            let type_name = type_names
+                .entry(id.get_spec())
+                .or_insert_with(|| package.get_spec_as_str(u32::from(id.get_spec())).ok());
+            let (type_name, key) = match (type_name, package.get_entries_string(entry.get_key())) {
+                (Some(type_name), Ok(key)) => (type_name.clone(), key),
+                _ => continue,
+            };
+
+            // Obfuscated tables may repeat names: the lowest ID wins
+            let short_name = format!("{}/{}", type_name, key);
+            let _ = self
+                .ids
+                .entry(package_id)
+                .or_default()
+                .entry(short_name.clone())
+                .or_insert(*id);
+
+            let prefix = if type_name == "attr" { "?" } else { "@" };
+            let name = match &namespace {
+                Some(namespace) => format!("{}{}:{}", prefix, namespace, short_name),
+                None => format!("{}{}", prefix, short_name),
+            };
+
+            let variants = package.get_variants(*id);
+            let values = if variants.is_empty() {
+                string_values(package, entry)
+                    .map(|value| (String::new(), value))
+                    .collect()
+            } else {
+                variants
+                    .iter()
+                    .flat_map(|(config, entry)| {
+                        string_values(package, entry).map(move |value| (config.clone(), value))
+                    })
+                    .collect()
+            };
+
+            self.entries.push(IndexedEntry {
+                id: *id,
+                name,
+                values,
+            });
+        }
+    }
+
+    /// Returns the ID of a resource given its name, on any of the forms used on XMLs and code:
+    /// `@string/app_name`, `@android:string/ok`, `android:attr/textColor`, `?attr/colorPrimary`
+    /// or `?colorPrimary`. Names without package refer to the main package.
+    pub fn resolve(&self, reference: &str) -> Option<u32> {
+        let (is_attr, reference) = match reference.chars().next() {
+            Some('?') => (true, &reference[1..]),
+            Some('@') => (false, &reference[1..]),
+            _ => (false, reference),
+        };
+        let reference = reference.trim_start_matches(['+', '*']);
+
+        let (package_id, reference) = match reference.split_once(':') {
+            Some((package, reference)) => (*self.packages.get(package)?, reference),
+            None => (self.main_package?, reference),
+        };
+
+        let ids = self.ids.get(&package_id)?;
+        if reference.contains('/') {
+            ids.get(reference).copied()
+        } else if is_attr {
+            ids.get(&format!("attr/{}", reference)).copied()
+        } else {
+            None
+        }
+    }
+
+    /// Returns the name of the resource with the given ID, as indexed
+    pub fn get_name(&self, id: u32) -> Option<&str> {
+        self.entries
+            .binary_search_by_key(&id, |entry| entry.id)
+            .ok()
+            .map(|position| self.entries[position].name.as_str())
+    }
+
+    /// Returns the resources whose name or string values match, on ID order. A resource whose
+    /// value matches on several configurations produces a hit for each of them.
+    pub fn search(&self, matcher: &Matcher) -> Vec<SearchHit> {
+        let mut hits = Vec::new();
+
+        for entry in &self.entries {
+            if matcher.is_match(&entry.name) {
+                hits.push(SearchHit {
+                    id: entry.id,
+                    name: entry.name.clone(),
+                    config: None,
+                    text: entry.name.clone(),
+                });
+            }
+
+            for (config, value) in &entry.values {
+                if matcher.is_match(value) {
+                    hits.push(SearchHit {
+                        id: entry.id,
+                        name: entry.name.clone(),
+                        config: Some(config.clone()),
+                        text: value.clone(),
+                    });
+                }
+            }
+        }
+
+        hits
+    }
+
+    /// Amount of resources on the index
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Strings held by the entry: its value, or the items of a bag like a `string-array`
+fn string_values<'a>(package: &'a Library, entry: &'a Entry) -> impl Iterator<Item = String> + 'a {
+    let items = match entry {
+        Entry::Simple(simple) => vec![*simple],
+        Entry::Complex(complex) => complex.get_entries().clone(),
+        Entry::Empty(_, _) => Vec::new(),
+    };
+
+    items
+        .into_iter()
+        .filter(|item| item.get_type() == TOKEN_TYPE_STRING)
+        .filter_map(move |item| package.get_string(item.get_value()).ok())
+        .map(|value| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Matcher, ResourceIndex};
+    use crate::test::example_resources;
+
+    #[test]
+    fn it_resolves_names_to_ids() {
+        let index = ResourceIndex::new(&example_resources());
+        assert_eq!(7, index.len());
+
+        assert_eq!(Some(0x7f03_0000), index.resolve("@string/app_name"));
+        assert_eq!(
+            Some(0x7f03_0000),
+            index.resolve("string/app_name"),
+            "the prefix is optional"
+        );
+        assert_eq!(Some(0x7f01_0000), index.resolve("?textSize"));
+        assert_eq!(Some(0x7f01_0000), index.resolve("?attr/textSize"));
+        assert_eq!(Some("@string/app_name"), index.get_name(0x7f03_0000));
+        assert_eq!(Some("?attr/textSize"), index.get_name(0x7f01_0000));
+        assert!(index.resolve("@string/does_not_exist_anywhere").is_none());
+        assert!(index.resolve("@unknown_package:string/ok").is_none());
+
+        let hits = index.search(&Matcher::substring("APP_NAME"));
+        assert_eq!(1, hits.len());
+        assert_eq!(0x7f03_0000, hits[0].id);
+        assert!(hits[0].config.is_none());
+    }
+
+    #[test]
+    fn it_searches_string_values_per_config() {
+        let index = ResourceIndex::new(&example_resources());
+
+        let hits = index.search(&Matcher::regex("^M. app$").unwrap());
+        let configs: Vec<_> = hits
+            .iter()
+            .map(|hit| (hit.id, hit.config.as_deref(), hit.text.as_str()))
+            .collect();
+        assert_eq!(
+            vec![
+                (0x7f03_0000, Some(""), "My app"),
+                (0x7f03_0000, Some("es"), "Mi app")
+            ],
+            configs
+        );
+
+        let hits = index.search(&Matcher::regex(".").unwrap());
+        assert!(hits.windows(2).all(|pair| pair[0].id <= pair[1].id));
+        assert!(Matcher::regex("(").is_err());
+        assert!(index.search(&Matcher::regex("^$x").unwrap()).is_empty());
+    }
+}
@@ -0,0 +1,437 @@
+//! Owned, serializable snapshots of the decoded documents, meant to be exported as JSON or YAML
+//! and compared against fixtures.
+//!
+//! Unlike the model, snapshots do not borrow the original buffer and have a stable layout: maps
+//! are sorted and values are rendered as strings, so two decodings of the same document produce
+//! the same output. Any change on the layout bumps [`SCHEMA_VERSION`].
+//!
+//! A resource table is serialized as:
+//!
+//! ```text
+//! {
+//!   "schema_version": 1,
+//!   "packages": [{
+//!     "id": 127, "name": "com.example", "main": true,
+//!     "strings": ["res/layout/main.xml", ...],   // value string pool
+//!     "type_strings": ["attr", "layout", ...],
+//!     "key_strings": ["main", ...],
+//!     "types": [{
+//!       "id": 3, "name": "layout", "flags": ["sparse"], "configs": ["", "land"],
+//!       "entries": [{
+//!         "id": 2130903040, "name": "main", "public": false, "staged_api": false,
+//!         "config_changes": ["orientation"],
+//!         "values": [{
+//!           "config": "land",
+//!           "value": { "kind": "simple", "data_type": 3, "data": 0, "value": "res/layout/main.xml" }
+//!         }]
+//!       }]
+//!     }]
+//!   }]
+//! }
+//! ```
+//!
+//! Bags are serialized as `{ "kind": "bag", "parent": 0, "items": [{ "key", "key_name",
+//! "data_type", "data", "value" }] }` and entries without value as `{ "kind": "empty" }`. A binary
+//! XML is serialized as `{ "schema_version", "root": { "name", "attributes", "children" },
+//! "diagnostics" }`.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    model::{
+        owned::{BagKey, Entry, SimpleEntry},
+        Element, Identifier, Library as _, Resources as _, StringTable, Value,
+    },
+    visitor::{model::Library, DecodedXml, Origin, Resources},
+};
+
+/// Version of the layout of the snapshots
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Snapshot of a whole resource table
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourcesSnapshot {
+    pub schema_version: u32,
+    /// Packages, sorted by ID
+    pub packages: Vec<PackageSnapshot>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageSnapshot {
+    pub id: u8,
+    pub name: Option<String>,
+    /// True for the package references without namespace are resolved against
+    pub main: bool,
+    pub strings: Vec<String>,
+    pub type_strings: Vec<String>,
+    pub key_strings: Vec<String>,
+    /// Types, sorted by ID
+    pub types: Vec<TypeSnapshot>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypeSnapshot {
+    pub id: u8,
+    pub name: String,
+    /// Flags of the table types, like `sparse`
+    pub flags: Vec<String>,
+    /// Qualifiers of every configuration the type has values on, empty for the default one
+    pub configs: Vec<String>,
+    /// Entries, sorted by ID
+    pub entries: Vec<EntrySnapshot>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntrySnapshot {
+    pub id: u32,
+    pub name: String,
+    pub public: bool,
+    pub staged_api: bool,
+    pub config_changes: Vec<String>,
+    /// Value on each configuration, in the order they appear on the table
+    pub values: Vec<ConfigValueSnapshot>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfigValueSnapshot {
+    pub config: String,
+    pub value: ValueSnapshot,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ValueSnapshot {
+    Simple {
+        data_type: u8,
+        data: u32,
+        /// Value as shown on a decoded XML: strings and references are resolved
+        value: String,
+    },
+    Bag {
+        parent: u32,
+        items: Vec<BagItemSnapshot>,
+    },
+    Empty,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BagItemSnapshot {
+    pub key: u32,
+    /// Key as an attribute name, `^type` or `quantity=one`
+    pub key_name: String,
+    pub data_type: u8,
+    pub data: u32,
+    pub value: String,
+}
+
+/// Snapshot of a decoded binary XML
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct XmlSnapshot {
+    pub schema_version: u32,
+    pub root: Option<ElementSnapshot>,
+    /// Problems found while decoding, as `@offset (chunk 0xtype): message`
+    pub diagnostics: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ElementSnapshot {
+    pub name: String,
+    pub attributes: BTreeMap<String, String>,
+    pub children: Vec<ElementSnapshot>,
+}
+
+impl ResourcesSnapshot {
+    pub fn new(resources: &Resources) -> Self {
+        let mut ids: Vec<&u8> = resources.packages.keys().collect();
+        ids.sort();
+
+        let packages = ids
+            .into_iter()
+            .map(|id| PackageSnapshot::new(resources, *id, &resources.packages[id]))
+            .collect();
+
+        Self {
+            schema_version: SCHEMA_VERSION,
+            packages,
+        }
+    }
+}
+
+impl PackageSnapshot {
+    fn new(resources: &Resources, id: u8, package: &Library) -> Self {
+        let mut spec_ids: Vec<u32> = package.iter_specs().map(|(id, _)| *id).collect();
+        spec_ids.sort_unstable();
+
+        let mut entries: Vec<(&u32, &Entry)> = package.iter_entries().collect();
+        entries.sort_by_key(|(id, _)| **id);
+
+        let types = spec_ids
+            .into_iter()
+            .map(|spec_id| {
+                let type_entries: Vec<(&u32, &Entry)> = entries
+                    .iter()
+                    .filter(|(id, _)| u32::from(id.get_spec()) == spec_id)
+                    .copied()
+                    .collect();
+
+                TypeSnapshot::new(resources, package, spec_id, &type_entries)
+            })
+            .collect();
+
+        Self {
+            id,
+            name: package.get_name(),
+            main: resources.is_main_package(id),
+            strings: pool_strings(package, Origin::Global),
+            type_strings: pool_strings(package, Origin::Spec),
+            key_strings: pool_strings(package, Origin::Entries),
+            types,
+        }
+    }
+}
+
+impl TypeSnapshot {
+    fn new(
+        resources: &Resources,
+        package: &Library,
+        spec_id: u32,
+        entries: &[(&u32, &Entry)],
+    ) -> Self {
+        let entries: Vec<EntrySnapshot> = entries
+            .iter()
+            .map(|(id, entry)| EntrySnapshot::new(resources, package, **id, entry))
+            .collect();
+
+        let mut configs: Vec<String> = Vec::new();
+        for value in entries.iter().flat_map(|entry| &entry.values) {
+            if !configs.contains(&value.config) {
+                configs.push(value.config.clone());
+            }
+        }
+
+        Self {
+            id: spec_id as u8,
+            name: package.get_spec_as_str(spec_id).unwrap_or_default(),
+            flags: names(package.get_type_flags(spec_id).get_names()),
+            configs,
+            entries,
+        }
+    }
+}
+
+impl EntrySnapshot {
+    fn new(resources: &Resources, package: &Library, id: u32, entry: &Entry) -> Self {
+        let flags = package.get_spec_flags(id).unwrap_or_default();
+        let variants = package.get_variants(id);
+
+        let values = if variants.is_empty() {
+            vec![ConfigValueSnapshot {
+                config: String::new(),
+                value: ValueSnapshot::new(resources, package, entry),
+            }]
+        } else {
+            variants
+                .iter()
+                .filter(|(_, entry)| !entry.is_empty())
+                .map(|(config, entry)| ConfigValueSnapshot {
+                    config: config.clone(),
+                    value: ValueSnapshot::new(resources, package, entry),
+                })
+                .collect()
+        };
+
+        Self {
+            id,
+            name: package
+                .get_entries_string(entry.get_key())
+                .map(|key| key.to_string())
+                .unwrap_or_default(),
+            public: flags.public,
+            staged_api: flags.staged_api,
+            config_changes: names(flags.get_config_change_names()),
+            values,
+        }
+    }
+}
+
+impl ValueSnapshot {
+    fn new(resources: &Resources, package: &Library, entry: &Entry) -> Self {
+        match entry {
+            Entry::Empty(_, _) => Self::Empty,
+            Entry::Simple(simple) => Self::Simple {
+                data_type: simple.get_type(),
+                data: simple.get_value(),
+                value: render(resources, package, simple),
+            },
+            Entry::Complex(complex) => Self::Bag {
+                parent: complex.get_parent_entry_id(),
+                items: complex
+                    .get_entries()
+                    .iter()
+                    .map(|item| BagItemSnapshot {
+                        key: item.get_id(),
+                        key_name: match BagKey::new(item.get_id()) {
+                            BagKey::Resource(id) => resolve(resources, id, "@")
+                                .map(|name| name.trim_start_matches(['@', '?']).to_string())
+                                .unwrap_or_else(|| format!("0x{:08x}", id)),
+                            key => key.to_string(),
+                        },
+                        data_type: item.get_type(),
+                        data: item.get_value(),
+                        value: render(resources, package, item),
+                    })
+                    .collect(),
+            },
+        }
+    }
+}
+
+impl XmlSnapshot {
+    pub fn new(decoded: &DecodedXml) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            root: decoded.root.as_ref().map(ElementSnapshot::from),
+            diagnostics: decoded
+                .diagnostics
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+        }
+    }
+}
+
+impl From<&Element> for ElementSnapshot {
+    fn from(element: &Element) -> Self {
+        Self {
+            name: element.get_tag().get_name().to_string(),
+            attributes: element
+                .get_attributes()
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+            children: element.get_children().iter().map(Self::from).collect(),
+        }
+    }
+}
+
+fn pool_strings(package: &Library, origin: Origin) -> Vec<String> {
+    package
+        .get_string_pool(origin)
+        .map(|pool| {
+            (0..pool.get_strings_len())
+                .map(|i| {
+                    pool.get_string(i)
+                        .map(|s| s.to_string())
+                        .unwrap_or_default()
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn names(names: Vec<&'static str>) -> Vec<String> {
+    names.into_iter().map(ToString::to_string).collect()
+}
+
+/// Renders the value the way a decoded XML shows it
+fn render(resources: &Resources, package: &Library, entry: &SimpleEntry) -> String {
+    match Value::create(entry.get_type(), entry.get_value()) {
+        Ok(Value::ReferenceId(0)) => "@null".to_string(),
+        Ok(Value::StringReference(index)) => package
+            .get_string(index)
+            .map(|s| s.to_string())
+            .unwrap_or_else(|_| format!("@string/{}", index)),
+        Ok(Value::ReferenceId(id)) => {
+            resolve(resources, id, "@").unwrap_or_else(|| format!("@0x{:08x}", id))
+        }
+        Ok(Value::AttributeReferenceId(id)) => {
+            resolve(resources, id, "?").unwrap_or_else(|| format!("?0x{:08x}", id))
+        }
+        Ok(value) => value.to_string(),
+        Err(_) => format!("0x{:08x}", entry.get_value()),
+    }
+}
+
+/// Name of the resource with the given prefix, like `@android:string/ok` or `?attr/colorPrimary`
+fn resolve(resources: &Resources, id: u32, prefix: &str) -> Option<String> {
+    let package_id = id.get_package();
+    let package = resources.get_package(package_id)?;
+    let entry = package.get_entry(id).ok()?;
+    let namespace = if resources.is_main_package(package_id) {
+        None
+    } else {
+        package.get_name()
+    };
+
+    package
+        .format_reference(id, entry.get_key(), namespace)
+        .ok()
+        .map(|name| format!("{}{}", prefix, name.trim_start_matches(['@', '?'])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ResourcesSnapshot, ValueSnapshot, XmlSnapshot, SCHEMA_VERSION};
+    use crate::{test::example_resources, visitor::DecodedXml};
+
+    #[test]
+    fn it_takes_a_sorted_snapshot_of_a_resource_table() {
+        let resources = example_resources();
+        let snapshot = ResourcesSnapshot::new(&resources);
+
+        assert_eq!(SCHEMA_VERSION, snapshot.schema_version);
+        let package = &snapshot.packages[0];
+        assert_eq!(Some("com.example".to_string()), package.name);
+        assert!(package.main);
+        assert_eq!("textSize", package.key_strings[0]);
+        assert!(package.types.windows(2).all(|t| t[0].id < t[1].id));
+
+        let strings = package.types.iter().find(|t| t.name == "string").unwrap();
+        assert_eq!(vec!["".to_string(), "es".to_string()], strings.configs);
+        let app_name = &strings.entries[0];
+        assert_eq!(0x7f03_0000, app_name.id);
+        assert_eq!("app_name", app_name.name);
+        assert_eq!(
+            ValueSnapshot::Simple {
+                data_type: 3,
+                data: 1,
+                value: "My app".to_string(),
+            },
+            app_name.values[0].value
+        );
+        assert_eq!("es", app_name.values[1].config);
+
+        let entries = package.types.iter().flat_map(|t| &t.entries);
+        assert!(entries.clone().all(|e| !e.values.is_empty()));
+        assert!(entries
+            .flat_map(|e| &e.values)
+            .any(|v| matches!(v.value, ValueSnapshot::Bag { .. })));
+
+        assert_eq!(
+            snapshot,
+            ResourcesSnapshot::new(&resources),
+            "snapshots are deterministic"
+        );
+    }
+
+    #[test]
+    fn it_round_trips_through_json() {
+        let snapshot = ResourcesSnapshot::new(&example_resources());
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let parsed: ResourcesSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(snapshot, parsed);
+
+        let xml = XmlSnapshot::new(&DecodedXml {
+            xml: None,
+            root: None,
+            diagnostics: Vec::new(),
+        });
+        assert_eq!(
+            r#"{"schema_version":1,"root":null,"diagnostics":[]}"#,
+            serde_json::to_string(&xml).unwrap()
+        );
+    }
+}
@@ -0,0 +1,356 @@
+//! Generation of the symbols of a package (`R.txt`, `R.java` and `R.kt`) from its
+//! `resources.arsc`, to link against an APK or AAR whose sources are not available.
+//!
+//! The table does not keep the `declare-styleable` definitions, as they are not resources. They
+//! can be added back with [`SymbolTable::add_styleable`], which resolves the attribute names to
+//! their IDs and orders them the same way aapt does. Otherwise, the generated `R.java` and `R.kt`
+//! say that the styleables are missing.
+
+use std::collections::{BTreeMap, HashSet};
+
+use anyhow::{format_err, Error};
+
+use crate::{model::Library as _, search::ResourceIndex, visitor::Resources};
+
+/// A resource, as exposed on the `R` class
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub id: u32,
+    /// Name as a Java identifier: `Theme.App` becomes `Theme_App`
+    pub name: String,
+}
+
+/// A reconstructed `declare-styleable`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Styleable {
+    pub name: String,
+    /// Name of the index field of each attribute (`android_textColor`) and its ID, sorted by ID
+    pub attributes: Vec<(String, u32)>,
+}
+
+/// Symbols of a package, sorted by type and name
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    /// Name of the package, used as the package of the generated sources
+    pub package_name: String,
+    pub types: BTreeMap<String, Vec<Symbol>>,
+    pub styleables: Vec<Styleable>,
+}
+
+impl SymbolTable {
+    pub fn new(resources: &Resources, package_id: u8) -> Result<Self, Error> {
+        let package = resources
+            .packages
+            .get(&package_id)
+            .ok_or_else(|| format_err!("package {} not found", package_id))?;
+
+        let mut ids: Vec<u32> = package.iter_entries().map(|(id, _)| *id).collect();
+        ids.sort_unstable();
+
+        let mut types: BTreeMap<String, Vec<Symbol>> = BTreeMap::new();
+        let mut names = HashSet::new();
+        for id in ids {
+            let entry = package.get_entry(id)?;
+            // Same naming as the decoded XMLs: `@string/app_name` or `?attr/colorPrimary`
+            let reference = match package.format_reference(id, entry.get_key(), None) {
+                Ok(reference) => reference,
+                Err(_) => continue,
+            };
+            let (type_name, name) = match reference[1..].split_once('/') {
+                Some(type_and_name) => type_and_name,
+                None => continue,
+            };
+            let type_name = if type_name == "^attr-private" {
+                "attr"
+            } else {
+                type_name
+            };
+
+            let name = java_identifier(name);
+            // Obfuscated tables may repeat names: the lowest ID wins
+            if names.insert((type_name.to_string(), name.clone())) {
+                types
+                    .entry(type_name.to_string())
+                    .or_default()
+                    .push(Symbol { id, name });
+            }
+        }
+
+        for symbols in types.values_mut() {
+            symbols.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+
+        Ok(Self {
+            package_name: package.get_name().unwrap_or_default(),
+            types,
+            styleables: Vec::new(),
+        })
+    }
+
+    /// Adds a `declare-styleable` with the given attributes, like `colorPrimary` or
+    /// `android:textColor`. The names are resolved on the given indexes, on order, so the index of
+    /// the framework can be used for the `android:` ones. Returns the attributes that could not be
+    /// resolved, which are left out.
+    pub fn add_styleable(
+        &mut self,
+        name: &str,
+        attributes: &[&str],
+        indexes: &[&ResourceIndex],
+    ) -> Vec<String> {
+        let mut resolved = Vec::new();
+        let mut missing = Vec::new();
+
+        for attribute in attributes {
+            let id = indexes
+                .iter()
+                .find_map(|index| index.resolve(&format!("?{}", attribute)));
+
+            match id {
+                Some(id) => resolved.push((java_identifier(attribute), id)),
+                None => missing.push((*attribute).to_string()),
+            }
+        }
+
+        // aapt sorts the attributes by ID, which gives the index of each of them
+        resolved.sort_by_key(|(_, id)| *id);
+        resolved.dedup_by_key(|(_, id)| *id);
+
+        self.styleables.push(Styleable {
+            name: java_identifier(name),
+            attributes: resolved,
+        });
+        self.styleables.sort_by(|a, b| a.name.cmp(&b.name));
+
+        missing
+    }
+
+    /// Generates the `R.txt` that aapt writes along an AAR
+    pub fn r_txt(&self) -> String {
+        let mut out = String::new();
+
+        for (type_name, symbols) in &self.types {
+            for symbol in symbols {
+                out.push_str(&format!(
+                    "int {} {} 0x{:08x}\n",
+                    type_name, symbol.name, symbol.id
+                ));
+            }
+        }
+
+        for styleable in &self.styleables {
+            let ids: Vec<String> = styleable
+                .attributes
+                .iter()
+                .map(|(_, id)| format!("0x{:08x}", id))
+                .collect();
+            out.push_str(&format!(
+                "int[] styleable {} {{ {} }}\n",
+                styleable.name,
+                ids.join(", ")
+            ));
+
+            for (index, (attribute, _)) in styleable.attributes.iter().enumerate() {
+                out.push_str(&format!(
+                    "int styleable {}_{} {}\n",
+                    styleable.name, attribute, index
+                ));
+            }
+        }
+
+        out
+    }
+
+    /// Generates the `R.java` of the package
+    pub fn r_java(&self) -> String {
+        let mut out = self.header();
+        if !self.package_name.is_empty() {
+            out.push_str(&format!("package {};\n\n", self.package_name));
+        }
+        out.push_str("public final class R {\n");
+
+        for (type_name, symbols) in self.types_with_styleables() {
+            out.push_str(&format!(
+                "    public static final class {} {{\n",
+                java_identifier(type_name)
+            ));
+            for symbol in symbols {
+                out.push_str(&format!(
+                    "        public static final int {}=0x{:08x};\n",
+                    symbol.name, symbol.id
+                ));
+            }
+
+            if type_name == "styleable" {
+                for styleable in &self.styleables {
+                    let ids: Vec<String> = styleable
+                        .attributes
+                        .iter()
+                        .map(|(_, id)| format!("0x{:08x}", id))
+                        .collect();
+                    out.push_str(&format!(
+                        "        public static final int[] {}={{ {} }};\n",
+                        styleable.name,
+                        ids.join(", ")
+                    ));
+                    for (index, (attribute, _)) in styleable.attributes.iter().enumerate() {
+                        out.push_str(&format!(
+                            "        public static final int {}_{}={};\n",
+                            styleable.name, attribute, index
+                        ));
+                    }
+                }
+            }
+
+            out.push_str("    }\n");
+        }
+
+        out.push_str("}\n");
+        out
+    }
+
+    /// Generates the `R.kt` of the package
+    pub fn r_kotlin(&self) -> String {
+        let mut out = self.header();
+        if !self.package_name.is_empty() {
+            out.push_str(&format!("package {}\n\n", self.package_name));
+        }
+        out.push_str("object R {\n");
+
+        for (type_name, symbols) in self.types_with_styleables() {
+            out.push_str(&format!("    object {} {{\n", java_identifier(type_name)));
+            for symbol in symbols {
+                out.push_str(&format!(
+                    "        const val {}: Int = {}\n",
+                    symbol.name,
+                    kotlin_int(symbol.id)
+                ));
+            }
+
+            if type_name == "styleable" {
+                for styleable in &self.styleables {
+                    let ids: Vec<String> = styleable
+                        .attributes
+                        .iter()
+                        .map(|(_, id)| kotlin_int(*id))
+                        .collect();
+                    out.push_str(&format!(
+                        "        val {}: IntArray = intArrayOf({})\n",
+                        styleable.name,
+                        ids.join(", ")
+                    ));
+                    for (index, (attribute, _)) in styleable.attributes.iter().enumerate() {
+                        out.push_str(&format!(
+                            "        const val {}_{}: Int = {}\n",
+                            styleable.name, attribute, index
+                        ));
+                    }
+                }
+            }
+
+            out.push_str("    }\n");
+        }
+
+        out.push_str("}\n");
+        out
+    }
+
+    /// Comment the generated sources start with
+    fn header(&self) -> String {
+        let mut out = String::from("/* AUTO-GENERATED FILE. DO NOT MODIFY. */\n");
+        if self.styleables.is_empty() {
+            out.push_str(
+                "/* The declare-styleable definitions are not kept on resources.arsc: R.styleable \
+                 is missing. */\n",
+            );
+        }
+        out.push('\n');
+
+        out
+    }
+
+    /// Types with their symbols, including `styleable` if there is any styleable but no resource
+    /// of that type
+    fn types_with_styleables(&self) -> Vec<(&str, &[Symbol])> {
+        let mut types: Vec<(&str, &[Symbol])> = self
+            .types
+            .iter()
+            .map(|(type_name, symbols)| (type_name.as_str(), symbols.as_slice()))
+            .collect();
+
+        if !self.styleables.is_empty() && !self.types.contains_key("styleable") {
+            types.push(("styleable", &[]));
+            types.sort_by_key(|(type_name, _)| *type_name);
+        }
+
+        types
+    }
+}
+
+/// Converts a resource name to the field aapt generates for it
+fn java_identifier(name: &str) -> String {
+    name.replace(['.', '-', ':'], "_")
+}
+
+/// Kotlin `Int` literal of the ID. IDs of packages over 0x7f do not fit on a positive literal.
+fn kotlin_int(id: u32) -> String {
+    if id > i32::MAX as u32 {
+        format!("0x{:08x}.toInt()", id)
+    } else {
+        format!("0x{:08x}", id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{java_identifier, Symbol, SymbolTable};
+    use crate::{search::ResourceIndex, test::example_resources};
+
+    #[test]
+    fn it_generates_the_symbols_of_a_package() {
+        let resources = example_resources();
+        let mut table = SymbolTable::new(&resources, 0x7f).unwrap();
+        assert_eq!("com.example", table.package_name);
+        assert_eq!(
+            vec![Symbol {
+                id: 0x7f01_0000,
+                name: "textSize".to_string(),
+            }],
+            table.types["attr"]
+        );
+        assert_eq!(
+            vec!["app_name", "path_like"],
+            table.types["string"]
+                .iter()
+                .map(|symbol| symbol.name.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert!(table.r_java().contains("R.styleable is missing"));
+
+        let index = ResourceIndex::new(&resources);
+        let missing = table.add_styleable("My.View", &["textSize", "does_not_exist"], &[&index]);
+        assert_eq!(vec!["does_not_exist".to_string()], missing);
+
+        let r_txt = table.r_txt();
+        assert!(r_txt.contains("int attr textSize 0x7f010000\n"));
+        assert!(r_txt.contains("int string app_name 0x7f030000\n"));
+        assert!(r_txt.contains("int[] styleable My_View { 0x7f010000 }\n"));
+        assert!(r_txt.contains("int styleable My_View_textSize 0\n"));
+
+        let r_java = table.r_java();
+        assert!(
+            r_java.starts_with("/* AUTO-GENERATED FILE. DO NOT MODIFY. */\n\npackage com.example;")
+        );
+        assert!(r_java.contains("public static final class styleable {"));
+        assert!(r_java.contains("public static final int My_View_textSize=0;"));
+
+        let r_kotlin = table.r_kotlin();
+        assert!(r_kotlin.contains("object R {"));
+        assert!(r_kotlin.contains("val My_View: IntArray = intArrayOf(0x7f010000)"));
+    }
+
+    #[test]
+    fn it_converts_names_to_java_identifiers() {
+        assert_eq!("Theme_App_Light", java_identifier("Theme.App.Light"));
+        assert_eq!("android_textColor", java_identifier("android:textColor"));
+    }
+}
@@ -1,4 +1,4 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use anyhow::Error;
 
@@ -8,8 +8,15 @@ use crate::{
         XmlNamespaceEndWrapper, XmlNamespaceStartWrapper, XmlTagEndWrapper, XmlTagStartWrapper,
         XmlTextWrapper,
     },
-    model,
-    visitor::{ChunkVisitor, Origin},
+    model::{
+        self,
+        builder::Arsc,
+        owned::{
+            ComplexEntry, ConfigurationBuf, Entry, PackageBuf, SimpleEntry, StringTableBuf,
+            TableTypeBuf, TableTypeSpecBuf,
+        },
+    },
+    visitor::{ChunkVisitor, Executor, ModelVisitor, Origin, Resources},
 };
 
 #[derive(Default, Debug, Copy, Clone)]
@@ -90,17 +97,127 @@ impl model::StringTable for FakeStringTable {
         0
     }
 
-    fn get_string(&self, idx: u32) -> Result<Rc<String>, Error> {
+    fn get_string(&self, idx: u32) -> Result<Arc<String>, Error> {
         match idx {
-            0 => Ok(Rc::new("Zero".to_string())),
-            11 => Ok(Rc::new("Ones".to_string())),
-            22 => Ok(Rc::new("Twos".to_string())),
-            33 => Ok(Rc::new("Threes".to_string())),
-            44 => Ok(Rc::new("Fours".to_string())),
-            123 => Ok(Rc::new("center".to_string())),
-            456 => Ok(Rc::new("left".to_string())),
-            789 => Ok(Rc::new("right".to_string())),
+            0 => Ok(Arc::new("Zero".to_string())),
+            11 => Ok(Arc::new("Ones".to_string())),
+            22 => Ok(Arc::new("Twos".to_string())),
+            33 => Ok(Arc::new("Threes".to_string())),
+            44 => Ok(Arc::new("Fours".to_string())),
+            123 => Ok(Arc::new("center".to_string())),
+            456 => Ok(Arc::new("left".to_string())),
+            789 => Ok(Arc::new("right".to_string())),
             _ => panic!("index out of bounds"),
         }
     }
 }
+
+/// Tiny application resource table (`com.example`, package `0x7f`) to test against without
+/// loading the framework one. It holds, on the default configuration and on `es`:
+///
+/// * `attr/textSize` (`0x7f010000`): a dimension attribute
+/// * `drawable/icon` (`0x7f020000`): `res/drawable/icon.png`
+/// * `string/app_name` (`0x7f030000`) and `string/path_like` (`0x7f030001`), whose value looks
+///   like a path but is not a file
+/// * `style/AppTheme` (`0x7f040000`): sets `textSize`
+/// * `plurals/items` (`0x7f050000`): `one` and `other` quantities
+/// * `layout/main` (`0x7f060000`): on the shortened path `r/a.xml`
+pub fn example_arsc() -> Vec<u8> {
+    let strings = [
+        "res/drawable/icon.png",
+        "My app",
+        "Mi app",
+        "res/not_a_file.xml",
+        "r/a.xml",
+        "one item",
+        "%d items",
+    ];
+    let types = ["attr", "drawable", "string", "style", "plurals", "layout"];
+    let keys = [
+        "textSize",
+        "icon",
+        "app_name",
+        "path_like",
+        "AppTheme",
+        "items",
+        "main",
+    ];
+
+    let simple =
+        |id, key, value_type, value| Entry::Simple(SimpleEntry::new(id, key, value_type, value));
+    let complex = |id, key, items: &[(u32, u8, u32)]| {
+        let items = items
+            .iter()
+            .map(|(key, value_type, value)| SimpleEntry::new(*key, 0, *value_type, *value))
+            .collect();
+
+        Entry::Complex(ComplexEntry::new(id, key, 0, items))
+    };
+    let default_entries = [
+        vec![complex(0, 0, &[(0x0100_0000, 0x10, 0x40)])],
+        vec![simple(0, 1, 0x03, 0)],
+        vec![simple(0, 2, 0x03, 1), simple(1, 3, 0x03, 3)],
+        vec![complex(0, 4, &[(0x7f01_0000, 0x05, 0x0000_1001)])],
+        vec![complex(
+            0,
+            5,
+            &[(0x0100_0006, 0x03, 5), (0x0100_0004, 0x03, 6)],
+        )],
+        vec![simple(0, 6, 0x03, 4)],
+    ];
+
+    let mut package = PackageBuf::create(0x7f, "com.example".to_string()).unwrap();
+    package.add_chunk(Box::new(string_table(&types)));
+    package.add_chunk(Box::new(string_table(&keys)));
+
+    for (index, entries) in default_entries.iter().enumerate() {
+        let id = index as u8 + 1;
+        let mut spec = TableTypeSpecBuf::new(u16::from(id));
+        let mut table_type = TableTypeBuf::new(id, configuration(""));
+        for entry in entries {
+            spec.push_flag(0);
+            table_type.add_entry(entry.clone());
+        }
+        package.add_chunk(Box::new(spec));
+        package.add_chunk(Box::new(table_type));
+
+        if types[index] == "string" {
+            let mut spanish = TableTypeBuf::new(id, configuration("es"));
+            spanish.add_entry(simple(0, 2, 0x03, 2));
+            spanish.add_entry(simple(1, 3, 0x03, 3));
+            package.add_chunk(Box::new(spanish));
+        }
+    }
+
+    let mut arsc = Arsc::default();
+    arsc.push_owned(Box::new(string_table(&strings)));
+    arsc.push_owned(Box::new(package));
+
+    arsc.to_vec().unwrap()
+}
+
+/// Resources decoded from `example_arsc`
+pub fn example_resources() -> Resources {
+    let content = example_arsc();
+    let mut visitor = ModelVisitor::default();
+    Executor::arsc(&content, &mut visitor).unwrap();
+
+    visitor.into_resources()
+}
+
+fn configuration(language: &str) -> ConfigurationBuf {
+    let mut config = vec![36, 0, 0, 0, 0, 0, 0, 0];
+    config.extend(language.bytes().chain([0, 0]).take(2));
+    config.resize(36, 0);
+
+    ConfigurationBuf::from_cursor(config).unwrap()
+}
+
+fn string_table(strings: &[&str]) -> StringTableBuf {
+    let mut table = StringTableBuf::default();
+    for string in strings {
+        table.add_string((*string).to_string());
+    }
+
+    table
+}
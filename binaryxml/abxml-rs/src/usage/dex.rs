@@ -0,0 +1,346 @@
+//! Minimal walk over the bytecode of a dex file, looking for `const` and `const/high16`
+//! instructions whose literal looks like an application resource ID.
+//!
+//! IDs held elsewhere are not scanned: the initial values of static fields and the payloads of
+//! `fill-array-data` instructions are skipped.
+
+use std::io::Cursor;
+
+use anyhow::{ensure, format_err, Context, Error};
+use byteorder::{LittleEndian, ReadBytesExt};
+
+const DEX_MAGIC: &[u8] = b"dex\n";
+const MAP_OFFSET_POSITION: u64 = 0x34;
+const TYPE_CODE_ITEM: u16 = 0x2001;
+
+const OPCODE_CONST: u16 = 0x14;
+const OPCODE_CONST_HIGH16: u16 = 0x15;
+const PACKED_SWITCH_PAYLOAD: u16 = 0x0100;
+const SPARSE_SWITCH_PAYLOAD: u16 = 0x0200;
+const FILL_ARRAY_DATA_PAYLOAD: u16 = 0x0300;
+
+/// Size in code units of each instruction, indexed by opcode
+const INSTRUCTION_WIDTHS: [u8; 256] = [
+    // 0x00
+    1, 1, 2, 3, 1, 2, 3, 1, 2, 3, 1, 1, 1, 1, 1, 1, //
+    // 0x10
+    1, 1, 1, 2, 3, 2, 2, 3, 5, 2, 2, 3, 2, 1, 1, 2, //
+    // 0x20
+    2, 1, 2, 2, 3, 3, 3, 1, 1, 2, 3, 3, 3, 2, 2, 2, //
+    // 0x30
+    2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 1, 1, //
+    // 0x40
+    1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, //
+    // 0x50
+    2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, //
+    // 0x60
+    2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 3, 3, //
+    // 0x70
+    3, 3, 3, 1, 3, 3, 3, 3, 3, 1, 1, 1, 1, 1, 1, 1, //
+    // 0x80
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, //
+    // 0x90
+    2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, //
+    // 0xa0
+    2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, //
+    // 0xb0
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, //
+    // 0xc0
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, //
+    // 0xd0
+    2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, //
+    // 0xe0
+    2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, //
+    // 0xf0
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 4, 4, 3, 3, 2, 2, //
+];
+
+/// Returns the literals of all the `const vAA, #+BBBBBBBB` and `const/high16 vAA, #+BBBB0000`
+/// instructions found on the given dex file which belong to any of the packages on `package_ids`
+/// (`0xPPxxxxxx`). The file is walked once, whatever the amount of packages.
+pub fn const_literals(dex: &[u8], package_ids: &[u8]) -> Result<Vec<u32>, Error> {
+    ensure!(
+        dex.len() > MAP_OFFSET_POSITION as usize && dex.starts_with(DEX_MAGIC),
+        "buffer is not a dex file"
+    );
+
+    let mut cursor = Cursor::new(dex);
+    cursor.set_position(MAP_OFFSET_POSITION);
+    let map_offset = cursor.read_u32::<LittleEndian>()?;
+
+    cursor.set_position(u64::from(map_offset));
+    let map_size = cursor
+        .read_u32::<LittleEndian>()
+        .context("could not read map list size")?;
+
+    let mut literals = Vec::new();
+
+    for _ in 0..map_size {
+        let item_type = cursor.read_u16::<LittleEndian>()?;
+        let _unused = cursor.read_u16::<LittleEndian>()?;
+        let size = cursor.read_u32::<LittleEndian>()?;
+        let offset = cursor.read_u32::<LittleEndian>()?;
+
+        if item_type == TYPE_CODE_ITEM {
+            let mut code_cursor = Cursor::new(dex);
+            code_cursor.set_position(u64::from(offset));
+
+            for _ in 0..size {
+                read_code_item(&mut code_cursor, package_ids, &mut literals)
+                    .context("could not read code item")?;
+            }
+        }
+    }
+
+    Ok(literals)
+}
+
+fn read_code_item(
+    cursor: &mut Cursor<&[u8]>,
+    package_ids: &[u8],
+    literals: &mut Vec<u32>,
+) -> Result<(), Error> {
+    // Code items are 4-byte aligned
+    let aligned = (cursor.position() + 3) & !3;
+    cursor.set_position(aligned);
+
+    let _registers = cursor.read_u16::<LittleEndian>()?;
+    let _ins = cursor.read_u16::<LittleEndian>()?;
+    let _outs = cursor.read_u16::<LittleEndian>()?;
+    let tries_size = cursor.read_u16::<LittleEndian>()?;
+    let _debug_info = cursor.read_u32::<LittleEndian>()?;
+    let insns_size = cursor.read_u32::<LittleEndian>()?;
+
+    // `usize` is 32 bits wide on wasm32, so the size may overflow before being compared
+    let end = (insns_size as usize)
+        .checked_mul(2)
+        .and_then(|size| size.checked_add(cursor.position() as usize))
+        .ok_or_else(|| format_err!("code item with {} instructions overflows", insns_size))?;
+    ensure!(
+        end <= cursor.get_ref().len(),
+        "instructions out of the dex file bounds"
+    );
+
+    let mut insns = Vec::with_capacity(insns_size as usize);
+    for _ in 0..insns_size {
+        insns.push(cursor.read_u16::<LittleEndian>()?);
+    }
+    scan_instructions(&insns, package_ids, literals);
+
+    if tries_size > 0 {
+        if insns_size % 2 == 1 {
+            let _padding = cursor.read_u16::<LittleEndian>()?;
+        }
+
+        cursor.set_position(cursor.position() + u64::from(tries_size) * 8);
+        skip_handlers(cursor)?;
+    }
+
+    Ok(())
+}
+
+fn scan_instructions(insns: &[u16], package_ids: &[u8], literals: &mut Vec<u32>) {
+    let mut pc = 0;
+
+    while pc < insns.len() {
+        let unit = insns[pc];
+        let width = match unit {
+            PACKED_SWITCH_PAYLOAD => {
+                let size = insns.get(pc + 1).copied().unwrap_or(0) as usize;
+                size * 2 + 4
+            }
+            SPARSE_SWITCH_PAYLOAD => {
+                let size = insns.get(pc + 1).copied().unwrap_or(0) as usize;
+                size * 4 + 2
+            }
+            FILL_ARRAY_DATA_PAYLOAD => {
+                let element_width = insns.get(pc + 1).copied().unwrap_or(0) as usize;
+                let low = insns.get(pc + 2).copied().unwrap_or(0) as usize;
+                let high = insns.get(pc + 3).copied().unwrap_or(0) as usize;
+                let size = (high << 16) | low;
+                size.saturating_mul(element_width).div_ceil(2).saturating_add(4)
+            }
+            _ => usize::from(INSTRUCTION_WIDTHS[usize::from(unit & 0xFF)]),
+        };
+
+        let literal = match unit & 0xFF {
+            OPCODE_CONST if pc + 2 < insns.len() => {
+                Some(u32::from(insns[pc + 1]) | (u32::from(insns[pc + 2]) << 16))
+            }
+            OPCODE_CONST_HIGH16 if pc + 1 < insns.len() => Some(u32::from(insns[pc + 1]) << 16),
+            _ => None,
+        };
+
+        if let Some(literal) = literal {
+            if package_ids.contains(&((literal >> 24) as u8)) {
+                literals.push(literal);
+            }
+        }
+
+        pc = pc.saturating_add(width);
+    }
+}
+
+fn skip_handlers(cursor: &mut Cursor<&[u8]>) -> Result<(), Error> {
+    let handlers = read_uleb128(cursor)?;
+
+    for _ in 0..handlers {
+        let size = read_sleb128(cursor)?;
+
+        for _ in 0..size.unsigned_abs() {
+            let _type_idx = read_uleb128(cursor)?;
+            let _address = read_uleb128(cursor)?;
+        }
+
+        if size <= 0 {
+            let _catch_all_address = read_uleb128(cursor)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn read_uleb128(cursor: &mut Cursor<&[u8]>) -> Result<u32, Error> {
+    let mut result = 0;
+
+    for i in 0..5 {
+        let byte = cursor.read_u8()?;
+        result |= u32::from(byte & 0x7f) << (i * 7);
+
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+
+    Ok(result)
+}
+
+fn read_sleb128(cursor: &mut Cursor<&[u8]>) -> Result<i32, Error> {
+    let mut result: i32 = 0;
+    let mut shift = 0;
+
+    loop {
+        let byte = cursor.read_u8()?;
+        result |= i32::from(byte & 0x7f) << shift;
+        shift += 7;
+
+        if byte & 0x80 == 0 {
+            if shift < 32 && byte & 0x40 != 0 {
+                result |= -1 << shift;
+            }
+            break;
+        }
+
+        if shift >= 35 {
+            break;
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    use super::const_literals;
+
+    fn fake_dex(insns: &[u16]) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend(b"dex\n035\0");
+        out.resize(0x70, 0);
+
+        // Code item
+        let code_offset = out.len() as u32;
+        out.write_u16::<LittleEndian>(2).unwrap();
+        out.write_u16::<LittleEndian>(0).unwrap();
+        out.write_u16::<LittleEndian>(0).unwrap();
+        out.write_u16::<LittleEndian>(0).unwrap();
+        out.write_u32::<LittleEndian>(0).unwrap();
+        out.write_u32::<LittleEndian>(insns.len() as u32).unwrap();
+        for unit in insns {
+            out.write_u16::<LittleEndian>(*unit).unwrap();
+        }
+        while out.len() % 4 != 0 {
+            out.push(0);
+        }
+
+        // Map list
+        let map_offset = out.len() as u32;
+        out.write_u32::<LittleEndian>(1).unwrap();
+        out.write_u16::<LittleEndian>(0x2001).unwrap();
+        out.write_u16::<LittleEndian>(0).unwrap();
+        out.write_u32::<LittleEndian>(1).unwrap();
+        out.write_u32::<LittleEndian>(code_offset).unwrap();
+
+        (&mut out[0x34..0x38])
+            .write_u32::<LittleEndian>(map_offset)
+            .unwrap();
+
+        out
+    }
+
+    #[test]
+    fn it_finds_const_literals_of_the_given_package() {
+        let insns = [
+            // const v0, 0x7f0a0001
+            0x0014, 0x0001, 0x7f0a, //
+            // const v1, 0x01020003 (framework)
+            0x0114, 0x0003, 0x0102, //
+            // return-void
+            0x000e,
+        ];
+        let dex = fake_dex(&insns);
+
+        assert_eq!(vec![0x7f0a0001], const_literals(&dex, &[0x7f]).unwrap());
+    }
+
+    #[test]
+    fn it_finds_const_high16_literals_of_any_of_the_packages() {
+        let insns = [
+            // const/high16 v0, 0x7f0a0000
+            0x0015, 0x7f0a, //
+            // const v1, 0x80020003
+            0x0114, 0x0003, 0x8002, //
+            // const/high16 v2, 0x01020000 (framework)
+            0x0215, 0x0102, //
+            // return-void
+            0x000e,
+        ];
+        let dex = fake_dex(&insns);
+
+        assert_eq!(
+            vec![0x7f0a0000, 0x80020003],
+            const_literals(&dex, &[0x7f, 0x80]).unwrap()
+        );
+    }
+
+    #[test]
+    fn it_skips_payloads() {
+        let insns = [
+            // return-void
+            0x000e, //
+            // fill-array-data-payload with 3 elements of 2 bytes, whose data looks like a `const`
+            0x0300, 0x0002, 0x0003, 0x0000, 0x0014, 0x0001, 0x7f0a,
+        ];
+        let dex = fake_dex(&insns);
+
+        assert!(const_literals(&dex, &[0x7f]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn it_can_not_scan_instructions_out_of_bounds() {
+        let mut dex = fake_dex(&[0x000e]);
+        // Size of the instructions of the code item
+        (&mut dex[0x7c..0x80])
+            .write_u32::<LittleEndian>(0xFFFF_FFFF)
+            .unwrap();
+
+        assert!(const_literals(&dex, &[0x7f]).is_err());
+    }
+
+    #[test]
+    fn it_can_not_scan_a_non_dex_buffer() {
+        assert!(const_literals(&[0; 0x80], &[0x7f]).is_err());
+    }
+}
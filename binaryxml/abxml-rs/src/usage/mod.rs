@@ -0,0 +1,337 @@
+//! Reference graph between the resources declared on a `resources.arsc` and the places where they
+//! are used: binary XML documents, dex bytecode and other resources.
+//!
+//! A resource is considered reachable if it can be reached from a root (the manifest, any binary
+//! XML that is not owned by a resource, or the dex code) following the references. File based
+//! resources (`res/layout/main.xml`) make the references found on its file reachable once the
+//! resource itself is reachable.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use serde::Serialize;
+
+use crate::{
+    model::{
+        owned::Entry,
+        value::{
+            TOKEN_TYPE_ATTRIBUTE_REFERENCE_ID, TOKEN_TYPE_DYN_ATTRIBUTE, TOKEN_TYPE_DYN_REFERENCE,
+            TOKEN_TYPE_REFERENCE_ID,
+        },
+        Identifier, Library as _,
+    },
+    values,
+    visitor::Resources,
+};
+
+pub mod dex;
+
+/// Identifies who holds a reference to a resource
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(tag = "kind", content = "source", rename_all = "snake_case")]
+pub enum Referrer {
+    /// A binary XML document, identified by its path inside the APK
+    Xml(String),
+    /// A dex file with a `const` instruction holding the ID
+    Dex(String),
+    /// Another resource, whose value, parent or bag references the ID
+    Resource(u32),
+}
+
+/// Usage information of a single declared resource
+#[derive(Debug, Serialize)]
+pub struct UsageEntry {
+    pub id: u32,
+    pub name: String,
+    pub reachable: bool,
+    pub referenced_by: Vec<Referrer>,
+}
+
+#[derive(Default, Debug)]
+pub struct ResourceUsage {
+    declared: BTreeSet<u32>,
+    files: BTreeMap<String, u32>,
+    references: BTreeMap<Referrer, BTreeSet<u32>>,
+    referrers: BTreeMap<u32, BTreeSet<Referrer>>,
+    diagnostics: Vec<String>,
+}
+
+impl ResourceUsage {
+    /// Declares all the resources of the given package and adds the references between them, on
+    /// any of their configurations: values pointing to other resources, style parents and bag
+    /// entries. It also records which resource owns each of the files.
+    pub fn add_package(&mut self, resources: &Resources, package_id: u8) {
+        let package = match resources.packages.get(&package_id) {
+            Some(package) => package,
+            None => return,
+        };
+
+        for (id, entry) in package.iter_entries() {
+            self.declared.insert(*id);
+            let referrer = Referrer::Resource(*id);
+            let variants = package.get_variants(*id);
+
+            // References may differ between configurations, like a style with another parent on
+            // `values-v21`: all of them are followed
+            if variants.is_empty() {
+                self.add_entry_references(&referrer, entry);
+            }
+
+            for (_, variant) in variants {
+                if let Some(path) = values::file_path(package, *id, variant) {
+                    self.files.insert(path, *id);
+                }
+
+                self.add_entry_references(&referrer, variant);
+            }
+        }
+    }
+
+    /// Adds the references held by the value, the parent or the bag items of an entry
+    fn add_entry_references(&mut self, referrer: &Referrer, entry: &Entry) {
+        match entry {
+            Entry::Simple(simple) => match simple.get_type() {
+                TOKEN_TYPE_REFERENCE_ID
+                | TOKEN_TYPE_ATTRIBUTE_REFERENCE_ID
+                | TOKEN_TYPE_DYN_REFERENCE
+                | TOKEN_TYPE_DYN_ATTRIBUTE => {
+                    self.add_reference(referrer.clone(), simple.get_value());
+                }
+                _ => (),
+            },
+            Entry::Complex(complex) => {
+                self.add_reference(referrer.clone(), complex.get_parent_entry_id());
+
+                for child in complex.get_entries() {
+                    self.add_reference(referrer.clone(), child.get_id());
+
+                    match child.get_type() {
+                        TOKEN_TYPE_REFERENCE_ID
+                        | TOKEN_TYPE_ATTRIBUTE_REFERENCE_ID
+                        | TOKEN_TYPE_DYN_REFERENCE
+                        | TOKEN_TYPE_DYN_ATTRIBUTE => {
+                            self.add_reference(referrer.clone(), child.get_value());
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            Entry::Empty(_, _) => (),
+        }
+    }
+
+    /// Adds the references found on the binary XML on `path`
+    pub fn add_xml_references<I: IntoIterator<Item = u32>>(&mut self, path: &str, ids: I) {
+        for id in ids {
+            self.add_reference(Referrer::Xml(path.to_string()), id);
+        }
+    }
+
+    /// Adds the `const` literals found on the dex file `name`
+    pub fn add_dex_references<I: IntoIterator<Item = u32>>(&mut self, name: &str, ids: I) {
+        for id in ids {
+            self.add_reference(Referrer::Dex(name.to_string()), id);
+        }
+    }
+
+    /// Records that the file on `path` could not be scanned, so the references it may hold are
+    /// missing from the graph
+    pub fn add_diagnostic(&mut self, path: &str, problem: String) {
+        self.diagnostics.push(format!("{}: {}", path, problem));
+    }
+
+    /// Returns the problems found while scanning the files of the APK
+    pub fn get_diagnostics(&self) -> &[String] {
+        &self.diagnostics
+    }
+
+    fn add_reference(&mut self, referrer: Referrer, id: u32) {
+        if id == 0 {
+            return;
+        }
+
+        self.referrers
+            .entry(id)
+            .or_default()
+            .insert(referrer.clone());
+        self.references.entry(referrer).or_default().insert(id);
+    }
+
+    /// Returns who references the given resource
+    pub fn get_referrers(&self, id: u32) -> Option<&BTreeSet<Referrer>> {
+        self.referrers.get(&id)
+    }
+
+    /// Returns the resource that owns the given `res/` file, if any
+    pub fn get_file_owner(&self, path: &str) -> Option<u32> {
+        self.files.get(path).copied()
+    }
+
+    pub fn is_referenced(&self, id: u32) -> bool {
+        self.referrers.contains_key(&id)
+    }
+
+    /// Returns all the resources reachable from the roots
+    pub fn reachable(&self) -> BTreeSet<u32> {
+        let mut owned_files: BTreeMap<u32, Vec<&String>> = BTreeMap::new();
+        for (path, owner) in &self.files {
+            owned_files.entry(*owner).or_default().push(path);
+        }
+
+        let mut reachable = BTreeSet::new();
+        let mut pending: VecDeque<u32> = self
+            .references
+            .iter()
+            .filter(|(referrer, _)| self.is_root(referrer))
+            .flat_map(|(_, ids)| ids.iter().copied())
+            .collect();
+
+        while let Some(id) = pending.pop_front() {
+            if !reachable.insert(id) {
+                continue;
+            }
+
+            if let Some(ids) = self.references.get(&Referrer::Resource(id)) {
+                pending.extend(ids.iter().copied());
+            }
+
+            for path in owned_files.get(&id).into_iter().flatten() {
+                if let Some(ids) = self.references.get(&Referrer::Xml((*path).clone())) {
+                    pending.extend(ids.iter().copied());
+                }
+            }
+        }
+
+        reachable
+    }
+
+    /// Returns the declared resources which can not be reached from any root
+    pub fn unused(&self) -> Vec<u32> {
+        let reachable = self.reachable();
+
+        self.declared
+            .iter()
+            .filter(|id| !reachable.contains(id))
+            .copied()
+            .collect()
+    }
+
+    /// Builds a report with an entry per declared resource
+    pub fn report(&self, resources: &Resources) -> Vec<UsageEntry> {
+        let reachable = self.reachable();
+
+        self.declared
+            .iter()
+            .map(|id| {
+                let name = resources
+                    .packages
+                    .get(&id.get_package())
+                    .map(|package| package.resid_to_string(*id, None))
+                    .unwrap_or_else(|| format!("0x{:08x}", id));
+
+                UsageEntry {
+                    id: *id,
+                    name,
+                    reachable: reachable.contains(id),
+                    referenced_by: self
+                        .referrers
+                        .get(id)
+                        .map(|r| r.iter().cloned().collect())
+                        .unwrap_or_default(),
+                }
+            })
+            .collect()
+    }
+
+    fn is_root(&self, referrer: &Referrer) -> bool {
+        match referrer {
+            Referrer::Dex(_) => true,
+            Referrer::Xml(path) => !self.files.contains_key(path),
+            Referrer::Resource(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{Referrer, ResourceUsage};
+    use crate::{
+        model::{
+            owned::{Entry, SimpleEntry},
+            value::TOKEN_TYPE_REFERENCE_ID,
+        },
+        test::example_resources,
+    };
+
+    fn usage() -> ResourceUsage {
+        let mut usage = ResourceUsage::default();
+        usage
+            .declared
+            .extend(&[0x7f010001, 0x7f020001, 0x7f030001, 0x7f030002]);
+        usage
+            .files
+            .insert("res/layout/main.xml".to_string(), 0x7f020001);
+
+        usage
+    }
+
+    #[test]
+    fn it_follows_references_from_the_manifest_and_owned_files() {
+        let mut usage = usage();
+        usage.add_xml_references("AndroidManifest.xml", vec![0x7f020001]);
+        usage.add_xml_references("res/layout/main.xml", vec![0x7f030001]);
+        usage.add_reference(Referrer::Resource(0x7f030001), 0x7f010001);
+
+        assert_eq!(vec![0x7f030002], usage.unused());
+        assert!(usage.reachable().contains(&0x7f010001));
+    }
+
+    #[test]
+    fn it_does_not_follow_files_of_unreachable_resources() {
+        let mut usage = usage();
+        usage.add_xml_references("res/layout/main.xml", vec![0x7f030001]);
+
+        assert!(usage.is_referenced(0x7f030001));
+        assert_eq!(
+            vec![0x7f010001, 0x7f020001, 0x7f030001, 0x7f030002],
+            usage.unused()
+        );
+    }
+
+    #[test]
+    fn it_treats_dex_references_as_roots() {
+        let mut usage = usage();
+        usage.add_dex_references("classes.dex", vec![0x7f030002]);
+
+        let referrers = usage.get_referrers(0x7f030002).unwrap();
+        assert!(referrers.contains(&Referrer::Dex("classes.dex".to_string())));
+        assert!(usage.reachable().contains(&0x7f030002));
+    }
+
+    #[test]
+    fn it_follows_references_of_every_configuration() {
+        let mut resources = example_resources();
+        let package = resources.packages.get_mut(&0x7f).unwrap();
+
+        // `string/path_like` only points to `drawable/icon` on `v21`, which is not the last
+        // configuration read
+        let mut v21 = HashMap::new();
+        v21.insert(
+            0x7f03_0001,
+            Entry::Simple(SimpleEntry::new(1, 3, TOKEN_TYPE_REFERENCE_ID, 0x7f02_0000)),
+        );
+        package.add_variants("v21", v21);
+        let mut night = HashMap::new();
+        night.insert(0x7f03_0001, Entry::Simple(SimpleEntry::new(1, 3, 0x03, 3)));
+        package.add_variants("night", night);
+
+        let mut usage = ResourceUsage::default();
+        usage.add_package(&resources, 0x7f);
+
+        let referrers = usage.get_referrers(0x7f02_0000).unwrap();
+        assert!(referrers.contains(&Referrer::Resource(0x7f03_0001)));
+        usage.add_dex_references("classes.dex", vec![0x7f03_0001]);
+        assert!(!usage.unused().contains(&0x7f02_0000));
+    }
+}
@@ -0,0 +1,599 @@
+//! Regeneration of the `res/values*/` files of an application from its `resources.arsc`, laid out
+//! the same way as apktool does: one folder per configuration and one file per type of resource
+//! (`strings.xml`, `styles.xml`...), plus a `public.xml` with the IDs of all the resources.
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{format_err, Error};
+
+use crate::{
+    model::{
+        owned::{AttrDefinition, Bag, ComplexEntry, Entry, SimpleEntry},
+        value::{
+            TOKEN_TYPE_ATTRIBUTE_REFERENCE_ID, TOKEN_TYPE_DYN_ATTRIBUTE, TOKEN_TYPE_DYN_REFERENCE,
+            TOKEN_TYPE_FLAGS, TOKEN_TYPE_INTEGER, TOKEN_TYPE_REFERENCE_ID, TOKEN_TYPE_STRING,
+        },
+        Identifier, Library as _, Value,
+    },
+    visitor::{model::Library, Resources},
+};
+
+const XML_HEADER: &str = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n";
+
+/// Types whose string values are the path of a file of the APK instead of a text. Obfuscated
+/// APKs move these files anywhere (`r/a.xml`), so the path itself can not tell them apart.
+const FILE_TYPES: [&str; 13] = [
+    "anim",
+    "animator",
+    "color",
+    "drawable",
+    "font",
+    "interpolator",
+    "layout",
+    "menu",
+    "mipmap",
+    "navigation",
+    "raw",
+    "transition",
+    "xml",
+];
+
+/// A generated file of the `res/values*/` folders
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValuesFile {
+    /// Path of the file, like `res/values-es/strings.xml`
+    pub path: String,
+    pub contents: String,
+}
+
+/// Generates the values files of the given package
+pub fn values_files(resources: &Resources, package_id: u8) -> Result<Vec<ValuesFile>, Error> {
+    let package = resources
+        .packages
+        .get(&package_id)
+        .ok_or_else(|| format_err!("package {} not found", package_id))?;
+
+    let mut ids: Vec<u32> = package.iter_entries().map(|(id, _)| *id).collect();
+    ids.sort_unstable();
+
+    let mut files: BTreeMap<(String, String), Vec<String>> = BTreeMap::new();
+
+    for id in ids {
+        let (type_name, name) = match type_and_name(package, id) {
+            Some(type_and_name) => type_and_name,
+            None => continue,
+        };
+
+        for (qualifiers, entry) in package.get_variants(id) {
+            if file_path(package, id, entry).is_some() {
+                continue;
+            }
+
+            let (file_name, element) = match entry {
+                Entry::Simple(simple) => {
+                    simple_element(resources, package, &type_name, &name, simple)
+                }
+                Entry::Complex(complex) => {
+                    complex_element(resources, package, &type_name, &name, complex)
+                }
+                Entry::Empty(_, _) => continue,
+            };
+
+            files
+                .entry((qualifiers.clone(), file_name))
+                .or_default()
+                .push(element);
+        }
+    }
+
+    let mut result: Vec<ValuesFile> = files
+        .into_iter()
+        .map(|((qualifiers, file_name), elements)| ValuesFile {
+            path: format!("res/{}/{}", folder("values", &qualifiers), file_name),
+            contents: resources_document(&elements),
+        })
+        .collect();
+    result.push(public_file(resources, package_id)?);
+
+    Ok(result)
+}
+
+/// Generates the `public.xml` of the given package, which pins the ID of every resource so they
+/// stay the same when the package is built again
+pub fn public_file(resources: &Resources, package_id: u8) -> Result<ValuesFile, Error> {
+    let package = resources
+        .packages
+        .get(&package_id)
+        .ok_or_else(|| format_err!("package {} not found", package_id))?;
+
+    let mut ids: Vec<u32> = package.iter_entries().map(|(id, _)| *id).collect();
+    ids.sort_unstable();
+
+    let public: Vec<String> = ids
+        .into_iter()
+        .filter_map(|id| {
+            type_and_name(package, id).map(|(type_name, name)| {
+                format!(
+                    "    <public type=\"{}\" name=\"{}\" id=\"0x{:08x}\" />",
+                    type_name,
+                    escape_text(&name),
+                    id
+                )
+            })
+        })
+        .collect();
+
+    Ok(ValuesFile {
+        path: "res/values/public.xml".to_string(),
+        contents: resources_document(&public),
+    })
+}
+
+/// Resource that a file of the `res/` folder belongs to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourcePath {
+    pub id: u32,
+    /// Type of the resource, like `layout` or `drawable`
+    pub type_name: String,
+    pub name: String,
+    /// Qualifiers of the configuration the file is used on, empty for the default one
+    pub config: String,
+    /// Extension of the file, including the leading dot (`.xml`, `.9.png`...)
+    pub extension: String,
+}
+
+impl ResourcePath {
+    /// Path apktool would give to the file: `res/<type>[-<qualifiers>]/<name><extension>`
+    pub fn path(&self) -> String {
+        format!(
+            "res/{}/{}{}",
+            folder(&self.type_name, &self.config),
+            self.name,
+            self.extension
+        )
+    }
+}
+
+/// Maps the path of each file based resource of the package to the resource it holds. Shrunk
+/// APKs store these files with short, meaningless paths (like `res/Ab.xml`), and this is the only
+/// way to know what they are.
+pub fn resource_paths(resources: &Resources, package_id: u8) -> HashMap<String, ResourcePath> {
+    let mut paths = HashMap::new();
+    let package = match resources.packages.get(&package_id) {
+        Some(package) => package,
+        None => return paths,
+    };
+
+    let mut ids: Vec<u32> = package.iter_entries().map(|(id, _)| *id).collect();
+    ids.sort_unstable();
+
+    for id in ids {
+        let (type_name, name) = match type_and_name(package, id) {
+            Some(type_and_name) => type_and_name,
+            None => continue,
+        };
+
+        for (qualifiers, entry) in package.get_variants(id) {
+            if let Some(path) = file_path(package, id, entry) {
+                let file_name = path.rsplit('/').next().unwrap_or(&path);
+                let extension = file_name.find('.').map_or("", |dot| &file_name[dot..]);
+
+                // The same file may be shared by several resources: keep the first one
+                paths.entry(path.clone()).or_insert_with(|| ResourcePath {
+                    id,
+                    type_name: type_name.clone(),
+                    name: name.clone(),
+                    config: qualifiers.clone(),
+                    extension: extension.to_string(),
+                });
+            }
+        }
+    }
+
+    paths
+}
+
+fn type_and_name(package: &Library, id: u32) -> Option<(String, String)> {
+    let entry = package.get_entry(id).ok()?;
+    let type_name = package.get_spec_as_str(u32::from(id.get_spec())).ok()?;
+    let name = package.get_entries_string(entry.get_key()).ok()?;
+
+    Some((type_name, (*name).clone()))
+}
+
+/// Path inside the APK of the file of a file based resource
+pub(crate) fn file_path(package: &Library, id: u32, entry: &Entry) -> Option<String> {
+    let simple = entry.simple().ok()?;
+    if simple.get_type() != TOKEN_TYPE_STRING {
+        return None;
+    }
+
+    let type_name = package.get_spec_as_str(u32::from(id.get_spec())).ok()?;
+    if !FILE_TYPES.contains(&type_name.as_str()) {
+        return None;
+    }
+
+    package
+        .get_string(simple.get_value())
+        .ok()
+        .map(|path| (*path).clone())
+}
+
+fn folder(base: &str, qualifiers: &str) -> String {
+    if qualifiers.is_empty() {
+        base.to_string()
+    } else {
+        format!("{}-{}", base, qualifiers)
+    }
+}
+
+fn resources_document(elements: &[String]) -> String {
+    let mut document = format!("{}<resources>\n", XML_HEADER);
+    for element in elements {
+        document.push_str(element);
+        document.push('\n');
+    }
+    document.push_str("</resources>\n");
+
+    document
+}
+
+fn simple_element(
+    resources: &Resources,
+    package: &Library,
+    type_name: &str,
+    name: &str,
+    entry: &SimpleEntry,
+) -> (String, String) {
+    let value = value_text(resources, package, entry);
+    let name = escape_text(name);
+
+    match type_name {
+        "string" | "color" | "dimen" | "bool" | "integer" | "fraction" => (
+            format!("{}s.xml", type_name),
+            format!(
+                "    <{} name=\"{}\">{}</{}>",
+                type_name, name, value, type_name
+            ),
+        ),
+        "id" => (
+            "ids.xml".to_string(),
+            format!("    <item type=\"id\" name=\"{}\" />", name),
+        ),
+        _ => (
+            format!("{}s.xml", type_name),
+            format!(
+                "    <item type=\"{}\" name=\"{}\">{}</item>",
+                type_name, name, value
+            ),
+        ),
+    }
+}
+
+fn complex_element(
+    resources: &Resources,
+    package: &Library,
+    type_name: &str,
+    name: &str,
+    entry: &ComplexEntry,
+) -> (String, String) {
+    let name = escape_text(name);
+    let mut lines = Vec::new();
+
+    let file_name = match Bag::new(type_name, entry) {
+        Bag::Plurals(items) => {
+            lines.push(format!("    <plurals name=\"{}\">", name));
+            for (quantity, item) in items {
+                lines.push(format!(
+                    "        <item quantity=\"{}\">{}</item>",
+                    quantity.as_str(),
+                    value_text(resources, package, &item)
+                ));
+            }
+            lines.push("    </plurals>".to_string());
+
+            "plurals.xml"
+        }
+        Bag::Array(items) => {
+            let tag = if items.iter().all(|i| i.get_type() == TOKEN_TYPE_STRING) {
+                "string-array"
+            } else if items.iter().all(|i| i.get_type() == TOKEN_TYPE_INTEGER) {
+                "integer-array"
+            } else {
+                "array"
+            };
+
+            lines.push(format!("    <{} name=\"{}\">", tag, name));
+            for item in items {
+                lines.push(format!(
+                    "        <item>{}</item>",
+                    value_text(resources, package, &item)
+                ));
+            }
+            lines.push(format!("    </{}>", tag));
+
+            "arrays.xml"
+        }
+        Bag::Attr(definition) => {
+            lines.extend(attr_lines(resources, &name, &definition));
+
+            "attrs.xml"
+        }
+        Bag::Style(items) => {
+            match reference(resources, entry.get_parent_entry_id()) {
+                Some(parent) => lines.push(format!(
+                    "    <style name=\"{}\" parent=\"{}\">",
+                    name, parent
+                )),
+                None => lines.push(format!("    <style name=\"{}\">", name)),
+            }
+            for (attribute, item) in items {
+                lines.push(format!(
+                    "        <item name=\"{}\">{}</item>",
+                    attribute_name(resources, attribute),
+                    style_value(resources, package, attribute, &item)
+                ));
+            }
+            lines.push("    </style>".to_string());
+
+            "styles.xml"
+        }
+        Bag::Other(items) => {
+            lines.push(format!(
+                "    <item type=\"{}\" name=\"{}\">",
+                type_name, name
+            ));
+            for (key, item) in items {
+                lines.push(format!(
+                    "        <item name=\"{}\">{}</item>",
+                    key,
+                    value_text(resources, package, &item)
+                ));
+            }
+            lines.push("    </item>".to_string());
+
+            return (format!("{}s.xml", type_name), lines.join("\n"));
+        }
+    };
+
+    (file_name.to_string(), lines.join("\n"))
+}
+
+fn attr_lines(resources: &Resources, name: &str, definition: &AttrDefinition) -> Vec<String> {
+    let formats: Vec<&str> = definition
+        .get_format_names()
+        .into_iter()
+        .filter(|format| *format != "enum" && *format != "flags")
+        .collect();
+
+    let mut opening = format!("    <attr name=\"{}\"", name);
+    if !formats.is_empty() {
+        opening.push_str(&format!(" format=\"{}\"", formats.join("|")));
+    }
+    if let Some(min) = definition.min {
+        opening.push_str(&format!(" min=\"{}\"", min));
+    }
+    if let Some(max) = definition.max {
+        opening.push_str(&format!(" max=\"{}\"", max));
+    }
+
+    if definition.symbols.is_empty() {
+        return vec![format!("{} />", opening)];
+    }
+
+    let mut lines = vec![format!("{}>", opening)];
+    for (id, value) in &definition.symbols {
+        let symbol = symbol_name(resources, *id);
+        if definition.is_enum() {
+            lines.push(format!(
+                "        <enum name=\"{}\" value=\"{}\" />",
+                symbol, *value as i32
+            ));
+        } else {
+            lines.push(format!(
+                "        <flag name=\"{}\" value=\"0x{:08x}\" />",
+                symbol, value
+            ));
+        }
+    }
+    lines.push("    </attr>".to_string());
+
+    lines
+}
+
+/// Value of an item of a style. Enum and flag values are printed with the symbols of the attribute
+/// they set.
+fn style_value(
+    resources: &Resources,
+    package: &Library,
+    attribute: u32,
+    item: &SimpleEntry,
+) -> String {
+    if item.get_type() == TOKEN_TYPE_INTEGER || item.get_type() == TOKEN_TYPE_FLAGS {
+        let definition = resources
+            .packages
+            .get(&attribute.get_package())
+            .and_then(|attr_package| attr_package.get_entry(attribute).ok())
+            .and_then(|entry| entry.complex().ok())
+            .map(AttrDefinition::new);
+
+        if let Some(definition) = definition {
+            let symbols: Vec<String> = definition
+                .resolve_symbols(item.get_value())
+                .into_iter()
+                .map(|id| symbol_name(resources, id))
+                .collect();
+
+            if !symbols.is_empty() {
+                return symbols.join("|");
+            }
+        }
+    }
+
+    value_text(resources, package, item)
+}
+
+/// Textual value, escaped to be used as the contents of an element
+fn value_text(resources: &Resources, package: &Library, entry: &SimpleEntry) -> String {
+    let data = entry.get_value();
+
+    match entry.get_type() {
+        0x00 if data == 1 => "@empty".to_string(),
+        0x00 => "@null".to_string(),
+        TOKEN_TYPE_STRING => package
+            .get_string(data)
+            .map(|string| escape_string(&string))
+            .unwrap_or_default(),
+        TOKEN_TYPE_REFERENCE_ID | TOKEN_TYPE_DYN_REFERENCE => {
+            reference(resources, data).unwrap_or_else(|| format!("@0x{:08x}", data))
+        }
+        TOKEN_TYPE_ATTRIBUTE_REFERENCE_ID | TOKEN_TYPE_DYN_ATTRIBUTE => reference(resources, data)
+            .map(|name| format!("?{}", name.trim_start_matches(['@', '?'])))
+            .unwrap_or_else(|| format!("?0x{:08x}", data)),
+        TOKEN_TYPE_INTEGER => (data as i32).to_string(),
+        TOKEN_TYPE_FLAGS => format!("0x{:08x}", data),
+        value_type => Value::create(value_type, data)
+            .map(|value| value.to_string())
+            .unwrap_or_else(|_| format!("0x{:08x}", data)),
+    }
+}
+
+/// Name of the resource, like `@string/app_name` or `@android:color/black`
+fn reference(resources: &Resources, id: u32) -> Option<String> {
+    if id == 0 {
+        return None;
+    }
+
+    let package_id = id.get_package();
+    let package = resources.packages.get(&package_id)?;
+    let key = package.get_entry(id).ok()?.get_key();
+    let namespace = if package_id == 1 {
+        Some("android".to_string())
+    } else {
+        None
+    };
+
+    package.format_reference(id, key, namespace).ok()
+}
+
+/// Name of an attribute used as the key of a style item, like `android:textColor`
+fn attribute_name(resources: &Resources, id: u32) -> String {
+    match reference(resources, id) {
+        Some(name) => {
+            let name = name.trim_start_matches(['@', '?']);
+            match name.find("attr/") {
+                Some(position) => format!("{}{}", &name[..position], &name[position + 5..]),
+                None => name.to_string(),
+            }
+        }
+        None => format!("0x{:08x}", id),
+    }
+}
+
+/// Name of the `@id` resource that names a symbol of an enum or flags attribute
+fn symbol_name(resources: &Resources, id: u32) -> String {
+    resources
+        .packages
+        .get(&id.get_package())
+        .and_then(|package| {
+            let key = package.get_entry(id).ok()?.get_key();
+            package.get_entries_string(key).ok()
+        })
+        .map_or_else(|| format!("0x{:08x}", id), |name| escape_text(&name))
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Escapes a string the way `aapt` expects it on a values file
+fn escape_string(string: &str) -> String {
+    let mut escaped = String::with_capacity(string.len());
+
+    if string.starts_with('@') || string.starts_with('?') || string.starts_with('#') {
+        escaped.push('\\');
+    }
+    for c in string.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\'' => escaped.push_str("\\'"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            c => escaped.push(c),
+        }
+    }
+
+    if string.starts_with(' ') || string.ends_with(' ') {
+        format!("\"{}\"", escaped)
+    } else {
+        escaped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape_string, resource_paths, values_files, ResourcePath};
+    use crate::test::example_resources;
+
+    #[test]
+    fn it_escapes_strings_like_aapt_expects() {
+        assert_eq!("\\@home", escape_string("@home"));
+        assert_eq!("it\\'s &lt;b&gt;", escape_string("it's <b>"));
+        assert_eq!("\" padded \"", escape_string(" padded "));
+    }
+
+    #[test]
+    fn it_builds_the_path_of_a_resource_file() {
+        let path = ResourcePath {
+            id: 0x7f03_0001,
+            type_name: "drawable".to_string(),
+            name: "ic_launcher".to_string(),
+            config: "xhdpi-v4".to_string(),
+            extension: ".9.png".to_string(),
+        };
+
+        assert_eq!("res/drawable-xhdpi-v4/ic_launcher.9.png", path.path());
+    }
+
+    #[test]
+    fn it_generates_the_values_files_of_a_package() {
+        let resources = example_resources();
+
+        let files = values_files(&resources, 0x7f).unwrap();
+        let public = files
+            .iter()
+            .find(|f| f.path == "res/values/public.xml")
+            .unwrap();
+
+        assert!(public.contents.starts_with("<?xml"));
+        assert!(public.contents.contains("<public type="));
+        assert!(files
+            .iter()
+            .any(|f| f.path.ends_with("/plurals.xml") && f.contents.contains("<item quantity=")));
+        assert!(files
+            .iter()
+            .any(|f| f.path == "res/values/styles.xml" && f.contents.contains("<style name=")));
+        assert!(files
+            .iter()
+            .any(|f| f.path == "res/values-es/strings.xml"
+                && f.contents.contains("res/not_a_file.xml")));
+    }
+
+    #[test]
+    fn it_tells_files_from_strings_by_the_resource_type() {
+        let resources = example_resources();
+
+        let paths = resource_paths(&resources, 0x7f);
+        let mut files: Vec<&String> = paths.keys().collect();
+        files.sort();
+        assert_eq!(vec!["r/a.xml", "res/drawable/icon.png"], files);
+        assert_eq!("res/layout/main.xml", paths["r/a.xml"].path());
+    }
+}
@@ -0,0 +1,73 @@
+use std::fmt::{self, Display, Formatter};
+
+use serde::Serialize;
+
+use crate::model::Element;
+
+/// How a visitor should react to malformed data
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum DecodeMode {
+    /// Elements that can not be decoded are dropped from the output
+    #[default]
+    Strict,
+    /// Recover from malformed data the same way the Android runtime does: unknown chunks are
+    /// skipped, the attribute layout declared on the tag is honored, and bogus string indices,
+    /// undeclared namespaces or unresolvable references are replaced by placeholders.
+    Lenient,
+}
+
+/// A problem found while decoding a binary document
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    /// Absolute offset of the chunk in which the problem was found
+    pub offset: u64,
+    /// Type of the chunk in which the problem was found, if known
+    pub chunk_type: Option<u16>,
+    pub problem: String,
+}
+
+impl Diagnostic {
+    pub fn new(offset: u64, chunk_type: Option<u16>, problem: String) -> Self {
+        Self {
+            offset,
+            chunk_type,
+            problem,
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.chunk_type {
+            Some(chunk_type) => write!(
+                f,
+                "@{} (chunk 0x{:X}): {}",
+                self.offset, chunk_type, self.problem
+            ),
+            None => write!(f, "@{}: {}", self.offset, self.problem),
+        }
+    }
+}
+
+/// Result of decoding a binary XML: the text XML and the element tree, if any could be produced,
+/// and all the problems found on the way
+#[derive(Debug, Serialize)]
+pub struct DecodedXml {
+    pub xml: Option<String>,
+    pub root: Option<Element>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Diagnostic;
+
+    #[test]
+    fn it_formats_a_diagnostic() {
+        let with_type = Diagnostic::new(40, Some(0x102), "bad string".to_string());
+        let without_type = Diagnostic::new(8, None, "truncated".to_string());
+
+        assert_eq!("@40 (chunk 0x102): bad string", with_type.to_string());
+        assert_eq!("@8: truncated", without_type.to_string());
+    }
+}
@@ -1,23 +1,29 @@
 //! Collection of visitors that are fed from chunk iterator
 use std::io::Cursor;
 
-use anyhow::{bail, Context, Error};
+use anyhow::{bail, ensure, Context, Error};
 use byteorder::{LittleEndian, ReadBytesExt};
 use log::warn;
 
 use crate::chunks::{
-    Chunk, ChunkLoaderStream, PackageWrapper, ResourceWrapper, StringTableWrapper,
+    Chunk, ChunkHeader, ChunkLoaderStream, PackageWrapper, ResourceWrapper, StringTableWrapper,
     TableTypeWrapper, TypeSpecWrapper, XmlNamespaceEndWrapper, XmlNamespaceStartWrapper,
     XmlTagEndWrapper, XmlTagStartWrapper, XmlTextWrapper,
 };
 
+mod diagnostics;
 pub mod model;
 mod print;
+mod pull;
+mod reference;
 mod xml;
 
 pub use self::{
-    model::{ModelVisitor, RefPackage, Resources},
-    xml::XmlVisitor,
+    diagnostics::{DecodeMode, DecodedXml, Diagnostic},
+    model::{ModelVisitor, Resources},
+    pull::{PullEvent, XmlPullParser},
+    reference::ReferenceVisitor,
+    xml::{XmlAttribute, XmlVisitor},
 };
 
 pub trait ChunkVisitor<'a> {
@@ -31,6 +37,10 @@ pub trait ChunkVisitor<'a> {
     fn visit_xml_tag_end(&mut self, _tag_end: XmlTagEndWrapper<'a>) {}
     fn visit_xml_text(&mut self, _text: XmlTextWrapper<'a>) {}
     fn visit_resource(&mut self, _resource: ResourceWrapper<'a>) {}
+    /// Called with the header of every chunk, before visiting the chunk itself
+    fn visit_chunk_header(&mut self, _header: &ChunkHeader) {}
+    /// Called for chunks which are unknown or not expected on the current document
+    fn visit_unexpected_chunk(&mut self, _header: &ChunkHeader) {}
 }
 
 /// Methods to decode a binary resource.arsc file or a binary xml file
@@ -59,14 +69,24 @@ impl Executor {
         let _package_amount = cursor
             .read_u32::<LittleEndian>()
             .context("error reading package amount")?;
-        // TODO: Avoid infinite loop
+        ensure!(
+            header_size >= 12 && usize::from(header_size) <= buffer.len(),
+            "invalid ARSC header size: {}",
+            header_size
+        );
         cursor.set_position(u64::from(header_size));
 
-        let stream = ChunkLoaderStream::new(cursor);
+        let mut stream = ChunkLoaderStream::new(cursor);
         let mut origin = Origin::Global;
 
-        for c in stream {
-            match c.context("error reading next chunk")? {
+        while let Some(c) = stream.next() {
+            let chunk = c.context("error reading next chunk")?;
+            let header = stream.get_current_header();
+            if let Some(header) = &header {
+                visitor.visit_chunk_header(header);
+            }
+
+            match chunk {
                 Chunk::StringTable(stw) => {
                     visitor.visit_string_table(stw, origin);
                     origin = Origin::next(origin);
@@ -82,6 +102,9 @@ impl Executor {
                 }
                 _ => {
                     warn!("Not expected chunk on ARSC");
+                    if let Some(header) = &header {
+                        visitor.visit_unexpected_chunk(header);
+                    }
                 }
             }
         }
@@ -92,28 +115,19 @@ impl Executor {
     /// Given a valid binary XML file contents, it will call to the proper methods on the
     /// given visitor.
     pub fn xml<'a, V: ChunkVisitor<'a>>(
-        mut cursor: Cursor<&'a [u8]>,
+        cursor: Cursor<&'a [u8]>,
         visitor: &mut V,
     ) -> Result<(), Error> {
-        let token = cursor
-            .read_u16::<LittleEndian>()
-            .context("error reading first token")?;
-
-        if token != 0x3 {
-            bail!("document does not start with XML token: {:X}", token);
-        }
+        let mut stream = Self::xml_stream(cursor)?;
 
-        let header_size = cursor
-            .read_u16::<LittleEndian>()
-            .context("error reading header size")?;
-        let _chunk_size = cursor
-            .read_u32::<LittleEndian>()
-            .context("error reading chunk size")?;
-        cursor.set_position(u64::from(header_size));
-        let stream = ChunkLoaderStream::new(cursor);
+        while let Some(c) = stream.next() {
+            let chunk = c.context("error reading next chunk")?;
+            let header = stream.get_current_header();
+            if let Some(header) = &header {
+                visitor.visit_chunk_header(header);
+            }
 
-        for c in stream {
-            match c.context("error reading next chunk")? {
+            match chunk {
                 Chunk::StringTable(stw) => {
                     visitor.visit_string_table(stw, Origin::Global);
                 }
@@ -135,12 +149,44 @@ impl Executor {
                 Chunk::Resource(rw) => {
                     visitor.visit_resource(rw);
                 }
-                _ => (),
+                _ => {
+                    if let Some(header) = &header {
+                        visitor.visit_unexpected_chunk(header);
+                    }
+                }
             }
         }
 
         Ok(())
     }
+
+    /// Checks the header of a binary XML and returns the stream of the chunks that follow it
+    pub(crate) fn xml_stream<'a>(
+        mut cursor: Cursor<&'a [u8]>,
+    ) -> Result<ChunkLoaderStream<'a>, Error> {
+        let token = cursor
+            .read_u16::<LittleEndian>()
+            .context("error reading first token")?;
+
+        if token != 0x3 {
+            bail!("document does not start with XML token: {:X}", token);
+        }
+
+        let header_size = cursor
+            .read_u16::<LittleEndian>()
+            .context("error reading header size")?;
+        let _chunk_size = cursor
+            .read_u32::<LittleEndian>()
+            .context("error reading chunk size")?;
+        ensure!(
+            header_size >= 8 && u64::from(header_size) <= cursor.get_ref().len() as u64,
+            "invalid XML header size: {}",
+            header_size
+        );
+        cursor.set_position(u64::from(header_size));
+
+        Ok(ChunkLoaderStream::new(cursor))
+    }
 }
 
 #[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
@@ -1,15 +1,18 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{collections::HashMap, sync::Arc};
 
 use anyhow::{format_err, Context, Error};
 use log::error;
 
 use crate::{
     chunks::{
-        PackageWrapper, StringTableCache, StringTableWrapper, TableTypeWrapper, TypeSpecWrapper,
+        OwnedStringTable, PackageWrapper, StringTableCache, StringTableWrapper, TableTypeWrapper,
+        TypeSpecWrapper,
     },
     model::{
-        owned::Entry, Entries, Identifier, Library as LibraryTrait, LibraryBuilder,
-        Resources as ResourcesTrait, StringTable as StringTableTrait, TypeSpec as TypeSpecTrait,
+        owned::{Entry, SpecFlags, TableTypeSpecBuf, TypeFlags},
+        Configuration, Entries, Identifier, Library as LibraryTrait, LibraryBuilder,
+        Resources as ResourcesTrait, StringTable as StringTableTrait, TableType,
+        TypeSpec as TypeSpecTrait,
     },
 };
 
@@ -18,31 +21,37 @@ use super::{ChunkVisitor, Origin};
 #[derive(Default, Debug)]
 pub struct ModelVisitor<'a> {
     package_mask: u32,
-    resources: Resources<'a>,
+    resources: Resources,
     current_spec: Option<TypeSpecWrapper<'a>>,
-    tables: HashMap<Origin, StringTableCache<StringTableWrapper<'a>>>,
+    tables: HashMap<Origin, StringTableCache<OwnedStringTable>>,
 }
 
 impl<'a> ModelVisitor<'a> {
-    pub fn get_resources(&self) -> &'a Resources {
+    pub fn get_resources(&self) -> &Resources {
         &self.resources
     }
 
-    pub fn get_mut_resources(&mut self) -> &'a mut Resources {
+    pub fn get_mut_resources(&mut self) -> &mut Resources {
         &mut self.resources
     }
+
+    /// Returns the decoded resources, which do not borrow the visited buffers
+    pub fn into_resources(self) -> Resources {
+        self.resources
+    }
 }
 
 impl<'a> ChunkVisitor<'a> for ModelVisitor<'a> {
     fn visit_string_table(&mut self, string_table: StringTableWrapper<'a>, origin: Origin) {
+        let string_table = StringTableCache::new(string_table.to_owned_table());
+
         if let Origin::Global = origin {
-            self.tables
-                .insert(origin, StringTableCache::new(string_table));
+            self.tables.insert(origin, string_table);
         } else {
             let package_id = self.package_mask.get_package();
 
             let st_res = self.resources.get_mut_package(package_id).map(|package| {
-                package.set_string_table(StringTableCache::new(string_table), origin);
+                package.set_string_table(string_table, origin);
             });
 
             if st_res.is_none() {
@@ -102,22 +111,36 @@ impl<'a> ChunkVisitor<'a> for ModelVisitor<'a> {
             }
         }
 
+        let spec_id = self.current_spec.as_ref().and_then(|ts| ts.get_id().ok());
         let package_id = self.package_mask.get_package();
+        let qualifiers = table_type
+            .get_configuration()
+            .map(|configuration| configuration.get_qualifiers());
 
-        self.resources
-            .get_mut_package(package_id)
-            .and_then(|package| {
-                package.add_entries(entries);
-                Some(())
-            });
+        if let Some(package) = self.resources.get_mut_package(package_id) {
+            match qualifiers {
+                Ok(qualifiers) => package.add_variants(&qualifiers, entries),
+                Err(err) => {
+                    error!("Could not read the configuration: {}", err);
+                    package.add_entries(entries);
+                }
+            }
+
+            match (spec_id, table_type.get_flags()) {
+                (Some(spec_id), Ok(flags)) => package.add_type_flags(u32::from(spec_id), flags),
+                (_, Err(err)) => error!("Could not read the table type flags: {}", err),
+                _ => {}
+            }
+        }
     }
 
     fn visit_type_spec(&mut self, type_spec: TypeSpecWrapper<'a>) {
         self.current_spec = Some(type_spec.clone());
         let package_id = (self.package_mask >> 24) as u8;
         if let Some(package) = self.resources.get_mut_package(package_id) {
-            let _ = package
-                .add_type_spec(type_spec)
+            let _ = type_spec
+                .to_buffer()
+                .and_then(|type_spec| package.add_type_spec(type_spec))
                 .map_err(|e| error!("Could not add type spec: {}", e));
         } else {
             error!("Type spec refers to a non existing package");
@@ -125,16 +148,16 @@ impl<'a> ChunkVisitor<'a> for ModelVisitor<'a> {
     }
 }
 
-pub type RefPackage<'a> = Rc<RefCell<Library<'a>>>;
-
+/// Resources decoded from one or more `resources.arsc`. They own all their data, so they can be
+/// kept after the buffers they were read from are dropped, and shared between threads.
 #[derive(Default, Debug)]
-pub struct Resources<'a> {
-    pub packages: HashMap<u8, Library<'a>>,
+pub struct Resources {
+    pub packages: HashMap<u8, Library>,
     main_package: Option<u8>,
 }
 
-impl<'a> Resources<'a> {
-    pub fn push_package(&mut self, package_id: u8, package: Library<'a>) {
+impl Resources {
+    pub fn push_package(&mut self, package_id: u8, package: Library) {
         if self.packages.is_empty() {
             self.main_package = Some(package_id);
         }
@@ -143,8 +166,8 @@ impl<'a> Resources<'a> {
     }
 }
 
-impl<'a> ResourcesTrait<'a> for Resources<'a> {
-    type Library = Library<'a>;
+impl<'a> ResourcesTrait<'a> for Resources {
+    type Library = Library;
 
     fn get_package(&self, package_id: u8) -> Option<&Self::Library> {
         self.packages.get(&package_id)
@@ -173,36 +196,95 @@ impl<'a> ResourcesTrait<'a> for Resources<'a> {
 }
 
 #[derive(Debug)]
-pub struct Library<'a> {
-    package: PackageWrapper<'a>,
-    specs: HashMap<u32, TypeSpecWrapper<'a>>,
-    string_table: Option<StringTableCache<StringTableWrapper<'a>>>,
-    spec_string_table: Option<StringTableCache<StringTableWrapper<'a>>>,
-    entries_string_table: Option<StringTableCache<StringTableWrapper<'a>>>,
+pub struct Library {
+    name: Option<String>,
+    specs: HashMap<u32, TableTypeSpecBuf>,
+    string_table: Option<StringTableCache<OwnedStringTable>>,
+    spec_string_table: Option<StringTableCache<OwnedStringTable>>,
+    entries_string_table: Option<StringTableCache<OwnedStringTable>>,
     entries: Entries,
+    variants: HashMap<u32, Vec<(String, Entry)>>,
+    type_flags: HashMap<u32, TypeFlags>,
 }
 
-impl<'a> Library<'a> {
-    pub fn new(package: PackageWrapper<'a>) -> Self {
+impl Library {
+    pub fn new(package: PackageWrapper<'_>) -> Self {
         Self {
-            package,
+            name: package.get_name().ok(),
             specs: HashMap::new(),
             string_table: None,
             spec_string_table: None,
             entries_string_table: None,
             entries: Entries::default(),
+            variants: HashMap::new(),
+            type_flags: HashMap::new(),
         }
     }
 
-    pub fn iter_specs(&self) -> impl Iterator<Item = (&u32, &TypeSpecWrapper<'a>)> {
+    pub fn iter_specs(&self) -> impl Iterator<Item = (&u32, &TableTypeSpecBuf)> {
         self.specs.iter()
     }
 
+    /// Returns the string pool of the given origin: the values, the type names or the entry keys
+    pub fn get_string_pool(&self, origin: Origin) -> Option<&StringTableCache<OwnedStringTable>> {
+        match origin {
+            Origin::Global => self.string_table.as_ref(),
+            Origin::Spec => self.spec_string_table.as_ref(),
+            Origin::Entries => self.entries_string_table.as_ref(),
+        }
+    }
+
+    /// Returns an entry per resource ID. When a resource is defined on several configurations, the
+    /// entry is the one of the default configuration, or the first one read if the resource has no
+    /// default value: use `get_variants` to pick another configuration.
     pub fn iter_entries(&self) -> impl Iterator<Item = (&u32, &Entry)> {
         self.entries.iter()
     }
 
-    pub fn get_string(&self, str_id: u32) -> Result<Rc<String>, Error> {
+    /// Returns the value of the entry on each configuration it is defined, along the qualifiers of
+    /// that configuration (empty for the default one)
+    pub fn get_variants(&self, id: u32) -> &[(String, Entry)] {
+        self.variants.get(&id).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the public and staged status of the entry, and the configuration changes its value
+    /// depends on
+    pub fn get_spec_flags(&self, id: u32) -> Option<SpecFlags> {
+        let spec = self.specs.get(&u32::from(id.get_spec()))?;
+
+        spec.get_flag(u32::from(id.get_id()))
+            .ok()
+            .map(SpecFlags::new)
+    }
+
+    /// Returns the flags of the table types of the given spec, merged across its configurations
+    pub fn get_type_flags(&self, spec_id: u32) -> TypeFlags {
+        self.type_flags.get(&spec_id).copied().unwrap_or_default()
+    }
+
+    pub fn add_type_flags(&mut self, spec_id: u32, flags: TypeFlags) {
+        let merged = self.get_type_flags(spec_id).merge(flags);
+        self.type_flags.insert(spec_id, merged);
+    }
+
+    /// Adds the entries of the configuration with the given qualifiers. The default configuration
+    /// replaces the entry kept for each resource, whichever order the configurations are read.
+    pub fn add_variants(&mut self, qualifiers: &str, entries: Entries) {
+        for (id, entry) in entries {
+            if qualifiers.is_empty() {
+                self.entries.insert(id, entry.clone());
+            } else {
+                self.entries.entry(id).or_insert_with(|| entry.clone());
+            }
+
+            self.variants
+                .entry(id)
+                .or_default()
+                .push((qualifiers.to_string(), entry));
+        }
+    }
+
+    pub fn get_string(&self, str_id: u32) -> Result<Arc<String>, Error> {
         if let Some(string_table) = &self.string_table {
             let out_string = string_table.get_string(str_id).context(format_err!(
                 "could not find string {} on string table",
@@ -228,9 +310,9 @@ impl<'a> Library<'a> {
     }
 }
 
-impl<'a> LibraryTrait for Library<'a> {
+impl LibraryTrait for Library {
     fn get_name(&self) -> Option<String> {
-        self.package.get_name().ok()
+        self.name.clone()
     }
 
     fn format_reference(
@@ -264,7 +346,7 @@ impl<'a> LibraryTrait for Library<'a> {
             .ok_or_else(|| format_err!("could not find entry"))
     }
 
-    fn get_entries_string(&self, str_id: u32) -> Result<Rc<String>, Error> {
+    fn get_entries_string(&self, str_id: u32) -> Result<Arc<String>, Error> {
         if let Some(string_table) = &self.entries_string_table {
             let out_string = string_table.get_string(str_id).context(format_err!(
                 "could not find string {} on entries string table",
@@ -277,7 +359,7 @@ impl<'a> LibraryTrait for Library<'a> {
         Err(format_err!("string not found on entries string table"))
     }
 
-    fn get_spec_string(&self, str_id: u32) -> Result<Rc<String>, Error> {
+    fn get_spec_string(&self, str_id: u32) -> Result<Arc<String>, Error> {
         if let Some(string_table) = &self.spec_string_table {
             let out_string = string_table.get_string(str_id).context(format_err!(
                 "could not find string {} on spec string table",
@@ -291,9 +373,9 @@ impl<'a> LibraryTrait for Library<'a> {
     }
 }
 
-impl<'a> LibraryBuilder<'a> for Library<'a> {
-    type StringTable = StringTableCache<StringTableWrapper<'a>>;
-    type TypeSpec = TypeSpecWrapper<'a>;
+impl<'a> LibraryBuilder<'a> for Library {
+    type StringTable = StringTableCache<OwnedStringTable>;
+    type TypeSpec = TableTypeSpecBuf;
 
     fn set_string_table(&mut self, string_table: Self::StringTable, origin: Origin) {
         match origin {
@@ -314,3 +396,33 @@ impl<'a> LibraryBuilder<'a> for Library<'a> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        model::{Library as LibraryTrait, Resources as ResourcesTrait},
+        test::example_resources,
+    };
+
+    #[test]
+    fn it_keeps_the_entry_of_the_default_configuration() {
+        let resources = example_resources();
+        let package = resources.get_package(0x7f).unwrap();
+
+        // `string/app_name` is read on the default configuration, then on `es`
+        let entry = package.get_entry(0x7f03_0000).unwrap();
+        assert_eq!(Some(1), entry.get_value());
+        let (_, iterated) = package
+            .iter_entries()
+            .find(|(id, _)| **id == 0x7f03_0000)
+            .unwrap();
+        assert_eq!(Some(1), iterated.get_value());
+
+        let qualifiers: Vec<&str> = package
+            .get_variants(0x7f03_0000)
+            .iter()
+            .map(|(qualifiers, _)| qualifiers.as_str())
+            .collect();
+        assert_eq!(vec!["", "es"], qualifiers);
+    }
+}
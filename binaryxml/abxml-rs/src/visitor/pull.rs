@@ -0,0 +1,452 @@
+//! Pull parser for binary XML documents, modelled on Android's `XmlPullParser`: the document is
+//! read as a stream of events, with the names and values already resolved, without building the
+//! whole element tree.
+
+use std::{collections::VecDeque, io::Cursor};
+
+use anyhow::{format_err, Error};
+
+use crate::{
+    chunks::{Chunk, ChunkLoaderStream},
+    model::{NamespaceEnd, NamespaceStart, StringTable, TagStart},
+};
+
+use super::{
+    ChunkVisitor, DecodeMode, Diagnostic, Executor, Origin, Resources, XmlAttribute, XmlVisitor,
+};
+
+/// An event of a binary XML document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PullEvent {
+    StartDocument,
+    StartNamespace {
+        prefix: String,
+        uri: String,
+    },
+    StartTag {
+        name: String,
+        /// URI of the namespace of the tag
+        namespace: Option<String>,
+        attributes: Vec<XmlAttribute>,
+    },
+    Text(String),
+    EndTag {
+        name: String,
+        namespace: Option<String>,
+    },
+    EndNamespace {
+        prefix: String,
+        uri: String,
+    },
+    EndDocument,
+}
+
+/// Iterator over the events of a binary XML document.
+///
+/// Problems found on the document are handled as the `XmlVisitor` does: elements that can not be
+/// read are skipped along their end tag, and recorded as diagnostics. On lenient mode, a document
+/// that is truncated or has unclosed elements ends with the end tags that are missing, while on
+/// strict mode it ends with an error.
+#[derive(Debug)]
+pub struct XmlPullParser<'a> {
+    content: &'a [u8],
+    visitor: XmlVisitor<'a>,
+    stream: Option<ChunkLoaderStream<'a>>,
+    /// Events produced but not returned yet
+    pending: VecDeque<Result<PullEvent, Error>>,
+    /// Name and namespace of the open tags. Tags that could not be read have no name.
+    open_tags: Vec<Option<(String, Option<String>)>>,
+    started: bool,
+    finished: bool,
+}
+
+impl<'a> XmlPullParser<'a> {
+    pub fn new(resources: &'a Resources, content: &'a [u8], mode: DecodeMode) -> Self {
+        Self {
+            content,
+            visitor: XmlVisitor::with_mode(resources, mode),
+            stream: None,
+            pending: VecDeque::new(),
+            open_tags: Vec::new(),
+            started: false,
+            finished: false,
+        }
+    }
+
+    /// Problems found so far while reading the document
+    pub fn get_diagnostics(&self) -> &[Diagnostic] {
+        self.visitor.get_diagnostics()
+    }
+
+    /// Reads the next chunk, queueing the events it produces. Returns false once the document has
+    /// no more chunks.
+    fn read_chunk(&mut self) -> bool {
+        let stream = match &mut self.stream {
+            Some(stream) => stream,
+            None => return false,
+        };
+
+        let chunk = match stream.next() {
+            Some(Ok(chunk)) => chunk,
+            Some(Err(e)) => {
+                self.visitor.add_error(&e);
+                if self.visitor.get_mode() == DecodeMode::Strict {
+                    self.pending.push_back(Err(e));
+                    self.finished = true;
+                }
+                return false;
+            }
+            None => return false,
+        };
+        let header = stream.get_current_header();
+        if let Some(header) = &header {
+            self.visitor.visit_chunk_header(header);
+        }
+
+        match chunk {
+            Chunk::StringTable(string_table) => {
+                self.visitor
+                    .visit_string_table(string_table, Origin::Global);
+            }
+            Chunk::Resource(resource) => self.visitor.visit_resource(resource),
+            Chunk::XmlNamespaceStart(namespace_start) => {
+                if let Some(string_table) = self.visitor.get_string_table() {
+                    if let (Ok(prefix), Ok(uri)) = (
+                        namespace_start.get_prefix(string_table),
+                        namespace_start.get_namespace(string_table),
+                    ) {
+                        self.pending.push_back(Ok(PullEvent::StartNamespace {
+                            prefix: (*prefix).clone(),
+                            uri: (*uri).clone(),
+                        }));
+                    }
+                }
+                self.visitor.visit_xml_namespace_start(namespace_start);
+            }
+            Chunk::XmlNamespaceEnd(namespace_end) => {
+                if let Some(string_table) = self.visitor.get_string_table() {
+                    if let (Ok(prefix), Ok(uri)) = (
+                        namespace_end.get_prefix(string_table),
+                        namespace_end.get_namespace(string_table),
+                    ) {
+                        self.pending.push_back(Ok(PullEvent::EndNamespace {
+                            prefix: (*prefix).clone(),
+                            uri: (*uri).clone(),
+                        }));
+                    }
+                }
+                self.visitor.visit_xml_namespace_end(namespace_end);
+            }
+            Chunk::XmlTagStart(tag_start) => {
+                let mut problems = Vec::new();
+                let element = match self.visitor.get_string_table() {
+                    Some(string_table) => self
+                        .visitor
+                        .get_element_data(string_table, &tag_start, &mut problems)
+                        .map(|(tag, attributes)| {
+                            let namespace = tag_start
+                                .get_namespace_index()
+                                .ok()
+                                .filter(|index| *index != 0xFFFF_FFFF)
+                                .and_then(|index| string_table.get_string(index).ok())
+                                .map(|namespace| (*namespace).clone());
+
+                            (tag.get_name().to_string(), namespace, attributes)
+                        }),
+                    None => Err(format_err!("no main string table found")),
+                };
+                for problem in problems {
+                    self.visitor.add_diagnostic(problem);
+                }
+
+                match element {
+                    Ok((name, namespace, attributes)) => {
+                        self.open_tags.push(Some((name.clone(), namespace.clone())));
+                        self.pending.push_back(Ok(PullEvent::StartTag {
+                            name,
+                            namespace,
+                            attributes,
+                        }));
+                    }
+                    Err(e) => {
+                        self.visitor
+                            .add_diagnostic(format!("dropped element: {:#}", e));
+                        self.open_tags.push(None);
+                    }
+                }
+            }
+            Chunk::XmlTagEnd(_) => match self.open_tags.pop() {
+                Some(Some((name, namespace))) => {
+                    self.pending
+                        .push_back(Ok(PullEvent::EndTag { name, namespace }));
+                }
+                Some(None) => (),
+                None => self
+                    .visitor
+                    .add_diagnostic("end tag without a matching start tag".to_string()),
+            },
+            // Text of an element that could not be read is dropped along it
+            Chunk::XmlText(_) if matches!(self.open_tags.last(), Some(None)) => (),
+            Chunk::XmlText(text) => {
+                let string = self
+                    .visitor
+                    .get_string_table()
+                    .as_ref()
+                    .and_then(|string_table| {
+                        text.get_text_index()
+                            .and_then(|index| string_table.get_string(index))
+                            .ok()
+                    });
+
+                match string {
+                    Some(string) => self
+                        .pending
+                        .push_back(Ok(PullEvent::Text((*string).clone()))),
+                    None => self
+                        .visitor
+                        .add_diagnostic("could not read text from the string table".to_string()),
+                }
+            }
+            _ => {
+                if let Some(header) = &header {
+                    self.visitor.visit_unexpected_chunk(header);
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Closes the elements left open by a truncated document, and ends it
+    fn end_document(&mut self) {
+        self.finished = true;
+
+        let depth = self.open_tags.len();
+        if depth > 0 {
+            self.visitor
+                .add_diagnostic(format!("{} elements were not closed", depth));
+
+            if self.visitor.get_mode() == DecodeMode::Strict {
+                self.pending
+                    .push_back(Err(format_err!("{} elements were not closed", depth)));
+                return;
+            }
+
+            while let Some(tag) = self.open_tags.pop() {
+                if let Some((name, namespace)) = tag {
+                    self.pending
+                        .push_back(Ok(PullEvent::EndTag { name, namespace }));
+                }
+            }
+        }
+
+        self.pending.push_back(Ok(PullEvent::EndDocument));
+    }
+}
+
+impl<'a> Iterator for XmlPullParser<'a> {
+    type Item = Result<PullEvent, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.started {
+            self.started = true;
+
+            return match Executor::xml_stream(Cursor::new(self.content)) {
+                Ok(stream) => {
+                    self.stream = Some(stream);
+                    Some(Ok(PullEvent::StartDocument))
+                }
+                Err(e) => {
+                    self.visitor.add_error(&e);
+                    self.finished = true;
+                    Some(Err(e))
+                }
+            };
+        }
+
+        while self.pending.is_empty() && !self.finished {
+            if !self.read_chunk() && !self.finished {
+                self.end_document();
+            }
+        }
+
+        self.pending.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Error;
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    use super::{PullEvent, XmlPullParser};
+    use crate::{
+        chunks::TOKEN_XML_TEXT,
+        encoder::Xml as Encoder,
+        model::{
+            builder::Xml,
+            owned::{
+                AttributeBuf, OwnedBuf, StringTableBuf, XmlNamespaceEndBuf, XmlNamespaceStartBuf,
+                XmlTagEndBuf, XmlTagStartBuf,
+            },
+        },
+        visitor::{DecodeMode, Resources, XmlVisitor},
+    };
+
+    /// Text chunk with the string at the given index
+    #[derive(Debug)]
+    struct XmlTextBuf(u32);
+
+    impl OwnedBuf for XmlTextBuf {
+        fn get_token(&self) -> u16 {
+            TOKEN_XML_TEXT
+        }
+
+        fn get_body_data(&self) -> Result<Vec<u8>, Error> {
+            let mut out = Vec::new();
+            out.write_u32::<LittleEndian>(self.0)?;
+            // Typed value, unused
+            out.write_u16::<LittleEndian>(8)?;
+            out.write_u16::<LittleEndian>(0)?;
+            out.write_u32::<LittleEndian>(0)?;
+
+            Ok(out)
+        }
+
+        fn get_header(&self) -> Result<Vec<u8>, Error> {
+            let mut out = Vec::new();
+            out.write_u32::<LittleEndian>(1)?;
+            out.write_u32::<LittleEndian>(0xFFFF_FFFF)?;
+
+            Ok(out)
+        }
+    }
+
+    fn document(close: bool) -> Vec<u8> {
+        let mut xml = Xml::default();
+        let mut st = StringTableBuf::default();
+        st.add_string("android".to_string());
+        st.add_string("http://schemas.android.com/apk/res/android".to_string());
+        st.add_string("manifest".to_string());
+        st.add_string("application".to_string());
+        st.add_string("label".to_string());
+        st.add_string("My app".to_string());
+        xml.push_owned(Box::new(st));
+        xml.push_owned(Box::new(XmlNamespaceStartBuf::new(1, 0, 1)));
+
+        let manifest = XmlTagStartBuf::new(1, 0, 0xFFFF_FFFF, 2, 0x0014_0014, 0);
+        let mut application = XmlTagStartBuf::new(2, 0, 0xFFFF_FFFF, 3, 0x0014_0014, 0);
+        application.add_attribute(AttributeBuf::new(1, 4, 5, 3 << 24, 5));
+        xml.push_owned(Box::new(manifest));
+        xml.push_owned(Box::new(application));
+        xml.push_owned(Box::new(XmlTagEndBuf::new(3)));
+        if close {
+            xml.push_owned(Box::new(XmlTagEndBuf::new(2)));
+            xml.push_owned(Box::new(XmlNamespaceEndBuf::new(1, 0, 1)));
+        }
+
+        xml.into_vec().unwrap()
+    }
+
+    #[test]
+    fn it_pulls_the_events_of_a_document() {
+        let resources = Resources::default();
+        let content = document(true);
+
+        let events: Vec<PullEvent> = XmlPullParser::new(&resources, &content, DecodeMode::Strict)
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(8, events.len());
+        assert_eq!(PullEvent::StartDocument, events[0]);
+        assert_eq!(
+            PullEvent::StartNamespace {
+                prefix: "android".to_string(),
+                uri: "http://schemas.android.com/apk/res/android".to_string(),
+            },
+            events[1]
+        );
+        match &events[3] {
+            PullEvent::StartTag {
+                name, attributes, ..
+            } => {
+                assert_eq!("application", name);
+                assert_eq!("android:label", attributes[0].get_qualified_name());
+                assert_eq!("My app", attributes[0].value);
+                assert_eq!(3, attributes[0].data_type);
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+        assert_eq!(PullEvent::EndDocument, events[7]);
+
+        let parser = XmlPullParser::new(&resources, &content, DecodeMode::Strict);
+        let xml = Encoder::encode_events(parser).unwrap();
+        let decoded = XmlVisitor::decode(&resources, &content, DecodeMode::Strict);
+        assert_eq!(decoded.xml.unwrap(), xml);
+    }
+
+    #[test]
+    fn it_closes_truncated_documents_on_lenient_mode() {
+        let resources = Resources::default();
+        let content = document(false);
+
+        let mut parser = XmlPullParser::new(&resources, &content, DecodeMode::Lenient);
+        let events: Vec<PullEvent> = parser.by_ref().map(Result::unwrap).collect();
+        assert_eq!(
+            PullEvent::EndTag {
+                name: "manifest".to_string(),
+                namespace: None,
+            },
+            events[events.len() - 2]
+        );
+        assert_eq!(1, parser.get_diagnostics().len());
+
+        let strict = XmlPullParser::new(&resources, &content, DecodeMode::Strict);
+        assert!(strict.collect::<Result<Vec<_>, _>>().is_err());
+    }
+
+    #[test]
+    fn it_drops_the_text_of_elements_that_can_not_be_read() {
+        let mut xml = Xml::default();
+        let mut st = StringTableBuf::default();
+        st.add_string("string".to_string());
+        st.add_string("hidden".to_string());
+        st.add_string("shown".to_string());
+        xml.push_owned(Box::new(st));
+
+        xml.push_owned(Box::new(XmlTagStartBuf::new(
+            1,
+            0,
+            0xFFFF_FFFF,
+            0,
+            0x0014_0014,
+            0,
+        )));
+        // Its name is not on the string table
+        xml.push_owned(Box::new(XmlTagStartBuf::new(
+            2,
+            0,
+            0xFFFF_FFFF,
+            99,
+            0x0014_0014,
+            0,
+        )));
+        xml.push_owned(Box::new(XmlTextBuf(1)));
+        xml.push_owned(Box::new(XmlTagEndBuf::new(99)));
+        xml.push_owned(Box::new(XmlTextBuf(2)));
+        xml.push_owned(Box::new(XmlTagEndBuf::new(0)));
+        let content = xml.into_vec().unwrap();
+
+        // Lenient mode would make up a name for it
+        let resources = Resources::default();
+        let mut parser = XmlPullParser::new(&resources, &content, DecodeMode::Strict);
+        let texts: Vec<PullEvent> = parser
+            .by_ref()
+            .map(Result::unwrap)
+            .filter(|event| matches!(event, PullEvent::Text(_)))
+            .collect();
+
+        assert_eq!(vec![PullEvent::Text("shown".to_string())], texts);
+        assert_eq!(1, parser.get_diagnostics().len());
+    }
+}
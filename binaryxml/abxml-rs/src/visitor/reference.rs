@@ -0,0 +1,112 @@
+use std::collections::BTreeSet;
+
+use log::error;
+
+use super::ChunkVisitor;
+use crate::{
+    chunks::XmlTagStartWrapper,
+    model::{AttributeTrait, TagStart, Value},
+};
+
+/// Collects every resource ID referenced from the attributes of a binary XML document
+/// (`@type/name` and `?attr/name` values), without building the element tree.
+#[derive(Default, Debug)]
+pub struct ReferenceVisitor {
+    references: BTreeSet<u32>,
+}
+
+impl ReferenceVisitor {
+    pub fn get_references(&self) -> &BTreeSet<u32> {
+        &self.references
+    }
+
+    pub fn into_references(self) -> BTreeSet<u32> {
+        self.references
+    }
+}
+
+impl<'a> ChunkVisitor<'a> for ReferenceVisitor {
+    fn visit_xml_tag_start(&mut self, tag_start: XmlTagStartWrapper<'a>) {
+        let amount = match tag_start.get_attributes_amount() {
+            Ok(amount) => amount,
+            Err(e) => {
+                error!("Could not read the amount of attributes: {}", e);
+                return;
+            }
+        };
+
+        for i in 0..amount {
+            let value = tag_start
+                .get_attribute(i)
+                .and_then(|attribute| attribute.get_value());
+
+            match value {
+                Ok(Value::ReferenceId(id)) | Ok(Value::AttributeReferenceId(id)) if id != 0 => {
+                    self.references.insert(id);
+                }
+                Ok(_) => (),
+                Err(e) => error!("Could not read attribute {}: {}", i, e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::ReferenceVisitor;
+    use crate::{
+        model::{
+            builder::Xml,
+            owned::{AttributeBuf, StringTableBuf, XmlTagEndBuf, XmlTagStartBuf},
+        },
+        visitor::Executor,
+    };
+
+    #[test]
+    fn it_collects_references_and_attribute_references() {
+        let mut xml = Xml::default();
+        let mut st = StringTableBuf::default();
+        st.add_string("tag".to_string());
+        st.add_string("text".to_string());
+        st.add_string("color".to_string());
+        st.add_string("plain".to_string());
+
+        let mut tag_start = XmlTagStartBuf::new(2, 0, 0xFFFF_FFFF, 0, 0, 0);
+        tag_start.add_attribute(AttributeBuf::new(
+            0xFFFF_FFFF,
+            1,
+            0xFFFF_FFFF,
+            1 << 24,
+            0x7f0a0001,
+        ));
+        tag_start.add_attribute(AttributeBuf::new(
+            0xFFFF_FFFF,
+            2,
+            0xFFFF_FFFF,
+            2 << 24,
+            0x7f010002,
+        ));
+        tag_start.add_attribute(AttributeBuf::new(
+            0xFFFF_FFFF,
+            3,
+            0xFFFF_FFFF,
+            0x10 << 24,
+            0x7f0a0003,
+        ));
+        // A reference to 0 stands for `@null`
+        tag_start.add_attribute(AttributeBuf::new(0xFFFF_FFFF, 3, 0xFFFF_FFFF, 1 << 24, 0));
+
+        xml.push_owned(Box::new(st));
+        xml.push_owned(Box::new(tag_start));
+        xml.push_owned(Box::new(XmlTagEndBuf::new(0)));
+
+        let content = xml.into_vec().unwrap();
+        let mut visitor = ReferenceVisitor::default();
+        Executor::xml(Cursor::new(&content), &mut visitor).unwrap();
+
+        let references: Vec<u32> = visitor.into_references().into_iter().collect();
+        assert_eq!(vec![0x7f010002, 0x7f0a0001], references);
+    }
+}
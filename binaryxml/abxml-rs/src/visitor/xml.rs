@@ -1,34 +1,49 @@
-use std::{cmp::Ordering, collections::HashMap, rc::Rc};
+use std::{io::Cursor, sync::Arc};
 
-use anyhow::{format_err, Context, Error};
-use log::{error, info, warn};
+use anyhow::{bail, format_err, Context, Error};
+use log::{error, info};
 
-use super::{ChunkVisitor, Origin};
+use super::{ChunkVisitor, DecodeMode, DecodedXml, Diagnostic, Executor, Origin};
 use crate::{
     chunks::{
-        ResourceWrapper, StringTableCache, StringTableWrapper, XmlNamespaceEndWrapper,
-        XmlNamespaceStartWrapper, XmlTagEndWrapper, XmlTagStartWrapper,
+        ChunkHeader, ResourceWrapper, StringTableCache, StringTableWrapper, XmlNamespaceEndWrapper,
+        XmlNamespaceStartWrapper, XmlTagEndWrapper, XmlTagStartWrapper, XmlTextWrapper,
     },
     encoder::Xml,
     model::{
-        owned::SimpleEntry, AttributeTrait, Element, ElementContainer, Identifier, Library,
-        NamespaceStart, Namespaces, Resources as ResourceTrait, StringTable, Tag, TagStart, Value,
+        owned::{AttrDefinition, Entry},
+        AttributeTrait, Element, ElementContainer, Identifier, Library, NamespaceStart, Namespaces,
+        Resources as ResourceTrait, StringTable, Tag, TagStart, Value,
     },
     visitor::model::Resources,
 };
 
+/// Size of each attribute, as written by `aapt`
+const DEFAULT_ATTRIBUTE_SIZE: u16 = 20;
+/// Offset of the first attribute from the start of the tag body, as written by `aapt`
+const DEFAULT_ATTRIBUTE_START: u16 = 20;
+
 #[derive(Debug)]
 pub struct XmlVisitor<'a> {
     main_string_table: Option<StringTableCache<StringTableWrapper<'a>>>,
     namespaces: Namespaces,
     container: ElementContainer,
     res: Vec<u32>,
-    resources: &'a Resources<'a>,
-    namespace_prefixes: Vec<Rc<String>>,
+    resources: &'a Resources,
+    namespace_prefixes: Vec<Arc<String>>,
+    mode: DecodeMode,
+    diagnostics: Vec<Diagnostic>,
+    current_header: Option<ChunkHeader>,
+    /// Whether each of the open tags produced an element, to keep start and end tags balanced
+    open_tags: Vec<bool>,
 }
 
 impl<'a> XmlVisitor<'a> {
-    pub fn new(resources: &'a Resources<'a>) -> Self {
+    pub fn new(resources: &'a Resources) -> Self {
+        Self::with_mode(resources, DecodeMode::Strict)
+    }
+
+    pub fn with_mode(resources: &'a Resources, mode: DecodeMode) -> Self {
         Self {
             main_string_table: None,
             namespaces: Namespaces::default(),
@@ -36,7 +51,32 @@ impl<'a> XmlVisitor<'a> {
             res: Vec::new(),
             resources,
             namespace_prefixes: Vec::new(),
+            mode,
+            diagnostics: Vec::new(),
+            current_header: None,
+            open_tags: Vec::new(),
+        }
+    }
+
+    /// Decodes the given binary XML, collecting all the problems found instead of failing on the
+    /// first one. On lenient mode, the part of the document read before an unrecoverable error is
+    /// still returned.
+    pub fn decode(resources: &'a Resources, content: &'a [u8], mode: DecodeMode) -> DecodedXml {
+        let mut visitor = Self::with_mode(resources, mode);
+
+        if let Err(e) = Executor::xml(Cursor::new(content), &mut visitor) {
+            visitor.add_error(&e);
+
+            if mode == DecodeMode::Strict {
+                return DecodedXml {
+                    xml: None,
+                    root: None,
+                    diagnostics: visitor.diagnostics,
+                };
+            }
         }
+
+        visitor.into_decoded()
     }
 
     pub fn get_namespaces(&self) -> &Namespaces {
@@ -55,115 +95,343 @@ impl<'a> XmlVisitor<'a> {
         &self.res
     }
 
+    pub fn get_mode(&self) -> DecodeMode {
+        self.mode
+    }
+
+    /// Problems found so far while visiting the document
+    pub fn get_diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
     pub fn arsc(&self) -> &Resources {
         self.resources
     }
 
+    /// Records an error which stopped the visit of the document. It is reported right after the
+    /// last chunk that could be read.
+    pub fn add_error(&mut self, error: &Error) {
+        let offset = self
+            .current_header
+            .map(|header| header.get_chunk_end())
+            .unwrap_or(0);
+
+        self.diagnostics
+            .push(Diagnostic::new(offset, None, format!("{:#}", error)));
+    }
+
     // TODO: Convert to TryInto once it will be stable
-    pub fn into_string(self) -> Result<String, Error> {
-        if let Some(root) = self.get_root() {
-            if self.get_string_table().is_some() {
-                let res =
-                    Xml::encode(self.get_namespaces(), root).context("could note encode XML")?;
-                return Ok(res);
-            } else {
-                warn!("No string table found");
+    pub fn into_string(mut self) -> Result<String, Error> {
+        self.close_open_elements();
+
+        self.encode()
+            .with_context(|| match self.diagnostics.first() {
+                Some(first) => format!(
+                    "could not decode XML ({} problems found, first one {})",
+                    self.diagnostics.len(),
+                    first
+                ),
+                None => "could not decode XML".to_string(),
+            })
+    }
+
+    /// Consumes the visitor, returning the decoded XML along with all the problems found
+    pub fn into_decoded(mut self) -> DecodedXml {
+        self.close_open_elements();
+
+        let xml = match self.encode() {
+            Ok(xml) => Some(xml),
+            Err(e) => {
+                self.diagnostics.push(Diagnostic::new(
+                    self.current_header
+                        .map(|header| header.get_chunk_end())
+                        .unwrap_or(0),
+                    None,
+                    format!("{:#}", e),
+                ));
+                None
             }
-        } else {
-            warn!("No root on target XML");
+        };
+
+        DecodedXml {
+            xml,
+            root: self.container.into_root(),
+            diagnostics: self.diagnostics,
         }
+    }
+
+    fn encode(&self) -> Result<String, Error> {
+        let root = self
+            .get_root()
+            .as_ref()
+            .ok_or_else(|| format_err!("no root element found"))?;
 
-        Err(format_err!("could not decode XML"))
+        if self.get_string_table().is_none() {
+            bail!("no string table found");
+        }
+
+        Xml::encode(self.get_namespaces(), root).context("could not encode XML")
     }
 
-    fn build_element(&self, tag_start: &XmlTagStartWrapper) -> Result<Element, Error> {
+    /// Truncated documents leave elements open. On lenient mode they are closed, so the part of
+    /// the document that could be read is still returned.
+    fn close_open_elements(&mut self) {
+        let depth = self.container.get_depth();
+        if depth == 0 {
+            return;
+        }
+
+        self.add_diagnostic(format!("{} elements were not closed", depth));
+
+        if self.mode == DecodeMode::Lenient {
+            for _ in 0..depth {
+                self.container.end_element();
+            }
+        }
+    }
+
+    pub(super) fn add_diagnostic(&mut self, problem: String) {
+        let (offset, chunk_type) = match self.current_header {
+            Some(header) => (header.get_offset(), Some(header.get_token())),
+            None => (0, None),
+        };
+
+        self.diagnostics
+            .push(Diagnostic::new(offset, chunk_type, problem));
+    }
+
+    fn build_element(
+        &self,
+        tag_start: &XmlTagStartWrapper,
+        problems: &mut Vec<String>,
+    ) -> Result<Element, Error> {
         match &self.main_string_table {
             Some(string_table) => {
                 let (tag, attributes) = self
-                    .get_element_data(&string_table, tag_start)
+                    .get_element_data(string_table, tag_start, problems)
                     .context("could not get element data")?;
+                let attributes = attributes
+                    .into_iter()
+                    .map(|attribute| (attribute.get_qualified_name(), attribute.value))
+                    .collect();
                 Ok(Element::new(tag, attributes))
             }
             None => Err(format_err!("no main string table found")),
         }
     }
 
-    fn get_element_data(
+    /// Resolves the name of the tag and the names and values of its attributes, on the order they
+    /// are declared
+    pub(super) fn get_element_data(
         &self,
         string_table: &StringTableCache<StringTableWrapper<'a>>,
         tag_start: &XmlTagStartWrapper,
-    ) -> Result<(Tag, HashMap<String, String>), Error> {
+        problems: &mut Vec<String>,
+    ) -> Result<(Tag, Vec<XmlAttribute>), Error> {
+        let lenient = self.mode == DecodeMode::Lenient;
+
         let name_index = tag_start
             .get_element_name_index()
             .context("name index not found")?;
-        let rc_string = string_table
-            .get_string(name_index)
-            .context("element name is not on the string table")?;
-        let tag = Tag::new(rc_string.clone(), self.namespace_prefixes.clone());
+        let rc_string = match string_table.get_string(name_index) {
+            Ok(name) if !name.is_empty() || !lenient => name,
+            Ok(_) => {
+                problems.push(format!("element name {} is empty", name_index));
+                Arc::new(format!("unknown_tag_{}", name_index))
+            }
+            Err(e) if lenient => {
+                problems.push(format!("element name {} is invalid: {}", name_index, e));
+                Arc::new(format!("unknown_tag_{}", name_index))
+            }
+            Err(e) => return Err(e.context("element name is not on the string table")),
+        };
+        let tag = Tag::new(rc_string, self.namespace_prefixes.clone());
 
-        let mut attributes = HashMap::new();
+        let mut attributes = Vec::new();
         let num_attributes = tag_start
             .get_attributes_amount()
             .context("could not get the amount of attributes")?;
 
+        let attribute_start = tag_start.get_attribute_start()?;
+        let attribute_size = tag_start.get_attribute_size()?;
+        let custom_layout =
+            attribute_start != DEFAULT_ATTRIBUTE_START || attribute_size != DEFAULT_ATTRIBUTE_SIZE;
+        if custom_layout && num_attributes > 0 {
+            problems.push(format!(
+                "attributes declared at {} with size {}",
+                attribute_start, attribute_size
+            ));
+        }
+
         for i in 0..num_attributes {
             let mut final_name = String::new();
-            let current_attribute = tag_start
-                .get_attribute(i)
+            let mut prefix = None;
+            let mut namespace = None;
+            let current_attribute =
+                if lenient && custom_layout && attribute_size >= DEFAULT_ATTRIBUTE_SIZE {
+                    tag_start.get_declared_attribute(i)
+                } else {
+                    tag_start.get_attribute(i)
+                }
                 .context(format_err!("could not read attribute {} ", i))?;
 
             let namespace_index = current_attribute.get_namespace()?;
             if namespace_index != 0xFFFF_FFFF {
-                let namespace = (*string_table.get_string(namespace_index)?).clone();
-                let prefix = self
-                    .namespaces
-                    .get(&namespace)
-                    .ok_or_else(|| format_err!("namespace not found"))?;
-                final_name.push_str(prefix);
-                final_name.push(':');
+                namespace = string_table
+                    .get_string(namespace_index)
+                    .ok()
+                    .map(|namespace| (*namespace).clone());
+                prefix = namespace
+                    .as_ref()
+                    .and_then(|namespace| self.namespaces.get(namespace))
+                    .cloned();
+
+                match prefix {
+                    Some(_) => (),
+                    None if lenient => {
+                        problems.push(format!("attribute {} has an undeclared namespace", i));
+                    }
+                    None => bail!("namespace not found"),
+                }
             }
 
             let name_index = current_attribute.get_name()?;
-            let name = string_table.get_string(name_index)?;
-            final_name.push_str((*name).as_str());
-
-            let current_value = current_attribute.get_value()?;
-            let value = match current_value {
-                Value::StringReference(index) => (*string_table.get_string(index)?).clone(),
-                Value::ReferenceId(id) => AttributeHelper::resolve_reference(self.resources, id)
-                    .context("could not resolve reference")?,
-                Value::AttributeReferenceId(id) => {
-                    AttributeHelper::resolve_reference(self.resources, id)
-                        .context("could not resolve attribute reference")?
+            let pool_name = string_table.get_string(name_index);
+            match self.get_mapped_name(name_index) {
+                // Android only uses the resource ID of the attribute, so obfuscators are free to
+                // blank or randomize its name on the string pool
+                Some((id, resource_name)) => {
+                    match &pool_name {
+                        Ok(name) if **name == *resource_name => (),
+                        Ok(name) => problems.push(format!(
+                            "attribute name {} is \"{}\", but resource 0x{:08x} is named \"{}\"",
+                            name_index, name, id, resource_name
+                        )),
+                        Err(e) => problems.push(format!(
+                            "attribute name {} is invalid ({}), named after resource 0x{:08x}",
+                            name_index, e, id
+                        )),
+                    }
+                    final_name.push_str(resource_name.as_str());
                 }
-                Value::Integer(value) | Value::Flags(value) => {
-                    let flag_resolution = AttributeHelper::resolve_flags(
-                        &current_attribute,
-                        value,
-                        &self.res,
-                        self.resources,
-                    );
-
-                    if let Some(flag_resolution) = flag_resolution {
-                        flag_resolution
-                    } else {
-                        current_attribute.get_value()?.to_string()
+                None => match pool_name {
+                    Ok(name) if !name.is_empty() || !lenient => final_name.push_str(name.as_str()),
+                    Ok(_) => {
+                        problems.push(format!("attribute name {} is empty", name_index));
+                        final_name.push_str(&format!("unknown_attr_{}", name_index));
+                    }
+                    Err(e) if lenient => {
+                        problems.push(format!("attribute name {} is invalid: {}", name_index, e));
+                        final_name.push_str(&format!("unknown_attr_{}", name_index));
                     }
+                    Err(e) => return Err(e),
+                },
+            }
+
+            let value = match self.get_attribute_value(string_table, &current_attribute) {
+                Ok(value) => value,
+                Err(e) if lenient => {
+                    let qualified_name = match &prefix {
+                        Some(prefix) => format!("{}:{}", prefix, final_name),
+                        None => final_name.clone(),
+                    };
+                    problems.push(format!("value of {} is invalid: {:#}", qualified_name, e));
+                    Self::raw_value(&current_attribute)
                 }
-                _ => current_value.to_string(),
+                Err(e) => return Err(e),
             };
 
-            attributes.insert(final_name, value);
+            attributes.push(XmlAttribute {
+                prefix,
+                namespace,
+                name: final_name,
+                resource_id: self.res.get(name_index as usize).copied(),
+                data_type: (current_attribute.get_resource_value()? >> 24) as u8,
+                data: current_attribute.get_data()?,
+                value,
+            });
         }
 
         Ok((tag, attributes))
     }
+
+    fn get_attribute_value<A: AttributeTrait>(
+        &self,
+        string_table: &StringTableCache<StringTableWrapper<'a>>,
+        current_attribute: &A,
+    ) -> Result<String, Error> {
+        let current_value = current_attribute.get_value()?;
+        let value = match current_value {
+            Value::StringReference(index) => (*string_table.get_string(index)?).clone(),
+            Value::ReferenceId(id) => AttributeHelper::resolve_reference(self.resources, id)
+                .context("could not resolve reference")?,
+            Value::AttributeReferenceId(id) => {
+                AttributeHelper::resolve_reference(self.resources, id)
+                    .context("could not resolve attribute reference")?
+            }
+            Value::Integer(value) | Value::Flags(value) => {
+                let flag_resolution = AttributeHelper::resolve_flags(
+                    current_attribute,
+                    value,
+                    &self.res,
+                    self.resources,
+                );
+
+                if let Some(flag_resolution) = flag_resolution {
+                    flag_resolution
+                } else {
+                    current_attribute.get_value()?.to_string()
+                }
+            }
+            _ => current_value.to_string(),
+        };
+
+        Ok(value)
+    }
+
+    /// Returns the ID and the name on the resource tables of the attribute with the given name
+    /// index, if the index falls inside the resource map of the document
+    fn get_mapped_name(&self, name_index: u32) -> Option<(u32, Arc<String>)> {
+        let id = *self.res.get(name_index as usize)?;
+        let package = self.resources.get_package(id.get_package())?;
+        let key = package.get_entry(id).ok()?.get_key();
+        let name = package.get_entries_string(key).ok()?;
+
+        if name.is_empty() {
+            None
+        } else {
+            Some((id, name))
+        }
+    }
+
+    /// Representation of a value that could not be resolved
+    fn raw_value<A: AttributeTrait>(attribute: &A) -> String {
+        match attribute.get_value() {
+            Ok(Value::ReferenceId(id)) => format!("@0x{:08x}", id),
+            Ok(Value::AttributeReferenceId(id)) => format!("?0x{:08x}", id),
+            Ok(Value::StringReference(_)) => String::new(),
+            Ok(value) => value.to_string(),
+            Err(_) => format!("0x{:08x}", attribute.get_data().unwrap_or(0)),
+        }
+    }
 }
 
 impl<'a> ChunkVisitor<'a> for XmlVisitor<'a> {
+    fn visit_chunk_header(&mut self, header: &ChunkHeader) {
+        self.current_header = Some(*header);
+    }
+
+    fn visit_unexpected_chunk(&mut self, header: &ChunkHeader) {
+        self.add_diagnostic(format!(
+            "skipped unknown chunk of {} bytes",
+            header.get_chunk_end() - header.get_offset()
+        ));
+    }
+
     fn visit_string_table(&mut self, string_table: StringTableWrapper<'a>, _: Origin) {
         if self.main_string_table.is_some() {
             error!("Secondary table!");
+            self.add_diagnostic("ignored secondary string table".to_string());
         } else {
             self.main_string_table = Some(StringTableCache::new(string_table));
         }
@@ -180,22 +448,55 @@ impl<'a> ChunkVisitor<'a> for XmlVisitor<'a> {
                 self.namespace_prefixes.push(namespace.clone());
             } else {
                 error!("Error reading namespace from the string table");
+                self.add_diagnostic("could not read namespace from the string table".to_string());
             }
         }
     }
 
     fn visit_xml_tag_start(&mut self, tag_start: XmlTagStartWrapper<'a>) {
-        let element_result = self.build_element(&tag_start);
+        let mut problems = Vec::new();
+        let element_result = self.build_element(&tag_start, &mut problems);
+        for problem in problems {
+            self.add_diagnostic(problem);
+        }
+
         match element_result {
             Ok(element) => {
                 self.container.start_element(element);
+                self.open_tags.push(true);
+            }
+            Err(e) => {
+                error!("Could not build a XML element: {e}");
+                self.add_diagnostic(format!("dropped element: {:#}", e));
+                self.open_tags.push(false);
             }
-            Err(e) => error!("Could not build a XML element: {e}"),
         }
     }
 
     fn visit_xml_tag_end(&mut self, _: XmlTagEndWrapper<'a>) {
-        self.container.end_element()
+        match self.open_tags.pop() {
+            Some(true) => self.container.end_element(),
+            Some(false) => (),
+            None => self.add_diagnostic("end tag without a matching start tag".to_string()),
+        }
+    }
+
+    fn visit_xml_text(&mut self, text: XmlTextWrapper<'a>) {
+        // Text of an element that could not be read is dropped along it
+        if self.open_tags.last() == Some(&false) {
+            return;
+        }
+
+        let string = self.main_string_table.as_ref().and_then(|string_table| {
+            text.get_text_index()
+                .and_then(|index| string_table.get_string(index))
+                .ok()
+        });
+
+        match string {
+            Some(string) => self.container.add_text(&string),
+            None => self.add_diagnostic("could not read text from the string table".to_string()),
+        }
     }
 
     fn visit_xml_namespace_end(&mut self, _: XmlNamespaceEndWrapper<'a>) {
@@ -203,8 +504,36 @@ impl<'a> ChunkVisitor<'a> for XmlVisitor<'a> {
     }
 
     fn visit_resource(&mut self, resource: ResourceWrapper<'a>) {
-        if let Ok(res) = resource.get_resources() {
-            self.res = res;
+        match resource.get_resources() {
+            Ok(res) => self.res = res,
+            Err(e) => self.add_diagnostic(format!("could not read resource map: {:#}", e)),
+        }
+    }
+}
+
+/// An attribute of a start tag, with its name and value resolved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XmlAttribute {
+    /// Prefix of the namespace of the attribute, like `android`
+    pub prefix: Option<String>,
+    /// URI of the namespace of the attribute
+    pub namespace: Option<String>,
+    pub name: String,
+    /// ID of the attribute on the resource map of the document, if it has one
+    pub resource_id: Option<u32>,
+    /// Raw type of the value (see `Value`)
+    pub data_type: u8,
+    pub data: u32,
+    /// Value as written on the decoded XML, with strings and references resolved
+    pub value: String,
+}
+
+impl XmlAttribute {
+    /// Name of the attribute including its prefix, like `android:name`
+    pub fn get_qualified_name(&self) -> String {
+        match &self.prefix {
+            Some(prefix) => format!("{}:{}", prefix, self.name),
+            None => self.name.clone(),
         }
     }
 }
@@ -246,7 +575,7 @@ impl AttributeHelper {
     ) -> Option<String> {
         // Check if it's the special value in which the integer is an Enum
         // In that case, we return a crafted string instead of the integer itself
-        let name_index = attribute.get_name().unwrap();
+        let name_index = attribute.get_name().ok()?;
         if name_index < xml_resources.len() as u32 {
             Self::search_values(flags, name_index, xml_resources, resources)
         } else {
@@ -296,99 +625,43 @@ impl AttributeHelper {
     }
 
     fn get_strings(flags: u32, entry_ref: u32, package: &dyn Library) -> Vec<u32> {
-        use crate::model::owned::Entry;
-
-        let mut strs = Vec::new();
-        let mut masks = Vec::new();
-
-        let inner_entries = package
-            .get_entry(entry_ref)
-            .and_then(Entry::complex)
-            .and_then(|c| Ok(c.get_entries().to_vec()))
-            .unwrap_or_else(|_| Vec::new());
-
-        let mut sorted = inner_entries.to_vec();
-
-        sorted.sort_by(Self::compare_entries);
-
-        for ie in sorted {
-            let mask = ie.get_value();
-            if (mask & flags) == mask {
-                let maybe_entry = package.get_entry(ie.get_id());
-
-                if let Ok(entry) = maybe_entry {
-                    let mut has_to_add = true;
-
-                    for s in &masks {
-                        if mask & s == mask {
-                            has_to_add = false;
-                            break;
-                        }
-                    }
+        let definition = match package.get_entry(entry_ref).and_then(Entry::complex) {
+            Ok(complex) => AttrDefinition::new(complex),
+            Err(_) => return Vec::new(),
+        };
 
-                    if has_to_add {
-                        entry
-                            .simple()
-                            .and_then(|s| Ok(s.get_key()))
-                            .and_then(|key| {
-                                strs.push(key);
-                                masks.push(mask);
-                                Ok(())
-                            })
-                            .unwrap_or_else(|_| {
-                                error!(
-                                    "Value should be added but there was an issue reading \
-                                     the entry"
-                                );
-                            });
-                    }
-                } else {
+        definition
+            .resolve_symbols(flags)
+            .into_iter()
+            .filter_map(|id| match package.get_entry(id).and_then(Entry::simple) {
+                Ok(symbol) => Some(symbol.get_key()),
+                Err(_) => {
                     info!("Some entry matched but could not found on entries");
+                    None
                 }
-            }
-        }
-
-        strs
-    }
-
-    fn compare_entries(a: &SimpleEntry, b: &SimpleEntry) -> Ordering {
-        let id_a = a.get_value();
-        let id_b = b.get_value();
-
-        // TODO: This code is to create an exact match with Apktool.
-        // A simple descending ordering seems to be also ok.
-        let mut i = id_a;
-        i -= (i >> 1) & 0x55555555;
-        i = (i & 0x33333333) + ((i >> 2) & 0x33333333);
-        i = (i + (i >> 4)) & 0x0f0f0f0f;
-        i += i >> 8;
-        i += i >> 16;
-        i &= 0x3f;
-
-        let mut j = id_b;
-        j -= (j >> 1) & 0x55555555;
-        j = (j & 0x33333333) + ((j >> 2) & 0x33333333);
-        j = (j + (j >> 4)) & 0x0f0f0f0f;
-        j += j >> 8;
-        j += j >> 16;
-        j &= 0x3f;
-
-        j.cmp(&i)
+            })
+            .collect()
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::io::Cursor;
+
     use anyhow::{bail, Error};
 
-    use super::{format_err, AttributeHelper, Rc};
+    use super::{format_err, Arc, AttributeHelper, XmlVisitor};
     use crate::{
         model::{
-            owned::{AttributeBuf, ComplexEntry, Entry, SimpleEntry},
+            builder::Xml,
+            owned::{
+                AttributeBuf, ComplexEntry, Entry, ResourcesBuf, SimpleEntry, StringTableBuf,
+                XmlTagEndBuf, XmlTagStartBuf,
+            },
             Entries, Library, LibraryBuilder, Resources, StringTable, TypeSpec,
         },
-        test::FakeStringTable,
-        visitor::Origin,
+        test::{example_resources, FakeStringTable},
+        visitor::{self, DecodeMode, Executor, Origin},
     };
 
     struct FakeLibrary {
@@ -462,13 +735,13 @@ mod tests {
                 .ok_or_else(|| format_err!("could not find entry"))
         }
 
-        fn get_entries_string(&self, str_id: u32) -> Result<Rc<String>, Error> {
+        fn get_entries_string(&self, str_id: u32) -> Result<Arc<String>, Error> {
             let st = FakeStringTable;
 
             Ok(st.get_string(str_id)?)
         }
 
-        fn get_spec_string(&self, _: u32) -> Result<Rc<String>, Error> {
+        fn get_spec_string(&self, _: u32) -> Result<Arc<String>, Error> {
             bail!("spec string")
         }
     }
@@ -616,4 +889,130 @@ mod tests {
 
         assert_eq!("left|right", result.unwrap());
     }
+
+    fn document_with_bogus_attribute_name() -> Vec<u8> {
+        let mut xml = Xml::default();
+        let mut st = StringTableBuf::default();
+        st.add_string("manifest".to_string());
+        st.add_string("text".to_string());
+
+        let mut tag_start = XmlTagStartBuf::new(2, 0, 0xFFFF_FFFF, 0, 0x0014_0014, 0);
+        tag_start.add_attribute(AttributeBuf::new(
+            0xFFFF_FFFF,
+            1,
+            0xFFFF_FFFF,
+            0x10 << 24,
+            1,
+        ));
+        tag_start.add_attribute(AttributeBuf::new(
+            0xFFFF_FFFF,
+            42,
+            0xFFFF_FFFF,
+            0x10 << 24,
+            2,
+        ));
+
+        xml.push_owned(Box::new(st));
+        xml.push_owned(Box::new(tag_start));
+        xml.push_owned(Box::new(XmlTagEndBuf::new(0)));
+
+        xml.into_vec().unwrap()
+    }
+
+    #[test]
+    fn it_drops_elements_with_bogus_strings_on_strict_mode() {
+        let resources = visitor::Resources::default();
+        let content = document_with_bogus_attribute_name();
+
+        let mut visitor = XmlVisitor::new(&resources);
+        Executor::xml(Cursor::new(&content), &mut visitor).unwrap();
+
+        assert_eq!(1, visitor.get_diagnostics().len());
+        assert_eq!(Some(0x102), visitor.get_diagnostics()[0].chunk_type);
+        assert!(visitor.into_string().is_err());
+    }
+
+    #[test]
+    fn it_uses_placeholders_for_bogus_strings_on_lenient_mode() {
+        let resources = visitor::Resources::default();
+        let content = document_with_bogus_attribute_name();
+
+        let mut visitor = XmlVisitor::with_mode(&resources, DecodeMode::Lenient);
+        Executor::xml(Cursor::new(&content), &mut visitor).unwrap();
+        let decoded = visitor.into_decoded();
+
+        let xml = decoded.xml.unwrap();
+        assert!(xml.contains("<manifest "));
+        assert!(xml.contains(" text="));
+        assert!(xml.contains(" unknown_attr_42="));
+        assert_eq!(1, decoded.diagnostics.len());
+    }
+
+    #[test]
+    fn it_names_attributes_after_the_resource_map() {
+        let resources = example_resources();
+        let (first_id, first_name) = (0x7f01_0000, "textSize");
+        let (second_id, second_name) = (0x7f03_0000, "app_name");
+
+        let mut xml = Xml::default();
+        let mut st = StringTableBuf::default();
+        st.add_string(String::new());
+        st.add_string("wrong".to_string());
+        st.add_string("manifest".to_string());
+        let mut resource_map = ResourcesBuf::default();
+        resource_map.push_resource(first_id);
+        resource_map.push_resource(second_id);
+
+        let mut tag_start = XmlTagStartBuf::new(2, 0, 0xFFFF_FFFF, 2, 0x0014_0014, 0);
+        tag_start.add_attribute(AttributeBuf::new(
+            0xFFFF_FFFF,
+            0,
+            0xFFFF_FFFF,
+            0x10 << 24,
+            1,
+        ));
+        tag_start.add_attribute(AttributeBuf::new(
+            0xFFFF_FFFF,
+            1,
+            0xFFFF_FFFF,
+            0x10 << 24,
+            2,
+        ));
+
+        xml.push_owned(Box::new(st));
+        xml.push_owned(Box::new(resource_map));
+        xml.push_owned(Box::new(tag_start));
+        xml.push_owned(Box::new(XmlTagEndBuf::new(2)));
+        let content = xml.into_vec().unwrap();
+
+        let decoded = XmlVisitor::decode(&resources, &content, DecodeMode::Strict);
+        let xml = decoded.xml.unwrap();
+
+        assert!(xml.contains(&format!(" {}=\"1\"", first_name)));
+        assert!(xml.contains(&format!(" {}=\"2\"", second_name)));
+        assert!(!xml.contains("wrong"));
+        assert_eq!(2, decoded.diagnostics.len());
+        assert!(decoded.diagnostics[1].problem.contains("\"wrong\""));
+    }
+
+    #[test]
+    fn it_reports_unbalanced_end_tags() {
+        let mut xml = Xml::default();
+        let mut st = StringTableBuf::default();
+        st.add_string("manifest".to_string());
+
+        xml.push_owned(Box::new(st));
+        xml.push_owned(Box::new(XmlTagStartBuf::new(2, 0, 0xFFFF_FFFF, 0, 0, 0)));
+        xml.push_owned(Box::new(XmlTagEndBuf::new(0)));
+        xml.push_owned(Box::new(XmlTagEndBuf::new(0)));
+        let content = xml.into_vec().unwrap();
+
+        let resources = visitor::Resources::default();
+        let mut visitor = XmlVisitor::new(&resources);
+        Executor::xml(Cursor::new(&content), &mut visitor).unwrap();
+
+        assert!(visitor.get_root().is_some());
+        assert_eq!(1, visitor.get_diagnostics().len());
+        assert_eq!(Some(0x103), visitor.get_diagnostics()[0].chunk_type);
+    }
 }
@@ -0,0 +1,465 @@
+//! Typed JSON schema of the entries of a resource table, as consumed by the viewer
+
+use std::collections::HashMap;
+
+use abxml::{
+    model::{
+        owned::{self, ComplexEntry, Entry, SimpleEntry},
+        value::{DIMENSION_UNITS, FRACTION_UNITS},
+        Identifier, Library as LibraryTrait, Value,
+    },
+    visitor::{model::Library, Resources},
+};
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct ArscResource {
+    pub package_id: u8,
+    pub type_name: String,
+    pub entry_id: u32,
+    pub name: String,
+    /// Part of the public API of the package, as declared on `public.xml`
+    pub public: bool,
+    /// Exposed on a staged API: its ID may change once the API is finalized
+    pub staged_api: bool,
+    /// Configuration changes the value depends on, like `locale` or `orientation`
+    pub config_changes: Vec<&'static str>,
+    /// Flags of the table types of the resource type, like `sparse`
+    pub type_flags: Vec<&'static str>,
+    /// Value on the default configuration. Resources without one (only defined for some
+    /// qualifiers) get the value of the first configuration they are defined on.
+    pub value: EntryValue,
+    /// Value of the resource on each configuration it is defined on
+    pub variants: Vec<Variant>,
+}
+
+#[derive(Serialize)]
+pub struct Variant {
+    /// Qualifiers of the configuration, empty for the default one
+    pub config: String,
+    pub value: EntryValue,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EntryValue {
+    Simple(TypedValue),
+    Bag(Bag),
+}
+
+/// A resource value: its interpretation, together with the raw type and data it comes from
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TypedValue {
+    Null {
+        data_type: u8,
+        data: u32,
+    },
+    Reference {
+        data_type: u8,
+        data: u32,
+        name: Option<String>,
+    },
+    Attribute {
+        data_type: u8,
+        data: u32,
+        name: Option<String>,
+    },
+    String {
+        data_type: u8,
+        data: u32,
+        value: Option<String>,
+    },
+    Dimension {
+        data_type: u8,
+        data: u32,
+        value: f32,
+        unit: Option<&'static str>,
+    },
+    Fraction {
+        data_type: u8,
+        data: u32,
+        value: f32,
+        unit: Option<&'static str>,
+    },
+    Color {
+        data_type: u8,
+        data: u32,
+        /// Color as `#aarrggbb`
+        argb: String,
+    },
+    Float {
+        data_type: u8,
+        data: u32,
+        value: f32,
+    },
+    Integer {
+        data_type: u8,
+        data: u32,
+        value: i32,
+    },
+    Flags {
+        data_type: u8,
+        data: u32,
+    },
+    Boolean {
+        data_type: u8,
+        data: u32,
+        value: bool,
+    },
+    Unknown {
+        data_type: u8,
+        data: u32,
+    },
+}
+
+/// Kind of bag, taken from the type of the resource that holds it
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum BagType {
+    Plurals,
+    Array,
+    Attr,
+    Style,
+    Other,
+}
+
+#[derive(Serialize)]
+pub struct Bag {
+    pub bag_type: BagType,
+    pub parent: Option<ResourceReference>,
+    /// Formats accepted by an `attr`, like `reference` or `color`
+    pub formats: Option<Vec<&'static str>>,
+    pub items: Vec<BagItem>,
+}
+
+#[derive(Serialize)]
+pub struct ResourceReference {
+    pub id: u32,
+    pub name: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct BagItem {
+    pub key: BagKey,
+    pub value: TypedValue,
+}
+
+/// Meaning of the key of a bag item
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BagKey {
+    /// `^type`: format mask of an `attr`
+    Type,
+    /// `^min`: minimum integer value of an `attr`
+    Min,
+    /// `^max`: maximum integer value of an `attr`
+    Max,
+    /// `^l10n`: localization hint of an `attr`
+    L10n,
+    /// `quantity=...` item of a `plurals`
+    Quantity { quantity: &'static str },
+    /// `^index_N` item of an `array`
+    Index { index: u32 },
+    /// Enum or flag symbol of an `attr`
+    Symbol { id: u32, name: Option<String> },
+    /// Attribute set by a `style`, or any other resource used as a key
+    Attribute { id: u32, name: Option<String> },
+}
+
+/// Returns the typed entries of all the packages of the resource table. The framework package
+/// (`android`, ID 1) is only included if asked to.
+pub fn extract(resources: &Resources, include_framework: bool) -> Vec<ArscResource> {
+    let packages = &resources.packages;
+    let mut result = Vec::new();
+
+    for (package_id, package) in packages.iter() {
+        if *package_id == 1 && !include_framework {
+            continue;
+        }
+
+        for (entry_id, entry) in package.iter_entries() {
+            let type_name = package
+                .get_spec_as_str(u32::from(entry_id.get_spec()))
+                .unwrap_or_else(|e| format!("{e}"));
+            let name = package
+                .format_reference(*entry_id, entry.get_key(), None)
+                .unwrap_or_else(|_| "Unknown".into());
+            let spec_flags = package.get_spec_flags(*entry_id).unwrap_or_default();
+            let variants = package.get_variants(*entry_id);
+
+            result.push(ArscResource {
+                package_id: *package_id,
+                entry_id: *entry_id,
+                name,
+                public: spec_flags.public,
+                staged_api: spec_flags.staged_api,
+                config_changes: spec_flags.get_config_change_names(),
+                type_flags: package
+                    .get_type_flags(u32::from(entry_id.get_spec()))
+                    .get_names(),
+                value: entry_value(packages, *package_id, &type_name, entry),
+                variants: variants
+                    .iter()
+                    .filter(|(_, entry)| !entry.is_empty())
+                    .map(|(config, entry)| Variant {
+                        config: config.clone(),
+                        value: entry_value(packages, *package_id, &type_name, entry),
+                    })
+                    .collect(),
+                type_name,
+            });
+        }
+    }
+
+    result
+}
+
+fn entry_value(
+    packages: &HashMap<u8, Library>,
+    package_id: u8,
+    type_name: &str,
+    entry: &Entry,
+) -> EntryValue {
+    match entry {
+        Entry::Complex(complex) => EntryValue::Bag(bag(packages, package_id, type_name, complex)),
+        Entry::Simple(simple) => EntryValue::Simple(simple_value(packages, package_id, simple)),
+        Entry::Empty(_, _) => EntryValue::Simple(TypedValue::Null {
+            data_type: 0,
+            data: 0,
+        }),
+    }
+}
+
+fn simple_value(
+    packages: &HashMap<u8, Library>,
+    package_id: u8,
+    entry: &SimpleEntry,
+) -> TypedValue {
+    typed_value(packages, package_id, entry.get_type(), entry.get_value())
+}
+
+fn bag(
+    packages: &HashMap<u8, Library>,
+    package_id: u8,
+    type_name: &str,
+    complex: &ComplexEntry,
+) -> Bag {
+    let parent_id = complex.get_parent_entry_id();
+    let parent = if parent_id == 0 {
+        None
+    } else {
+        Some(ResourceReference {
+            id: parent_id,
+            name: resolve(packages, parent_id),
+        })
+    };
+
+    let mut formats = None;
+    let (bag_type, entries) = match owned::Bag::new(type_name, complex) {
+        owned::Bag::Plurals(items) => (
+            BagType::Plurals,
+            items.into_iter().map(|(_, item)| item).collect(),
+        ),
+        owned::Bag::Array(items) => (BagType::Array, items),
+        owned::Bag::Attr(definition) => {
+            formats = Some(definition.get_format_names());
+            (BagType::Attr, complex.get_entries().clone())
+        }
+        owned::Bag::Style(items) => (
+            BagType::Style,
+            items.into_iter().map(|(_, item)| item).collect(),
+        ),
+        owned::Bag::Other(items) => (
+            BagType::Other,
+            items.into_iter().map(|(_, item)| item).collect(),
+        ),
+    };
+
+    Bag {
+        bag_type,
+        parent,
+        formats,
+        items: entries
+            .iter()
+            .map(|item| BagItem {
+                key: bag_key(packages, bag_type, item.get_id()),
+                value: simple_value(packages, package_id, item),
+            })
+            .collect(),
+    }
+}
+
+fn bag_key(packages: &HashMap<u8, Library>, bag_type: BagType, key: u32) -> BagKey {
+    match owned::BagKey::new(key) {
+        owned::BagKey::Type => BagKey::Type,
+        owned::BagKey::Min => BagKey::Min,
+        owned::BagKey::Max => BagKey::Max,
+        owned::BagKey::L10n => BagKey::L10n,
+        owned::BagKey::Quantity(quantity) => BagKey::Quantity {
+            quantity: quantity.as_str(),
+        },
+        owned::BagKey::Index(index) => BagKey::Index { index },
+        owned::BagKey::Resource(id) if bag_type == BagType::Attr => BagKey::Symbol {
+            id,
+            name: resolve(packages, id),
+        },
+        owned::BagKey::Resource(id) => BagKey::Attribute {
+            id,
+            name: resolve(packages, id),
+        },
+    }
+}
+
+fn typed_value(
+    packages: &HashMap<u8, Library>,
+    package_id: u8,
+    data_type: u8,
+    data: u32,
+) -> TypedValue {
+    let value = match Value::create(data_type, data) {
+        Ok(value) => value,
+        Err(_) => return TypedValue::Unknown { data_type, data },
+    };
+
+    match value {
+        Value::ReferenceId(0) | Value::Unknown(0x00, _) => TypedValue::Null { data_type, data },
+        Value::ReferenceId(id) => TypedValue::Reference {
+            data_type,
+            data,
+            name: resolve(packages, id),
+        },
+        Value::AttributeReferenceId(id) => TypedValue::Attribute {
+            data_type,
+            data,
+            name: resolve(packages, id),
+        },
+        Value::StringReference(idx) => TypedValue::String {
+            data_type,
+            data,
+            value: packages
+                .get(&package_id)
+                .and_then(|package| package.get_string(idx).ok())
+                .map(|s| s.to_string()),
+        },
+        Value::Dimension(_) => TypedValue::Dimension {
+            data_type,
+            data,
+            value: Value::complex(data),
+            unit: DIMENSION_UNITS.get((data & 0xF) as usize).copied(),
+        },
+        Value::Fraction(_) => TypedValue::Fraction {
+            data_type,
+            data,
+            value: Value::complex(data),
+            unit: FRACTION_UNITS.get((data & 0xF) as usize).copied(),
+        },
+        Value::ColorARGB8(argb)
+        | Value::ColorRGB8(argb)
+        | Value::ColorARGB4(argb)
+        | Value::ColorRGB4(argb) => TypedValue::Color {
+            data_type,
+            data,
+            argb,
+        },
+        Value::Float(value) => TypedValue::Float {
+            data_type,
+            data,
+            value,
+        },
+        Value::Integer(value) => TypedValue::Integer {
+            data_type,
+            data,
+            value: value as i32,
+        },
+        Value::Flags(_) => TypedValue::Flags { data_type, data },
+        Value::Boolean(value) => TypedValue::Boolean {
+            data_type,
+            data,
+            value,
+        },
+        Value::Unknown(..) => TypedValue::Unknown { data_type, data },
+    }
+}
+
+/// Returns the name of the given resource, like `@android:string/ok`
+fn resolve(packages: &HashMap<u8, Library>, id: u32) -> Option<String> {
+    let package_id = id.get_package();
+    let package = packages.get(&package_id)?;
+    let entry = package.get_entry(id).ok()?;
+    let namespace = if package_id == 1 {
+        Some("android".to_string())
+    } else {
+        None
+    };
+
+    package
+        .format_reference(id, entry.get_key(), namespace)
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bag_key, extract, BagKey, BagType, EntryValue, TypedValue};
+
+    #[test]
+    fn test_bag_keys() {
+        let packages = Default::default();
+
+        assert_eq!(
+            BagKey::Quantity { quantity: "one" },
+            bag_key(&packages, BagType::Plurals, 0x0100_0006)
+        );
+        assert_eq!(
+            BagKey::Index { index: 3 },
+            bag_key(&packages, BagType::Array, 0x0200_0003)
+        );
+        assert_eq!(BagKey::Max, bag_key(&packages, BagType::Attr, 0x0100_0002));
+        assert_eq!(
+            BagKey::Symbol {
+                id: 0x7f05_0001,
+                name: None
+            },
+            bag_key(&packages, BagType::Attr, 0x7f05_0001)
+        );
+    }
+
+    #[test]
+    fn test_extract_typed_values() {
+        let bytes = include_bytes!("example_resources.arsc");
+        let decoder = abxml::decoder::Decoder::from_arsc(bytes).unwrap();
+        let resources = extract(decoder.get_resources(), true);
+
+        assert!(!resources.is_empty());
+        assert!(resources.iter().all(|r| !r.variants.is_empty()));
+        assert!(resources
+            .iter()
+            .any(|r| matches!(r.value, EntryValue::Simple(TypedValue::Color { .. }))));
+        assert!(resources
+            .iter()
+            .any(|r| matches!(r.value, EntryValue::Bag(_))));
+        assert!(resources
+            .iter()
+            .filter(|r| r.variants.len() > 1)
+            .all(|r| !r.config_changes.is_empty()));
+    }
+
+    #[test]
+    fn test_value_is_the_default_variant() {
+        let bytes = include_bytes!("example_resources.arsc");
+        let decoder = abxml::decoder::Decoder::from_arsc(bytes).unwrap();
+        let resources = extract(decoder.get_resources(), true);
+
+        let mut checked = 0;
+        for resource in resources.iter().filter(|r| r.variants.len() > 1) {
+            if let Some(default) = resource.variants.iter().find(|v| v.config.is_empty()) {
+                assert_eq!(
+                    serde_json::to_string(&default.value).unwrap(),
+                    serde_json::to_string(&resource.value).unwrap()
+                );
+                checked += 1;
+            }
+        }
+        assert!(checked > 0);
+    }
+}
@@ -1,12 +1,21 @@
 use abxml::{
     apk::Apk,
-    model::{owned::Entry, Identifier, Library as LibraryTrait},
+    decoder::Decoder,
+    interpret::{self, Interpretation, TableResolver},
+    search::{Matcher, ResourceIndex},
+    snapshot::ResourcesSnapshot,
+    symbols::SymbolTable,
+    values,
+    visitor::{DecodeMode, DecodedXml, Resources, XmlVisitor},
 };
 use log::{debug, error, info};
+use serde::Serialize;
 use serde_bytes::ByteBuf;
-use std::{collections::HashMap, fs::File};
+use std::fs::File;
 use wasm_bindgen::prelude::*;
 
+mod arsc;
+
 // Initialize panic hook and logger
 fn init() {
     debug!("abxml init");
@@ -20,16 +29,6 @@ pub fn start() {
     info!("ARSC parser initialized");
 }
 
-#[derive(serde::Serialize, serde::Deserialize)]
-struct ArscResource {
-    package_id: u8,
-    type_name: String,
-    entry_id: u32,
-    name: String,
-    value: String,
-    entries: Option<HashMap<String, String>>,
-}
-
 #[wasm_bindgen]
 pub fn decode_apk(bytes: Vec<u8>) -> Result<JsValue, wasm_bindgen::JsError> {
     info!("Decoding APK of size {} bytes", bytes.len());
@@ -37,6 +36,8 @@ pub fn decode_apk(bytes: Vec<u8>) -> Result<JsValue, wasm_bindgen::JsError> {
         error!("Failed to decode APK: {}", e);
         JsError::new(&format!("{e}"))
     })?;
+    // Shrunk APKs store their resource files with meaningless names: show the resource instead
+    apk.set_rename_paths(true);
     let strings = apk.export_string().map_err(|e| {
         error!("Failed to export strings: {}", e);
         JsError::new(&format!("{e}"))
@@ -54,71 +55,295 @@ pub fn decode_apk(bytes: Vec<u8>) -> Result<JsValue, wasm_bindgen::JsError> {
     })
 }
 
+/// Lists the native libraries of an APK, with their ABI, ELF metadata and 16 KB page alignment
+#[wasm_bindgen]
+pub fn native_libraries(bytes: Vec<u8>) -> Result<JsValue, wasm_bindgen::JsError> {
+    let mut apk = Apk::<File>::from_bytes(&bytes).map_err(|e| {
+        error!("Failed to decode APK: {}", e);
+        JsError::new(&format!("{e}"))
+    })?;
+    let inventory = apk.native_libraries().map_err(|e| {
+        error!("Failed to list native libraries: {}", e);
+        JsError::new(&format!("{e}"))
+    })?;
+
+    info!("Found {} native libraries", inventory.libraries.len());
+    inventory
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| {
+            error!("Failed to serialize result: {}", e);
+            JsError::new(&format!("{e}"))
+        })
+}
+
+/// Reports the network security config and backup rules declared on the manifest of an APK:
+/// cleartext domains, trusted certificate authorities, pins and backed up paths
 #[wasm_bindgen]
-pub fn extract_arsc(bytes: Vec<u8>) -> Result<JsValue, wasm_bindgen::JsError> {
+pub fn privacy_report(bytes: Vec<u8>) -> Result<JsValue, wasm_bindgen::JsError> {
+    let mut apk = Apk::<File>::from_bytes(&bytes).map_err(|e| {
+        error!("Failed to decode APK: {}", e);
+        JsError::new(&format!("{e}"))
+    })?;
+    let report = apk.privacy_report().map_err(|e| {
+        error!("Failed to build the privacy report: {}", e);
+        JsError::new(&format!("{e}"))
+    })?;
+
+    report
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| {
+            error!("Failed to serialize result: {}", e);
+            JsError::new(&format!("{e}"))
+        })
+}
+
+/// Returns the typed entries of a `resources.arsc`. The entries of the framework package are
+/// skipped unless `include_framework` is set.
+#[wasm_bindgen]
+pub fn extract_arsc(
+    bytes: Vec<u8>,
+    include_framework: Option<bool>,
+) -> Result<JsValue, wasm_bindgen::JsError> {
     info!("Extracting ARSC of size {} bytes", bytes.len());
     let decoder = abxml::decoder::Decoder::from_arsc(&bytes).map_err(|e| {
         error!("Failed to decode ARSC: {}", e);
         JsError::new(&format!("XX {e}"))
     })?;
 
-    let resources = decoder.get_resources();
-    let mut result = Vec::new();
+    let result = arsc::extract(decoder.get_resources(), include_framework.unwrap_or(false));
 
-    // Iterate through all packages
-    for (package_id, package) in resources.packages.iter() {
-        debug!("Processing package {}", package_id);
-        if package_id == &1 {
-            // Skip processing the Android system package
-            continue;
-        }
+    info!("Successfully extracted {} resources", result.len());
+    result
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| {
+            error!("Failed to serialize result: {}", e);
+            JsError::new(&format!("{e}"))
+        })
+}
+
+/// Returns the snapshot of a `resources.arsc`, with the layout documented on `abxml::snapshot`,
+/// as pretty printed JSON
+#[wasm_bindgen]
+pub fn snapshot_arsc(bytes: Vec<u8>) -> Result<String, wasm_bindgen::JsError> {
+    let decoder = Decoder::from_arsc(&bytes).map_err(|e| {
+        error!("Failed to decode ARSC: {}", e);
+        JsError::new(&format!("{e}"))
+    })?;
 
-        // Iterate through all type specs
-        let type_map: HashMap<u32, String> = package
-            .iter_specs()
-            .map(|(type_id, _type_spec)| {
-                (
-                    *type_id,
-                    package
-                        .get_spec_string(*type_id)
-                        .map(|s| s.to_string())
-                        .unwrap_or_else(|_| format!("type_{}", type_id)),
-                )
+    serde_json::to_string_pretty(&ResourcesSnapshot::new(decoder.get_resources())).map_err(|e| {
+        error!("Failed to serialize snapshot: {}", e);
+        JsError::new(&format!("{e}"))
+    })
+}
+
+/// Generates the symbols of the application package of a `resources.arsc`: `r_txt`, `r_java`,
+/// `r_kotlin` or `public_xml`. The table does not keep the `declare-styleable` definitions, so
+/// the output has no styleables; `r_java` and `r_kotlin` say so on their header.
+#[wasm_bindgen]
+pub fn arsc_symbols(bytes: Vec<u8>, format: &str) -> Result<String, wasm_bindgen::JsError> {
+    let decoder = Decoder::from_arsc(&bytes).map_err(|e| {
+        error!("Failed to decode ARSC: {}", e);
+        JsError::new(&format!("{e}"))
+    })?;
+
+    generate_symbols(decoder.get_resources(), format).map_err(|e| {
+        error!("Failed to generate symbols: {}", e);
+        JsError::new(&format!("{e}"))
+    })
+}
+
+fn generate_symbols(resources: &Resources, format: &str) -> anyhow::Result<String> {
+    let package_id = resources
+        .packages
+        .keys()
+        .copied()
+        .filter(|id| *id != 1)
+        .min()
+        .ok_or_else(|| anyhow::format_err!("the table has no application package"))?;
+
+    Ok(match format {
+        "public_xml" => values::public_file(resources, package_id)?.contents,
+        "r_txt" => SymbolTable::new(resources, package_id)?.r_txt(),
+        "r_java" => SymbolTable::new(resources, package_id)?.r_java(),
+        "r_kotlin" => SymbolTable::new(resources, package_id)?.r_kotlin(),
+        _ => anyhow::bail!("unknown symbols format: {}", format),
+    })
+}
+
+/// Index of the resources of a `resources.arsc` by name. It is built once and owns all its data,
+/// so the viewer can keep it around to search while the user types, and extract the resources
+/// from it instead of decoding the table again.
+#[wasm_bindgen]
+pub struct ArscIndex {
+    resources: Resources,
+    index: ResourceIndex,
+}
+
+#[wasm_bindgen]
+impl ArscIndex {
+    #[wasm_bindgen(constructor)]
+    pub fn new(bytes: Vec<u8>) -> Result<ArscIndex, wasm_bindgen::JsError> {
+        let decoder = Decoder::from_arsc(&bytes).map_err(|e| {
+            error!("Failed to decode ARSC: {}", e);
+            JsError::new(&format!("{e}"))
+        })?;
+
+        let resources = decoder.into_resources();
+        let index = ResourceIndex::new(&resources);
+
+        Ok(Self { resources, index })
+    }
+
+    /// Returns the resources of the table, as `extract_arsc` does
+    pub fn extract(
+        &self,
+        include_framework: Option<bool>,
+    ) -> Result<JsValue, wasm_bindgen::JsError> {
+        arsc::extract(&self.resources, include_framework.unwrap_or(false))
+            .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+            .map_err(|e| {
+                error!("Failed to serialize result: {}", e);
+                JsError::new(&format!("{e}"))
             })
-            .collect();
-        debug!("Type map: {type_map:?}");
-
-        // Get entries for this type
-        for (entry_id, entry) in package.iter_entries() {
-            let entry_name = package
-                .format_reference(*entry_id, entry.get_key(), None)
-                .unwrap_or_else(|_| "Unknown".into());
-
-            let value = entry.to_string(&resources.packages, *package_id);
-
-            let spec_id = u32::from(entry_id.get_spec());
-            let spec_str = package
-                .get_spec_as_str(spec_id)
-                .unwrap_or_else(|e| format!("{e}"));
-
-            result.push(ArscResource {
-                package_id: *package_id,
-                type_name: spec_str,
-                entry_id: *entry_id,
-                name: entry_name,
-                value,
-                entries: match entry {
-                    Entry::Complex(complex_entry) => {
-                        Some(complex_entry.to_hash_map(&resources.packages, *package_id))
-                    }
-                    _ => None,
-                },
-            });
-        }
     }
 
-    info!("Successfully extracted {} resources", result.len());
-    serde_wasm_bindgen::to_value(&result).map_err(|e| {
+    /// Returns the ID of a name like `@string/app_name` or `?android:attr/textColor`
+    pub fn resolve(&self, reference: &str) -> Option<u32> {
+        self.index.resolve(reference)
+    }
+
+    /// Searches the names and string values of the resources, either by case insensitive
+    /// substring or by regular expression
+    pub fn search(
+        &self,
+        query: &str,
+        regex: Option<bool>,
+    ) -> Result<JsValue, wasm_bindgen::JsError> {
+        let matcher = if regex.unwrap_or(false) {
+            Matcher::regex(query).map_err(|e| JsError::new(&format!("{e}")))?
+        } else {
+            Matcher::substring(query)
+        };
+
+        self.index
+            .search(&matcher)
+            .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+            .map_err(|e| {
+                error!("Failed to serialize result: {}", e);
+                JsError::new(&format!("{e}"))
+            })
+    }
+}
+
+/// Decodes a standalone binary XML (for example an `AndroidManifest.xml` pulled from a device).
+/// Without a resource table, references to the application resources are left as `@0x7f...`.
+#[wasm_bindgen]
+pub fn decode_axml(
+    bytes: Vec<u8>,
+    arsc_bytes: Option<Vec<u8>>,
+) -> Result<JsValue, wasm_bindgen::JsError> {
+    info!("Decoding binary XML of size {} bytes", bytes.len());
+    let decoded = decode_standalone_xml(&bytes, arsc_bytes.as_deref()).map_err(|e| {
+        error!("Failed to load resources: {}", e);
+        JsError::new(&format!("{e}"))
+    })?;
+
+    decoded
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| {
+            error!("Failed to serialize result: {}", e);
+            JsError::new(&format!("{e}"))
+        })
+}
+
+fn decode_standalone_xml(bytes: &[u8], arsc_bytes: Option<&[u8]>) -> anyhow::Result<DecodedXml> {
+    let resources = standalone_resources(arsc_bytes)?;
+
+    Ok(XmlVisitor::decode(&resources, bytes, DecodeMode::Lenient))
+}
+
+/// Resources a standalone document is decoded with: the framework ones and, if given, the ones of
+/// the resource table. Without a table, the IDs of any other package are left unresolved.
+fn standalone_resources(arsc_bytes: Option<&[u8]>) -> anyhow::Result<Resources> {
+    Ok(match arsc_bytes {
+        Some(arsc_bytes) => Decoder::from_arsc(arsc_bytes)?.into_resources(),
+        None => {
+            let mut resources = Decoder::from_framework()?.into_resources();
+            resources.packages.retain(|package_id, _| *package_id == 1);
+            resources
+        }
+    })
+}
+
+/// Typed view of a binary XML of a known family, along the SVG or DOT it can be exported to
+#[derive(Serialize)]
+struct InterpretedXml {
+    interpretation: Interpretation,
+    svg: Option<String>,
+    dot: Option<String>,
+}
+
+/// Interprets a standalone binary XML (vector drawable, navigation graph, font family, network
+/// security config or shortcuts). Returns `null` if it is not one of those. Colors are resolved on
+/// the given resource table, but files referenced from the document can not be read.
+#[wasm_bindgen]
+pub fn interpret_axml(
+    bytes: Vec<u8>,
+    arsc_bytes: Option<Vec<u8>>,
+) -> Result<JsValue, wasm_bindgen::JsError> {
+    let interpreted = interpret_standalone_xml(&bytes, arsc_bytes.as_deref()).map_err(|e| {
+        error!("Failed to interpret XML: {}", e);
+        JsError::new(&format!("{e}"))
+    })?;
+
+    interpreted
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| {
+            error!("Failed to serialize result: {}", e);
+            JsError::new(&format!("{e}"))
+        })
+}
+
+fn interpret_standalone_xml(
+    bytes: &[u8],
+    arsc_bytes: Option<&[u8]>,
+) -> anyhow::Result<Option<InterpretedXml>> {
+    let resources = &standalone_resources(arsc_bytes)?;
+
+    let root = match XmlVisitor::decode(resources, bytes, DecodeMode::Lenient).root {
+        Some(root) => root,
+        None => return Ok(None),
+    };
+    let resolver = TableResolver::new(resources, |_: &str| None);
+    let interpretation = match interpret::interpret(&root, &resolver) {
+        Some(interpretation) => interpretation?,
+        None => return Ok(None),
+    };
+
+    let svg = match &interpretation {
+        Interpretation::Vector(vector) => Some(vector.to_svg()),
+        _ => None,
+    };
+    let dot = match &interpretation {
+        Interpretation::Navigation(graph) => Some(graph.to_dot()),
+        _ => None,
+    };
+
+    Ok(Some(InterpretedXml {
+        interpretation,
+        svg,
+        dot,
+    }))
+}
+
+/// Returns the raw chunk structure of a `resources.arsc` or binary XML file. Meant to inspect
+/// files that can not be decoded, so it never fails on malformed input.
+#[wasm_bindgen]
+pub fn dump_chunks(bytes: Vec<u8>) -> Result<JsValue, wasm_bindgen::JsError> {
+    info!("Dumping chunks of {} bytes", bytes.len());
+    let tree = abxml::chunks::chunk_tree(&bytes);
+
+    serde_wasm_bindgen::to_value(&tree).map_err(|e| {
         error!("Failed to serialize result: {}", e);
         JsError::new(&format!("{e}"))
     })
@@ -132,4 +357,133 @@ mod tests {
         let decoder = abxml::decoder::Decoder::from_arsc(&bytes).unwrap();
         decoder.get_resources();
     }
+
+    #[test]
+    fn test_generate_symbols() {
+        let bytes = include_bytes!("example_resources.arsc").to_vec();
+        let decoder = abxml::decoder::Decoder::from_arsc(&bytes).unwrap();
+        let resources = decoder.get_resources();
+
+        let r_txt = super::generate_symbols(resources, "r_txt").unwrap();
+        assert!(r_txt.lines().all(|line| line.starts_with("int ")));
+        assert!(super::generate_symbols(resources, "r_java")
+            .unwrap()
+            .contains("R.styleable is missing"));
+        assert!(super::generate_symbols(resources, "public_xml")
+            .unwrap()
+            .contains("<public type="));
+        assert!(super::generate_symbols(resources, "r_swift").is_err());
+    }
+
+    #[test]
+    fn test_snapshot_arsc() {
+        let bytes = include_bytes!("example_resources.arsc").to_vec();
+        let json = super::snapshot_arsc(bytes).ok().unwrap();
+        let snapshot: abxml::snapshot::ResourcesSnapshot = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(abxml::snapshot::SCHEMA_VERSION, snapshot.schema_version);
+        assert!(snapshot.packages.iter().any(|p| !p.types.is_empty()));
+    }
+
+    #[test]
+    fn test_decode_standalone_xml() {
+        use abxml::model::{
+            builder::Xml,
+            owned::{AttributeBuf, StringTableBuf, XmlTagEndBuf, XmlTagStartBuf},
+        };
+
+        let mut xml = Xml::default();
+        let mut st = StringTableBuf::default();
+        st.add_string("manifest".to_string());
+        st.add_string("icon".to_string());
+        xml.push_owned(Box::new(st));
+        let mut tag_start = XmlTagStartBuf::new(1, 0, 0xFFFF_FFFF, 0, 0x0014_0014, 0);
+        tag_start.add_attribute(AttributeBuf::new(
+            0xFFFF_FFFF,
+            1,
+            0xFFFF_FFFF,
+            1 << 24,
+            0x7f020001,
+        ));
+        xml.push_owned(Box::new(tag_start));
+        xml.push_owned(Box::new(XmlTagEndBuf::new(0)));
+        let bytes = xml.into_vec().unwrap();
+
+        let decoded = super::decode_standalone_xml(&bytes, None).unwrap();
+
+        // Without a resource table, the IDs of the app are kept as they are
+        assert!(decoded
+            .xml
+            .unwrap()
+            .contains("<manifest icon=\"@0x7f020001\" />"));
+        let root = decoded.root.unwrap();
+        assert_eq!("manifest", *root.get_tag().get_name());
+        let problems: Vec<&str> = decoded
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.problem.as_str())
+            .collect();
+        assert_eq!(
+            vec!["value of icon is invalid: could not resolve reference: package not found"],
+            problems
+        );
+    }
+
+    #[test]
+    fn test_chunk_tree() {
+        let bytes = include_bytes!("example_resources.arsc");
+        let tree = abxml::chunks::chunk_tree(bytes);
+
+        assert_eq!(1, tree.len());
+        assert_eq!("table", tree[0].type_name);
+        assert!(tree[0]
+            .children
+            .iter()
+            .any(|chunk| chunk.type_name == "table_package"));
+    }
+
+    #[test]
+    fn test_interpret_standalone_xml() {
+        use abxml::model::{
+            builder::Xml,
+            owned::{AttributeBuf, StringTableBuf, XmlTagEndBuf, XmlTagStartBuf},
+        };
+
+        let mut xml = Xml::default();
+        let mut st = StringTableBuf::default();
+        st.add_string("network-security-config".to_string());
+        st.add_string("base-config".to_string());
+        st.add_string("cleartextTrafficPermitted".to_string());
+        xml.push_owned(Box::new(st));
+        let mut base_config = XmlTagStartBuf::new(1, 0, 0xFFFF_FFFF, 1, 0x0014_0014, 0);
+        base_config.add_attribute(AttributeBuf::new(
+            0xFFFF_FFFF,
+            2,
+            0xFFFF_FFFF,
+            0x12 << 24,
+            0xFFFF_FFFF,
+        ));
+        xml.push_owned(Box::new(XmlTagStartBuf::new(
+            1,
+            0,
+            0xFFFF_FFFF,
+            0,
+            0x0014_0014,
+            0,
+        )));
+        xml.push_owned(Box::new(base_config));
+        xml.push_owned(Box::new(XmlTagEndBuf::new(1)));
+        xml.push_owned(Box::new(XmlTagEndBuf::new(0)));
+
+        let interpreted = super::interpret_standalone_xml(&xml.into_vec().unwrap(), None)
+            .unwrap()
+            .unwrap();
+        let json = serde_json::to_value(&interpreted).unwrap();
+        assert_eq!("network_security_config", json["interpretation"]["kind"]);
+        assert_eq!(
+            true,
+            json["interpretation"]["value"]["base_config"]["cleartext_traffic_permitted"]
+        );
+        assert!(interpreted.svg.is_none());
+    }
 }
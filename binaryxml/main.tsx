@@ -1,9 +1,10 @@
 import { createRoot } from 'react-dom/client'
-import init, { decode_apk, extract_arsc } from './abxml-wasm-bindings/pkg'
+import init, { ArscIndex, decode_apk, decode_axml, dump_chunks, interpret_axml } from './abxml-wasm-bindings/pkg'
 import React, { useState, useEffect } from 'react'
 import { Tab, Tabs, TabList, TabPanel } from 'react-tabs'
 import 'react-tabs/style/react-tabs.css'
 import { ColumnView } from '../components/ColumnView'
+import { offset, renderAscii, renderHex } from '../hex_viewer/hex'
 
 const OUTPUT = createRoot(document.getElementById('output')!);
 let wasmInitialized = false;
@@ -37,12 +38,39 @@ function pathToTree(paths: [string, string][]): { [key: string]: any } {
     return result
 }
 
+function isBinaryXml(content: Uint8Array): boolean {
+    return content.length >= 8 && content[0] === 3 && content[1] === 0
+}
+
 function App() {
-    const [view, setView] = useState<'file' | 'resource'>('file');
+    const [view, setView] = useState<'file' | 'resource' | 'structure' | 'xml'>('file');
     const [fileTree, setFileTree] = useState<{ [key: string]: any }>({});
-    const [resources, setResources] = useState<any[]>([]);
+    const [resources, setResources] = useState<ArscResource[]>([]);
+    const [arscIndex, setArscIndex] = useState<ArscIndex | null>(null);
+    const [structure, setStructure] = useState<{ buffer: Uint8Array, error: string } | null>(null);
+    const [decodedXml, setDecodedXml] = useState<DecodedXml | null>(null);
+    const [interpretedXml, setInterpretedXml] = useState<InterpretedXml | null>(null);
     const [error, setError] = useState<string | null>(null);
 
+    // The index lives on the WebAssembly memory: free it once it is replaced or unmounted
+    useEffect(() => () => arscIndex?.free(), [arscIndex]);
+
+    // Falls back to the chunk structure when the file can not be decoded
+    const showArsc = (bytes: Uint8Array) => {
+        // The table is decoded once, for both the resources and the search index
+        let index: ArscIndex | null = null;
+        try {
+            index = new ArscIndex(bytes);
+            setResources(index.extract());
+            setArscIndex(index);
+            setView('resource');
+        } catch (e) {
+            index?.free();
+            setStructure({ buffer: bytes, error: e.message });
+            setView('structure');
+        }
+    };
+
     useEffect(() => {
         // Request file from parent window
         if (window.parent) {
@@ -65,6 +93,24 @@ function App() {
         return () => window.removeEventListener('message', handleMessage);
     }, []);
 
+    const showBinaryXml = (bytes: Uint8Array, arsc?: Uint8Array) => {
+        const decoded: DecodedXml = decode_axml(bytes, arsc);
+        if (decoded.xml === null) {
+            setStructure({ buffer: bytes, error: decoded.diagnostics.map(d => d.problem).join('; ') });
+            setView('structure');
+            return;
+        }
+        setDecodedXml(decoded);
+        // The interpretation is only a bonus on top of the XML, so failing to build it is not fatal
+        try {
+            setInterpretedXml(interpret_axml(bytes, arsc));
+        } catch (e) {
+            console.error('Error interpreting XML:', e);
+            setInterpretedXml(null);
+        }
+        setView('xml');
+    };
+
     const handleFile = async (file: File) => {
         if (!wasmInitialized) {
             await initializeWasm();
@@ -73,9 +119,12 @@ function App() {
 
         // Check if it's an ARSC file
         if (file.name.endsWith('.arsc')) {
-            const resources = extract_arsc(fileBytes);
-            setResources(resources);
-            setView('resource');
+            showArsc(fileBytes);
+            return;
+        }
+
+        if (file.name.endsWith('.xml') && isBinaryXml(fileBytes)) {
+            showBinaryXml(fileBytes);
             return;
         }
 
@@ -88,9 +137,12 @@ function App() {
     const handleItemClick = (level: number, key: string, content: any) => {
         // Check if it's an ARSC file
         if (key.endsWith('.arsc') && content instanceof Uint8Array) {
-            const resources = extract_arsc(content);
-            setResources(resources);
-            setView('resource');
+            showArsc(content);
+        } else if (key.endsWith('.xml') && content instanceof Uint8Array && isBinaryXml(content)) {
+            // Binary XML files are only kept as binary when they could not be decoded with the
+            // APK, so retry leniently to show what can be recovered
+            const arsc = fileTree['resources.arsc'];
+            showBinaryXml(content, arsc instanceof Uint8Array ? arsc : undefined);
         }
     };
 
@@ -99,7 +151,15 @@ function App() {
     }
 
     if (view === 'resource') {
-        return <ResourceTableViewer resources={resources} onBack={() => setView('file')} />;
+        return <ResourceTableViewer resources={resources} index={arscIndex} onBack={() => setView('file')} />;
+    }
+
+    if (view === 'xml' && decodedXml) {
+        return <XmlViewer decoded={decodedXml} interpreted={interpretedXml} onBack={() => setView('file')} />;
+    }
+
+    if (view === 'structure' && structure) {
+        return <StructureViewer buffer={structure.buffer} error={structure.error} onBack={() => setView('file')} />;
     }
 
     return <FileViewer files={fileTree} onItemClick={handleItemClick} />;
@@ -160,7 +220,141 @@ function FileViewer({ files, onItemClick }: {
     );
 }
 
-function ResourceTableViewer({ resources, onBack }: { resources: any[], onBack: () => void }) {
+type TypedValue =
+    | { type: 'null', data_type: number, data: number }
+    | { type: 'reference' | 'attribute', data_type: number, data: number, name: string | null }
+    | { type: 'string', data_type: number, data: number, value: string | null }
+    | { type: 'dimension' | 'fraction', data_type: number, data: number, value: number, unit: string | null }
+    | { type: 'color', data_type: number, data: number, argb: string }
+    | { type: 'float' | 'integer', data_type: number, data: number, value: number }
+    | { type: 'boolean', data_type: number, data: number, value: boolean }
+    | { type: 'flags' | 'unknown', data_type: number, data: number };
+
+type BagKey =
+    | { kind: 'type' | 'min' | 'max' | 'l10n' }
+    | { kind: 'quantity', quantity: string }
+    | { kind: 'index', index: number }
+    | { kind: 'symbol' | 'attribute', id: number, name: string | null };
+
+type EntryValue =
+    | ({ kind: 'simple' } & TypedValue)
+    | {
+        kind: 'bag',
+        bag_type: 'plurals' | 'array' | 'attr' | 'style' | 'other',
+        parent: { id: number, name: string | null } | null,
+        formats: string[] | null,
+        items: { key: BagKey, value: TypedValue }[],
+    };
+
+type ArscResource = {
+    package_id: number,
+    type_name: string,
+    entry_id: number,
+    name: string,
+    public: boolean,
+    staged_api: boolean,
+    config_changes: string[],
+    type_flags: string[],
+    value: EntryValue,
+    variants: { config: string, value: EntryValue }[],
+}
+
+function hex(value: number): string {
+    return `0x${(value >>> 0).toString(16)}`;
+}
+
+function formatValue(value: TypedValue): string {
+    switch (value.type) {
+        case 'null': return '@null';
+        case 'reference': return value.name ?? `@${hex(value.data)}`;
+        case 'attribute': return value.name ?? `?${hex(value.data)}`;
+        case 'string': return value.value ?? `string #${value.data}`;
+        case 'dimension':
+        case 'fraction': return `${value.value}${value.unit ?? ''}`;
+        case 'color': return value.argb;
+        case 'float':
+        case 'integer':
+        case 'boolean': return `${value.value}`;
+        default: return hex(value.data);
+    }
+}
+
+function formatBagKey(key: BagKey): string {
+    switch (key.kind) {
+        case 'quantity': return `quantity=${key.quantity}`;
+        case 'index': return `^index_${key.index}`;
+        case 'symbol':
+        case 'attribute': return key.name ?? hex(key.id);
+        default: return `^${key.kind}`;
+    }
+}
+
+function formatEntryValue(value: EntryValue): string {
+    if (value.kind === 'simple') {
+        return formatValue(value);
+    }
+    return value.items.map(item => `${formatBagKey(item.key)}: ${formatValue(item.value)}`).join(', ');
+}
+
+function ValueView({ value }: { value: TypedValue }) {
+    if (value.type !== 'color') {
+        return <span>{formatValue(value)}</span>;
+    }
+    const argb = value.argb;
+    return (
+        <span style={{ display: 'inline-flex', alignItems: 'center', gap: '8px' }}>
+            {argb}
+            <span style={{
+                display: 'inline-block',
+                width: '20px',
+                height: '20px',
+                backgroundColor: `rgba(${parseInt(argb.slice(3, 5), 16)}, ${parseInt(argb.slice(5, 7), 16)}, ${parseInt(argb.slice(7, 9), 16)}, ${parseInt(argb.slice(1, 3), 16) / 255})`,
+                border: '1px solid #ccc',
+                borderRadius: '4px'
+            }} />
+        </span>
+    );
+}
+
+function EntryValueView({ value }: { value: EntryValue }) {
+    if (value.kind === 'simple') {
+        return <div><ValueView value={value} /></div>;
+    }
+
+    return (
+        <div>
+            {value.parent && <div style={{ color: '#666' }}>parent: {value.parent.name ?? hex(value.parent.id)}</div>}
+            {value.formats && <div style={{ color: '#666' }}>format: {value.formats.join('|')}</div>}
+            {value.bag_type === 'array' ? (
+                <ol start={0} style={{ margin: 0 }}>
+                    {value.items.map((item, i) => <li key={i}><ValueView value={item.value} /></li>)}
+                </ol>
+            ) : (
+                <table style={{ borderCollapse: 'collapse' }}>
+                    <tbody>
+                        {value.items.map((item, i) => (
+                            <tr key={i} style={{ borderTop: i > 0 ? '1px solid #eee' : 'none' }}>
+                                <td style={{ fontWeight: 'bold', paddingRight: '8px' }}>
+                                    {item.key.kind === 'quantity' ? item.key.quantity : formatBagKey(item.key)}
+                                </td>
+                                <td><ValueView value={item.value} /></td>
+                            </tr>
+                        ))}
+                    </tbody>
+                </table>
+            )}
+        </div>
+    );
+}
+
+type SearchHit = {
+    id: number,
+    name: string,
+    config: string | null,
+    text: string,
+};
+
+function ResourceTableViewer({ resources, index, onBack }: { resources: ArscResource[], index: ArscIndex | null, onBack: () => void }) {
     // Group resources by type name
     const resourcesByType = resources.reduce((acc, resource) => {
         const typeName = resource.type_name;
@@ -169,9 +363,22 @@ function ResourceTableViewer({ resources, onBack }: { resources: any[], onBack:
         }
         acc[typeName].push(resource);
         return acc;
-    }, {} as Record<string, any[]>);
+    }, {} as Record<string, ArscResource[]>);
 
     const [selectedType, setSelectedType] = useState<string>(Object.keys(resourcesByType)[0]);
+    const [query, setQuery] = useState('');
+    const [useRegex, setUseRegex] = useState(false);
+
+    let searchHits: SearchHit[] = [];
+    let searchError: string | null = null;
+    if (index && query) {
+        try {
+            searchHits = index.search(query, useRegex);
+        } catch (e) {
+            searchError = e.message;
+        }
+    }
+
     const [sortConfig, setSortConfig] = useState<{ key: 'entry_id' | 'name' | 'value', direction: 'asc' | 'desc' }>({
         key: 'entry_id',
         direction: 'asc'
@@ -193,7 +400,7 @@ function ResourceTableViewer({ resources, onBack }: { resources: any[], onBack:
             } else if (sortConfig.key === 'name') {
                 comparison = a.name.localeCompare(b.name);
             } else {
-                comparison = a.value.localeCompare(b.value);
+                comparison = formatEntryValue(a.value).localeCompare(formatEntryValue(b.value));
             }
             return sortConfig.direction === 'asc' ? comparison : -comparison;
         });
@@ -242,8 +449,40 @@ function ResourceTableViewer({ resources, onBack }: { resources: any[], onBack:
                     Back
                 </button>
                 <h3 style={{ margin: 0 }}>Resource Table</h3>
+                {index && (
+                    <>
+                        <input
+                            type="search"
+                            placeholder="Search names and strings"
+                            value={query}
+                            onChange={(e) => setQuery(e.target.value)}
+                            style={{ padding: '6px', minWidth: '240px' }}
+                        />
+                        <label>
+                            <input type="checkbox" checked={useRegex} onChange={(e) => setUseRegex(e.target.checked)} /> Regex
+                        </label>
+                    </>
+                )}
             </div>
 
+            {query && (
+                <div style={{ marginBottom: '16px', fontFamily: 'monospace' }}>
+                    {searchError ? (
+                        <div style={{ color: 'red' }}>{searchError}</div>
+                    ) : (
+                        <>
+                            <div>{searchHits.length} matches</div>
+                            {searchHits.slice(0, 500).map((hit, i) => (
+                                <div key={i} style={{ padding: '2px 0' }}>
+                                    {hex(hit.id)} {hit.name}
+                                    {hit.config !== null && <span style={{ color: '#666' }}> [{hit.config || 'default'}] {hit.text}</span>}
+                                </div>
+                            ))}
+                        </>
+                    )}
+                </div>
+            )}
+
             {/* Type selector tabs */}
             <div style={{ marginBottom: '16px' }}>
                 <Tabs
@@ -320,33 +559,27 @@ function ResourceTableViewer({ resources, onBack }: { resources: any[], onBack:
                                     {getSortedResources().map((resource, index) => (
                                         <React.Fragment key={index}>
                                             <div style={{ padding: '8px', borderBottom: '1px solid #eee', fontFamily: 'monospace', whiteSpace: 'nowrap' }}>0x{resource.entry_id.toString(16).toUpperCase()}</div>
-                                            <div style={{ padding: '8px', borderBottom: '1px solid #eee', fontFamily: 'monospace', whiteSpace: 'nowrap' }}>{resource.name}</div>
+                                            <div style={{ padding: '8px', borderBottom: '1px solid #eee', fontFamily: 'monospace', whiteSpace: 'nowrap' }}>
+                                                {resource.name}
+                                                {resource.public && <span title="Part of the public API" style={{ marginLeft: '6px', color: '#2a7' }}>public</span>}
+                                                {resource.staged_api && <span title="Staged API: the ID may change" style={{ marginLeft: '6px', color: '#c80' }}>staged</span>}
+                                                {resource.config_changes.length > 0 && (
+                                                    <div style={{ fontSize: '0.85em', color: '#666' }}>varies on {resource.config_changes.join(', ')}</div>
+                                                )}
+                                            </div>
                                             {selectedType !== 'id' && (
                                                 <div style={{ padding: '8px', borderBottom: '1px solid #eee', fontFamily: 'monospace' }}>
-                                                    {resource.type_name !== 'attr' && <div style={{ display: 'flex', alignItems: 'center', gap: '8px' }}>
-                                                        {resource.value}
-                                                        {resource.value.startsWith('#') && resource.value.length === 9 && (
-                                                            <div style={{
-                                                                width: '20px',
-                                                                height: '20px',
-                                                                backgroundColor: `rgba(${parseInt(resource.value.slice(3, 5), 16)}, ${parseInt(resource.value.slice(5, 7), 16)}, ${parseInt(resource.value.slice(7, 9), 16)}, ${parseInt(resource.value.slice(1, 3), 16) / 255})`,
-                                                                border: '1px solid #ccc',
-                                                                borderRadius: '4px'
-                                                            }} />
-                                                        )}
-                                                    </div>}
-                                                    {resource.entries && (
-                                                        <div style={{ marginTop: '8px' }}>
-                                                            {Array.from(resource.entries).map(([key, value], i) => (
-                                                                <div key={i} style={{
-                                                                    padding: '4px 0',
-                                                                    borderTop: i > 0 ? '1px solid #eee' : 'none'
-                                                                }}>
-                                                                    <span style={{ fontWeight: 'bold' }}>{key}:</span> {value}
+                                                    <EntryValueView value={resource.value} />
+                                                    {resource.variants.length > 1 && (
+                                                        <details style={{ marginTop: '8px' }}>
+                                                            <summary>{resource.variants.length} configurations</summary>
+                                                            {resource.variants.map((variant, i) => (
+                                                                <div key={i} style={{ padding: '4px 0', borderTop: i > 0 ? '1px solid #eee' : 'none' }}>
+                                                                    <span style={{ fontWeight: 'bold' }}>{variant.config || 'default'}:</span>
+                                                                    <EntryValueView value={variant.value} />
                                                                 </div>
-                                                            ))
-                                                            }
-                                                        </div>
+                                                            ))}
+                                                        </details>
                                                     )}
                                                 </div>
                                             )}
@@ -362,6 +595,140 @@ function ResourceTableViewer({ resources, onBack }: { resources: any[], onBack:
     );
 }
 
+type DecodedXml = {
+    xml: string | null,
+    root: XmlElement | null,
+    diagnostics: { offset: number, chunk_type: number | null, problem: string }[],
+}
+
+type XmlElement = {
+    name: string,
+    attributes: { [name: string]: string },
+    children: XmlElement[],
+}
+
+type InterpretedXml = {
+    interpretation: { kind: string, value: any },
+    svg: string | null,
+    dot: string | null,
+}
+
+function XmlViewer({ decoded, interpreted, onBack }: {
+    decoded: DecodedXml,
+    interpreted: InterpretedXml | null,
+    onBack: () => void,
+}) {
+    return (
+        <div style={{ display: 'flex', flexDirection: 'column', height: '100%', padding: '20px', overflow: 'hidden' }}>
+            <div style={{ display: 'flex', alignItems: 'center', gap: '16px', marginBottom: '16px' }}>
+                <button onClick={onBack} style={{ padding: '8px 16px', border: '1px solid #ccc', borderRadius: '4px', background: 'white', cursor: 'pointer' }}>
+                    Back
+                </button>
+                <h3 style={{ margin: 0 }}>Binary XML</h3>
+            </div>
+            {decoded.diagnostics.length > 0 && (
+                <details style={{ marginBottom: '16px', color: '#a60' }}>
+                    <summary>{decoded.diagnostics.length} problems found while decoding</summary>
+                    {decoded.diagnostics.map((d, i) => (
+                        <div key={i} style={{ fontFamily: 'monospace' }}>
+                            @0x{d.offset.toString(16)}{d.chunk_type !== null && ` (chunk 0x${d.chunk_type.toString(16)})`}: {d.problem}
+                        </div>
+                    ))}
+                </details>
+            )}
+            {interpreted && (
+                <details open style={{ marginBottom: '16px' }}>
+                    <summary>Interpreted as {interpreted.interpretation.kind.replace(/_/g, ' ')}</summary>
+                    {interpreted.svg !== null && (
+                        <img
+                            src={`data:image/svg+xml;charset=utf-8,${encodeURIComponent(interpreted.svg)}`}
+                            style={{ width: '128px', height: '128px', border: '1px solid #ccc', margin: '8px 0' }}
+                        />
+                    )}
+                    <pre style={{ maxHeight: '240px', overflow: 'auto', margin: 0 }}>
+                        {interpreted.dot ?? interpreted.svg ?? JSON.stringify(interpreted.interpretation.value, null, 2)}
+                    </pre>
+                </details>
+            )}
+            <pre style={{ flex: 1, overflow: 'auto', margin: 0 }}>{decoded.xml}</pre>
+        </div>
+    );
+}
+
+type ChunkNode = {
+    chunk_type: number,
+    type_name: string,
+    offset: number,
+    header_size: number,
+    size: number,
+    fields: { name: string, value: string }[],
+    children: ChunkNode[],
+    data?: string,
+    error?: string,
+}
+
+function StructureViewer({ buffer, error, onBack }: { buffer: Uint8Array, error: string, onBack: () => void }) {
+    const tree: ChunkNode[] = dump_chunks(buffer);
+    const [selected, setSelected] = useState<ChunkNode | null>(tree[0] ?? null);
+
+    // Show at most 256 lines of the selected chunk
+    const start = selected ? Math.floor(selected.offset / 16) : 0;
+    const end = Math.min(
+        Math.ceil(buffer.length / 16),
+        selected ? Math.ceil((selected.offset + Math.max(selected.size, selected.header_size)) / 16) : 0,
+        start + 256,
+    );
+    const lines = Array.from({ length: Math.max(0, end - start) }, (_, i) => start + i);
+
+    const renderNode = (node: ChunkNode, index: number) => (
+        <details key={`${node.offset}-${index}`} open={node.children.length > 0}>
+            <summary
+                onClick={() => setSelected(node)}
+                style={{
+                    cursor: 'pointer',
+                    fontFamily: 'monospace',
+                    background: selected === node ? '#e0e8ff' : undefined,
+                    color: node.error ? 'red' : undefined,
+                }}
+            >
+                {node.type_name} (0x{node.chunk_type.toString(16)}) @0x{node.offset.toString(16)}, header {node.header_size}, size {node.size}
+            </summary>
+            <div style={{ paddingLeft: '16px' }}>
+                {node.error && <div style={{ color: 'red' }}>{node.error}</div>}
+                {node.fields.map(field => (
+                    <div key={field.name} style={{ fontFamily: 'monospace' }}>{field.name}: {field.value}</div>
+                ))}
+                {node.data && <div style={{ fontFamily: 'monospace', wordBreak: 'break-all' }}>{node.data}</div>}
+                {node.children.map(renderNode)}
+            </div>
+        </details>
+    );
+
+    return (
+        <div style={{ display: 'flex', flexDirection: 'column', height: '100%', padding: '20px', overflow: 'hidden' }}>
+            <div style={{ display: 'flex', alignItems: 'center', gap: '16px', marginBottom: '16px' }}>
+                <button onClick={onBack} style={{ padding: '8px 16px', border: '1px solid #ccc', borderRadius: '4px', background: 'white', cursor: 'pointer' }}>
+                    Back
+                </button>
+                <h3 style={{ margin: 0 }}>Chunk structure</h3>
+                <span style={{ color: 'red' }}>{error}</span>
+            </div>
+            <div style={{ display: 'flex', gap: '16px', flex: 1, overflow: 'hidden' }}>
+                <div style={{ flex: 1, overflow: 'auto' }}>
+                    {tree.map(renderNode)}
+                </div>
+                <pre style={{ flex: 1, overflow: 'auto', margin: 0 }}>
+                    {lines.map(line => (
+                        <div key={line}>
+                            {offset(line, buffer)}  {renderHex(line, buffer)}  {renderAscii(line, buffer)}
+                        </div>
+                    ))}
+                </pre>
+            </div>
+        </div>
+    );
+}
+
 // Initialize WebAssembly when the component mounts
 initializeWasm().catch(error => {
     console.error('Failed to initialize WebAssembly:', error);
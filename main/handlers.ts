@@ -236,6 +236,10 @@ export const HANDLERS: HandlerDefinition[] = [
             {
                 mime: "application/zip",
                 filename: /.*\.apk$/i,
+            },
+            { // Binary XML, like an AndroidManifest.xml pulled from a device
+                mime: "application/octet-stream",
+                filename: /.*\.xml$/i,
             }
         ]
     },
@@ -1,14 +1,32 @@
 //! Structures defining the contents of a `Method`'s code.
-use scroll::{ctx, Pread, Uleb128};
-use std::{fmt, ops::Deref};
+use scroll::{ctx, Pread, Sleb128, Uleb128};
+use std::{collections::HashMap, convert::TryFrom, fmt, iter, ops::Deref};
 
 use getset::{CopyGetters, Getters};
 
 use crate::{
-    encoded_item::EncodedCatchHandlers, error::Error, jtype::Type, string::DexString, uint, ulong,
-    ushort,
+    encoded_item::EncodedCatchHandlers,
+    error::Error,
+    instruction::Instructions,
+    jtype::{Type, TypeId},
+    string::DexString,
+    uint, ulong, ushort,
 };
 
+const DBG_END_SEQUENCE: u8 = 0x00;
+const DBG_ADVANCE_PC: u8 = 0x01;
+const DBG_ADVANCE_LINE: u8 = 0x02;
+const DBG_START_LOCAL: u8 = 0x03;
+const DBG_START_LOCAL_EXTENDED: u8 = 0x04;
+const DBG_END_LOCAL: u8 = 0x05;
+const DBG_RESTART_LOCAL: u8 = 0x06;
+const DBG_SET_PROLOGUE_END: u8 = 0x07;
+const DBG_SET_EPILOGUE_BEGIN: u8 = 0x08;
+const DBG_SET_FILE: u8 = 0x09;
+const DBG_FIRST_SPECIAL: u8 = 0x0a;
+const DBG_LINE_BASE: i64 = -4;
+const DBG_LINE_RANGE: u8 = 15;
+
 /// Debug Info of a method.
 /// [Android docs](https://source.android.com/devices/tech/dalvik/dex-format#debug-info-item)
 #[derive(Debug, Getters, CopyGetters)]
@@ -19,6 +37,103 @@ pub struct DebugInfoItem {
     /// Names of the incoming parameters.
     #[get = "pub"]
     parameter_names: Vec<Option<DexString>>,
+    /// Entries of the position table, in the order of their addresses.
+    #[get = "pub"]
+    positions: Vec<Position>,
+    /// Entries of the local variable table, in the order they start. `this` and the parameters
+    /// come first, live from the start of the method.
+    #[get = "pub"]
+    locals: Vec<LocalVariable>,
+}
+
+impl DebugInfoItem {
+    /// The position of the instruction at `address`: the last entry of the position table at or
+    /// before it.
+    pub fn position_at(&self, address: uint) -> Option<&Position> {
+        let index = self
+            .positions
+            .partition_point(|position| position.address <= address);
+        index.checked_sub(1).map(|index| &self.positions[index])
+    }
+}
+
+/// An entry of the position table, mapping an address to a line of the source code.
+#[derive(Debug, Getters, CopyGetters)]
+pub struct Position {
+    /// Address of the first instruction at this position, in code units.
+    #[get_copy = "pub"]
+    address: uint,
+    /// Line number in the source file.
+    #[get_copy = "pub"]
+    line: usize,
+    /// Source file of the line, if it is not the one of the class.
+    #[get = "pub"]
+    source_file: Option<DexString>,
+    /// Whether the method prologue ends at this address.
+    #[get_copy = "pub"]
+    prologue_end: bool,
+    /// Whether the method epilogue begins at this address.
+    #[get_copy = "pub"]
+    epilogue_begin: bool,
+}
+
+/// An entry of the local variable table: a variable held in a register over a range of
+/// addresses.
+#[derive(Debug, Getters, CopyGetters)]
+pub struct LocalVariable {
+    /// Register holding the variable.
+    #[get_copy = "pub"]
+    register: uint,
+    /// Name of the variable.
+    #[get = "pub"]
+    name: Option<DexString>,
+    /// Type of the variable.
+    #[get = "pub"]
+    jtype: Option<Type>,
+    /// Generic signature of the variable, like `Ljava/util/List<Ljava/lang/String;>;`.
+    #[get = "pub"]
+    signature: Option<DexString>,
+    /// Address at which the variable becomes live.
+    #[get_copy = "pub"]
+    start_address: uint,
+    /// Address at which the variable stops being live, or `None` if it is live until the end
+    /// of the method.
+    #[get_copy = "pub"]
+    end_address: Option<uint>,
+    /// Whether the variable was brought back to life after having been ended.
+    #[get_copy = "pub"]
+    restarted: bool,
+    /// Whether the variable is `this` or a parameter, which are not started by the debug info.
+    #[get_copy = "pub"]
+    parameter: bool,
+}
+
+/// Registers holding `this` and the parameters of a method when its code starts, which the debug
+/// info can end and restart as any other local.
+#[derive(Clone, Copy)]
+pub(crate) struct ParameterRegisters<'p> {
+    /// Register of `this`, or of the first parameter of static methods.
+    first: uint,
+    /// Type of `this`, for instance methods.
+    this: Option<&'p Type>,
+    params: &'p [Type],
+}
+
+impl<'p> ParameterRegisters<'p> {
+    /// Register and type of each parameter, starting with `this`. Long and double parameters
+    /// take two registers.
+    fn registers(&self) -> impl Iterator<Item = (uint, &'p Type)> + 'p {
+        let mut register = self.first;
+        self.this.into_iter().chain(self.params).map(move |jtype| {
+            let current = register;
+            register += if jtype.is_long() || jtype.is_double() {
+                2
+            } else {
+                1
+            };
+            (current, jtype)
+        })
+    }
 }
 
 /// Code and Debug Info of a method.
@@ -48,6 +163,11 @@ impl CodeItem {
     pub fn debug_info_item(&self) -> Option<&DebugInfoItem> {
         self.debug_info_item.as_ref()
     }
+
+    /// Decoded instructions of this method, along their address in code units.
+    pub fn instructions(&self) -> Instructions<'_> {
+        Instructions::new(&self.insns)
+    }
 }
 
 impl fmt::Debug for CodeItem {
@@ -156,7 +276,7 @@ where
     }
 }
 
-impl<'a, S> ctx::TryFromCtx<'a, &super::Dex<S>> for DebugInfoItem
+impl<'a, 'p, S> ctx::TryFromCtx<'a, (&super::Dex<S>, ParameterRegisters<'p>)> for DebugInfoItem
 where
     S: AsRef<[u8]>,
 {
@@ -165,31 +285,201 @@ where
 
     fn try_from_ctx(
         source: &'a [u8],
-        dex: &super::Dex<S>,
+        (dex, parameters): (&super::Dex<S>, ParameterRegisters<'p>),
     ) -> Result<(Self, Self::Size), Self::Error> {
         let offset = &mut 0;
         let line_start = Uleb128::read(source, offset)? as usize;
         let parameters_size = Uleb128::read(source, offset)?;
         let mut parameter_names = Vec::with_capacity(parameters_size as usize);
         for _ in 0..parameters_size {
-            let string_id = Uleb128::read(source, offset)? + 1;
-            parameter_names.push(if string_id != u64::from(crate::NO_INDEX) {
-                Some(dex.get_string(string_id as uint)?)
-            } else {
-                None
+            parameter_names.push(read_string(source, offset, dex)?);
+        }
+
+        let mut address: uint = 0;
+        let mut line = line_start as i64;
+        let mut source_file = None;
+        let mut prologue_end = false;
+        let mut epilogue_begin = false;
+        let mut positions = Vec::new();
+        let mut locals: Vec<LocalVariable> = Vec::new();
+        // indices into `locals` of the live variables, and of the last variable of each register
+        let mut live: HashMap<uint, usize> = HashMap::new();
+        let mut last: HashMap<uint, usize> = HashMap::new();
+        // `this` and the parameters are live from the start
+        let this = parameters
+            .this
+            .map(|_| Some(DexString::from("this".to_string())));
+        let names = this
+            .into_iter()
+            .chain(parameter_names.iter().cloned())
+            .chain(iter::repeat(None));
+        for ((register, jtype), name) in parameters.registers().zip(names) {
+            live.insert(register, locals.len());
+            last.insert(register, locals.len());
+            locals.push(LocalVariable {
+                register,
+                name,
+                jtype: Some(jtype.clone()),
+                signature: None,
+                start_address: 0,
+                end_address: None,
+                restarted: false,
+                parameter: true,
             });
         }
+        loop {
+            let opcode: u8 = source.gread(offset)?;
+            match opcode {
+                DBG_END_SEQUENCE => break,
+                DBG_ADVANCE_PC => {
+                    let advance = Uleb128::read(source, offset)?;
+                    address = advance_address(address, advance)?;
+                }
+                DBG_ADVANCE_LINE => {
+                    let advance = Sleb128::read(source, offset)?;
+                    line = advance_line(line, advance, address)?;
+                }
+                DBG_START_LOCAL | DBG_START_LOCAL_EXTENDED => {
+                    let register = Uleb128::read(source, offset)? as uint;
+                    let name = read_string(source, offset, dex)?;
+                    let jtype = match Uleb128::read(source, offset)? {
+                        0 => None,
+                        type_id => Some(dex.get_type((type_id - 1) as TypeId)?),
+                    };
+                    let signature = if opcode == DBG_START_LOCAL_EXTENDED {
+                        read_string(source, offset, dex)?
+                    } else {
+                        None
+                    };
+                    if let Some(index) = live.remove(&register) {
+                        locals[index].end_address = Some(address);
+                    }
+                    live.insert(register, locals.len());
+                    last.insert(register, locals.len());
+                    locals.push(LocalVariable {
+                        register,
+                        name,
+                        jtype,
+                        signature,
+                        start_address: address,
+                        end_address: None,
+                        restarted: false,
+                        parameter: false,
+                    });
+                }
+                DBG_END_LOCAL => {
+                    let register = Uleb128::read(source, offset)? as uint;
+                    if let Some(index) = live.remove(&register) {
+                        locals[index].end_address = Some(address);
+                    }
+                }
+                DBG_RESTART_LOCAL => {
+                    let register = Uleb128::read(source, offset)? as uint;
+                    if live.contains_key(&register) {
+                        continue;
+                    }
+                    let previous = match last.get(&register) {
+                        Some(index) => &locals[*index],
+                        None => {
+                            debug!(target: "debug-info", "restarting unknown local in register {}", register);
+                            continue;
+                        }
+                    };
+                    let restarted = LocalVariable {
+                        register,
+                        name: previous.name.clone(),
+                        jtype: previous.jtype.clone(),
+                        signature: previous.signature.clone(),
+                        start_address: address,
+                        end_address: None,
+                        restarted: true,
+                        parameter: false,
+                    };
+                    live.insert(register, locals.len());
+                    last.insert(register, locals.len());
+                    locals.push(restarted);
+                }
+                DBG_SET_PROLOGUE_END => prologue_end = true,
+                DBG_SET_EPILOGUE_BEGIN => epilogue_begin = true,
+                DBG_SET_FILE => source_file = read_string(source, offset, dex)?,
+                special => {
+                    let adjusted = special - DBG_FIRST_SPECIAL;
+                    line = advance_line(
+                        line,
+                        DBG_LINE_BASE + i64::from(adjusted % DBG_LINE_RANGE),
+                        address,
+                    )?;
+                    address = advance_address(address, u64::from(adjusted / DBG_LINE_RANGE))?;
+                    // An entry with a negative line is dropped, the following ones may be fine
+                    if line < 0 {
+                        debug!(target: "debug-info", "negative line number {} at address {}", line, address);
+                        prologue_end = false;
+                        epilogue_begin = false;
+                        continue;
+                    }
+                    positions.push(Position {
+                        address,
+                        line: line as usize,
+                        source_file: source_file.clone(),
+                        prologue_end,
+                        epilogue_begin,
+                    });
+                    prologue_end = false;
+                    epilogue_begin = false;
+                }
+            }
+        }
         Ok((
             Self {
                 line_start,
                 parameter_names,
+                positions,
+                locals,
             },
             *offset,
         ))
     }
 }
 
-impl<'a, S> ctx::TryFromCtx<'a, &super::Dex<S>> for CodeItem
+/// Advances the address register of the debug info state machine.
+fn advance_address(address: uint, advance: u64) -> super::Result<uint> {
+    uint::try_from(advance)
+        .ok()
+        .and_then(|advance| address.checked_add(advance))
+        .ok_or_else(|| {
+            Error::MalFormed(format!(
+                "Address {} advanced by {} overflows",
+                address, advance
+            ))
+        })
+}
+
+/// Advances the line register of the debug info state machine.
+fn advance_line(line: i64, advance: i64, address: uint) -> super::Result<i64> {
+    line.checked_add(advance).ok_or_else(|| {
+        Error::MalFormed(format!(
+            "Line {} advanced by {} overflows at address {}",
+            line, advance, address
+        ))
+    })
+}
+
+/// Reads a `uleb128p1` encoded string index, where `NO_INDEX` is encoded as 0.
+fn read_string<S: AsRef<[u8]>>(
+    source: &[u8],
+    offset: &mut usize,
+    dex: &super::Dex<S>,
+) -> super::Result<Option<DexString>> {
+    Ok(match Uleb128::read(source, offset)? {
+        0 => None,
+        string_id => Some(dex.get_string((string_id - 1) as uint)?),
+    })
+}
+
+/// Types of `this`, for instance methods, and of the parameters of the method of a `CodeItem`.
+pub(crate) type MethodParameters<'p> = (Option<&'p Type>, &'p [Type]);
+
+impl<'a, 'p, S> ctx::TryFromCtx<'a, (&super::Dex<S>, MethodParameters<'p>)> for CodeItem
 where
     S: AsRef<[u8]>,
 {
@@ -198,17 +488,29 @@ where
 
     fn try_from_ctx(
         source: &'a [u8],
-        dex: &super::Dex<S>,
+        (dex, (this, params)): (&super::Dex<S>, MethodParameters<'p>),
     ) -> Result<(Self, Self::Size), Self::Error> {
         let offset = &mut 0;
         let endian = dex.get_endian();
         let registers_size: ushort = source.gread_with(offset, endian)?;
-        let ins_size = source.gread_with(offset, endian)?;
+        let ins_size: ushort = source.gread_with(offset, endian)?;
         let outs_size = source.gread_with(offset, endian)?;
         let tries_size: ushort = source.gread_with(offset, endian)?;
         let debug_info_off = source.gread_with(offset, endian)?;
         let debug_info_item = if debug_info_off != 0 {
-            Some(dex.get_debug_info_item(debug_info_off)?)
+            // The incoming arguments are in the last registers
+            let first = registers_size.checked_sub(ins_size).ok_or_else(|| {
+                Error::MalFormed(format!(
+                    "{} incoming registers out of {}",
+                    ins_size, registers_size
+                ))
+            })?;
+            let parameters = ParameterRegisters {
+                first: uint::from(first),
+                this,
+                params,
+            };
+            Some(dex.get_debug_info_item(debug_info_off, parameters)?)
         } else {
             None
         };
@@ -235,3 +537,125 @@ where
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use scroll::Pread;
+
+    use super::{DebugInfoItem, ParameterRegisters};
+    use crate::error::Error;
+
+    #[test]
+    fn test_debug_info() {
+        let data = std::fs::read("resources/classes.dex").expect("cannot read dex file");
+        let dex = crate::DexReader::from_vec(data).expect("cannot open dex file");
+        let class = dex
+            .find_class_by_name("Lorg/adw/launcher/Launcher;")
+            .expect("cannot load class")
+            .expect("class not found");
+        let method = class
+            .methods()
+            .find(|method| method.name() == "onCreate")
+            .expect("method not found");
+        let debug_info = method
+            .code()
+            .and_then(|code| code.debug_info_item())
+            .expect("no debug info");
+
+        let names = debug_info.parameter_names();
+        assert_eq!(names.len(), 1);
+        assert_eq!(names[0].as_ref().unwrap(), "savedInstanceState");
+
+        let positions = debug_info.positions();
+        assert!(!positions.is_empty());
+        assert!(positions[0].prologue_end());
+        assert!(positions
+            .windows(2)
+            .all(|pair| pair[0].address() <= pair[1].address()));
+        let last = positions.last().unwrap();
+        assert_eq!(
+            debug_info.position_at(last.address() + 1).unwrap().line(),
+            last.line()
+        );
+
+        assert!(debug_info
+            .locals()
+            .iter()
+            .all(|local| !matches!(local.end_address(), Some(end) if end < local.start_address())));
+
+        let code = method.code().unwrap();
+        let this = &debug_info.locals()[0];
+        assert!(this.parameter());
+        assert_eq!(this.name().as_ref().unwrap(), "this");
+        assert_eq!(
+            this.register(),
+            u32::from(code.registers_size() - code.ins_size())
+        );
+        let param = &debug_info.locals()[1];
+        assert!(param.parameter());
+        assert_eq!(param.name().as_ref().unwrap(), "savedInstanceState");
+        assert_eq!(param.register(), this.register() + 1);
+    }
+
+    #[test]
+    fn test_debug_info_state_machine() {
+        let data = std::fs::read("resources/classes.dex").expect("cannot read dex file");
+        let dex = crate::DexReader::from_vec(data).expect("cannot open dex file");
+        let class = dex
+            .find_class_by_name("Lorg/adw/launcher/Launcher;")
+            .expect("cannot load class")
+            .expect("class not found");
+        let method = class
+            .methods()
+            .find(|method| method.name() == "onCreate")
+            .expect("method not found");
+        let parameters = ParameterRegisters {
+            first: 2,
+            this: Some(class.jtype()),
+            params: method.params(),
+        };
+        let decode = |bytes: &[u8]| bytes.pread_with::<DebugInfoItem>(0, (&dex, parameters));
+
+        let debug_info = decode(&[
+            0x0a, 0x01, 0x00, // line 10, an unnamed parameter
+            0x01, 0x02, // address 2
+            0x05, 0x03, // end the parameter
+            0x02, 0x6c, 0x0a, // line -14, dropped
+            0x02, 0x1e, 0x1d, // line 16 at address 3
+            0x06, 0x03, // restart the parameter
+            0x00,
+        ])
+        .expect("cannot decode debug info");
+        let positions = debug_info.positions();
+        assert_eq!(positions.len(), 1);
+        assert_eq!((positions[0].address(), positions[0].line()), (3, 16));
+        let locals = debug_info.locals();
+        assert_eq!(locals.len(), 3);
+        assert_eq!((locals[0].register(), locals[0].end_address()), (2, None));
+        assert_eq!(
+            (locals[1].register(), locals[1].end_address()),
+            (3, Some(2))
+        );
+        assert!(locals[1].name().is_none());
+        assert!(locals[2].restarted() && !locals[2].parameter());
+        assert_eq!((locals[2].register(), locals[2].start_address()), (3, 3));
+        assert_eq!(
+            locals[2].jtype().as_ref().unwrap().type_descriptor(),
+            "Landroid/os/Bundle;"
+        );
+
+        let overflows = [
+            // address over u32::MAX
+            &[
+                0x00, 0x00, 0x01, 0xff, 0xff, 0xff, 0xff, 0x0f, 0x01, 0x01, 0x00,
+            ][..],
+            // line over i64::MAX
+            &[
+                0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f, 0x00, 0x02, 0x01, 0x00,
+            ][..],
+        ];
+        for bytes in overflows.iter() {
+            assert!(matches!(decode(bytes), Err(Error::MalFormed(_))));
+        }
+    }
+}
@@ -13,14 +13,14 @@ use crate::{
         AnnotationItem, AnnotationSetItem, AnnotationSetRefList, AnnotationsDirectoryItem,
     },
     class::{Class, ClassDataItem, ClassDefItem, ClassDefItemIter},
-    code::{CodeItem, DebugInfoItem},
+    code::{CodeItem, DebugInfoItem, ParameterRegisters},
     encoded_value::{EncodedArray, EncodedValue},
     error::{self, Error},
     field::{EncodedField, Field, FieldId, FieldIdItem},
     jtype::{Type, TypeId},
     method::{
-        EncodedMethod, Method, MethodHandleId, MethodHandleItem, MethodId, MethodIdItem, ProtoId,
-        ProtoIdItem,
+        CallSiteId, CallSiteItem, EncodedMethod, Method, MethodHandleId, MethodHandleItem,
+        MethodId, MethodIdItem, ProtoId, ProtoIdItem,
     },
     search::Section,
     source::Source,
@@ -171,6 +171,14 @@ impl DexInner {
     fn method_handles_len(&self) -> Option<uint> {
         self.map_list.get_len(ItemType::MethodHandleItem)
     }
+
+    fn call_site_ids_offset(&self) -> Option<uint> {
+        self.map_list.get_offset(ItemType::CallSiteIdItem)
+    }
+
+    fn call_site_ids_len(&self) -> Option<uint> {
+        self.map_list.get_len(ItemType::CallSiteIdItem)
+    }
 }
 
 // TODO: this should be try_from_dex
@@ -510,6 +518,29 @@ where
         ProtoIdItem::try_from_dex(self, offset)
     }
 
+    /// Returns the types of the parameters of a method prototype.
+    pub fn get_proto_params(&self, proto_item: &ProtoIdItem) -> Result<Vec<Type>> {
+        let params_off = proto_item.params_off();
+        if params_off == 0 {
+            return Ok(Default::default());
+        }
+        if !self.is_offset_in_data_section(params_off) {
+            return Err(Error::BadOffset(
+                params_off as usize,
+                format!(
+                    "Params offset not in data section for proto_item: {:?}",
+                    proto_item
+                ),
+            ));
+        }
+        let offset = &mut (params_off as usize);
+        let source = &self.source;
+        let endian = self.get_endian();
+        let len = source.gread_with::<uint>(offset, endian)?;
+        let type_ids: Vec<ushort> = try_gread_vec_with!(source, offset, len, endian);
+        utils::get_types(self, &type_ids)
+    }
+
     /// Returns the `MethodIdItem` represented by `MethodId`.
     pub fn get_method_item(&self, method_id: MethodId) -> Result<MethodIdItem> {
         let offset = ulong::from(self.inner.method_ids_offset()) + method_id * 8;
@@ -587,6 +618,29 @@ where
         self.source.gread_with(&mut (offset as usize), self)
     }
 
+    /// Returns the `CallSiteItem` represented by the `CallSiteId`, with its bootstrap method.
+    pub fn get_call_site(&self, call_site_id: CallSiteId) -> Result<CallSiteItem> {
+        let err = || Error::InvalidId(format!("Invalid call site id: {}", call_site_id));
+        let offset = self.inner.call_site_ids_offset().ok_or_else(err)?;
+        let len = self.inner.call_site_ids_len().ok_or_else(err)?;
+        if call_site_id >= len {
+            return Err(err());
+        }
+        let call_site_id_off = call_site_id
+            .checked_mul(4)
+            .and_then(|off| off.checked_add(offset))
+            .ok_or_else(|| {
+                Error::MalFormed(format!(
+                    "Call site id {} overflows the call_site_ids section",
+                    call_site_id
+                ))
+            })?;
+        let call_site_off: uint = self
+            .source
+            .pread_with(call_site_id_off as usize, self.get_endian())?;
+        CallSiteItem::try_from_dex(self, call_site_off, call_site_id)
+    }
+
     /// Returns the endianness in the header section.
     pub fn get_endian(&self) -> Endian {
         self.inner.endian()
@@ -633,14 +687,26 @@ where
         })
     }
 
+    /// Iterator over the call sites
+    pub fn call_sites(&self) -> impl Iterator<Item = Result<CallSiteItem>> + '_ {
+        let call_sites_len = self.inner.call_site_ids_len().unwrap_or(0);
+        (0..call_sites_len).map(move |call_site_id| self.get_call_site(call_site_id))
+    }
+
     /// Iterator over the classes
     pub fn classes(&self) -> impl Iterator<Item = Result<Class>> + '_ {
         self.class_defs()
             .map(move |class_def_item| Class::try_from_dex(&self, &class_def_item?))
     }
 
-    /// Returns the `CodeItem` at the offset.
-    pub fn get_code_item(&self, code_off: ulong) -> Result<Option<CodeItem>> {
+    /// Returns the `CodeItem` at the offset, of a method with the given parameters, and `this`
+    /// type if it is not static.
+    pub fn get_code_item(
+        &self,
+        code_off: ulong,
+        this: Option<&Type>,
+        params: &[Type],
+    ) -> Result<Option<CodeItem>> {
         if code_off == 0 {
             return Ok(None);
         }
@@ -650,7 +716,10 @@ where
                 "CodeItem offset not in data section".to_string(),
             ));
         }
-        Ok(Some(self.source.pread_with(code_off as usize, self)?))
+        Ok(Some(
+            self.source
+                .pread_with(code_off as usize, (self, (this, params)))?,
+        ))
     }
 
     /// Returns the `AnnotationItem` at the offset.
@@ -735,7 +804,11 @@ where
     }
 
     /// Returns the `DebugInfoItem` at the offset.
-    pub fn get_debug_info_item(&self, debug_info_off: uint) -> Result<DebugInfoItem> {
+    pub(crate) fn get_debug_info_item(
+        &self,
+        debug_info_off: uint,
+        parameters: ParameterRegisters,
+    ) -> Result<DebugInfoItem> {
         if !self.is_offset_in_data_section(debug_info_off) {
             return Err(Error::BadOffset(
                 debug_info_off as usize,
@@ -743,7 +816,9 @@ where
             ));
         }
 
-        Ok(self.source.pread_with(debug_info_off as usize, self)?)
+        Ok(self
+            .source
+            .pread_with(debug_info_off as usize, (self, parameters))?)
     }
 }
 
@@ -0,0 +1,1015 @@
+//! Decoder for the Dalvik bytecode of a `CodeItem`.
+//! [Android docs](https://source.android.com/docs/core/runtime/dalvik-bytecode)
+//!
+//! Instructions are decoded after their format, so they keep their raw operands: registers,
+//! literals, branch offsets (in code units, relative to the instruction) and indices into the
+//! sections of the dex file. Indices can be resolved with `Index::resolve`.
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+
+use crate::{
+    error::Error,
+    field::{FieldId, FieldIdItem},
+    int,
+    jtype::{Type, TypeId},
+    long,
+    method::{
+        CallSiteId, CallSiteItem, MethodHandleId, MethodHandleItem, MethodId, MethodIdItem,
+        ProtoId, ProtoIdItem,
+    },
+    string::{DexString, StringId},
+    ubyte, uint, ushort, Dex, Result,
+};
+
+/// Identifiers of the payload pseudo-instructions, stored on the first code unit. They share the
+/// `nop` opcode, with a non-zero high byte.
+const PACKED_SWITCH_PAYLOAD: ushort = 0x0100;
+const SPARSE_SWITCH_PAYLOAD: ushort = 0x0200;
+const FILL_ARRAY_DATA_PAYLOAD: ushort = 0x0300;
+
+/// Layout of the operands of an instruction. The name follows the Dalvik docs: number of code
+/// units, number of registers and kind of extra data.
+/// [Android docs](https://source.android.com/docs/core/runtime/instruction-formats)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    Format10x,
+    Format12x,
+    Format11n,
+    Format11x,
+    Format10t,
+    Format20t,
+    Format22x,
+    Format21t,
+    Format21s,
+    Format21h,
+    Format21c,
+    Format23x,
+    Format22b,
+    Format22t,
+    Format22s,
+    Format22c,
+    Format30t,
+    Format32x,
+    Format31i,
+    Format31t,
+    Format31c,
+    Format35c,
+    Format3rc,
+    Format45cc,
+    Format4rcc,
+    Format51l,
+}
+
+impl Format {
+    /// Number of code units taken by instructions of this format.
+    pub fn size(self) -> usize {
+        use Format::*;
+        match self {
+            Format10x | Format12x | Format11n | Format11x | Format10t => 1,
+            Format20t | Format22x | Format21t | Format21s | Format21h | Format21c | Format23x
+            | Format22b | Format22t | Format22s | Format22c => 2,
+            Format30t | Format32x | Format31i | Format31t | Format31c | Format35c | Format3rc => 3,
+            Format45cc | Format4rcc => 4,
+            Format51l => 5,
+        }
+    }
+}
+
+macro_rules! opcodes {
+    ($($variant:ident = $value:expr, $name:expr, $format:ident;)*) => {
+        /// Dalvik opcodes.
+        #[derive(FromPrimitive, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum Opcode {
+            $($variant = $value,)*
+        }
+
+        impl Opcode {
+            /// Mnemonic of the opcode, like `invoke-virtual/range`.
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(Opcode::$variant => $name,)*
+                }
+            }
+
+            /// Format of the instructions with this opcode.
+            pub fn format(self) -> Format {
+                match self {
+                    $(Opcode::$variant => Format::$format,)*
+                }
+            }
+        }
+    };
+}
+
+opcodes! {
+Nop = 0x00, "nop", Format10x;
+Move = 0x01, "move", Format12x;
+MoveFrom16 = 0x02, "move/from16", Format22x;
+Move16 = 0x03, "move/16", Format32x;
+MoveWide = 0x04, "move-wide", Format12x;
+MoveWideFrom16 = 0x05, "move-wide/from16", Format22x;
+MoveWide16 = 0x06, "move-wide/16", Format32x;
+MoveObject = 0x07, "move-object", Format12x;
+MoveObjectFrom16 = 0x08, "move-object/from16", Format22x;
+MoveObject16 = 0x09, "move-object/16", Format32x;
+MoveResult = 0x0a, "move-result", Format11x;
+MoveResultWide = 0x0b, "move-result-wide", Format11x;
+MoveResultObject = 0x0c, "move-result-object", Format11x;
+MoveException = 0x0d, "move-exception", Format11x;
+ReturnVoid = 0x0e, "return-void", Format10x;
+Return = 0x0f, "return", Format11x;
+ReturnWide = 0x10, "return-wide", Format11x;
+ReturnObject = 0x11, "return-object", Format11x;
+Const4 = 0x12, "const/4", Format11n;
+Const16 = 0x13, "const/16", Format21s;
+Const = 0x14, "const", Format31i;
+ConstHigh16 = 0x15, "const/high16", Format21h;
+ConstWide16 = 0x16, "const-wide/16", Format21s;
+ConstWide32 = 0x17, "const-wide/32", Format31i;
+ConstWide = 0x18, "const-wide", Format51l;
+ConstWideHigh16 = 0x19, "const-wide/high16", Format21h;
+ConstString = 0x1a, "const-string", Format21c;
+ConstStringJumbo = 0x1b, "const-string/jumbo", Format31c;
+ConstClass = 0x1c, "const-class", Format21c;
+MonitorEnter = 0x1d, "monitor-enter", Format11x;
+MonitorExit = 0x1e, "monitor-exit", Format11x;
+CheckCast = 0x1f, "check-cast", Format21c;
+InstanceOf = 0x20, "instance-of", Format22c;
+ArrayLength = 0x21, "array-length", Format12x;
+NewInstance = 0x22, "new-instance", Format21c;
+NewArray = 0x23, "new-array", Format22c;
+FilledNewArray = 0x24, "filled-new-array", Format35c;
+FilledNewArrayRange = 0x25, "filled-new-array/range", Format3rc;
+FillArrayData = 0x26, "fill-array-data", Format31t;
+Throw = 0x27, "throw", Format11x;
+Goto = 0x28, "goto", Format10t;
+Goto16 = 0x29, "goto/16", Format20t;
+Goto32 = 0x2a, "goto/32", Format30t;
+PackedSwitch = 0x2b, "packed-switch", Format31t;
+SparseSwitch = 0x2c, "sparse-switch", Format31t;
+CmplFloat = 0x2d, "cmpl-float", Format23x;
+CmpgFloat = 0x2e, "cmpg-float", Format23x;
+CmplDouble = 0x2f, "cmpl-double", Format23x;
+CmpgDouble = 0x30, "cmpg-double", Format23x;
+CmpLong = 0x31, "cmp-long", Format23x;
+IfEq = 0x32, "if-eq", Format22t;
+IfNe = 0x33, "if-ne", Format22t;
+IfLt = 0x34, "if-lt", Format22t;
+IfGe = 0x35, "if-ge", Format22t;
+IfGt = 0x36, "if-gt", Format22t;
+IfLe = 0x37, "if-le", Format22t;
+IfEqz = 0x38, "if-eqz", Format21t;
+IfNez = 0x39, "if-nez", Format21t;
+IfLtz = 0x3a, "if-ltz", Format21t;
+IfGez = 0x3b, "if-gez", Format21t;
+IfGtz = 0x3c, "if-gtz", Format21t;
+IfLez = 0x3d, "if-lez", Format21t;
+Aget = 0x44, "aget", Format23x;
+AgetWide = 0x45, "aget-wide", Format23x;
+AgetObject = 0x46, "aget-object", Format23x;
+AgetBoolean = 0x47, "aget-boolean", Format23x;
+AgetByte = 0x48, "aget-byte", Format23x;
+AgetChar = 0x49, "aget-char", Format23x;
+AgetShort = 0x4a, "aget-short", Format23x;
+Aput = 0x4b, "aput", Format23x;
+AputWide = 0x4c, "aput-wide", Format23x;
+AputObject = 0x4d, "aput-object", Format23x;
+AputBoolean = 0x4e, "aput-boolean", Format23x;
+AputByte = 0x4f, "aput-byte", Format23x;
+AputChar = 0x50, "aput-char", Format23x;
+AputShort = 0x51, "aput-short", Format23x;
+Iget = 0x52, "iget", Format22c;
+IgetWide = 0x53, "iget-wide", Format22c;
+IgetObject = 0x54, "iget-object", Format22c;
+IgetBoolean = 0x55, "iget-boolean", Format22c;
+IgetByte = 0x56, "iget-byte", Format22c;
+IgetChar = 0x57, "iget-char", Format22c;
+IgetShort = 0x58, "iget-short", Format22c;
+Iput = 0x59, "iput", Format22c;
+IputWide = 0x5a, "iput-wide", Format22c;
+IputObject = 0x5b, "iput-object", Format22c;
+IputBoolean = 0x5c, "iput-boolean", Format22c;
+IputByte = 0x5d, "iput-byte", Format22c;
+IputChar = 0x5e, "iput-char", Format22c;
+IputShort = 0x5f, "iput-short", Format22c;
+Sget = 0x60, "sget", Format21c;
+SgetWide = 0x61, "sget-wide", Format21c;
+SgetObject = 0x62, "sget-object", Format21c;
+SgetBoolean = 0x63, "sget-boolean", Format21c;
+SgetByte = 0x64, "sget-byte", Format21c;
+SgetChar = 0x65, "sget-char", Format21c;
+SgetShort = 0x66, "sget-short", Format21c;
+Sput = 0x67, "sput", Format21c;
+SputWide = 0x68, "sput-wide", Format21c;
+SputObject = 0x69, "sput-object", Format21c;
+SputBoolean = 0x6a, "sput-boolean", Format21c;
+SputByte = 0x6b, "sput-byte", Format21c;
+SputChar = 0x6c, "sput-char", Format21c;
+SputShort = 0x6d, "sput-short", Format21c;
+InvokeVirtual = 0x6e, "invoke-virtual", Format35c;
+InvokeSuper = 0x6f, "invoke-super", Format35c;
+InvokeDirect = 0x70, "invoke-direct", Format35c;
+InvokeStatic = 0x71, "invoke-static", Format35c;
+InvokeInterface = 0x72, "invoke-interface", Format35c;
+InvokeVirtualRange = 0x74, "invoke-virtual/range", Format3rc;
+InvokeSuperRange = 0x75, "invoke-super/range", Format3rc;
+InvokeDirectRange = 0x76, "invoke-direct/range", Format3rc;
+InvokeStaticRange = 0x77, "invoke-static/range", Format3rc;
+InvokeInterfaceRange = 0x78, "invoke-interface/range", Format3rc;
+NegInt = 0x7b, "neg-int", Format12x;
+NotInt = 0x7c, "not-int", Format12x;
+NegLong = 0x7d, "neg-long", Format12x;
+NotLong = 0x7e, "not-long", Format12x;
+NegFloat = 0x7f, "neg-float", Format12x;
+NegDouble = 0x80, "neg-double", Format12x;
+IntToLong = 0x81, "int-to-long", Format12x;
+IntToFloat = 0x82, "int-to-float", Format12x;
+IntToDouble = 0x83, "int-to-double", Format12x;
+LongToInt = 0x84, "long-to-int", Format12x;
+LongToFloat = 0x85, "long-to-float", Format12x;
+LongToDouble = 0x86, "long-to-double", Format12x;
+FloatToInt = 0x87, "float-to-int", Format12x;
+FloatToLong = 0x88, "float-to-long", Format12x;
+FloatToDouble = 0x89, "float-to-double", Format12x;
+DoubleToInt = 0x8a, "double-to-int", Format12x;
+DoubleToLong = 0x8b, "double-to-long", Format12x;
+DoubleToFloat = 0x8c, "double-to-float", Format12x;
+IntToByte = 0x8d, "int-to-byte", Format12x;
+IntToChar = 0x8e, "int-to-char", Format12x;
+IntToShort = 0x8f, "int-to-short", Format12x;
+AddInt = 0x90, "add-int", Format23x;
+SubInt = 0x91, "sub-int", Format23x;
+MulInt = 0x92, "mul-int", Format23x;
+DivInt = 0x93, "div-int", Format23x;
+RemInt = 0x94, "rem-int", Format23x;
+AndInt = 0x95, "and-int", Format23x;
+OrInt = 0x96, "or-int", Format23x;
+XorInt = 0x97, "xor-int", Format23x;
+ShlInt = 0x98, "shl-int", Format23x;
+ShrInt = 0x99, "shr-int", Format23x;
+UshrInt = 0x9a, "ushr-int", Format23x;
+AddLong = 0x9b, "add-long", Format23x;
+SubLong = 0x9c, "sub-long", Format23x;
+MulLong = 0x9d, "mul-long", Format23x;
+DivLong = 0x9e, "div-long", Format23x;
+RemLong = 0x9f, "rem-long", Format23x;
+AndLong = 0xa0, "and-long", Format23x;
+OrLong = 0xa1, "or-long", Format23x;
+XorLong = 0xa2, "xor-long", Format23x;
+ShlLong = 0xa3, "shl-long", Format23x;
+ShrLong = 0xa4, "shr-long", Format23x;
+UshrLong = 0xa5, "ushr-long", Format23x;
+AddFloat = 0xa6, "add-float", Format23x;
+SubFloat = 0xa7, "sub-float", Format23x;
+MulFloat = 0xa8, "mul-float", Format23x;
+DivFloat = 0xa9, "div-float", Format23x;
+RemFloat = 0xaa, "rem-float", Format23x;
+AddDouble = 0xab, "add-double", Format23x;
+SubDouble = 0xac, "sub-double", Format23x;
+MulDouble = 0xad, "mul-double", Format23x;
+DivDouble = 0xae, "div-double", Format23x;
+RemDouble = 0xaf, "rem-double", Format23x;
+AddInt2Addr = 0xb0, "add-int/2addr", Format12x;
+SubInt2Addr = 0xb1, "sub-int/2addr", Format12x;
+MulInt2Addr = 0xb2, "mul-int/2addr", Format12x;
+DivInt2Addr = 0xb3, "div-int/2addr", Format12x;
+RemInt2Addr = 0xb4, "rem-int/2addr", Format12x;
+AndInt2Addr = 0xb5, "and-int/2addr", Format12x;
+OrInt2Addr = 0xb6, "or-int/2addr", Format12x;
+XorInt2Addr = 0xb7, "xor-int/2addr", Format12x;
+ShlInt2Addr = 0xb8, "shl-int/2addr", Format12x;
+ShrInt2Addr = 0xb9, "shr-int/2addr", Format12x;
+UshrInt2Addr = 0xba, "ushr-int/2addr", Format12x;
+AddLong2Addr = 0xbb, "add-long/2addr", Format12x;
+SubLong2Addr = 0xbc, "sub-long/2addr", Format12x;
+MulLong2Addr = 0xbd, "mul-long/2addr", Format12x;
+DivLong2Addr = 0xbe, "div-long/2addr", Format12x;
+RemLong2Addr = 0xbf, "rem-long/2addr", Format12x;
+AndLong2Addr = 0xc0, "and-long/2addr", Format12x;
+OrLong2Addr = 0xc1, "or-long/2addr", Format12x;
+XorLong2Addr = 0xc2, "xor-long/2addr", Format12x;
+ShlLong2Addr = 0xc3, "shl-long/2addr", Format12x;
+ShrLong2Addr = 0xc4, "shr-long/2addr", Format12x;
+UshrLong2Addr = 0xc5, "ushr-long/2addr", Format12x;
+AddFloat2Addr = 0xc6, "add-float/2addr", Format12x;
+SubFloat2Addr = 0xc7, "sub-float/2addr", Format12x;
+MulFloat2Addr = 0xc8, "mul-float/2addr", Format12x;
+DivFloat2Addr = 0xc9, "div-float/2addr", Format12x;
+RemFloat2Addr = 0xca, "rem-float/2addr", Format12x;
+AddDouble2Addr = 0xcb, "add-double/2addr", Format12x;
+SubDouble2Addr = 0xcc, "sub-double/2addr", Format12x;
+MulDouble2Addr = 0xcd, "mul-double/2addr", Format12x;
+DivDouble2Addr = 0xce, "div-double/2addr", Format12x;
+RemDouble2Addr = 0xcf, "rem-double/2addr", Format12x;
+AddIntLit16 = 0xd0, "add-int/lit16", Format22s;
+RsubIntLit16 = 0xd1, "rsub-int/lit16", Format22s;
+MulIntLit16 = 0xd2, "mul-int/lit16", Format22s;
+DivIntLit16 = 0xd3, "div-int/lit16", Format22s;
+RemIntLit16 = 0xd4, "rem-int/lit16", Format22s;
+AndIntLit16 = 0xd5, "and-int/lit16", Format22s;
+OrIntLit16 = 0xd6, "or-int/lit16", Format22s;
+XorIntLit16 = 0xd7, "xor-int/lit16", Format22s;
+AddIntLit8 = 0xd8, "add-int/lit8", Format22b;
+RsubIntLit8 = 0xd9, "rsub-int/lit8", Format22b;
+MulIntLit8 = 0xda, "mul-int/lit8", Format22b;
+DivIntLit8 = 0xdb, "div-int/lit8", Format22b;
+RemIntLit8 = 0xdc, "rem-int/lit8", Format22b;
+AndIntLit8 = 0xdd, "and-int/lit8", Format22b;
+OrIntLit8 = 0xde, "or-int/lit8", Format22b;
+XorIntLit8 = 0xdf, "xor-int/lit8", Format22b;
+ShlIntLit8 = 0xe0, "shl-int/lit8", Format22b;
+ShrIntLit8 = 0xe1, "shr-int/lit8", Format22b;
+UshrIntLit8 = 0xe2, "ushr-int/lit8", Format22b;
+InvokePolymorphic = 0xfa, "invoke-polymorphic", Format45cc;
+InvokePolymorphicRange = 0xfb, "invoke-polymorphic/range", Format4rcc;
+InvokeCustom = 0xfc, "invoke-custom", Format35c;
+InvokeCustomRange = 0xfd, "invoke-custom/range", Format3rc;
+ConstMethodHandle = 0xfe, "const-method-handle", Format21c;
+ConstMethodType = 0xff, "const-method-type", Format21c;}
+
+impl Opcode {
+    /// Kind of the index held by instructions with this opcode, if any. `invoke-polymorphic`
+    /// also holds a proto index.
+    fn index(self, index: uint) -> Option<Index> {
+        use Opcode::*;
+        Some(match self {
+            ConstString | ConstStringJumbo => Index::String(index),
+            ConstClass | CheckCast | InstanceOf | NewInstance | NewArray | FilledNewArray
+            | FilledNewArrayRange => Index::Type(index),
+            Iget | IgetWide | IgetObject | IgetBoolean | IgetByte | IgetChar | IgetShort | Iput
+            | IputWide | IputObject | IputBoolean | IputByte | IputChar | IputShort | Sget
+            | SgetWide | SgetObject | SgetBoolean | SgetByte | SgetChar | SgetShort | Sput
+            | SputWide | SputObject | SputBoolean | SputByte | SputChar | SputShort => {
+                Index::Field(FieldId::from(index))
+            }
+            InvokeVirtual
+            | InvokeSuper
+            | InvokeDirect
+            | InvokeStatic
+            | InvokeInterface
+            | InvokeVirtualRange
+            | InvokeSuperRange
+            | InvokeDirectRange
+            | InvokeStaticRange
+            | InvokeInterfaceRange
+            | InvokePolymorphic
+            | InvokePolymorphicRange => Index::Method(MethodId::from(index)),
+            InvokeCustom | InvokeCustomRange => Index::CallSite(index),
+            ConstMethodHandle => Index::MethodHandle(index),
+            ConstMethodType => Index::Proto(ProtoId::from(index)),
+            _ => return None,
+        })
+    }
+}
+
+/// Index into one of the sections of the dex file, held by an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Index {
+    String(StringId),
+    Type(TypeId),
+    Field(FieldId),
+    Method(MethodId),
+    Proto(ProtoId),
+    MethodHandle(MethodHandleId),
+    CallSite(CallSiteId),
+}
+
+/// Item an `Index` points to.
+#[derive(Debug)]
+pub enum ResolvedIndex {
+    String(DexString),
+    Type(Type),
+    Field(FieldIdItem),
+    Method(MethodIdItem),
+    Proto(ProtoIdItem),
+    MethodHandle(MethodHandleItem),
+    CallSite(CallSiteItem),
+}
+
+impl Index {
+    /// Returns the item of the dex file this index points to.
+    pub fn resolve<S: AsRef<[u8]>>(self, dex: &Dex<S>) -> Result<ResolvedIndex> {
+        Ok(match self {
+            Index::String(id) => ResolvedIndex::String(dex.get_string(id)?),
+            Index::Type(id) => ResolvedIndex::Type(dex.get_type(id)?),
+            Index::Field(id) => ResolvedIndex::Field(dex.get_field_item(id)?),
+            Index::Method(id) => ResolvedIndex::Method(dex.get_method_item(id)?),
+            Index::Proto(id) => ResolvedIndex::Proto(dex.get_proto_item(id)?),
+            Index::MethodHandle(id) => ResolvedIndex::MethodHandle(dex.get_method_handle_item(id)?),
+            Index::CallSite(id) => ResolvedIndex::CallSite(dex.get_call_site(id)?),
+        })
+    }
+}
+
+/// A decoded instruction. The operands are named after the Dalvik docs: `a`, `b` and `c` are
+/// registers unless stated otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Instruction {
+    /// `op`
+    Format10x { opcode: Opcode },
+    /// `op vA, vB`
+    Format12x { opcode: Opcode, a: ubyte, b: ubyte },
+    /// `op vA, #+B`
+    Format11n {
+        opcode: Opcode,
+        a: ubyte,
+        literal: int,
+    },
+    /// `op vAA`
+    Format11x { opcode: Opcode, a: ubyte },
+    /// `op +AA`
+    Format10t { opcode: Opcode, offset: int },
+    /// `op +AAAA`
+    Format20t { opcode: Opcode, offset: int },
+    /// `op vAA, vBBBB`
+    Format22x { opcode: Opcode, a: ubyte, b: ushort },
+    /// `op vAA, +BBBB`
+    Format21t {
+        opcode: Opcode,
+        a: ubyte,
+        offset: int,
+    },
+    /// `op vAA, #+BBBB`
+    Format21s {
+        opcode: Opcode,
+        a: ubyte,
+        literal: int,
+    },
+    /// `op vAA, #+BBBB0000` or `op vAA, #+BBBB000000000000`. The literal is already shifted.
+    Format21h {
+        opcode: Opcode,
+        a: ubyte,
+        literal: long,
+    },
+    /// `op vAA, kind@BBBB`
+    Format21c {
+        opcode: Opcode,
+        a: ubyte,
+        index: Index,
+    },
+    /// `op vAA, vBB, vCC`
+    Format23x {
+        opcode: Opcode,
+        a: ubyte,
+        b: ubyte,
+        c: ubyte,
+    },
+    /// `op vAA, vBB, #+CC`
+    Format22b {
+        opcode: Opcode,
+        a: ubyte,
+        b: ubyte,
+        literal: int,
+    },
+    /// `op vA, vB, +CCCC`
+    Format22t {
+        opcode: Opcode,
+        a: ubyte,
+        b: ubyte,
+        offset: int,
+    },
+    /// `op vA, vB, #+CCCC`
+    Format22s {
+        opcode: Opcode,
+        a: ubyte,
+        b: ubyte,
+        literal: int,
+    },
+    /// `op vA, vB, kind@CCCC`
+    Format22c {
+        opcode: Opcode,
+        a: ubyte,
+        b: ubyte,
+        index: Index,
+    },
+    /// `op +AAAAAAAA`
+    Format30t { opcode: Opcode, offset: int },
+    /// `op vAAAA, vBBBB`
+    Format32x {
+        opcode: Opcode,
+        a: ushort,
+        b: ushort,
+    },
+    /// `op vAA, #+BBBBBBBB`
+    Format31i {
+        opcode: Opcode,
+        a: ubyte,
+        literal: int,
+    },
+    /// `op vAA, +BBBBBBBB`, pointing to a payload
+    Format31t {
+        opcode: Opcode,
+        a: ubyte,
+        offset: int,
+    },
+    /// `op vAA, string@BBBBBBBB`
+    Format31c {
+        opcode: Opcode,
+        a: ubyte,
+        index: Index,
+    },
+    /// `op {vC, vD, vE, vF, vG}, kind@BBBB`
+    Format35c {
+        opcode: Opcode,
+        registers: Vec<ubyte>,
+        index: Index,
+    },
+    /// `op {vCCCC .. vNNNN}, kind@BBBB`
+    Format3rc {
+        opcode: Opcode,
+        first_register: ushort,
+        register_count: ubyte,
+        index: Index,
+    },
+    /// `op {vC, vD, vE, vF, vG}, meth@BBBB, proto@HHHH`
+    Format45cc {
+        opcode: Opcode,
+        registers: Vec<ubyte>,
+        method: Index,
+        proto: Index,
+    },
+    /// `op {vCCCC .. vNNNN}, meth@BBBB, proto@HHHH`
+    Format4rcc {
+        opcode: Opcode,
+        first_register: ushort,
+        register_count: ubyte,
+        method: Index,
+        proto: Index,
+    },
+    /// `op vAA, #+BBBBBBBBBBBBBBBB`
+    Format51l {
+        opcode: Opcode,
+        a: ubyte,
+        literal: long,
+    },
+    /// Targets of a `packed-switch`, for the consecutive keys starting at `first_key`. The
+    /// targets are relative to the `packed-switch` instruction.
+    PackedSwitchPayload { first_key: int, targets: Vec<int> },
+    /// Keys of a `sparse-switch`, sorted, and their targets relative to the `sparse-switch`
+    /// instruction.
+    SparseSwitchPayload { keys: Vec<int>, targets: Vec<int> },
+    /// Elements of the array filled by `fill-array-data`, as little endian bytes.
+    FillArrayDataPayload {
+        element_width: ushort,
+        data: Vec<ubyte>,
+    },
+}
+
+impl Instruction {
+    /// Decodes the instruction that starts at the given code unit.
+    pub fn decode(insns: &[ushort], offset: usize) -> Result<Self> {
+        let unit = |i: usize| {
+            insns
+                .get(offset + i)
+                .copied()
+                .ok_or_else(|| Error::MalFormed(format!("Truncated instruction at {}", offset)))
+        };
+        let int_at = |i: usize| -> Result<int> {
+            Ok((uint::from(unit(i)?) | uint::from(unit(i + 1)?) << 16) as int)
+        };
+
+        let first = unit(0)?;
+        match first {
+            PACKED_SWITCH_PAYLOAD => {
+                let size = usize::from(unit(1)?);
+                let first_key = int_at(2)?;
+                let targets = (0..size)
+                    .map(|i| int_at(4 + i * 2))
+                    .collect::<Result<_>>()?;
+                return Ok(Instruction::PackedSwitchPayload { first_key, targets });
+            }
+            SPARSE_SWITCH_PAYLOAD => {
+                let size = usize::from(unit(1)?);
+                let keys = (0..size)
+                    .map(|i| int_at(2 + i * 2))
+                    .collect::<Result<_>>()?;
+                let targets = (0..size)
+                    .map(|i| int_at(2 + (size + i) * 2))
+                    .collect::<Result<_>>()?;
+                return Ok(Instruction::SparseSwitchPayload { keys, targets });
+            }
+            FILL_ARRAY_DATA_PAYLOAD => {
+                let element_width = unit(1)?;
+                let size = int_at(2)? as uint as usize;
+                let len = size
+                    .checked_mul(usize::from(element_width))
+                    .filter(|len| len / 2 < insns.len())
+                    .ok_or_else(|| {
+                        Error::MalFormed(format!("Invalid array data size at {}", offset))
+                    })?;
+                let data = (0..len)
+                    .map(|i| Ok(unit(4 + i / 2)?.to_le_bytes()[i % 2]))
+                    .collect::<Result<_>>()?;
+                return Ok(Instruction::FillArrayDataPayload {
+                    element_width,
+                    data,
+                });
+            }
+            _ => (),
+        }
+
+        let opcode = Opcode::from_u16(first & 0xff).ok_or_else(|| {
+            Error::MalFormed(format!(
+                "Unknown opcode {:#04x} at {}",
+                first & 0xff,
+                offset
+            ))
+        })?;
+        let index = |value: uint| {
+            opcode.index(value).ok_or_else(|| {
+                Error::MalFormed(format!("{} does not take an index", opcode.name()))
+            })
+        };
+        let aa = (first >> 8) as ubyte;
+        let a = aa & 0xf;
+        let b = aa >> 4;
+
+        Ok(match opcode.format() {
+            Format::Format10x => Instruction::Format10x { opcode },
+            Format::Format12x => Instruction::Format12x { opcode, a, b },
+            Format::Format11n => Instruction::Format11n {
+                opcode,
+                a,
+                // Sign extends the high nibble
+                literal: int::from((aa as i8) >> 4),
+            },
+            Format::Format11x => Instruction::Format11x { opcode, a: aa },
+            Format::Format10t => Instruction::Format10t {
+                opcode,
+                offset: int::from(aa as i8),
+            },
+            Format::Format20t => Instruction::Format20t {
+                opcode,
+                offset: int::from(unit(1)? as i16),
+            },
+            Format::Format22x => Instruction::Format22x {
+                opcode,
+                a: aa,
+                b: unit(1)?,
+            },
+            Format::Format21t => Instruction::Format21t {
+                opcode,
+                a: aa,
+                offset: int::from(unit(1)? as i16),
+            },
+            Format::Format21s => Instruction::Format21s {
+                opcode,
+                a: aa,
+                literal: int::from(unit(1)? as i16),
+            },
+            Format::Format21h => {
+                let high = long::from(unit(1)? as i16);
+                Instruction::Format21h {
+                    opcode,
+                    a: aa,
+                    literal: if opcode == Opcode::ConstHigh16 {
+                        high << 16
+                    } else {
+                        high << 48
+                    },
+                }
+            }
+            Format::Format21c => Instruction::Format21c {
+                opcode,
+                a: aa,
+                index: index(uint::from(unit(1)?))?,
+            },
+            Format::Format23x => {
+                let bc = unit(1)?;
+                Instruction::Format23x {
+                    opcode,
+                    a: aa,
+                    b: bc as ubyte,
+                    c: (bc >> 8) as ubyte,
+                }
+            }
+            Format::Format22b => {
+                let bc = unit(1)?;
+                Instruction::Format22b {
+                    opcode,
+                    a: aa,
+                    b: bc as ubyte,
+                    literal: int::from((bc >> 8) as i8),
+                }
+            }
+            Format::Format22t => Instruction::Format22t {
+                opcode,
+                a,
+                b,
+                offset: int::from(unit(1)? as i16),
+            },
+            Format::Format22s => Instruction::Format22s {
+                opcode,
+                a,
+                b,
+                literal: int::from(unit(1)? as i16),
+            },
+            Format::Format22c => Instruction::Format22c {
+                opcode,
+                a,
+                b,
+                index: index(uint::from(unit(1)?))?,
+            },
+            Format::Format30t => Instruction::Format30t {
+                opcode,
+                offset: int_at(1)?,
+            },
+            Format::Format32x => Instruction::Format32x {
+                opcode,
+                a: unit(1)?,
+                b: unit(2)?,
+            },
+            Format::Format31i => Instruction::Format31i {
+                opcode,
+                a: aa,
+                literal: int_at(1)?,
+            },
+            Format::Format31t => Instruction::Format31t {
+                opcode,
+                a: aa,
+                offset: int_at(1)?,
+            },
+            Format::Format31c => Instruction::Format31c {
+                opcode,
+                a: aa,
+                index: index(int_at(1)? as uint)?,
+            },
+            Format::Format35c => Instruction::Format35c {
+                opcode,
+                registers: registers(aa, unit(2)?, offset)?,
+                index: index(uint::from(unit(1)?))?,
+            },
+            Format::Format3rc => Instruction::Format3rc {
+                opcode,
+                first_register: unit(2)?,
+                register_count: aa,
+                index: index(uint::from(unit(1)?))?,
+            },
+            Format::Format45cc => Instruction::Format45cc {
+                opcode,
+                registers: registers(aa, unit(2)?, offset)?,
+                method: index(uint::from(unit(1)?))?,
+                proto: Index::Proto(ProtoId::from(unit(3)?)),
+            },
+            Format::Format4rcc => Instruction::Format4rcc {
+                opcode,
+                first_register: unit(2)?,
+                register_count: aa,
+                method: index(uint::from(unit(1)?))?,
+                proto: Index::Proto(ProtoId::from(unit(3)?)),
+            },
+            Format::Format51l => Instruction::Format51l {
+                opcode,
+                a: aa,
+                literal: (0..4).try_fold(0, |literal, i| {
+                    Ok::<_, Error>(literal | long::from(unit(1 + i)?) << (16 * i))
+                })?,
+            },
+        })
+    }
+
+    /// Opcode of the instruction, or `None` for payloads.
+    pub fn opcode(&self) -> Option<Opcode> {
+        use Instruction::*;
+        match self {
+            Format10x { opcode }
+            | Format12x { opcode, .. }
+            | Format11n { opcode, .. }
+            | Format11x { opcode, .. }
+            | Format10t { opcode, .. }
+            | Format20t { opcode, .. }
+            | Format22x { opcode, .. }
+            | Format21t { opcode, .. }
+            | Format21s { opcode, .. }
+            | Format21h { opcode, .. }
+            | Format21c { opcode, .. }
+            | Format23x { opcode, .. }
+            | Format22b { opcode, .. }
+            | Format22t { opcode, .. }
+            | Format22s { opcode, .. }
+            | Format22c { opcode, .. }
+            | Format30t { opcode, .. }
+            | Format32x { opcode, .. }
+            | Format31i { opcode, .. }
+            | Format31t { opcode, .. }
+            | Format31c { opcode, .. }
+            | Format35c { opcode, .. }
+            | Format3rc { opcode, .. }
+            | Format45cc { opcode, .. }
+            | Format4rcc { opcode, .. }
+            | Format51l { opcode, .. } => Some(*opcode),
+            PackedSwitchPayload { .. }
+            | SparseSwitchPayload { .. }
+            | FillArrayDataPayload { .. } => None,
+        }
+    }
+
+    /// Mnemonic of the instruction, like `const-string` or `packed-switch-payload`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Instruction::PackedSwitchPayload { .. } => "packed-switch-payload",
+            Instruction::SparseSwitchPayload { .. } => "sparse-switch-payload",
+            Instruction::FillArrayDataPayload { .. } => "fill-array-data-payload",
+            _ => self.opcode().map(Opcode::name).unwrap_or_default(),
+        }
+    }
+
+    /// Number of code units taken by the instruction.
+    pub fn size(&self) -> usize {
+        match self {
+            Instruction::PackedSwitchPayload { targets, .. } => 4 + targets.len() * 2,
+            Instruction::SparseSwitchPayload { keys, .. } => 2 + keys.len() * 4,
+            Instruction::FillArrayDataPayload { data, .. } => 4 + data.len().div_ceil(2),
+            _ => self.opcode().map_or(0, |opcode| opcode.format().size()),
+        }
+    }
+
+    /// Index held by the instruction: the method index for `invoke-polymorphic`.
+    pub fn index(&self) -> Option<Index> {
+        match self {
+            Instruction::Format21c { index, .. }
+            | Instruction::Format22c { index, .. }
+            | Instruction::Format31c { index, .. }
+            | Instruction::Format35c { index, .. }
+            | Instruction::Format3rc { index, .. }
+            | Instruction::Format45cc { method: index, .. }
+            | Instruction::Format4rcc { method: index, .. } => Some(*index),
+            _ => None,
+        }
+    }
+
+    /// Branch offset of the instruction, relative to its address. For `packed-switch`,
+    /// `sparse-switch` and `fill-array-data` it points to their payload.
+    pub fn branch_offset(&self) -> Option<int> {
+        match self {
+            Instruction::Format10t { offset, .. }
+            | Instruction::Format20t { offset, .. }
+            | Instruction::Format21t { offset, .. }
+            | Instruction::Format22t { offset, .. }
+            | Instruction::Format30t { offset, .. }
+            | Instruction::Format31t { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
+}
+
+/// Registers of a 35c or 45cc instruction: `A|G` holds the count and the fifth register, and
+/// `F|E|D|C` the first four.
+fn registers(ag: ubyte, fedc: ushort, offset: usize) -> Result<Vec<ubyte>> {
+    let count = usize::from(ag >> 4);
+    if count > 5 {
+        return Err(Error::MalFormed(format!(
+            "Invalid register count {} at {}",
+            count, offset
+        )));
+    }
+    let all = [
+        (fedc & 0xf) as ubyte,
+        ((fedc >> 4) & 0xf) as ubyte,
+        ((fedc >> 8) & 0xf) as ubyte,
+        (fedc >> 12) as ubyte,
+        ag & 0xf,
+    ];
+    Ok(all[..count].to_vec())
+}
+
+/// Iterator over the instructions of a method, along their address in code units. It stops after
+/// the first instruction that can not be decoded.
+#[derive(Debug, Clone)]
+pub struct Instructions<'a> {
+    insns: &'a [ushort],
+    offset: usize,
+}
+
+impl<'a> Instructions<'a> {
+    pub fn new(insns: &'a [ushort]) -> Self {
+        Self { insns, offset: 0 }
+    }
+}
+
+impl<'a> Iterator for Instructions<'a> {
+    type Item = Result<(usize, Instruction)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.insns.len() {
+            return None;
+        }
+        let address = self.offset;
+        match Instruction::decode(self.insns, address) {
+            Ok(instruction) => {
+                self.offset += instruction.size();
+                Some(Ok((address, instruction)))
+            }
+            Err(e) => {
+                self.offset = self.insns.len();
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Index, Instruction, Instructions, Opcode};
+
+    #[test]
+    fn test_decode_instructions() {
+        let insns = [
+            0x1212, // const/4 v2, #+1
+            0x021a, 0x0005, // const-string v2, string@5
+            0x2070, 0x0003, 0x0010, // invoke-direct {v0, v1}, meth@3
+            0x1132, 0xfffe, // if-eq v1, v1, -2
+            0x0018, 0x5678, 0x1234, 0x0000, 0x8000, // const-wide v0, #0x8000000012345678
+            0x000e, // return-void
+        ];
+        let instructions = Instructions::new(&insns)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(instructions.len(), 6);
+        assert_eq!(
+            instructions[0].1,
+            Instruction::Format11n {
+                opcode: Opcode::Const4,
+                a: 2,
+                literal: 1,
+            }
+        );
+        assert_eq!(instructions[1].1.index(), Some(Index::String(5)));
+        assert_eq!(
+            instructions[2],
+            (
+                3,
+                Instruction::Format35c {
+                    opcode: Opcode::InvokeDirect,
+                    registers: vec![0, 1],
+                    index: Index::Method(3),
+                }
+            )
+        );
+        assert_eq!(instructions[3].1.branch_offset(), Some(-2));
+        assert_eq!(
+            instructions[4].1,
+            Instruction::Format51l {
+                opcode: Opcode::ConstWide,
+                a: 0,
+                literal: 0x8000_0000_1234_5678_u64 as i64,
+            }
+        );
+        assert_eq!(
+            instructions[5],
+            (
+                13,
+                Instruction::Format10x {
+                    opcode: Opcode::ReturnVoid
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_decode_payloads() {
+        let insns = [
+            0x002b, 0x0004, 0x0000, // packed-switch v0, +4
+            0x0000, // nop, to align the payload
+            0x0100, 0x0002, 0xffff, 0xffff, // packed-switch-payload, 2 keys from -1
+            0x0010, 0x0000, 0x0020, 0x0000, // its targets
+            0x0300, 0x0001, 0x0003, 0x0000, // fill-array-data-payload, 3 bytes
+            0x0201, 0x0003, // its data
+        ];
+        let instructions = Instructions::new(&insns)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(instructions.len(), 4);
+        assert_eq!(instructions[0].1.branch_offset(), Some(4));
+        assert_eq!(
+            instructions[2],
+            (
+                4,
+                Instruction::PackedSwitchPayload {
+                    first_key: -1,
+                    targets: vec![0x10, 0x20],
+                }
+            )
+        );
+        assert_eq!(
+            instructions[3],
+            (
+                12,
+                Instruction::FillArrayDataPayload {
+                    element_width: 1,
+                    data: vec![1, 2, 3],
+                }
+            )
+        );
+        assert_eq!(instructions[3].1.size(), 6);
+    }
+
+    #[test]
+    fn test_reject_unknown_opcodes() {
+        assert!(Instruction::decode(&[0x003e], 0).is_err());
+        // Truncated const-string
+        assert!(Instruction::decode(&[0x001a], 0).is_err());
+    }
+}
@@ -31,9 +31,12 @@ mod encoded_item;
 pub mod encoded_value;
 mod error;
 pub mod field;
+pub mod instruction;
 pub mod jtype;
 pub mod method;
+pub mod profile;
 mod search;
+pub mod smali;
 mod source;
 pub mod string;
 
@@ -8,6 +8,7 @@ use crate::{
     annotation::{AnnotationSetItem, AnnotationSetRefList},
     code::CodeItem,
     encoded_item::{EncodedItem, EncodedItemArray},
+    encoded_value::{EncodedArray, EncodedValue},
     error::Error,
     field::FieldId,
     jtype::{Type, TypeId},
@@ -132,7 +133,6 @@ impl Method {
         param_annotations: AnnotationSetRefList,
     ) -> super::Result<Method> {
         debug!(target: "method", "encoded method: {:?}", encoded_method);
-        let source = &dex.source;
         let method_item = dex.get_method_item(encoded_method.method_id)?;
         let name = dex.get_string(method_item.name_idx)?;
         debug!(target: "method", "name: {}, method id item: {:?}", name, method_item);
@@ -140,35 +140,26 @@ impl Method {
         debug!(target: "method", "method proto_item: {:?}", proto_item);
         let shorty = dex.get_string(proto_item.shorty)?;
         let return_type = dex.get_type(proto_item.return_type)?;
-        let params = if proto_item.params_off != 0 {
-            if !dex.is_offset_in_data_section(proto_item.params_off) {
-                return Err(Error::BadOffset(
-                    proto_item.params_off as usize,
-                    format!(
-                        "Params offset not in data section for proto_item: {:?}",
-                        proto_item
-                    ),
-                ));
-            }
-            let offset = &mut (proto_item.params_off as usize);
-            let endian = dex.get_endian();
-            let len = source.gread_with::<uint>(offset, endian)?;
-            let type_ids: Vec<ushort> = try_gread_vec_with!(source, offset, len, endian);
-            utils::get_types(dex, &type_ids)?
-        } else {
-            Default::default()
-        };
+        let params = dex.get_proto_params(&proto_item)?;
         debug!(target: "method", "code item offset: {}", encoded_method.code_offset);
-        let code = dex.get_code_item(encoded_method.code_offset)?;
-        Ok(Self {
-            name,
-            class: dex.get_type(TypeId::from(method_item.class_idx))?,
-            access_flags: AccessFlags::from_bits(encoded_method.access_flags).ok_or_else(|| {
+        let class = dex.get_type(TypeId::from(method_item.class_idx))?;
+        let access_flags =
+            AccessFlags::from_bits(encoded_method.access_flags).ok_or_else(|| {
                 Error::InvalidId(format!(
                     "Invalid access flags for method {}",
                     method_item.name_idx
                 ))
-            })?,
+            })?;
+        let this = if access_flags.contains(AccessFlags::STATIC) {
+            None
+        } else {
+            Some(&class)
+        };
+        let code = dex.get_code_item(encoded_method.code_offset, this, &params)?;
+        Ok(Self {
+            name,
+            class,
+            access_flags,
             shorty,
             return_type,
             params,
@@ -328,3 +319,64 @@ impl<'a, S: AsRef<[u8]>> ctx::TryFromCtx<'a, &super::Dex<S>> for MethodHandleIte
         Ok((Self { handle_type, id }, *offset))
     }
 }
+
+/// Index into the `CallSiteIdItem`s list.
+pub type CallSiteId = uint;
+
+/// A call site of `invoke-custom` instructions, linked at runtime by its bootstrap method.
+/// [Android docs](https://source.android.com/devices/tech/dalvik/dex-format#call-site-item)
+#[derive(Debug, Getters, CopyGetters)]
+pub struct CallSiteItem {
+    /// `CallSiteId` of this call site.
+    #[get_copy = "pub"]
+    id: CallSiteId,
+    /// Bootstrap method linking the call site, like `LambdaMetafactory.metafactory`.
+    #[get = "pub"]
+    bootstrap_method: MethodHandleItem,
+    /// Name of the method the call site is linked to.
+    #[get = "pub"]
+    method_name: DexString,
+    /// Type of the method the call site is linked to.
+    #[get = "pub"]
+    method_type: ProtoIdItem,
+    /// Extra arguments passed to the bootstrap method.
+    #[get = "pub"]
+    arguments: Vec<EncodedValue>,
+}
+
+impl CallSiteItem {
+    pub(crate) fn try_from_dex<S: AsRef<[u8]>>(
+        dex: &super::Dex<S>,
+        call_site_off: uint,
+        id: CallSiteId,
+    ) -> super::Result<Self> {
+        if !dex.is_offset_in_data_section(call_site_off) {
+            return Err(Error::BadOffset(
+                call_site_off as usize,
+                format!("Call site offset not in data section for call site: {}", id),
+            ));
+        }
+        let values: EncodedArray = dex.source.pread_with(call_site_off as usize, dex)?;
+        let mut values = values.into_inner().into_iter();
+        let malformed = |what: &str| Error::MalFormed(format!("Call site {} has no {}", id, what));
+        let bootstrap_method = match values.next() {
+            Some(EncodedValue::MethodHandle(handle)) => handle,
+            _ => return Err(malformed("bootstrap method handle")),
+        };
+        let method_name = match values.next() {
+            Some(EncodedValue::String(name)) => name,
+            _ => return Err(malformed("method name")),
+        };
+        let method_type = match values.next() {
+            Some(EncodedValue::MethodType(proto)) => proto,
+            _ => return Err(malformed("method type")),
+        };
+        Ok(Self {
+            id,
+            bootstrap_method,
+            method_name,
+            method_type,
+            arguments: values.collect(),
+        })
+    }
+}
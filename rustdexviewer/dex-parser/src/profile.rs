@@ -0,0 +1,766 @@
+//! Decoder for the ART baseline profiles shipped on APKs (`assets/dexopt/baseline.prof`) and their
+//! metadata (`assets/dexopt/baseline.profm`).
+//! [ART sources](https://android.googlesource.com/platform/art/+/refs/heads/main/libprofile/profile/profile_compilation_info.cc)
+use std::collections::BTreeMap;
+
+use getset::{CopyGetters, Getters};
+use scroll::{Pread, LE};
+
+use crate::{
+    error::Error,
+    jtype::TypeId,
+    method::{MethodId, ProtoId},
+    uint, ushort, Dex, Result,
+};
+
+const PROFILE_MAGIC: &[u8] = b"pro\0";
+const METADATA_MAGIC: &[u8] = b"prm\0";
+const VERSION_P: &[u8] = b"010\0";
+const VERSION_S: &[u8] = b"015\0";
+const METADATA_VERSION_002: &[u8] = b"002\0";
+
+const SECTION_DEX_FILES: uint = 0;
+const SECTION_EXTRA_DESCRIPTORS: uint = 1;
+const SECTION_CLASSES: uint = 2;
+const SECTION_METHODS: uint = 3;
+
+const INLINE_CACHE_MISSING_TYPES: u8 = 6;
+const INLINE_CACHE_MEGAMORPHIC: u8 = 7;
+
+/// Upper bound of the inflated size of a profile, to reject corrupted files.
+const MAX_INFLATED_SIZE: usize = 64 * 1024 * 1024;
+
+bitflags! {
+    /// How a method was used while the profile was recorded.
+    pub struct MethodFlags: ushort {
+        /// The method was executed often enough to be compiled.
+        const HOT = 0x1;
+        /// The method was executed during startup.
+        const STARTUP = 0x2;
+        /// The method was executed after startup.
+        const POST_STARTUP = 0x4;
+    }
+}
+
+/// Version of the profile format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfileVersion {
+    /// `0.1.0`, used from Android P. This is the version written by the Android Gradle plugin.
+    P,
+    /// `0.1.5`, used from Android S.
+    S,
+}
+
+/// A decoded baseline profile.
+#[derive(Debug, Getters, CopyGetters)]
+pub struct Profile {
+    #[get_copy = "pub"]
+    version: ProfileVersion,
+    /// Profile of each dex file of the APK.
+    #[get = "pub"]
+    dex_files: Vec<DexProfile>,
+    /// Descriptors of the classes that are not on the type ids of their dex file. Type indices
+    /// above the number of type ids of a dex file point here.
+    #[get = "pub"]
+    extra_descriptors: Vec<String>,
+}
+
+/// Profile data of a single dex file.
+#[derive(Debug, Clone, Getters, CopyGetters)]
+pub struct DexProfile {
+    /// Name of the dex file, like `classes2.dex` or `base.apk!classes2.dex`.
+    #[get = "pub"]
+    profile_key: String,
+    /// Checksum of the dex file the profile was recorded for.
+    #[get_copy = "pub"]
+    checksum: uint,
+    /// Number of type ids of the dex file. Only known on `S` profiles, or once the metadata is
+    /// applied.
+    #[get_copy = "pub"]
+    num_type_ids: uint,
+    #[get_copy = "pub"]
+    num_method_ids: uint,
+    /// Classes loaded during startup.
+    #[get = "pub"]
+    classes: Vec<TypeId>,
+    /// Flags of each profiled method.
+    #[get = "pub"]
+    methods: BTreeMap<MethodId, MethodFlags>,
+}
+
+/// Metadata of a baseline profile (`baseline.profm`). The Android Gradle plugin stores on it
+/// the number of type ids and the startup classes of each dex file, which the `P` version of the
+/// profile can not hold.
+#[derive(Debug, Getters)]
+pub struct ProfileMetadata {
+    #[get = "pub"]
+    dex_files: Vec<DexMetadata>,
+}
+
+/// Metadata of a single dex file.
+#[derive(Debug, Clone, Getters, CopyGetters)]
+pub struct DexMetadata {
+    #[get = "pub"]
+    profile_key: String,
+    #[get_copy = "pub"]
+    num_type_ids: uint,
+    #[get = "pub"]
+    classes: Vec<TypeId>,
+}
+
+/// A dex profile whose classes and methods were resolved against its dex file.
+#[derive(Debug, Getters)]
+pub struct ResolvedProfile {
+    /// Descriptors of the startup classes.
+    #[get = "pub"]
+    classes: Vec<String>,
+    #[get = "pub"]
+    methods: Vec<ProfileMethod>,
+}
+
+/// A profiled method.
+#[derive(Debug, Getters, CopyGetters)]
+pub struct ProfileMethod {
+    #[get_copy = "pub"]
+    id: MethodId,
+    /// Name of the method, like `Lcom/example/Main;->onCreate(Landroid/os/Bundle;)V`.
+    #[get = "pub"]
+    name: String,
+    #[get_copy = "pub"]
+    flags: MethodFlags,
+}
+
+impl Profile {
+    /// Decodes a `baseline.prof` file.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < 8 || &data[..4] != PROFILE_MAGIC {
+            return Err(Error::MalFormed("not a baseline profile".to_string()));
+        }
+
+        match &data[4..8] {
+            VERSION_P => parse_p(data),
+            VERSION_S => parse_s(data),
+            version => Err(Error::MalFormed(format!(
+                "unsupported profile version: {}",
+                String::from_utf8_lossy(version)
+            ))),
+        }
+    }
+
+    /// Completes the profile with the data of its `baseline.profm` file.
+    pub fn apply_metadata(&mut self, metadata: &ProfileMetadata) {
+        for dex_file in &mut self.dex_files {
+            let dex_metadata = metadata
+                .dex_files
+                .iter()
+                .find(|m| dex_name(&m.profile_key) == dex_name(&dex_file.profile_key));
+
+            if let Some(dex_metadata) = dex_metadata {
+                dex_file.num_type_ids = dex_metadata.num_type_ids;
+                if dex_file.classes.is_empty() {
+                    dex_file.classes = dex_metadata.classes.clone();
+                }
+            }
+        }
+    }
+
+    /// Returns the profile of the given dex file, found by its checksum.
+    pub fn find_dex_profile<T: AsRef<[u8]>>(&self, dex: &Dex<T>) -> Option<&DexProfile> {
+        let checksum = dex.header().checksum();
+
+        self.dex_files.iter().find(|d| d.checksum == checksum)
+    }
+
+    /// Resolves the classes and methods of the profile of the given dex file, if the profile
+    /// has one for it.
+    pub fn resolve<T: AsRef<[u8]>>(&self, dex: &Dex<T>) -> Result<Option<ResolvedProfile>> {
+        match self.find_dex_profile(dex) {
+            Some(dex_profile) => Ok(Some(dex_profile.resolve(dex, &self.extra_descriptors)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+impl DexProfile {
+    /// Resolves the classes and methods of the profile against its dex file.
+    pub fn resolve<T: AsRef<[u8]>>(
+        &self,
+        dex: &Dex<T>,
+        extra_descriptors: &[String],
+    ) -> Result<ResolvedProfile> {
+        let classes = self
+            .classes
+            .iter()
+            .map(|type_id| {
+                // Types out of the dex file are described on the profile itself
+                let type_ids = dex.header().type_ids_size();
+                if *type_id >= type_ids {
+                    extra_descriptors
+                        .get((type_id - type_ids) as usize)
+                        .cloned()
+                        .ok_or_else(|| Error::InvalidId(format!("Invalid type id: {}", type_id)))
+                } else {
+                    Ok(dex.get_type(*type_id)?.to_string())
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let methods = self
+            .methods
+            .iter()
+            .map(|(id, flags)| {
+                Ok(ProfileMethod {
+                    id: *id,
+                    name: method_name(dex, *id)?,
+                    flags: *flags,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(ResolvedProfile { classes, methods })
+    }
+}
+
+impl ProfileMetadata {
+    /// Decodes a `baseline.profm` file.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < 8 || &data[..4] != METADATA_MAGIC {
+            return Err(Error::MalFormed(
+                "not a baseline profile metadata".to_string(),
+            ));
+        }
+        if &data[4..8] != METADATA_VERSION_002 {
+            return Err(Error::MalFormed(format!(
+                "unsupported profile metadata version: {}",
+                String::from_utf8_lossy(&data[4..8])
+            )));
+        }
+
+        let offset = &mut 8;
+        let dex_count: ushort = data.gread_with(offset, LE)?;
+        let body = read_compressed(data, offset)?;
+
+        let offset = &mut 0;
+        let mut dex_files = Vec::with_capacity(dex_count as usize);
+        for _ in 0..dex_count {
+            let key_size: ushort = body.gread_with(offset, LE)?;
+            let profile_key = read_string(&body, offset, key_size as usize)?;
+            let num_type_ids: uint = body.gread_with(offset, LE)?;
+            let class_count: ushort = body.gread_with(offset, LE)?;
+            let classes = read_classes(&body, offset, class_count)?;
+
+            dex_files.push(DexMetadata {
+                profile_key,
+                num_type_ids,
+                classes,
+            });
+        }
+
+        Ok(Self { dex_files })
+    }
+}
+
+impl ResolvedProfile {
+    /// Methods that were compiled ahead of time.
+    pub fn hot_methods(&self) -> impl Iterator<Item = &ProfileMethod> {
+        self.methods_with(MethodFlags::HOT)
+    }
+
+    /// Methods executed during startup.
+    pub fn startup_methods(&self) -> impl Iterator<Item = &ProfileMethod> {
+        self.methods_with(MethodFlags::STARTUP)
+    }
+
+    /// Methods executed after startup.
+    pub fn post_startup_methods(&self) -> impl Iterator<Item = &ProfileMethod> {
+        self.methods_with(MethodFlags::POST_STARTUP)
+    }
+
+    fn methods_with(&self, flag: MethodFlags) -> impl Iterator<Item = &ProfileMethod> {
+        self.methods.iter().filter(move |m| m.flags.contains(flag))
+    }
+}
+
+/// Version `0.1.0`: a single compressed block with a header for each dex file, followed by the
+/// hot methods, classes and method bitmap of each of them.
+fn parse_p(data: &[u8]) -> Result<Profile> {
+    let offset = &mut 8;
+    let dex_count: u8 = data.gread_with(offset, LE)?;
+    let body = read_compressed(data, offset)?;
+
+    let offset = &mut 0;
+    let mut headers = Vec::with_capacity(dex_count as usize);
+    for _ in 0..dex_count {
+        let key_size: ushort = body.gread_with(offset, LE)?;
+        let class_count: ushort = body.gread_with(offset, LE)?;
+        let hot_methods_size: uint = body.gread_with(offset, LE)?;
+        let checksum: uint = body.gread_with(offset, LE)?;
+        let num_method_ids: uint = body.gread_with(offset, LE)?;
+        let profile_key = read_string(&body, offset, key_size as usize)?;
+
+        headers.push((
+            profile_key,
+            class_count,
+            hot_methods_size,
+            checksum,
+            num_method_ids,
+        ));
+    }
+
+    let mut dex_files = Vec::with_capacity(headers.len());
+    for (profile_key, class_count, hot_methods_size, checksum, num_method_ids) in headers {
+        let mut methods = BTreeMap::new();
+        let end = end_of(*offset, hot_methods_size as usize)?;
+        read_hot_methods(&body, offset, end, ProfileVersion::P, &mut methods)?;
+        let classes = read_classes(&body, offset, class_count)?;
+        let bitmap_flags = MethodFlags::STARTUP | MethodFlags::POST_STARTUP;
+        read_method_bitmap(&body, offset, num_method_ids, bitmap_flags, &mut methods)?;
+
+        dex_files.push(DexProfile {
+            profile_key,
+            checksum,
+            num_type_ids: 0,
+            num_method_ids,
+            classes,
+            methods,
+        });
+    }
+
+    Ok(Profile {
+        version: ProfileVersion::P,
+        dex_files,
+        extra_descriptors: Vec::new(),
+    })
+}
+
+/// Version `0.1.5`: a table of sections, each of them optionally compressed.
+fn parse_s(data: &[u8]) -> Result<Profile> {
+    let offset = &mut 8;
+    let section_count: uint = data.gread_with(offset, LE)?;
+
+    let mut sections = BTreeMap::new();
+    for _ in 0..section_count {
+        let section_type: uint = data.gread_with(offset, LE)?;
+        let section_offset: uint = data.gread_with(offset, LE)?;
+        let size: uint = data.gread_with(offset, LE)?;
+        let inflated_size: uint = data.gread_with(offset, LE)?;
+
+        let start = section_offset as usize;
+        let raw = data
+            .get(start..end_of(start, size as usize)?)
+            .ok_or_else(|| Error::BadOffset(start, "Bad section".to_string()))?;
+        let contents = if inflated_size == 0 {
+            raw.to_vec()
+        } else {
+            inflate(raw, inflated_size as usize)?
+        };
+        sections.insert(section_type, contents);
+    }
+
+    let dex_section = sections
+        .get(&SECTION_DEX_FILES)
+        .ok_or_else(|| Error::MalFormed("profile without dex files".to_string()))?;
+    let offset = &mut 0;
+    let dex_count: ushort = dex_section.gread_with(offset, LE)?;
+    let mut dex_files = Vec::with_capacity(dex_count as usize);
+    for _ in 0..dex_count {
+        let checksum: uint = dex_section.gread_with(offset, LE)?;
+        let num_type_ids: uint = dex_section.gread_with(offset, LE)?;
+        let num_method_ids: uint = dex_section.gread_with(offset, LE)?;
+        let key_size: ushort = dex_section.gread_with(offset, LE)?;
+        let profile_key = read_string(dex_section, offset, key_size as usize)?;
+
+        dex_files.push(DexProfile {
+            profile_key,
+            checksum,
+            num_type_ids,
+            num_method_ids,
+            classes: Vec::new(),
+            methods: BTreeMap::new(),
+        });
+    }
+
+    let mut extra_descriptors = Vec::new();
+    if let Some(section) = sections.get(&SECTION_EXTRA_DESCRIPTORS) {
+        let offset = &mut 0;
+        let count: ushort = section.gread_with(offset, LE)?;
+        for _ in 0..count {
+            let size: ushort = section.gread_with(offset, LE)?;
+            extra_descriptors.push(read_string(section, offset, size as usize)?);
+        }
+    }
+
+    if let Some(section) = sections.get(&SECTION_CLASSES) {
+        let offset = &mut 0;
+        while *offset < section.len() {
+            let index: ushort = section.gread_with(offset, LE)?;
+            let class_count: ushort = section.gread_with(offset, LE)?;
+            let classes = read_classes(section, offset, class_count)?;
+            dex_file(&mut dex_files, index)?.classes = classes;
+        }
+    }
+
+    if let Some(section) = sections.get(&SECTION_METHODS) {
+        let offset = &mut 0;
+        while *offset < section.len() {
+            let index: ushort = section.gread_with(offset, LE)?;
+            let following_size: uint = section.gread_with(offset, LE)?;
+            let end = end_of(*offset, following_size as usize)?;
+            let bitmap_flags: ushort = section.gread_with(offset, LE)?;
+            let bitmap_flags = MethodFlags::from_bits_truncate(bitmap_flags);
+
+            let dex_profile = dex_file(&mut dex_files, index)?;
+            let mut methods = BTreeMap::new();
+            read_method_bitmap(
+                section,
+                offset,
+                dex_profile.num_method_ids,
+                bitmap_flags,
+                &mut methods,
+            )?;
+            read_hot_methods(section, offset, end, ProfileVersion::S, &mut methods)?;
+            dex_profile.methods = methods;
+        }
+    }
+
+    Ok(Profile {
+        version: ProfileVersion::S,
+        dex_files,
+        extra_descriptors,
+    })
+}
+
+fn dex_file(dex_files: &mut [DexProfile], index: ushort) -> Result<&mut DexProfile> {
+    dex_files
+        .get_mut(index as usize)
+        .ok_or_else(|| Error::InvalidId(format!("Invalid profile index: {}", index)))
+}
+
+/// Reads the methods with a delta encoded index, followed by their inline caches, up to `end`.
+fn read_hot_methods(
+    data: &[u8],
+    offset: &mut usize,
+    end: usize,
+    version: ProfileVersion,
+    methods: &mut BTreeMap<MethodId, MethodFlags>,
+) -> Result<()> {
+    let mut last_index: MethodId = 0;
+
+    while *offset < end {
+        let diff: ushort = data.gread_with(offset, LE)?;
+        let index = last_index + MethodId::from(diff);
+        *methods.entry(index).or_insert_with(MethodFlags::empty) |= MethodFlags::HOT;
+        last_index = index;
+
+        let inline_cache_size: ushort = data.gread_with(offset, LE)?;
+        for _ in 0..inline_cache_size {
+            skip_inline_cache(data, offset, version)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Inline caches tell the classes seen at each call site. They are not needed to know which
+/// methods are profiled.
+fn skip_inline_cache(data: &[u8], offset: &mut usize, version: ProfileVersion) -> Result<()> {
+    let _dex_pc: ushort = data.gread_with(offset, LE)?;
+    let size: u8 = data.gread_with(offset, LE)?;
+    if size == INLINE_CACHE_MISSING_TYPES || size == INLINE_CACHE_MEGAMORPHIC {
+        return Ok(());
+    }
+
+    match version {
+        // Classes grouped by the dex file that defines them
+        ProfileVersion::P => {
+            for _ in 0..size {
+                let _profile_index: u8 = data.gread_with(offset, LE)?;
+                let class_count: u8 = data.gread_with(offset, LE)?;
+                *offset += class_count as usize * 2;
+            }
+        }
+        // Type indices of the dex file of the method, or extra descriptors
+        ProfileVersion::S => *offset += size as usize * 2,
+    }
+
+    Ok(())
+}
+
+/// Reads a list of delta encoded type indices.
+fn read_classes(data: &[u8], offset: &mut usize, count: ushort) -> Result<Vec<TypeId>> {
+    let mut classes = Vec::with_capacity(count as usize);
+    let mut last_index: TypeId = 0;
+
+    for _ in 0..count {
+        let diff: ushort = data.gread_with(offset, LE)?;
+        last_index += TypeId::from(diff);
+        classes.push(last_index);
+    }
+
+    Ok(classes)
+}
+
+/// Reads the bitmap that holds, for each of the given flags (but `HOT`), a bit for each method
+/// of the dex file.
+fn read_method_bitmap(
+    data: &[u8],
+    offset: &mut usize,
+    num_method_ids: uint,
+    flags: MethodFlags,
+    methods: &mut BTreeMap<MethodId, MethodFlags>,
+) -> Result<()> {
+    let num_method_ids = num_method_ids as usize;
+    let flag_count = (flags.bits() & !MethodFlags::HOT.bits()).count_ones() as usize;
+    let bits = flag_count
+        .checked_mul(num_method_ids)
+        .ok_or_else(|| Error::MalFormed(format!("too many methods: {}", num_method_ids)))?;
+    let size = bits.div_ceil(8);
+    let bitmap = data
+        .get(*offset..end_of(*offset, size)?)
+        .ok_or_else(|| Error::BadOffset(*offset, "Method bitmap out of bounds".to_string()))?;
+    *offset += size;
+
+    // Each flag present on the bitmap takes `num_method_ids` bits, in ascending flag order
+    let mut flag_index = 0;
+    for bit in 1..ushort::BITS {
+        let flag = 1 << bit;
+        if flags.bits() & flag == 0 {
+            continue;
+        }
+
+        for method in 0..num_method_ids {
+            let position = flag_index * num_method_ids + method;
+            if bitmap[position / 8] & (1 << (position % 8)) != 0 {
+                *methods
+                    .entry(method as MethodId)
+                    .or_insert_with(MethodFlags::empty) |= MethodFlags::from_bits_truncate(flag);
+            }
+        }
+        flag_index += 1;
+    }
+
+    Ok(())
+}
+
+/// Reads the sizes of a zlib compressed block, followed by the block itself.
+fn read_compressed(data: &[u8], offset: &mut usize) -> Result<Vec<u8>> {
+    let inflated_size: uint = data.gread_with(offset, LE)?;
+    let size: uint = data.gread_with(offset, LE)?;
+    let compressed = data
+        .get(*offset..end_of(*offset, size as usize)?)
+        .ok_or_else(|| Error::BadOffset(*offset, "Compressed data out of bounds".to_string()))?;
+    *offset += size as usize;
+
+    inflate(compressed, inflated_size as usize)
+}
+
+fn inflate(data: &[u8], inflated_size: usize) -> Result<Vec<u8>> {
+    if inflated_size > MAX_INFLATED_SIZE {
+        return Err(Error::MalFormed(format!(
+            "profile too big: {} bytes",
+            inflated_size
+        )));
+    }
+
+    miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(data, inflated_size)
+        .map_err(|e| Error::MalFormed(format!("could not inflate the profile: {:?}", e.status)))
+}
+
+fn read_string(data: &[u8], offset: &mut usize, size: usize) -> Result<String> {
+    let bytes = data
+        .get(*offset..end_of(*offset, size)?)
+        .ok_or_else(|| Error::BadOffset(*offset, "String out of bounds".to_string()))?;
+    *offset += size;
+
+    Ok(String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// End of a region of `size` bytes starting at `offset`, which does not fit on an `usize` on
+/// 32 bits targets if the sizes of the profile are corrupt.
+fn end_of(offset: usize, size: usize) -> Result<usize> {
+    offset.checked_add(size).ok_or_else(|| {
+        Error::MalFormed(format!(
+            "region of {} bytes at offset {} out of bounds",
+            size, offset
+        ))
+    })
+}
+
+/// Name of the dex file on a profile key, without the APK it belongs to.
+fn dex_name(profile_key: &str) -> &str {
+    profile_key
+        .rsplit(['!', ':'])
+        .next()
+        .unwrap_or(profile_key)
+}
+
+/// Name of the method in smali notation, like `Lcom/example/Main;->onCreate(Landroid/os/Bundle;)V`.
+fn method_name<T: AsRef<[u8]>>(dex: &Dex<T>, method_id: MethodId) -> Result<String> {
+    let method_item = dex.get_method_item(method_id)?;
+    let class = dex.get_type(TypeId::from(method_item.class_idx()))?;
+    let name = dex.get_string(method_item.name_idx())?;
+    let proto_item = dex.get_proto_item(ProtoId::from(method_item.proto_idx()))?;
+    let params = dex.get_interfaces(proto_item.params_off())?;
+    let return_type = dex.get_type(proto_item.return_type())?;
+
+    let params: String = params.iter().map(|param| param.to_string()).collect();
+
+    Ok(format!("{}->{}({}){}", class, name, params, return_type))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{end_of, MethodFlags, Profile, ProfileMetadata, ProfileVersion};
+    use crate::error::Error;
+
+    fn compressed_block(body: &[u8]) -> Vec<u8> {
+        let compressed = miniz_oxide::deflate::compress_to_vec_zlib(body, 6);
+        let mut block = Vec::new();
+        block.extend(&(body.len() as u32).to_le_bytes());
+        block.extend(&(compressed.len() as u32).to_le_bytes());
+        block.extend(&compressed);
+        block
+    }
+
+    #[test]
+    fn test_parse_profile_p() {
+        let mut body = Vec::new();
+        // Header: key size, classes, hot methods region size, checksum, method ids
+        body.extend(&11u16.to_le_bytes());
+        body.extend(&2u16.to_le_bytes());
+        body.extend(&8u32.to_le_bytes());
+        body.extend(&0xcafe_babeu32.to_le_bytes());
+        body.extend(&4u32.to_le_bytes());
+        body.extend(b"classes.dex");
+        // Hot methods 1 and 3, without inline caches
+        body.extend(&[1, 0, 0, 0, 2, 0, 0, 0]);
+        // Classes 2 and 5
+        body.extend(&[2, 0, 3, 0]);
+        // Startup: methods 0 and 1, post startup: method 3
+        body.extend(&[0b1000_0011]);
+
+        let mut data = b"pro\x00010\0".to_vec();
+        data.push(1);
+        data.extend(compressed_block(&body));
+
+        let profile = Profile::parse(&data).expect("cannot parse profile");
+        assert_eq!(ProfileVersion::P, profile.version());
+        let dex_profile = &profile.dex_files()[0];
+        assert_eq!("classes.dex", dex_profile.profile_key());
+        assert_eq!(0xcafe_babe, dex_profile.checksum());
+        assert_eq!(&vec![2, 5], dex_profile.classes());
+        assert_eq!(Some(&MethodFlags::STARTUP), dex_profile.methods().get(&0));
+        assert_eq!(
+            Some(&(MethodFlags::HOT | MethodFlags::STARTUP)),
+            dex_profile.methods().get(&1)
+        );
+        assert_eq!(
+            Some(&(MethodFlags::HOT | MethodFlags::POST_STARTUP)),
+            dex_profile.methods().get(&3)
+        );
+    }
+
+    #[test]
+    fn test_parse_profile_s() {
+        let mut dex_files = Vec::new();
+        dex_files.extend(&1u16.to_le_bytes());
+        dex_files.extend(&0x1234u32.to_le_bytes());
+        dex_files.extend(&10u32.to_le_bytes());
+        dex_files.extend(&3u32.to_le_bytes());
+        dex_files.extend(&20u16.to_le_bytes());
+        dex_files.extend(b"base.apk!classes.dex");
+
+        // Dex 0, 1 class: 4
+        let classes = vec![0, 0, 1, 0, 4, 0];
+
+        let mut methods = Vec::new();
+        methods.extend(&0u16.to_le_bytes());
+        // Flags, 1 byte of bitmap and a hot method with a megamorphic inline cache
+        methods.extend(&(2u32 + 1 + 4 + 3).to_le_bytes());
+        methods.extend(
+            &(MethodFlags::HOT | MethodFlags::STARTUP)
+                .bits()
+                .to_le_bytes(),
+        );
+        methods.push(0b0000_0100);
+        methods.extend(&[2, 0, 1, 0]);
+        methods.extend(&[9, 0, 7]);
+
+        let sections = [(0u32, dex_files), (2, classes), (3, methods)];
+        let mut data = b"pro\x00015\0".to_vec();
+        data.extend(&(sections.len() as u32).to_le_bytes());
+        let mut offset = data.len() + sections.len() * 16;
+        for (section_type, contents) in &sections {
+            data.extend(&section_type.to_le_bytes());
+            data.extend(&(offset as u32).to_le_bytes());
+            data.extend(&(contents.len() as u32).to_le_bytes());
+            data.extend(&0u32.to_le_bytes());
+            offset += contents.len();
+        }
+        for (_, contents) in &sections {
+            data.extend(contents);
+        }
+
+        let profile = Profile::parse(&data).expect("cannot parse profile");
+        assert_eq!(ProfileVersion::S, profile.version());
+        let dex_profile = &profile.dex_files()[0];
+        assert_eq!(10, dex_profile.num_type_ids());
+        assert_eq!(&vec![4], dex_profile.classes());
+        assert_eq!(
+            Some(&(MethodFlags::HOT | MethodFlags::STARTUP)),
+            dex_profile.methods().get(&2)
+        );
+        assert_eq!(1, dex_profile.methods().len());
+    }
+
+    #[test]
+    fn test_apply_profile_metadata() {
+        let mut body = Vec::new();
+        body.extend(&11u16.to_le_bytes());
+        body.extend(b"classes.dex");
+        body.extend(&42u32.to_le_bytes());
+        body.extend(&1u16.to_le_bytes());
+        body.extend(&7u16.to_le_bytes());
+
+        let mut data = b"prm\x00002\0".to_vec();
+        data.extend(&1u16.to_le_bytes());
+        data.extend(compressed_block(&body));
+        let metadata = ProfileMetadata::parse(&data).expect("cannot parse metadata");
+
+        let mut profile_body = Vec::new();
+        profile_body.extend(&20u16.to_le_bytes());
+        profile_body.extend(&0u16.to_le_bytes());
+        profile_body.extend(&0u32.to_le_bytes());
+        profile_body.extend(&0u32.to_le_bytes());
+        profile_body.extend(&0u32.to_le_bytes());
+        profile_body.extend(b"base.apk!classes.dex");
+        let mut profile_data = b"pro\x00010\0".to_vec();
+        profile_data.push(1);
+        profile_data.extend(compressed_block(&profile_body));
+
+        let mut profile = Profile::parse(&profile_data).expect("cannot parse profile");
+        profile.apply_metadata(&metadata);
+        assert_eq!(42, profile.dex_files()[0].num_type_ids());
+        assert_eq!(&vec![7], profile.dex_files()[0].classes());
+    }
+
+    #[test]
+    fn test_reject_unknown_profiles() {
+        assert!(Profile::parse(b"pro\x00001\0").is_err());
+        assert!(Profile::parse(b"dex\n035\0").is_err());
+    }
+
+    #[test]
+    fn test_reject_regions_out_of_bounds() {
+        assert!(matches!(end_of(usize::MAX, 1), Err(Error::MalFormed(_))));
+        assert_eq!(12, end_of(4, 8).expect("region should fit"));
+
+        let mut data = b"pro\x00015\0".to_vec();
+        data.extend(&1u32.to_le_bytes());
+        data.extend(&0u32.to_le_bytes());
+        data.extend(&u32::MAX.to_le_bytes());
+        data.extend(&u32::MAX.to_le_bytes());
+        data.extend(&0u32.to_le_bytes());
+        assert!(Profile::parse(&data).is_err());
+    }
+}
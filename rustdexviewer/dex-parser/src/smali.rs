@@ -0,0 +1,1018 @@
+//! Disassembly of classes and methods to [smali](https://github.com/JesusFreke/smali), the syntax
+//! of baksmali. Labels are named after the address they point to, as baksmali does, and the
+//! registers holding the parameters are named `pN`. Line numbers and local variables come from
+//! the debug info of the methods.
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use crate::{
+    annotation::{AnnotationSetItem, EncodedAnnotation, Visibility},
+    class::{self, Class},
+    code::{CodeItem, DebugInfoItem, ExceptionType, LocalVariable},
+    encoded_value::EncodedValue,
+    field::{self, Field, FieldIdItem},
+    instruction::{Index, Instruction, Opcode, ResolvedIndex},
+    int,
+    jtype::TypeId,
+    long,
+    method::{
+        self, CallSiteItem, FieldOrMethodId, Method, MethodHandleItem, MethodHandleType,
+        MethodIdItem, ProtoId, ProtoIdItem,
+    },
+    Dex, Result,
+};
+
+const CLASS_FLAGS: &[(class::AccessFlags, &str)] = &[
+    (class::AccessFlags::PUBLIC, "public"),
+    (class::AccessFlags::PRIVATE, "private"),
+    (class::AccessFlags::PROTECTED, "protected"),
+    (class::AccessFlags::STATIC, "static"),
+    (class::AccessFlags::FINAL, "final"),
+    (class::AccessFlags::INTERFACE, "interface"),
+    (class::AccessFlags::ABSTRACT, "abstract"),
+    (class::AccessFlags::SYNTHETIC, "synthetic"),
+    (class::AccessFlags::ANNOTATION, "annotation"),
+    (class::AccessFlags::ENUM, "enum"),
+];
+
+const FIELD_FLAGS: &[(field::AccessFlags, &str)] = &[
+    (field::AccessFlags::PUBLIC, "public"),
+    (field::AccessFlags::PRIVATE, "private"),
+    (field::AccessFlags::PROTECTED, "protected"),
+    (field::AccessFlags::STATIC, "static"),
+    (field::AccessFlags::FINAL, "final"),
+    (field::AccessFlags::VOLATILE, "volatile"),
+    (field::AccessFlags::TRANSIENT, "transient"),
+    (field::AccessFlags::SYNTHETIC, "synthetic"),
+    (field::AccessFlags::ENUM, "enum"),
+];
+
+const METHOD_FLAGS: &[(method::AccessFlags, &str)] = &[
+    (method::AccessFlags::PUBLIC, "public"),
+    (method::AccessFlags::PRIVATE, "private"),
+    (method::AccessFlags::PROTECTED, "protected"),
+    (method::AccessFlags::STATIC, "static"),
+    (method::AccessFlags::FINAL, "final"),
+    (method::AccessFlags::SYNCHRONIZED, "synchronized"),
+    (method::AccessFlags::BRIDGE, "bridge"),
+    (method::AccessFlags::VARARGS, "varargs"),
+    (method::AccessFlags::NATIVE, "native"),
+    (method::AccessFlags::ABSTRACT, "abstract"),
+    (method::AccessFlags::STRICT, "strictfp"),
+    (method::AccessFlags::SYNTHETIC, "synthetic"),
+    (method::AccessFlags::CONSTRUCTOR, "constructor"),
+    (
+        method::AccessFlags::DECLARED_SYNCHRONIZED,
+        "declared-synchronized",
+    ),
+];
+
+/// Disassembles a class, with its fields and methods.
+pub fn class_to_smali<S: AsRef<[u8]>>(dex: &Dex<S>, class: &Class) -> Result<String> {
+    let printer = Printer { dex };
+    let mut out = format!(
+        ".class {}{}\n",
+        access_flags(CLASS_FLAGS, |flag| class.access_flags().contains(flag)),
+        class.jtype()
+    );
+    if let Some(super_class) = class.super_class() {
+        out.push_str(&format!(".super {}\n", dex.get_type(super_class)?));
+    }
+    if let Some(source_file) = class.source_file() {
+        out.push_str(&format!(".source {}\n", quote(&source_file.to_string())));
+    }
+
+    if !class.interfaces().is_empty() {
+        out.push_str("\n\n# interfaces\n");
+        for interface in class.interfaces() {
+            out.push_str(&format!(".implements {}\n", interface));
+        }
+    }
+
+    if !class.annotations().is_empty() {
+        out.push_str("\n\n# annotations\n");
+        printer.annotations(&mut out, class.annotations(), 0)?;
+    }
+
+    let fields = [
+        ("static fields", class.static_fields()),
+        ("instance fields", class.instance_fields()),
+    ];
+    for (section, fields) in fields.iter() {
+        if fields.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("\n\n# {}\n", section));
+        for (i, field) in fields.iter().enumerate() {
+            if i > 0 {
+                out.push('\n');
+            }
+            printer.field(&mut out, field)?;
+        }
+    }
+
+    let methods = [
+        ("direct methods", class.direct_methods()),
+        ("virtual methods", class.virtual_methods()),
+    ];
+    for (section, methods) in methods.iter() {
+        if methods.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("\n\n# {}\n", section));
+        for (i, method) in methods.iter().enumerate() {
+            if i > 0 {
+                out.push('\n');
+            }
+            printer.method(&mut out, method)?;
+        }
+    }
+    Ok(out)
+}
+
+/// Disassembles a method, from `.method` to `.end method`.
+pub fn method_to_smali<S: AsRef<[u8]>>(dex: &Dex<S>, method: &Method) -> Result<String> {
+    let mut out = String::new();
+    Printer { dex }.method(&mut out, method)?;
+    Ok(out)
+}
+
+/// Disassembles the instructions of a method, along their address in code units and the name of
+/// their opcode. Labels, debug and try/catch directives are not included, and branches point to
+/// the labels `method_to_smali` would print.
+pub fn instructions_to_smali<S: AsRef<[u8]>>(
+    dex: &Dex<S>,
+    method: &Method,
+) -> Result<Vec<(usize, &'static str, String)>> {
+    let code = match method.code() {
+        Some(code) => code,
+        None => return Ok(Vec::new()),
+    };
+    let printer = Printer { dex };
+    let writer = CodeWriter::new(&printer, code)?;
+    writer
+        .instructions
+        .iter()
+        .map(|(address, instruction)| {
+            Ok((
+                *address,
+                instruction.name(),
+                writer.instruction(*address, instruction)?,
+            ))
+        })
+        .collect()
+}
+
+/// Formats the items of a dex file the way smali refers to them.
+struct Printer<'a, S> {
+    dex: &'a Dex<S>,
+}
+
+impl<'a, S: AsRef<[u8]>> Printer<'a, S> {
+    fn field(&self, out: &mut String, field: &Field) -> Result<()> {
+        out.push_str(&format!(
+            ".field {}{}:{}",
+            access_flags(FIELD_FLAGS, |flag| field.access_flags().contains(flag)),
+            field.name(),
+            field.jtype()
+        ));
+        if let Some(value) = field.initial_value() {
+            out.push_str(&format!(" = {}", self.value(value, 0)?));
+        }
+        out.push('\n');
+        if !field.annotations().is_empty() {
+            self.annotations(out, field.annotations(), 1)?;
+            out.push_str(".end field\n");
+        }
+        Ok(())
+    }
+
+    fn method(&self, out: &mut String, method: &Method) -> Result<()> {
+        out.push_str(&format!(
+            ".method {}{}({}){}\n",
+            access_flags(METHOD_FLAGS, |flag| method.access_flags().contains(flag)),
+            method.name(),
+            method
+                .params()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<String>(),
+            method.return_type()
+        ));
+        if let Some(code) = method.code() {
+            out.push_str(&format!("    .registers {}\n", code.registers_size()));
+        }
+        self.parameters(out, method)?;
+        if !method.annotations().is_empty() {
+            self.annotations(out, method.annotations(), 1)?;
+        }
+        if let Some(code) = method.code() {
+            out.push('\n');
+            // A method whose code can not be read does not keep the rest of the class from being
+            // disassembled
+            let mut body = String::new();
+            match CodeWriter::new(self, code).and_then(|writer| writer.write(&mut body)) {
+                Ok(()) => out.push_str(&body),
+                Err(e) => out.push_str(&format!("    # could not disassemble the code: {}\n", e)),
+            }
+        }
+        out.push_str(".end method\n");
+        Ok(())
+    }
+
+    /// Writes the `.param` directives of the parameters which have a name or annotations.
+    fn parameters(&self, out: &mut String, method: &Method) -> Result<()> {
+        let names = method
+            .code()
+            .and_then(CodeItem::debug_info_item)
+            .map(|debug_info| debug_info.parameter_names().as_slice())
+            .unwrap_or_default();
+        let mut register = if method.is_static() { 0 } else { 1 };
+        for (i, param) in method.params().iter().enumerate() {
+            let name = names.get(i).and_then(Option::as_ref);
+            let annotations = method
+                .param_annotations()
+                .get(i)
+                .filter(|annotations| !annotations.is_empty());
+            if name.is_some() || annotations.is_some() {
+                out.push_str(&format!("    .param p{}", register));
+                if let Some(name) = name {
+                    out.push_str(&format!(", {}", quote(&name.to_string())));
+                }
+                out.push_str(&format!("    # {}\n", param));
+                if let Some(annotations) = annotations {
+                    self.annotations(out, annotations, 2)?;
+                    out.push_str("    .end param\n");
+                }
+            }
+            register += if param.is_long() || param.is_double() {
+                2
+            } else {
+                1
+            };
+        }
+        Ok(())
+    }
+
+    fn annotations(
+        &self,
+        out: &mut String,
+        annotations: &AnnotationSetItem,
+        indent: usize,
+    ) -> Result<()> {
+        for (i, annotation) in annotations.iter().enumerate() {
+            if i > 0 {
+                out.push('\n');
+            }
+            let visibility = match annotation.visibility() {
+                Visibility::Build => "build",
+                Visibility::Runtime => "runtime",
+                Visibility::System => "system",
+            };
+            out.push_str(&format!(
+                "{}.annotation {} {}\n",
+                pad(indent),
+                visibility,
+                annotation.jtype()
+            ));
+            self.annotation_elements(out, annotation, indent + 1)?;
+            out.push_str(&format!("{}.end annotation\n", pad(indent)));
+        }
+        Ok(())
+    }
+
+    fn annotation_elements(
+        &self,
+        out: &mut String,
+        annotation: &EncodedAnnotation,
+        indent: usize,
+    ) -> Result<()> {
+        for element in annotation.elements() {
+            out.push_str(&format!(
+                "{}{} = {}\n",
+                pad(indent),
+                element.name(),
+                self.value(element.value(), indent)?
+            ));
+        }
+        Ok(())
+    }
+
+    /// Formats an encoded value. Arrays and annotations span several lines, indented one level
+    /// more than `indent`.
+    fn value(&self, value: &EncodedValue, indent: usize) -> Result<String> {
+        Ok(match value {
+            EncodedValue::Byte(value) => format!("{}t", hex(i64::from(*value))),
+            EncodedValue::Short(value) => format!("{}s", hex(i64::from(*value))),
+            EncodedValue::Char(value) => escape(std::iter::once(*value), '\''),
+            EncodedValue::Int(value) => hex(i64::from(*value)),
+            EncodedValue::Long(value) => format!("{}L", hex(*value)),
+            EncodedValue::Float(value) => format!("{}f", float(*value)),
+            EncodedValue::Double(value) => float(*value),
+            EncodedValue::Type(jtype) => jtype.to_string(),
+            EncodedValue::MethodType(proto) => self.proto(proto)?,
+            EncodedValue::MethodHandle(handle) => self.method_handle(handle)?,
+            EncodedValue::String(string) => quote(&string.to_string()),
+            EncodedValue::Field(field) => self.field_ref(field)?,
+            EncodedValue::Method(method) => self.method_ref(method)?,
+            EncodedValue::Enum(field) => format!(".enum {}", self.field_ref(field)?),
+            EncodedValue::Annotation(annotation) => {
+                let mut out = format!(".subannotation {}\n", annotation.jtype());
+                self.annotation_elements(&mut out, annotation, indent + 1)?;
+                out.push_str(&format!("{}.end subannotation", pad(indent)));
+                out
+            }
+            EncodedValue::Array(values) if values.is_empty() => "{}".to_string(),
+            EncodedValue::Array(values) => {
+                let mut out = "{\n".to_string();
+                for (i, value) in values.iter().enumerate() {
+                    out.push_str(&format!(
+                        "{}{}{}\n",
+                        pad(indent + 1),
+                        self.value(value, indent + 1)?,
+                        if i + 1 < values.len() { "," } else { "" }
+                    ));
+                }
+                out.push_str(&format!("{}}}", pad(indent)));
+                out
+            }
+            EncodedValue::Null => "null".to_string(),
+            EncodedValue::Boolean(value) => value.to_string(),
+        })
+    }
+
+    /// Formats the item an instruction refers to.
+    fn index(&self, index: Index) -> Result<String> {
+        Ok(match index.resolve(self.dex)? {
+            ResolvedIndex::String(string) => quote(&string.to_string()),
+            ResolvedIndex::Type(jtype) => jtype.to_string(),
+            ResolvedIndex::Field(field) => self.field_ref(&field)?,
+            ResolvedIndex::Method(method) => self.method_ref(&method)?,
+            ResolvedIndex::Proto(proto) => self.proto(&proto)?,
+            ResolvedIndex::MethodHandle(handle) => self.method_handle(&handle)?,
+            ResolvedIndex::CallSite(call_site) => self.call_site(&call_site)?,
+        })
+    }
+
+    /// `call_site_N("name", (params)return, arguments...)@bootstrap`, where the bootstrap is the
+    /// method invoked by the handle.
+    fn call_site(&self, call_site: &CallSiteItem) -> Result<String> {
+        let mut arguments = vec![
+            quote(&call_site.method_name().to_string()),
+            self.proto(call_site.method_type())?,
+        ];
+        for argument in call_site.arguments() {
+            arguments.push(self.value(argument, 0)?);
+        }
+        let bootstrap_method = call_site.bootstrap_method();
+        let bootstrap_method = match bootstrap_method.id() {
+            FieldOrMethodId::Method(id) => self.method_ref(&self.dex.get_method_item(id)?)?,
+            FieldOrMethodId::Field(_) => self.method_handle(bootstrap_method)?,
+        };
+        Ok(format!(
+            "call_site_{}({})@{}",
+            call_site.id(),
+            arguments.join(", "),
+            bootstrap_method
+        ))
+    }
+
+    /// `(params)return`
+    fn proto(&self, proto: &ProtoIdItem) -> Result<String> {
+        let params = self.dex.get_proto_params(proto)?;
+        Ok(format!(
+            "({}){}",
+            params.iter().map(ToString::to_string).collect::<String>(),
+            self.dex.get_type(proto.return_type())?
+        ))
+    }
+
+    /// `Lclass;->name:type`
+    fn field_ref(&self, field: &FieldIdItem) -> Result<String> {
+        Ok(format!(
+            "{}->{}:{}",
+            self.dex.get_type(TypeId::from(field.class_idx()))?,
+            self.dex.get_string(field.name_idx())?,
+            self.dex.get_type(TypeId::from(field.type_idx()))?
+        ))
+    }
+
+    /// `Lclass;->name(params)return`
+    fn method_ref(&self, method: &MethodIdItem) -> Result<String> {
+        let proto = self.dex.get_proto_item(ProtoId::from(method.proto_idx()))?;
+        Ok(format!(
+            "{}->{}{}",
+            self.dex.get_type(TypeId::from(method.class_idx()))?,
+            self.dex.get_string(method.name_idx())?,
+            self.proto(&proto)?
+        ))
+    }
+
+    /// `kind@member`, like `invoke-static@Lclass;->name()V`
+    fn method_handle(&self, handle: &MethodHandleItem) -> Result<String> {
+        let kind = match handle.handle_type() {
+            MethodHandleType::StaticPut => "static-put",
+            MethodHandleType::StaticGet => "static-get",
+            MethodHandleType::InstancePut => "instance-put",
+            MethodHandleType::InstanceGet => "instance-get",
+            MethodHandleType::InvokeStatic => "invoke-static",
+            MethodHandleType::InvokeInstance => "invoke-instance",
+            MethodHandleType::InvokeConstructor => "invoke-constructor",
+            MethodHandleType::InvokeDirect => "invoke-direct",
+            MethodHandleType::InvokeInterface => "invoke-interface",
+        };
+        let member = match handle.id() {
+            FieldOrMethodId::Field(id) => self.field_ref(&self.dex.get_field_item(id)?)?,
+            FieldOrMethodId::Method(id) => self.method_ref(&self.dex.get_method_item(id)?)?,
+        };
+        Ok(format!("{}@{}", kind, member))
+    }
+}
+
+/// Writes the body of a method: its instructions, labels and try/catch blocks.
+struct CodeWriter<'p, 'a, S> {
+    printer: &'p Printer<'a, S>,
+    code: &'p CodeItem,
+    instructions: Vec<(usize, Instruction)>,
+    /// Labels by the address they point to
+    labels: BTreeMap<usize, BTreeSet<String>>,
+    /// `.catch` directives by the address of the end of their try block
+    catches: BTreeMap<usize, Vec<String>>,
+    /// Address of the switch instruction using each switch payload, whose targets are relative
+    /// to the switch and not to the payload.
+    switches: BTreeMap<usize, usize>,
+    /// `.line`, `.local` and other debug directives by address
+    debug: BTreeMap<usize, Vec<String>>,
+}
+
+impl<'p, 'a, S: AsRef<[u8]>> CodeWriter<'p, 'a, S> {
+    fn new(printer: &'p Printer<'a, S>, code: &'p CodeItem) -> Result<Self> {
+        let instructions = code.instructions().collect::<Result<Vec<_>>>()?;
+        let mut writer = Self {
+            printer,
+            code,
+            instructions,
+            labels: BTreeMap::new(),
+            catches: BTreeMap::new(),
+            switches: BTreeMap::new(),
+            debug: BTreeMap::new(),
+        };
+
+        for (address, instruction) in &writer.instructions {
+            if let Some((target, label)) = branch_label(*address, instruction) {
+                if let Some(Opcode::PackedSwitch) | Some(Opcode::SparseSwitch) =
+                    instruction.opcode()
+                {
+                    writer.switches.insert(target, *address);
+                }
+                writer.labels.entry(target).or_default().insert(label);
+            }
+        }
+
+        let mut case_labels = Vec::new();
+        for (address, instruction) in &writer.instructions {
+            let switch = writer.switches.get(address).copied().unwrap_or(*address);
+            match instruction {
+                Instruction::PackedSwitchPayload { targets, .. } => {
+                    for target in targets {
+                        let target = relative(switch, *target);
+                        case_labels.push((target, format!("pswitch_{:x}", target)));
+                    }
+                }
+                Instruction::SparseSwitchPayload { targets, .. } => {
+                    for target in targets {
+                        let target = relative(switch, *target);
+                        case_labels.push((target, format!("sswitch_{:x}", target)));
+                    }
+                }
+                _ => {}
+            }
+        }
+        for (target, label) in case_labels {
+            writer.labels.entry(target).or_default().insert(label);
+        }
+
+        for try_block in code.tries().iter() {
+            let start = try_block.start_addr() as usize;
+            let end = start + usize::from(try_block.insn_count());
+            let start_label = format!("try_start_{:x}", start);
+            let end_label = format!("try_end_{:x}", end);
+            for handler in try_block.catch_handlers() {
+                let handler_address = handler.addr() as usize;
+                let (label, directive) = match handler.exception() {
+                    ExceptionType::BaseException => (
+                        format!("catchall_{:x}", handler_address),
+                        ".catchall".to_string(),
+                    ),
+                    ExceptionType::Ty(jtype) => (
+                        format!("catch_{:x}", handler_address),
+                        format!(".catch {}", jtype),
+                    ),
+                };
+                writer.catches.entry(end).or_default().push(format!(
+                    "{} {{:{} .. :{}}} :{}",
+                    directive, start_label, end_label, label
+                ));
+                writer
+                    .labels
+                    .entry(handler_address)
+                    .or_default()
+                    .insert(label);
+            }
+            writer.labels.entry(start).or_default().insert(start_label);
+            writer.labels.entry(end).or_default().insert(end_label);
+        }
+        if let Some(debug_info) = code.debug_info_item() {
+            writer.debug = writer.debug_directives(debug_info);
+        }
+        Ok(writer)
+    }
+
+    fn debug_directives(&self, debug_info: &DebugInfoItem) -> BTreeMap<usize, Vec<String>> {
+        let mut directives: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+        for local in debug_info.locals() {
+            if let Some(end_address) = local.end_address() {
+                directives
+                    .entry(end_address as usize)
+                    .or_default()
+                    .push(format!(
+                        ".end local {}    # {}",
+                        self.reg(local.register()),
+                        local_variable(local)
+                    ));
+            }
+        }
+        let mut source_file = None;
+        for position in debug_info.positions() {
+            let directives = directives.entry(position.address() as usize).or_default();
+            if position.prologue_end() {
+                directives.push(".prologue".to_string());
+            }
+            if position.epilogue_begin() {
+                directives.push(".epilogue".to_string());
+            }
+            if position.source_file().is_some() && position.source_file() != &source_file {
+                source_file = position.source_file().clone();
+                if let Some(source_file) = &source_file {
+                    directives.push(format!(".source {}", quote(&source_file.to_string())));
+                }
+            }
+            directives.push(format!(".line {}", position.line()));
+        }
+        // `this` and the parameters are declared by `.param`
+        for local in debug_info
+            .locals()
+            .iter()
+            .filter(|local| !local.parameter())
+        {
+            let directive = if local.restarted() {
+                format!(
+                    ".restart local {}    # {}",
+                    self.reg(local.register()),
+                    local_variable(local)
+                )
+            } else {
+                let mut directive = format!(
+                    ".local {}, {}",
+                    self.reg(local.register()),
+                    local_variable(local)
+                );
+                if let Some(signature) = local.signature() {
+                    directive.push_str(&format!(", {}", quote(&signature.to_string())));
+                }
+                directive
+            };
+            directives
+                .entry(local.start_address() as usize)
+                .or_default()
+                .push(directive);
+        }
+        directives
+    }
+
+    fn write(&self, out: &mut String) -> Result<()> {
+        for (address, instruction) in &self.instructions {
+            self.write_directives(out, *address);
+            out.push_str(&format!(
+                "    {}\n",
+                self.instruction(*address, instruction)?
+            ));
+        }
+        self.write_directives(out, self.code.insns().len());
+        Ok(())
+    }
+
+    fn write_directives(&self, out: &mut String, address: usize) {
+        if let Some(labels) = self.labels.get(&address) {
+            out.push('\n');
+            for label in labels {
+                out.push_str(&format!("    :{}\n", label));
+            }
+        }
+        if let Some(catches) = self.catches.get(&address) {
+            for catch in catches {
+                out.push_str(&format!("    {}\n", catch));
+            }
+        }
+        if let Some(directives) = self.debug.get(&address) {
+            if !self.labels.contains_key(&address) {
+                out.push('\n');
+            }
+            for directive in directives {
+                out.push_str(&format!("    {}\n", directive));
+            }
+        }
+    }
+
+    fn instruction(&self, address: usize, instruction: &Instruction) -> Result<String> {
+        use Instruction::*;
+
+        let name = instruction.name();
+        let label = || {
+            branch_label(address, instruction)
+                .map(|(_, label)| format!(":{}", label))
+                .unwrap_or_default()
+        };
+        Ok(match instruction {
+            Format10x { .. } => name.to_string(),
+            Format12x { a, b, .. } => format!("{} {}, {}", name, self.reg(*a), self.reg(*b)),
+            Format11n { a, literal, .. } => {
+                format!("{} {}, {}", name, self.reg(*a), hex(i64::from(*literal)))
+            }
+            Format11x { a, .. } => format!("{} {}", name, self.reg(*a)),
+            Format10t { .. } | Format20t { .. } | Format30t { .. } => {
+                format!("{} {}", name, label())
+            }
+            Format22x { a, b, .. } => format!("{} {}, {}", name, self.reg(*a), self.reg(*b)),
+            Format32x { a, b, .. } => format!("{} {}, {}", name, self.reg(*a), self.reg(*b)),
+            Format21t { a, .. } | Format31t { a, .. } => {
+                format!("{} {}, {}", name, self.reg(*a), label())
+            }
+            Format21s { opcode, a, literal } | Format31i { opcode, a, literal } => format!(
+                "{} {}, {}",
+                name,
+                self.reg(*a),
+                literal_value(*opcode, i64::from(*literal))
+            ),
+            Format21h { opcode, a, literal } | Format51l { opcode, a, literal } => format!(
+                "{} {}, {}",
+                name,
+                self.reg(*a),
+                literal_value(*opcode, *literal)
+            ),
+            Format21c { a, index, .. } | Format31c { a, index, .. } => {
+                format!("{} {}, {}", name, self.reg(*a), self.printer.index(*index)?)
+            }
+            Format23x { a, b, c, .. } => format!(
+                "{} {}, {}, {}",
+                name,
+                self.reg(*a),
+                self.reg(*b),
+                self.reg(*c)
+            ),
+            Format22b { a, b, literal, .. } | Format22s { a, b, literal, .. } => format!(
+                "{} {}, {}, {}",
+                name,
+                self.reg(*a),
+                self.reg(*b),
+                hex(i64::from(*literal))
+            ),
+            Format22t { a, b, .. } => {
+                format!("{} {}, {}, {}", name, self.reg(*a), self.reg(*b), label())
+            }
+            Format22c { a, b, index, .. } => format!(
+                "{} {}, {}, {}",
+                name,
+                self.reg(*a),
+                self.reg(*b),
+                self.printer.index(*index)?
+            ),
+            Format35c {
+                registers, index, ..
+            } => format!(
+                "{} {{{}}}, {}",
+                name,
+                self.registers(registers),
+                self.printer.index(*index)?
+            ),
+            Format3rc {
+                first_register,
+                register_count,
+                index,
+                ..
+            } => format!(
+                "{} {{{}}}, {}",
+                name,
+                self.range(*first_register, *register_count),
+                self.printer.index(*index)?
+            ),
+            Format45cc {
+                registers,
+                method,
+                proto,
+                ..
+            } => format!(
+                "{} {{{}}}, {}, {}",
+                name,
+                self.registers(registers),
+                self.printer.index(*method)?,
+                self.printer.index(*proto)?
+            ),
+            Format4rcc {
+                first_register,
+                register_count,
+                method,
+                proto,
+                ..
+            } => format!(
+                "{} {{{}}}, {}, {}",
+                name,
+                self.range(*first_register, *register_count),
+                self.printer.index(*method)?,
+                self.printer.index(*proto)?
+            ),
+            PackedSwitchPayload { first_key, targets } => {
+                let switch = self.switches.get(&address).copied().unwrap_or(address);
+                let mut out = format!(".packed-switch {}\n", hex(i64::from(*first_key)));
+                for target in targets {
+                    out.push_str(&format!(
+                        "        :pswitch_{:x}\n",
+                        relative(switch, *target)
+                    ));
+                }
+                out.push_str("    .end packed-switch");
+                out
+            }
+            SparseSwitchPayload { keys, targets } => {
+                let switch = self.switches.get(&address).copied().unwrap_or(address);
+                let mut out = ".sparse-switch\n".to_string();
+                for (key, target) in keys.iter().zip(targets) {
+                    out.push_str(&format!(
+                        "        {} -> :sswitch_{:x}\n",
+                        hex(i64::from(*key)),
+                        relative(switch, *target)
+                    ));
+                }
+                out.push_str("    .end sparse-switch");
+                out
+            }
+            FillArrayDataPayload {
+                element_width,
+                data,
+            } => {
+                let mut out = format!(".array-data {}\n", element_width);
+                for element in array_elements(usize::from(*element_width), data) {
+                    out.push_str(&format!("        {}\n", element));
+                }
+                out.push_str("    .end array-data");
+                out
+            }
+        })
+    }
+
+    /// Name of a register: parameters are held by the last `ins_size` registers.
+    fn reg<R: Into<u32>>(&self, register: R) -> String {
+        let register = register.into();
+        let first_param =
+            u32::from(self.code.registers_size()).saturating_sub(u32::from(self.code.ins_size()));
+        if register >= first_param {
+            format!("p{}", register - first_param)
+        } else {
+            format!("v{}", register)
+        }
+    }
+
+    fn registers(&self, registers: &[u8]) -> String {
+        registers
+            .iter()
+            .map(|register| self.reg(*register))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn range(&self, first_register: u16, register_count: u8) -> String {
+        if register_count == 0 {
+            return String::new();
+        }
+        let last_register = u32::from(first_register) + u32::from(register_count) - 1;
+        format!(
+            "{} .. {}",
+            self.reg(first_register),
+            self.reg(last_register)
+        )
+    }
+}
+
+/// Label of the target of a branch instruction, along its address.
+fn branch_label(address: usize, instruction: &Instruction) -> Option<(usize, String)> {
+    let target = relative(address, instruction.branch_offset()?);
+    let prefix = match instruction {
+        Instruction::Format10t { .. }
+        | Instruction::Format20t { .. }
+        | Instruction::Format30t { .. } => "goto",
+        Instruction::Format21t { .. } | Instruction::Format22t { .. } => "cond",
+        _ => match instruction.opcode()? {
+            Opcode::PackedSwitch => "pswitch_data",
+            Opcode::SparseSwitch => "sswitch_data",
+            _ => "array",
+        },
+    };
+    Some((target, format!("{}_{:x}", prefix, target)))
+}
+
+fn relative(address: usize, offset: int) -> usize {
+    (address as long + long::from(offset)) as usize
+}
+
+/// `"name":Type`, as declared by `.local`
+fn local_variable(local: &LocalVariable) -> String {
+    format!(
+        "{}:{}",
+        local
+            .name()
+            .as_ref()
+            .map_or_else(|| "null".to_string(), |name| quote(&name.to_string())),
+        local
+            .jtype()
+            .as_ref()
+            .map_or_else(|| "V".to_string(), ToString::to_string)
+    )
+}
+
+fn literal_value(opcode: Opcode, value: long) -> String {
+    match opcode {
+        Opcode::ConstWide16 | Opcode::ConstWide32 | Opcode::ConstWide | Opcode::ConstWideHigh16 => {
+            format!("{}L", hex(value))
+        }
+        _ => hex(value),
+    }
+}
+
+/// Elements of a `fill-array-data` payload, sign extended and suffixed by their width.
+fn array_elements(element_width: usize, data: &[u8]) -> Vec<String> {
+    let suffix = match element_width {
+        1 => "t",
+        2 => "s",
+        8 => "L",
+        _ => "",
+    };
+    data.chunks(element_width.max(1))
+        .map(|element| {
+            let mut value = element
+                .iter()
+                .rev()
+                .fold(0, |value, byte| (value << 8) | long::from(*byte));
+            let unused_bits = 64 - 8 * element.len().min(8) as u32;
+            if unused_bits > 0 && unused_bits < 64 {
+                value = (value << unused_bits) >> unused_bits;
+            }
+            format!("{}{}", hex(value), suffix)
+        })
+        .collect()
+}
+
+fn access_flags<F: Copy>(names: &[(F, &str)], is_set: impl Fn(F) -> bool) -> String {
+    names
+        .iter()
+        .filter(|(flag, _)| is_set(*flag))
+        .map(|(_, name)| format!("{} ", name))
+        .collect()
+}
+
+fn pad(indent: usize) -> String {
+    "    ".repeat(indent)
+}
+
+/// Signed hexadecimal literal, like `-0x1`.
+fn hex(value: long) -> String {
+    if value < 0 {
+        format!("-0x{:x}", value.unsigned_abs())
+    } else {
+        format!("0x{:x}", value)
+    }
+}
+
+fn float<T: Copy + fmt::Debug + Into<f64>>(value: T) -> String {
+    let wide: f64 = value.into();
+    if wide.is_nan() {
+        "NaN".to_string()
+    } else if wide.is_infinite() {
+        if wide > 0.0 { "Infinity" } else { "-Infinity" }.to_string()
+    } else {
+        format!("{:?}", value)
+    }
+}
+
+fn quote(string: &str) -> String {
+    escape(string.encode_utf16(), '"')
+}
+
+/// Quotes UTF-16 code units, escaping the ones which are not printable ASCII.
+fn escape(units: impl Iterator<Item = u16>, quote: char) -> String {
+    let mut out = quote.to_string();
+    for unit in units {
+        match char::from_u32(u32::from(unit)) {
+            Some(c) if c == '"' || c == '\'' || c == '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            Some('\n') => out.push_str("\\n"),
+            Some('\r') => out.push_str("\\r"),
+            Some('\t') => out.push_str("\\t"),
+            Some(c) if (' '..='~').contains(&c) => out.push(c),
+            _ => out.push_str(&format!("\\u{:04x}", unit)),
+        }
+    }
+    out.push(quote);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{array_elements, class_to_smali, escape, hex, quote};
+
+    #[test]
+    fn test_literals() {
+        assert_eq!(hex(-1), "-0x1");
+        assert_eq!(hex(255), "0xff");
+        assert_eq!(quote("a \"b\"\n\u{e9}"), "\"a \\\"b\\\"\\n\\u00e9\"");
+        assert_eq!(escape(std::iter::once(0x27), '\''), "'\\''");
+        assert_eq!(
+            array_elements(2, &[0x01, 0x00, 0xff, 0xff]),
+            vec!["0x1s", "-0x1s"]
+        );
+        assert_eq!(
+            array_elements(4, &[0x00, 0x00, 0x00, 0x80]),
+            vec!["-0x80000000"]
+        );
+    }
+
+    #[test]
+    fn test_class_to_smali() {
+        let data = std::fs::read("resources/classes.dex").expect("cannot read dex file");
+        let dex = crate::DexReader::from_vec(data).expect("cannot open dex file");
+        let class = dex
+            .find_class_by_name("Lorg/adw/launcher/Launcher;")
+            .expect("cannot load class")
+            .expect("class not found");
+        let smali = class_to_smali(&dex, &class).expect("cannot disassemble class");
+        assert!(smali.starts_with(".class "));
+        assert!(smali.contains(" Lorg/adw/launcher/Launcher;\n.super "));
+        assert!(smali.contains("constructor <init>()V\n    .registers "));
+        assert!(smali.contains("    invoke-direct {p0}, "));
+        assert!(smali.contains("    .param p1, \"savedInstanceState\"    # Landroid/os/Bundle;\n"));
+        assert!(smali.contains("    .prologue\n    .line "));
+        assert!(smali.contains(".end method\n"));
+    }
+
+    #[test]
+    fn test_class_to_smali_with_unreadable_code() {
+        let mut data = std::fs::read("resources/classes.dex").expect("cannot read dex file");
+        let insns = {
+            let dex = crate::DexReader::from_vec(data.clone()).expect("cannot open dex file");
+            let class = dex
+                .find_class_by_name("Lorg/adw/launcher/Launcher;")
+                .expect("cannot load class")
+                .expect("class not found");
+            let method = class
+                .methods()
+                .find(|method| *method.name() == "onCreate")
+                .expect("method not found");
+            let insns: Vec<u8> = method
+                .code()
+                .expect("method without code")
+                .insns()
+                .iter()
+                .flat_map(|unit| unit.to_le_bytes())
+                .collect();
+            insns
+        };
+        // Replaces the first instruction with an unused opcode
+        let position = data
+            .windows(insns.len())
+            .position(|window| window == insns.as_slice())
+            .expect("code not found");
+        data[position] = 0x3e;
+        let checksum = adler32::adler32(&data[12..]).expect("cannot compute checksum");
+        data[8..12].copy_from_slice(&checksum.to_le_bytes());
+
+        let dex = crate::DexReader::from_vec(data).expect("cannot open dex file");
+        let class = dex
+            .find_class_by_name("Lorg/adw/launcher/Launcher;")
+            .expect("cannot load class")
+            .expect("class not found");
+        let smali = class_to_smali(&dex, &class).expect("cannot disassemble class");
+        let start = smali
+            .find("onCreate(Landroid/os/Bundle;)V\n")
+            .expect("method not disassembled");
+        let end = start + smali[start..].find(".end method\n").unwrap();
+        assert!(smali[start..end].contains("\n    # could not disassemble the code: "));
+        assert_eq!(1, smali.matches("# could not disassemble").count());
+        assert_eq!(
+            class.methods().count(),
+            smali.matches(".end method\n").count()
+        );
+    }
+}
@@ -57,6 +57,11 @@ impl TestBuilder {
     }
 
     fn compile(&self) -> PathBuf {
+        self.compile_with_args(&[])
+    }
+
+    /// Compiles with extra arguments for d8, like `--min-api`
+    fn compile_with_args(&self, d8_args: &[&str]) -> PathBuf {
         let android_lib_path = env::var("ANDROID_LIB_PATH").expect("$ANDROID_LIB_PATH not set");
         let _javac = Command::new("javac")
             .args(&self.sources)
@@ -69,6 +74,7 @@ impl TestBuilder {
             .args(&classes)
             .args(&["--lib", &android_lib_path])
             .args(&["--output", &self.root.path().display().to_string()])
+            .args(d8_args)
             .current_dir(self.root.path())
             .status()
             .expect(&format!("'d8 {:?}' failed", &classes));
@@ -995,4 +1001,83 @@ fn test_iterators() {
     for method_handle_item in dex.method_handles() {
         assert!(method_handle_item.is_ok());
     }
+
+    for call_site_item in dex.call_sites() {
+        assert!(call_site_item.is_ok());
+    }
+}
+
+#[test]
+fn test_call_sites() {
+    use dex::{method::MethodHandleType, DexReader};
+    let mut builder = TestBuilder::new();
+    builder.add_src(
+        "Main.java",
+        r#"
+            import java.util.function.Supplier;
+
+            class Main {
+                static String greet(String name) {
+                    Supplier<String> greeting = () -> "Hello " + name;
+                    return greeting.get();
+                }
+            }
+        "#,
+    );
+    let dex_path = builder.compile_with_args(&["--min-api", "26"]);
+    let dex = DexReader::from_file(dex_path.as_path()).expect("can't open dex");
+    let call_sites = dex
+        .call_sites()
+        .collect::<Result<Vec<_>, _>>()
+        .expect("can't load call sites");
+    assert!(!call_sites.is_empty());
+    for call_site in &call_sites {
+        assert_eq!(
+            call_site.bootstrap_method().handle_type(),
+            MethodHandleType::InvokeStatic
+        );
+        let call_site = dex
+            .get_call_site(call_site.id())
+            .expect("can't load call site");
+        assert_eq!(call_site.method_name(), "get");
+        let params = dex
+            .get_proto_params(call_site.method_type())
+            .expect("can't load the method type parameters");
+        let params = params.iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(params, ["Ljava/lang/String;"]);
+        let return_type = dex
+            .get_type(call_site.method_type().return_type())
+            .expect("can't load the method type return type");
+        assert_eq!(return_type.to_string(), "Ljava/util/function/Supplier;");
+    }
+    assert!(dex.get_call_site(call_sites.len() as u32).is_err());
+
+    let main = dex
+        .find_class_by_name("LMain;")
+        .expect("error finding class")
+        .expect("class not found");
+    let greet = main
+        .methods()
+        .find(|method| method.name() == "greet")
+        .expect("method not found");
+    let instructions = dex::smali::instructions_to_smali(&dex, greet).expect("can't disassemble");
+    let invoke_custom = instructions
+        .iter()
+        .find(|(_, name, _)| *name == "invoke-custom")
+        .map(|(_, _, smali)| smali.as_str());
+    assert_eq!(
+        invoke_custom,
+        Some(concat!(
+            "invoke-custom {p0}, call_site_0(\"get\", ",
+            "(Ljava/lang/String;)Ljava/util/function/Supplier;, ",
+            "()Ljava/lang/Object;, ",
+            "invoke-static@LMain;->lambda$greet$0(Ljava/lang/String;)Ljava/lang/String;, ",
+            "()Ljava/lang/String;",
+            ")@Ljava/lang/invoke/LambdaMetafactory;->metafactory(",
+            "Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;",
+            "Ljava/lang/invoke/MethodType;Ljava/lang/invoke/MethodType;",
+            "Ljava/lang/invoke/MethodHandle;Ljava/lang/invoke/MethodType;",
+            ")Ljava/lang/invoke/CallSite;"
+        ))
+    );
 }
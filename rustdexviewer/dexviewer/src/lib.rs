@@ -1,8 +1,6 @@
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
-mod opcodes;
-
 #[wasm_bindgen(getter_with_clone)]
 #[derive(Serialize, Deserialize)]
 pub struct JClass {
@@ -98,25 +96,89 @@ fn dex_instructions_impl(
     let Some(class) = dex.find_class_by_name(&method.class_descriptor)? else {
         return Err(anyhow::anyhow!("Class not found"));
     };
-    let method = class
+    let Some(method) = class
         .methods()
-        .filter(|m| m.name().to_string() == method.name)
-        .next()
-        .unwrap();
-    let mut ins_iter = method.code().into_iter().flat_map(|c| c.insns()).copied();
-    let mut instructions = Vec::new();
-    while let Some(i) = ins_iter.next() {
-        let op = i;
-        let Some((opname, _format, bit_size)) = opcodes::opcode_to_name((op >> 8) as u8) else {
-            continue;
-        };
-        for _ in 0..(bit_size / 16) - 1 {
-            ins_iter.next();
-        }
-        instructions.push(JInstruction {
-            name: op.to_string(),
+        .find(|m| m.name().to_string() == method.name)
+    else {
+        return Err(anyhow::anyhow!("Method not found"));
+    };
+    // The instructions are decoded once, for both their opcode and their smali
+    let instructions = dex::smali::instructions_to_smali(&dex, method)?
+        .into_iter()
+        .map(|(_, opname, name)| JInstruction {
+            name,
             opname: opname.to_string(),
-        });
-    }
+        })
+        .collect();
     Ok(instructions)
 }
+
+/// Disassembles a class to smali.
+#[wasm_bindgen]
+pub fn dex_smali(bytes: Vec<u8>, class_id: u32) -> Result<String, wasm_bindgen::JsError> {
+    dex_smali_impl(bytes, class_id).map_err(|e| JsError::new(&format!("{e}")))
+}
+
+fn dex_smali_impl(bytes: Vec<u8>, class_id: u32) -> Result<String, anyhow::Error> {
+    let dex = dex::DexReader::from_vec(bytes)?;
+    let Some(Ok(class)) = dex.classes().nth(class_id as usize) else {
+        return Err(anyhow::anyhow!("Class not found"));
+    };
+    Ok(dex::smali::class_to_smali(&dex, &class)?)
+}
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Serialize, Deserialize)]
+pub struct JProfile {
+    pub profile_key: String,
+    pub classes: Vec<String>,
+    pub hot_methods: Vec<String>,
+    pub startup_methods: Vec<String>,
+    pub post_startup_methods: Vec<String>,
+}
+
+/// Decodes a baseline profile (and its optional `.profm` metadata), and resolves the part of it
+/// that belongs to the given dex file. Returns `null` if the profile has no data for the dex.
+#[wasm_bindgen]
+pub fn dex_profile(
+    profile: Vec<u8>,
+    metadata: Option<Vec<u8>>,
+    bytes: Vec<u8>,
+) -> Result<JsValue, wasm_bindgen::JsError> {
+    let v =
+        dex_profile_impl(profile, metadata, bytes).map_err(|e| JsError::new(&format!("{e}")))?;
+    Ok(serde_wasm_bindgen::to_value(&v)
+        .map_err(|e| JsError::new(&format!("{e}")))?
+        .into())
+}
+
+fn dex_profile_impl(
+    profile: Vec<u8>,
+    metadata: Option<Vec<u8>>,
+    bytes: Vec<u8>,
+) -> Result<Option<JProfile>, anyhow::Error> {
+    let mut profile = dex::profile::Profile::parse(&profile)?;
+    if let Some(metadata) = metadata {
+        profile.apply_metadata(&dex::profile::ProfileMetadata::parse(&metadata)?);
+    }
+
+    let dex = dex::DexReader::from_vec(bytes)?;
+    let Some(dex_profile) = profile.find_dex_profile(&dex) else {
+        return Ok(None);
+    };
+    let resolved = dex_profile.resolve(&dex, profile.extra_descriptors())?;
+    let names = |methods: &mut dyn Iterator<Item = &dex::profile::ProfileMethod>| {
+        methods.map(|m| m.name().clone()).collect::<Vec<_>>()
+    };
+    let hot_methods = names(&mut resolved.hot_methods());
+    let startup_methods = names(&mut resolved.startup_methods());
+    let post_startup_methods = names(&mut resolved.post_startup_methods());
+
+    Ok(Some(JProfile {
+        profile_key: dex_profile.profile_key().clone(),
+        classes: resolved.classes().clone(),
+        hot_methods,
+        startup_methods,
+        post_startup_methods,
+    }))
+}
@@ -1,6 +1,6 @@
 import { createRoot } from 'react-dom/client'
 import React, { useState, useEffect } from 'react'
-import init, { dex_classes, dex_methods, dex_instructions, JClass, JMethod, JInstruction, init_logger } from './dexviewer/pkg'
+import init, { dex_classes, dex_methods, dex_instructions, dex_smali, dex_profile, JClass, JMethod, JInstruction, JProfile, init_logger } from './dexviewer/pkg'
 
 window.onmessage = (e) => {
     if (e.data.action === 'respondFile') {
@@ -22,7 +22,61 @@ async function handleFile(file: File) {
     init_logger()
 
     const classes = dex_classes(fileBytes)
-    OUTPUT.render(<ClassTree classes={classes} dexfile={fileBytes} />)
+    OUTPUT.render(<>
+        <ProfilePanel dexfile={fileBytes} />
+        <ClassTree classes={classes} dexfile={fileBytes} />
+    </>)
+}
+
+async function readFile(input: HTMLInputElement): Promise<Uint8Array | undefined> {
+    const file = input.files?.[0]
+    return file ? new Uint8Array(await file.arrayBuffer()) : undefined
+}
+
+// Resolves a baseline profile (`baseline.prof`, with its optional `baseline.profm`) against the dex
+function ProfilePanel({ dexfile }: { dexfile: Uint8Array }) {
+    const [profile, setProfile] = useState<Uint8Array | undefined>(undefined)
+    const [metadata, setMetadata] = useState<Uint8Array | undefined>(undefined)
+    const [result, setResult] = useState<JProfile | null | undefined>(undefined)
+    const [error, setError] = useState<string | null>(null)
+
+    useEffect(() => {
+        if (!profile) return
+        try {
+            setResult(dex_profile(profile, metadata, dexfile))
+            setError(null)
+        } catch (error) {
+            setResult(undefined)
+            setError(String(error))
+        }
+    }, [profile, metadata])
+
+    const sections: [string, string[]][] = result ? [
+        ['Classes', result.classes],
+        ['Hot methods', result.hot_methods],
+        ['Startup methods', result.startup_methods],
+        ['Post-startup methods', result.post_startup_methods],
+    ] : []
+
+    return (
+        <div className="profile">
+            <label>
+                Baseline profile: <input type="file" accept=".prof" onChange={async e => setProfile(await readFile(e.target))} />
+            </label>
+            <label>
+                Metadata (optional): <input type="file" accept=".profm" onChange={async e => setMetadata(await readFile(e.target))} />
+            </label>
+            {error !== null && <div className="error">{error}</div>}
+            {result === null && <div>The profile has no data for this dex file</div>}
+            {result && <div>Profile key: {result.profile_key}</div>}
+            {sections.map(([title, names]) => (
+                <details key={title}>
+                    <summary>{title} ({names.length})</summary>
+                    {names.map(name => <div className="instruction" key={name}>{name}</div>)}
+                </details>
+            ))}
+        </div>
+    )
 }
 
 function ClassTree({ classes, dexfile }: { classes: JClass[], dexfile: Uint8Array }) {
@@ -34,6 +88,19 @@ function DexClass({ javaClass, dexfile }: { javaClass: JClass, dexfile: Uint8Arr
     const [expanded, setExpanded] = useState(false)
     const [methods, setMethods] = useState<JMethod[]>([])
     const [loading, setLoading] = useState(false)
+    const [smali, setSmali] = useState<string | null>(null)
+
+    const toggleSmali = () => {
+        if (smali !== null) {
+            setSmali(null)
+            return
+        }
+        try {
+            setSmali(dex_smali(dexfile, javaClass.id))
+        } catch (error) {
+            console.error('Error disassembling class:', error)
+        }
+    }
 
     const loadMethods = async () => {
         if (!expanded || methods.length > 0) return
@@ -60,6 +127,8 @@ function DexClass({ javaClass, dexfile }: { javaClass: JClass, dexfile: Uint8Arr
                 {javaClass.name}
             </div>
             <div style={{ display: expanded ? 'block' : 'none', paddingLeft: 16 }}>
+                <button onClick={toggleSmali}>{smali === null ? 'Show smali' : 'Hide smali'}</button>
+                {smali !== null && <pre className="smali">{smali}</pre>}
                 {loading ? (
                     <div>Loading methods...</div>
                 ) : (